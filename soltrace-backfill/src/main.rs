@@ -1,17 +1,22 @@
 use anyhow::Result;
-use clap::Parser;
-use solana_client::rpc_client::RpcClient;
-use solana_client::rpc_config::RpcTransactionConfig;
-use solana_commitment_config::CommitmentConfig;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use futures::TryStreamExt;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 use soltrace_core::{
-    load_idls, process_transaction, retry_with_rate_limit, Database, EventDecoder, IdlParser,
-    ProgramPrefixConfig,
+    env_file_from_args, load_idls, parse_commitment, BackfillReport, Backfiller, Cluster, Database,
+    EventDecoder, EventFilter, EventNameCase, EventNameFilter, EventRecord, EventSink, EventStats,
+    FanoutSink, FileSink, IdlParser, InstructionDecoder, LogProgress, Metrics, ProgramAliases,
+    ProgramPrefixConfig, ProgressReporter, SampledSink, Signature, SinkKind, WebhookSink,
+    DEFAULT_WEBHOOK_CONCURRENCY,
 };
 use std::collections::HashSet;
-use std::sync::Arc;
-use std::time::Duration;
-use tokio::task;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
 /// Soltrace Backfill - Historical Solana event indexer
@@ -19,27 +24,105 @@ use tracing::{debug, error, info, warn};
 #[command(name = "soltrace-backfill")]
 #[command(about = "Backfill historical Solana events from RPC", long_about = None)]
 struct Cli {
-    /// Solana RPC URL
-    #[arg(
-        short,
-        long,
-        default_value = "https://api.mainnet-beta.solana.com",
-        env("SOLANA_RPC_URL")
-    )]
-    rpc_url: String,
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Load settings from this dotenv file instead of ./.env
+    #[arg(long, global = true)]
+    env_file: Option<PathBuf>,
+
+    /// Solana cluster whose public RPC endpoint to use (mainnet, devnet, testnet, localnet)
+    #[arg(long, env("SOLANA_CLUSTER"))]
+    cluster: Option<Cluster>,
+
+    /// Solana RPC URL (overrides --cluster; defaults to mainnet)
+    #[arg(short, long, env("SOLANA_RPC_URL"))]
+    rpc_url: Option<String>,
 
     /// Program prefix mappings (format: program_id:prefix, e.g., "TRibg8...:tributary")
     #[arg(short = 'm', long, env("PROGRAM_PREFIXES"))]
     program_prefixes: String,
 
+    /// Display names for programs in logs and metrics, leaving stored event names as they are
+    /// (format: program_id=name, comma-separated)
+    #[arg(long, env("PROGRAM_ALIASES"))]
+    program_alias: Option<String>,
+
+    /// Non-Anchor discriminator schemes (format: program_id:scheme, e.g. "TRibg8...:custom:1")
+    #[arg(long, env("DISCRIMINATOR_SCHEMES"))]
+    discriminator_schemes: Option<String>,
+
+    /// Casing for stored event names, prefix included (as-is, snake, camel, kebab)
+    #[arg(long, default_value = "as-is", env("EVENT_NAME_CASE"))]
+    event_name_case: EventNameCase,
+
     /// Database URL
     #[arg(short, long, default_value = "sqlite:./soltrace.db", env("DB_URL"))]
     db_url: String,
 
+    /// Table (or MongoDB collection) to store events in
+    #[arg(long, default_value = "events", env("TABLE_NAME"))]
+    table_name: String,
+
+    /// MongoDB database name, overriding the DB_URL path (default: soltrace)
+    #[arg(long, env("MONGO_DB_NAME"))]
+    mongo_db_name: Option<String>,
+
+    /// Don't create or upgrade the schema on connect (it must already exist, e.g. via
+    /// `soltrace-live init`)
+    #[arg(long, env("SKIP_MIGRATIONS"))]
+    skip_migrations: bool,
+
+    /// Where decoded events go: db, stdout (JSON lines, no database), both, or none
+    /// (only --output-dir)
+    #[arg(long, default_value = "db", env("SINK"))]
+    sink: SinkKind,
+
+    /// Also archive every event as JSON lines to rotating files in this directory
+    #[arg(long, env("OUTPUT_DIR"))]
+    output_dir: Option<PathBuf>,
+
+    /// Rotate archive files once they hold this many MiB of JSON
+    #[arg(long, default_value_t = 100, env("ROTATE_SIZE_MB"))]
+    rotate_size_mb: u64,
+
+    /// Also rotate archive files before they span this many slots
+    #[arg(long, env("ROTATE_SLOTS"))]
+    rotate_slots: Option<u64>,
+
+    /// Gzip archive files (needs the `gzip` feature)
+    #[arg(long, env("OUTPUT_GZIP"))]
+    output_gzip: bool,
+
+    /// POST every event as JSON to this URL, in addition to the sink
+    #[arg(long, env("WEBHOOK_URL"))]
+    webhook_url: Option<String>,
+
+    /// Header sent with each webhook POST, as "Name: value" (repeatable)
+    #[arg(long, env("WEBHOOK_HEADERS"), value_delimiter = ',')]
+    webhook_header: Vec<String>,
+
+    /// Maximum webhook POSTs in flight at once
+    #[arg(long, default_value_t = DEFAULT_WEBHOOK_CONCURRENCY, env("WEBHOOK_CONCURRENCY"))]
+    webhook_concurrency: usize,
+
+    /// Store only this fraction (0.0-1.0) of transactions, chosen by signature hash.
+    /// Sampled-out events still count in metrics; stored events are no longer complete
+    #[arg(long, default_value = "1.0", env("SAMPLE_RATE"))]
+    sample_rate: f64,
+
     /// IDL directory path
     #[arg(short, long, default_value = "./idls", env("IDL_DIR"))]
     idl_dir: String,
 
+    /// Single JSON file mapping program ID to IDL, loaded in addition to --idl-dir
+    #[arg(long, env("IDL_BUNDLE"))]
+    idl_bundle: Option<String>,
+
+    /// Commitment level for signature and transaction fetches (processed, confirmed, finalized)
+    #[arg(short, long, default_value = "confirmed", env("COMMITMENT"))]
+    commitment: String,
+
     /// Number of signatures to fetch (latest N transactions)
     #[arg(short, long, default_value = "1000", env("LIMIT"))]
     limit: u64,
@@ -52,291 +135,1360 @@ struct Cli {
     #[arg(short = 'w', long, default_value = "100", env("BATCH_DELAY"))]
     batch_delay: u64,
 
-    /// Number of concurrent transaction fetches
-    #[arg(long, default_value = "10")]
+    /// Number of transaction fetches kept in flight (a new one starts as each finishes)
+    #[arg(long, default_value = "10", env("CONCURRENCY"))]
     concurrency: usize,
 
     /// Maximum retry attempts for failed requests
     #[arg(long, default_value = "3")]
     max_retries: u32,
+
+    /// Only fetch signatures older than this one (resume a previous backfill)
+    #[arg(long, env("BEFORE_SIGNATURE"))]
+    before: Option<String>,
+
+    /// Stop fetching once this signature is reached (e.g. the newest already indexed)
+    #[arg(long, env("UNTIL_SIGNATURE"))]
+    until: Option<String>,
+
+    /// Index exactly these signatures (one per line) instead of discovering them by program
+    #[arg(long, env("SIGNATURES_FILE"))]
+    signatures_file: Option<PathBuf>,
+
+    /// Stop fetching once signatures are older than this many days (by block time)
+    #[arg(long, env("MAX_AGE_DAYS"))]
+    max_age_days: Option<u64>,
+
+    /// Stop the whole backfill after this long (e.g. 90s, 30m, 2h; plain numbers are seconds),
+    /// writing the unprocessed signatures to --checkpoint-file
+    #[arg(long, value_parser = parse_duration, env("DEADLINE"))]
+    deadline: Option<Duration>,
+
+    /// Where a run stopped by --deadline lists its unprocessed signatures (resume with --signatures-file)
+    #[arg(long, default_value = "./backfill.checkpoint", env("CHECKPOINT_FILE"))]
+    checkpoint_file: PathBuf,
+
+    /// Maximum RPC requests per second across all tasks (0 = unlimited)
+    #[arg(long, default_value = "0", env("MAX_RPS"))]
+    max_rps: u32,
+
+    /// Only store events matching these names (comma-separated, trailing * wildcard)
+    #[arg(long, env("INCLUDE_EVENTS"))]
+    include_events: Option<String>,
+
+    /// Skip events matching these names (comma-separated, trailing * wildcard, wins over include)
+    #[arg(long, env("EXCLUDE_EVENTS"))]
+    exclude_events: Option<String>,
+
+    /// Store the pre-decode event bytes alongside decoded data (enables `reindex`)
+    #[arg(long, env("STORE_RAW"))]
+    store_raw: bool,
+
+    /// Keep events from failed transactions, marked `tx_failed`, instead of skipping them
+    #[arg(long, env("INCLUDE_FAILED"))]
+    include_failed: bool,
+
+    /// Also decode program instructions from the IDLs into an instructions table (needs --sink db or both)
+    #[arg(long, env("DECODE_INSTRUCTIONS"))]
+    decode_instructions: bool,
+
+    /// Quietly skip log data whose discriminator matches no loaded IDL event
+    #[arg(long, env("DECODE_ONLY_KNOWN"))]
+    decode_only_known: bool,
+
+    /// Also decode log data prefixed with an IDL account discriminator (account snapshots)
+    #[arg(long, env("DECODE_ACCOUNTS"))]
+    decode_accounts: bool,
+
+    /// Bulk insert each batch of events (COPY on PostgreSQL) for large initial loads
+    #[arg(long, env("FAST_LOAD"))]
+    fast_load: bool,
+
+    /// Progress reporting: bar (log when stdout is not a TTY), log, or none
+    #[arg(long, value_enum, default_value = "bar", env("PROGRESS"))]
+    progress: ProgressMode,
+
+    /// Log a metrics summary every N seconds (0 = only at the end)
+    #[arg(long, default_value = "60", env("METRICS_INTERVAL"))]
+    metrics_interval: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum ProgressMode {
+    /// Live progress bar on a terminal
+    Bar,
+    /// Periodic "Progress: X/Y" log lines
+    Log,
+    /// No progress output
+    None,
+}
+
+impl ProgressMode {
+    /// Build the reporter for this mode; a bar only makes sense on a terminal
+    fn reporter(self, is_terminal: bool) -> Option<Arc<dyn ProgressReporter>> {
+        match self {
+            ProgressMode::Bar if is_terminal => Some(Arc::new(BarProgress::default())),
+            ProgressMode::Bar | ProgressMode::Log => Some(Arc::new(LogProgress)),
+            ProgressMode::None => None,
+        }
+    }
+}
+
+/// Draws an indicatif progress bar per program
+#[derive(Default)]
+struct BarProgress {
+    bar: Mutex<Option<ProgressBar>>,
+}
+
+impl ProgressReporter for BarProgress {
+    fn start(&self, total: usize) {
+        let bar = ProgressBar::with_draw_target(Some(total as u64), ProgressDrawTarget::stdout());
+        bar.set_style(
+            ProgressStyle::with_template(
+                "{bar:40.cyan/blue} {pos}/{len} signatures [{elapsed_precise}, eta {eta}] {msg}",
+            )
+            .expect("progress template is valid"),
+        );
+        *self.bar.lock().unwrap() = Some(bar);
+    }
+
+    fn update(&self, processed: usize, _total: usize, events: usize) {
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            bar.set_position(processed as u64);
+            bar.set_message(format!("{} events", events));
+        }
+    }
+
+    fn finish(&self, _report: &BackfillReport) {
+        if let Some(bar) = self.bar.lock().unwrap().take() {
+            bar.finish();
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Re-decode stored events from their raw data using the current IDLs, without RPC
+    ///
+    /// Rows are read `--batch-size` at a time and only events whose decoded name or
+    /// data changed are updated. Requires events stored with `--store-raw`.
+    #[command(alias = "redecode")]
+    Reindex {
+        /// Only reindex this program's events
+        #[arg(long)]
+        program: Option<String>,
+    },
+    /// Check every loaded IDL's event field types against what the decoder supports
+    ValidateIdls,
+    /// Reclaim space and refresh statistics (SQLite VACUUM, Postgres VACUUM ANALYZE, MongoDB compact)
+    Optimize,
+    /// Decode one event's base64 `Program data:` payload and print it as JSON, without RPC
+    Decode {
+        /// Program that emitted the event
+        #[arg(long)]
+        program: String,
+
+        /// Base64 payload, as shown after `Program data:` in an explorer's logs
+        #[arg(long)]
+        data: String,
+    },
+    /// Print each loaded program's events with their field types and docs
+    Schema {
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: SchemaFormat,
+    },
+    /// Print event counts per program and event name, slot and time ranges, and database size
+    Stats {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum SchemaFormat {
+    /// Aligned columns per event
+    Table,
+    /// A JSON array of programs
+    Json,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Load .env file if present
-    dotenv::dotenv().ok();
+    // Precedence: command line > process environment > env file > default
+    let env_file = env_file_from_args(std::env::args());
+    let env_file_keys = load_env_file(env_file.as_deref())?;
 
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
+    let command = Cli::command();
+    let matches = command.clone().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    let cli = Cli::parse();
+    // Keep stdout clean for piped events
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    let prints_events = matches!(
+        cli.command,
+        Some(Commands::Decode { .. } | Commands::Stats { .. })
+    ) || (cli.command.is_none() && cli.sink.uses_stdout());
+    if prints_events {
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
+            .init();
+    } else {
+        tracing_subscriber::fmt().with_env_filter(filter).init();
+    }
+    if let Some(path) = &cli.env_file {
+        info!("Loaded settings from {}", path.display());
+    }
+    log_config_sources(&command, &matches, &env_file_keys);
 
-    run_backfill(cli).await?;
+    match cli.command {
+        Some(Commands::Reindex { ref program }) => run_reindex(&cli, program.as_deref()).await?,
+        Some(Commands::ValidateIdls) => run_validate_idls(&cli).await?,
+        Some(Commands::Optimize) => run_optimize(&cli).await?,
+        Some(Commands::Decode {
+            ref program,
+            ref data,
+        }) => run_decode(&cli, program, data).await?,
+        Some(Commands::Schema { format }) => run_schema(&cli, format).await?,
+        Some(Commands::Stats { json }) => run_stats(&cli, json).await?,
+        None => run_backfill(cli).await?,
+    }
 
     Ok(())
 }
 
+/// Load a dotenv file: `--env-file` if given, otherwise `.env` in the working directory if present
+///
+/// Variables already set in the process environment win over the file. Returns the
+/// names the file set, so their source can be reported.
+fn load_env_file(path: Option<&Path>) -> Result<HashSet<String>> {
+    let before: HashSet<_> = std::env::vars_os().map(|(key, _)| key).collect();
+
+    match path {
+        Some(path) => {
+            dotenv::from_path(path).map_err(|e| {
+                anyhow::anyhow!("Failed to read env file {}: {}", path.display(), e)
+            })?;
+        }
+        None => {
+            dotenv::dotenv().ok();
+        }
+    }
+
+    Ok(std::env::vars_os()
+        .filter_map(|(key, _)| (!before.contains(&key)).then(|| key.into_string().ok())?)
+        .collect())
+}
+
+/// Log (at debug) where each setting came from: command line, environment, env file or default
+fn log_config_sources(
+    command: &clap::Command,
+    matches: &ArgMatches,
+    env_file_keys: &HashSet<String>,
+) {
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        let source = match matches.value_source(id) {
+            Some(ValueSource::CommandLine) => "command line",
+            Some(ValueSource::EnvVariable) => match arg.get_env().and_then(|name| name.to_str()) {
+                Some(name) if env_file_keys.contains(name) => "env file",
+                _ => "environment",
+            },
+            Some(ValueSource::DefaultValue) => "default",
+            _ => continue,
+        };
+        debug!("Setting {} from {}", id, source);
+    }
+
+    if let Some((name, sub_matches)) = matches.subcommand() {
+        if let Some(subcommand) = command.find_subcommand(name) {
+            log_config_sources(subcommand, sub_matches, env_file_keys);
+        }
+    }
+}
+
 async fn run_backfill(cli: Cli) -> Result<()> {
     info!("Starting Soltrace Backfill");
-    info!("RPC URL: {}", cli.rpc_url);
-    info!("Fetching latest {} signatures per program", cli.limit);
+    let rpc_url = cli
+        .rpc_url
+        .clone()
+        .unwrap_or_else(|| cli.cluster.unwrap_or_default().rpc_url().to_string());
+    info!("RPC URL: {}", rpc_url);
+    info!("Commitment: {}", cli.commitment);
+    if let Some(path) = &cli.signatures_file {
+        info!("Indexing the signatures listed in {}", path.display());
+    } else {
+        info!("Fetching latest {} signatures per program", cli.limit);
+    }
     info!("Batch size: {}", cli.batch_size);
     info!("Concurrency: {}", cli.concurrency);
     info!("Max retries: {}", cli.max_retries);
-
-    // Load IDLs first to extract program IDs
-    let mut idl_parser = IdlParser::new();
-    load_idls(&mut idl_parser, &cli.idl_dir).await?;
-
-    let loaded_idls = idl_parser.get_idls();
-    info!("Loaded {} IDL(s) from {}", loaded_idls.len(), cli.idl_dir);
-    for (addr, idl) in loaded_idls {
-        info!("  - {}: {} events", addr, idl.events.len());
+    if let Some(days) = cli.max_age_days {
+        info!("Only fetching signatures from the last {} day(s)", days);
+    }
+    if let Some(deadline) = cli.deadline {
+        info!("Deadline: stopping after {:?}", deadline);
+    }
+    if cli.max_rps > 0 {
+        info!("Max RPC requests/sec: {}", cli.max_rps);
+    }
+    if cli.store_raw {
+        info!("Storing raw event data");
+    }
+    if cli.include_failed {
+        info!("Keeping events from failed transactions");
+    }
+    if cli.decode_instructions {
+        if !cli.sink.uses_database() {
+            return Err(anyhow::anyhow!(
+                "--decode-instructions stores instructions in the database; use --sink db or both"
+            ));
+        }
+        info!("Decoding program instructions");
+    }
+    if cli.sink == SinkKind::None && cli.output_dir.is_none() {
+        return Err(anyhow::anyhow!("--sink none needs --output-dir"));
+    }
+    if cli.fast_load {
+        if !cli.sink.uses_database() {
+            warn!("Fast load only applies to database sinks; ignoring it for stdout");
+        } else if cli.db_url.starts_with("postgres") {
+            info!("Fast load enabled: bulk loading events with COPY");
+        } else {
+            warn!(
+                "Fast load only speeds up PostgreSQL; other databases insert events one at a time"
+            );
+        }
     }
 
-    // Create program prefix configuration from CLI/env
-    let mut prefix_config = ProgramPrefixConfig::new();
-    // Load programs from IDLs with default prefix
-    prefix_config.load_from_idls(loaded_idls);
-    // Apply custom prefix mappings from CLI/env
-    if !cli.program_prefixes.is_empty() {
-        prefix_config.add_mappings_from_string(&cli.program_prefixes);
-        info!(
-            "Applied {} custom program prefix mapping(s)",
-            cli.program_prefixes
-        );
+    let commitment = parse_commitment(&cli.commitment)?;
+    let before = cli.before.as_deref().map(Signature::parse).transpose()?;
+    let until = cli.until.as_deref().map(Signature::parse).transpose()?;
+    if let Some(before) = &before {
+        info!("Starting before signature: {}", before);
     }
+    if let Some(until) = &until {
+        info!("Stopping at signature: {}", until);
+    }
+    let signature_list = cli
+        .signatures_file
+        .as_deref()
+        .map(read_signatures_file)
+        .transpose()?;
 
-    let program_ids = prefix_config.get_program_ids();
+    let (event_decoder, program_ids) = load_event_decoder(
+        &cli.idl_dir,
+        cli.idl_bundle.as_deref(),
+        &cli.program_prefixes,
+        cli.discriminator_schemes.as_deref(),
+        cli.event_name_case,
+    )
+    .await?;
     if program_ids.is_empty() {
-        error!("No IDLs found in directory. Use --idl-dir <path>");
+        error!("No IDLs found. Use --idl-dir <path> or --idl-bundle <file>");
         return Ok(());
     }
 
-    info!("Indexing {} program(s):", program_ids.len());
-    for pid in &program_ids {
-        let prefix = prefix_config.get_prefix(pid);
-        info!("  - {} (prefix: {})", pid, prefix);
+    let event_filter = EventNameFilter::from_patterns(
+        cli.include_events.as_deref(),
+        cli.exclude_events.as_deref(),
+    );
+    if !event_filter.is_empty() {
+        info!(
+            "Event filters: include={:?}, exclude={:?}",
+            cli.include_events, cli.exclude_events
+        );
     }
+    let has_event_filter = !event_filter.is_empty();
 
-    // Create event decoder
-    let event_decoder = Arc::new(EventDecoder::new(idl_parser, prefix_config));
-
-    // Initialize database
-    let db = Arc::new(Database::new(&cli.db_url).await?);
-    info!("Database connected: {}", cli.db_url);
+    // Initialize the event sink (and database, unless events only go to stdout)
+    let db = if cli.sink.uses_database() {
+        let db = connect_db(&cli).await?;
+        info!("Database connected: {}", cli.db_url);
+        Some(Arc::new(db))
+    } else {
+        None
+    };
+    let sink = cli.sink.build(db.clone());
+    info!("Event sink: {}", cli.sink);
+    let sink: Arc<dyn EventSink> = match &cli.output_dir {
+        Some(dir) => {
+            let archive = Arc::new(
+                FileSink::new(dir)?
+                    .with_rotate_size(cli.rotate_size_mb * 1024 * 1024)
+                    .with_rotate_slots(cli.rotate_slots)
+                    .with_gzip(cli.output_gzip),
+            );
+            info!("Archiving events to {}", dir.display());
+            if cli.sink == SinkKind::None {
+                archive
+            } else {
+                Arc::new(FanoutSink::new(vec![sink, archive]))
+            }
+        }
+        None => sink,
+    };
 
     // Initialize RPC client
-    let rpc_client = Arc::new(RpcClient::new(cli.rpc_url));
+    let rpc_client = Arc::new(RpcClient::new_with_commitment(rpc_url, commitment));
+
+    let started = Instant::now();
+    let deadline = cli
+        .deadline
+        .map(|deadline| tokio::time::Instant::now() + deadline);
+    let program_aliases =
+        ProgramAliases::from_string(cli.program_alias.as_deref().unwrap_or_default())?;
+    let metrics = Arc::new(Metrics::new().with_program_aliases(program_aliases.clone()));
+    let summary_logger = (cli.metrics_interval > 0)
+        .then(|| metrics.spawn_summary_logger(Duration::from_secs(cli.metrics_interval)));
+
+    let sink: Arc<dyn EventSink> = match &cli.webhook_url {
+        Some(url) => {
+            let webhook = WebhookSink::new(url)?
+                .with_headers(&cli.webhook_header)?
+                .with_concurrency(cli.webhook_concurrency)
+                .with_metrics(metrics.clone());
+            info!(
+                "Webhook enabled: {} (up to {} concurrent POSTs)",
+                url, cli.webhook_concurrency
+            );
+            Arc::new(FanoutSink::new(vec![sink, Arc::new(webhook)]))
+        }
+        None => sink,
+    };
+    let sink: Arc<dyn EventSink> = if cli.sample_rate == 1.0 {
+        sink
+    } else {
+        warn!(
+            "Storing a {}% sample of transactions; stored events are incomplete",
+            cli.sample_rate * 100.0
+        );
+        Arc::new(SampledSink::new(sink, cli.sample_rate)?.with_metrics(metrics.clone()))
+    };
+
+    let instructions = match db.filter(|_| cli.decode_instructions) {
+        Some(db) => Some((
+            Arc::new(InstructionDecoder::new(event_decoder.idl_parser())?),
+            db,
+        )),
+        None => None,
+    };
+
+    let event_decoder = event_decoder
+        .with_decode_only_known(cli.decode_only_known)
+        .with_decode_accounts(cli.decode_accounts);
+    let mut backfiller = Backfiller::new(rpc_client, Arc::new(event_decoder), sink.clone())
+        .with_metrics(metrics.clone())
+        .with_progress(cli.progress.reporter(std::io::stdout().is_terminal()))
+        .with_event_filter(event_filter)
+        .with_max_rps(cli.max_rps)
+        .with_store_raw(cli.store_raw)
+        .with_include_failed(cli.include_failed)
+        .with_fast_load(cli.fast_load)
+        .with_concurrency(cli.concurrency)
+        .with_max_retries(cli.max_retries)
+        .with_deadline(deadline)
+        .with_max_age(
+            cli.max_age_days
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+        );
+    if let Some((instruction_decoder, db)) = instructions {
+        backfiller = backfiller.with_instructions(instruction_decoder, db);
+    }
 
     // Track processed signatures across all programs
-    let mut processed_signatures: HashSet<String> = HashSet::new();
+    let mut processed_signatures: HashSet<Signature> = HashSet::new();
 
     // Process each program
     let mut total_signatures_fetched = 0;
     let mut total_events_processed = 0;
+    let mut programs_completed = 0;
+    let mut unprocessed: Vec<Signature> = Vec::new();
 
     for program_id_str in &program_ids {
-        info!("\nProcessing program: {}", program_id_str);
+        if backfiller.deadline_exceeded() {
+            break;
+        }
+        let program_name = program_aliases.display(program_id_str);
+        info!("\nProcessing program: {}", program_name);
+        metrics.register_program(program_id_str).await;
 
         // Validate and parse program ID
         let program_id = program_id_str
             .parse::<Pubkey>()
             .map_err(|e| anyhow::anyhow!("Invalid program ID {}: {}", program_id_str, e))?;
 
-        // Check if program exists with retry
-        let account = retry_with_rate_limit(
-            || async { rpc_client.get_account(&program_id) },
-            cli.max_retries,
-        )
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to fetch account {}: {}", program_id_str, e))?;
-
-        if account.owner == solana_sdk_ids::system_program::ID {
+        if !backfiller.is_program(&program_id).await? {
             warn!(
                 "Program {} is not a program (owner is System Program)",
-                program_id_str
+                program_name
             );
             continue;
         }
 
-        // Get signatures for this program with retry
-        info!("Fetching signatures for program {}...", program_id_str);
-
-        use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
-        let signatures = retry_with_rate_limit(
-            || async {
-                let config = GetConfirmedSignaturesForAddress2Config {
-                    before: None,
-                    until: None,
-                    limit: Some(cli.limit as usize),
-                    commitment: Some(CommitmentConfig::confirmed()),
-                };
-                rpc_client.get_signatures_for_address_with_config(&program_id, config)
-            },
-            cli.max_retries,
-        )
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to get signatures for {}: {}", program_id_str, e))?;
+        // Get signatures for this program, unless they were listed up front
+        let signatures = match &signature_list {
+            Some(signatures) => signatures.clone(),
+            None => {
+                info!("Fetching signatures for program {}...", program_name);
+                let signatures = backfiller
+                    .fetch_signatures(
+                        &program_id,
+                        before.as_ref(),
+                        until.as_ref(),
+                        cli.limit as usize,
+                    )
+                    .await?;
+                info!("Found {} signatures", signatures.len());
+                signatures
+            }
+        };
 
         let signatures_count = signatures.len();
-        info!("Found {} signatures", signatures_count);
         total_signatures_fetched += signatures_count;
 
-        // Process signatures with concurrency
-        let signature_strings: Vec<String> = signatures
-            .iter()
-            .map(|sig| sig.signature.clone())
+        let signatures: Vec<Signature> = signatures
+            .into_iter()
             .filter(|sig| !processed_signatures.contains(sig))
             .collect();
 
-        let program_id_for_processing = program_id_str.clone();
-        let program_events = process_signatures_concurrent(
-            rpc_client.clone(),
-            signature_strings,
-            program_id_for_processing,
-            event_decoder.clone(),
-            db.clone(),
-            &mut processed_signatures,
-            cli.concurrency,
-            cli.max_retries,
-        )
-        .await?;
+        let report = backfiller
+            .process_signatures(program_id_str, signatures)
+            .await?;
 
-        total_events_processed += program_events;
+        total_events_processed += report.events;
+        processed_signatures.extend(report.processed);
+        if !report.unprocessed.is_empty() {
+            unprocessed = report.unprocessed;
+            break;
+        }
+        programs_completed += 1;
         info!(
-            "Program {} complete: {} events processed",
-            program_id_str, program_events
+            "Program {} complete: {} events processed, {} signature(s) skipped as missing",
+            program_name, report.events, report.missing
         );
 
         // Delay between programs to avoid rate limiting
         tokio::time::sleep(Duration::from_millis(cli.batch_delay)).await;
     }
 
-    info!("\nBackfill complete!");
+    if programs_completed < program_ids.len() {
+        warn!(
+            "\nDeadline of {:?} reached after {:?}: {}/{} program(s) completed",
+            cli.deadline.unwrap_or_default(),
+            started.elapsed(),
+            programs_completed,
+            program_ids.len()
+        );
+        write_checkpoint(
+            &cli.checkpoint_file,
+            &unprocessed,
+            &program_ids[programs_completed..],
+        )?;
+    } else {
+        info!("\nBackfill complete!");
+    }
     info!("Total signatures fetched: {}", total_signatures_fetched);
     info!("Total events processed: {}", total_events_processed);
     info!(
         "Unique signatures processed: {}",
         processed_signatures.len()
     );
+    if has_event_filter {
+        info!(
+            "Events filtered: {}",
+            metrics
+                .events_filtered
+                .load(std::sync::atomic::Ordering::Relaxed)
+        );
+    }
+
+    // Close any archive file still open so it is named after its slot range
+    sink.flush().await?;
+
+    if let Some(summary_logger) = summary_logger {
+        summary_logger.abort();
+    }
+    metrics.log_summary().await;
 
     Ok(())
 }
 
-async fn process_signatures_concurrent(
-    rpc_client: Arc<RpcClient>,
-    signatures: Vec<String>,
-    program_id_str: String,
-    event_decoder: Arc<EventDecoder>,
-    db: Arc<Database>,
-    processed_signatures: &mut HashSet<String>,
-    concurrency: usize,
-    max_retries: u32,
-) -> Result<usize> {
-    let total = signatures.len();
-    let mut processed_count = 0;
-    let mut events_count = 0;
-
-    // Process signatures in chunks to avoid overwhelming the RPC
-    for chunk in signatures.chunks(concurrency * 2) {
-        let mut handles = Vec::new();
-
-        for signature in chunk.iter() {
-            let rpc_client = rpc_client.clone();
-            let program_id_str = program_id_str.clone();
-            let event_decoder = event_decoder.clone();
-            let db = db.clone();
-            let sig_for_task = signature.clone();
-
-            let handle = task::spawn(async move {
-                process_single_signature(
-                    &rpc_client,
-                    &sig_for_task,
-                    &program_id_str,
-                    &event_decoder,
-                    &db,
-                    max_retries,
-                )
-                .await
-            });
-
-            handles.push((signature.clone(), handle));
+/// Write the signatures a deadline left unprocessed, in `--signatures-file` format
+///
+/// Programs that were never reached have no fetched signatures to list, so they
+/// are named in a comment and the log instead.
+fn write_checkpoint(path: &Path, unprocessed: &[Signature], pending: &[String]) -> Result<()> {
+    if unprocessed.is_empty() {
+        warn!(
+            "No signatures left unprocessed; program(s) not started: {}",
+            pending.join(", ")
+        );
+        return Ok(());
+    }
+
+    std::fs::write(path, checkpoint_contents(unprocessed, pending))
+        .map_err(|e| anyhow::anyhow!("Failed to write checkpoint {}: {}", path.display(), e))?;
+    warn!(
+        "Wrote {} unprocessed signature(s) to {}; resume with --signatures-file {}",
+        unprocessed.len(),
+        path.display(),
+        path.display()
+    );
+    if pending.len() > 1 {
+        warn!("Program(s) not started: {}", pending[1..].join(", "));
+    }
+
+    Ok(())
+}
+
+/// Checkpoint file body: a comment naming the unfinished programs, then one signature per line
+fn checkpoint_contents(unprocessed: &[Signature], pending: &[String]) -> String {
+    let mut contents = format!(
+        "# soltrace-backfill checkpoint; unfinished program(s): {}\n",
+        pending.join(", ")
+    );
+    for signature in unprocessed {
+        contents.push_str(&format!("{}\n", signature));
+    }
+    contents
+}
+
+/// Parse a duration like `90`, `90s`, `30m`, `2h` or `1d` (plain numbers are seconds)
+fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = value
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((value, ""), |at| value.split_at(at));
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "invalid duration unit '{}' (use s, m, h or d)",
+                unit
+            ))
         }
+    };
+    Ok(Duration::from_secs(number * seconds))
+}
+
+/// Read a newline-delimited signature list, skipping blank lines and `#` comments
+///
+/// Invalid lines are logged with their line number and left out; a file with no
+/// valid signature is an error.
+fn read_signatures_file(path: &Path) -> Result<Vec<Signature>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
 
-        // Wait for all tasks in this chunk
-        for (signature, handle) in handles {
-            processed_count += 1;
+    let (signatures, invalid) = parse_signature_list(&contents);
+    for (line, error) in &invalid {
+        warn!("{}:{}: {}", path.display(), line, error);
+    }
+    if signatures.is_empty() {
+        return Err(anyhow::anyhow!("No valid signatures in {}", path.display()));
+    }
+    info!(
+        "Read {} signature(s) from {} ({} invalid line(s) skipped)",
+        signatures.len(),
+        path.display(),
+        invalid.len()
+    );
 
-            match handle.await {
-                Ok(Ok(event_count)) => {
-                    events_count += event_count;
-                    processed_signatures.insert(signature);
+    Ok(signatures)
+}
+
+/// Parse one signature per line, returning the unique valid ones in order and
+/// the 1-based line number and error of each invalid line
+fn parse_signature_list(contents: &str) -> (Vec<Signature>, Vec<(usize, String)>) {
+    let mut seen = HashSet::new();
+    let mut signatures = Vec::new();
+    let mut invalid = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match Signature::parse(line) {
+            Ok(signature) => {
+                if seen.insert(signature.clone()) {
+                    signatures.push(signature);
                 }
-                Ok(Err(e)) => {
-                    debug!("Failed to process signature {}: {}", signature, e);
+            }
+            Err(e) => invalid.push((number + 1, e.to_string())),
+        }
+    }
+
+    (signatures, invalid)
+}
+
+/// Load the IDLs from the directory and optional bundle
+async fn load_idl_parser(idl_dir: &str, idl_bundle: Option<&str>) -> Result<IdlParser> {
+    let mut idl_parser = IdlParser::new();
+    load_idls(&mut idl_parser, idl_dir).await?;
+    if let Some(bundle) = idl_bundle {
+        let count = idl_parser
+            .load_from_bundle(bundle)
+            .map_err(|e| anyhow::anyhow!("Failed to load IDL bundle {}: {}", bundle, e))?;
+        info!("Loaded {} IDL(s) from bundle {}", count, bundle);
+    }
+    info!("Loaded {} IDL(s) in total", idl_parser.get_idls().len());
+
+    Ok(idl_parser)
+}
+
+/// Connect to the events database, running migrations unless --skip-migrations
+async fn connect_db(cli: &Cli) -> Result<Database> {
+    Ok(Database::open(
+        &cli.db_url,
+        &cli.table_name,
+        cli.mongo_db_name.as_deref(),
+        !cli.skip_migrations,
+    )
+    .await?)
+}
+
+/// Load IDLs and build the event decoder, returning it with the configured program IDs
+async fn load_event_decoder(
+    idl_dir: &str,
+    idl_bundle: Option<&str>,
+    program_prefixes: &str,
+    discriminator_schemes: Option<&str>,
+    event_name_case: EventNameCase,
+) -> Result<(EventDecoder, Vec<String>)> {
+    // Load IDLs first to extract program IDs
+    let mut idl_parser = load_idl_parser(idl_dir, idl_bundle).await?;
+    idl_parser.validate_field_types()?;
+
+    let loaded_idls = idl_parser.get_idls();
+    for (addr, idl) in loaded_idls {
+        info!("  - {}: {} events", addr, idl.events.len());
+    }
+
+    // Create program prefix configuration from CLI/env
+    let mut prefix_config = ProgramPrefixConfig::new();
+    // Load programs from IDLs with default prefix
+    prefix_config.load_from_idls(loaded_idls);
+    // Apply custom prefix mappings from CLI/env
+    if !program_prefixes.is_empty() {
+        prefix_config.add_mappings_from_string(program_prefixes);
+        info!(
+            "Applied {} custom program prefix mapping(s)",
+            program_prefixes
+        );
+    }
+
+    if let Some(schemes) = discriminator_schemes {
+        idl_parser.add_discriminator_schemes_from_string(schemes)?;
+        info!("Applied discriminator scheme(s): {}", schemes);
+    }
+
+    let program_ids = prefix_config.get_program_ids();
+    info!("Indexing {} program(s):", program_ids.len());
+    for pid in &program_ids {
+        let prefix = prefix_config.get_prefix(pid);
+        info!("  - {} (prefix: {})", pid, prefix);
+    }
+
+    Ok((
+        EventDecoder::new(idl_parser, prefix_config).with_name_case(event_name_case),
+        program_ids,
+    ))
+}
+
+/// Re-decode stored events from their raw bytes and update the ones whose result changed
+async fn run_reindex(cli: &Cli, program: Option<&str>) -> Result<()> {
+    info!("Starting Soltrace Reindex");
+
+    if let Some(program) = program {
+        program
+            .parse::<Pubkey>()
+            .map_err(|e| anyhow::anyhow!("Invalid program id '{}': {}", program, e))?;
+    }
+
+    let (event_decoder, _) = load_event_decoder(
+        &cli.idl_dir,
+        cli.idl_bundle.as_deref(),
+        &cli.program_prefixes,
+        cli.discriminator_schemes.as_deref(),
+        cli.event_name_case,
+    )
+    .await?;
+
+    // Account snapshots stored with --decode-accounts only re-decode with it set
+    let event_decoder = event_decoder.with_decode_accounts(cli.decode_accounts);
+
+    let db = connect_db(cli).await?;
+    info!("Database connected: {}", cli.db_url);
+
+    let started = Instant::now();
+    let report = reindex_events(&db, &event_decoder, program, cli.batch_size).await?;
+
+    info!("\nReindex complete in {:?}!", started.elapsed());
+    info!("Events scanned: {}", report.scanned);
+    info!("Events changed: {}", report.changed);
+    info!("Events without raw data: {}", report.without_raw_data);
+    info!("Events failed: {}", report.failed);
+
+    Ok(())
+}
+
+/// Counts from a reindex run
+#[derive(Debug, Default, PartialEq)]
+struct ReindexReport {
+    scanned: usize,
+    changed: usize,
+    without_raw_data: usize,
+    failed: usize,
+}
+
+/// Re-decode every stored event (of `program_id`, if given) a page of `batch_size` at a time
+///
+/// Each page is read to completion before its updates are written, so the read
+/// never holds the connection an update needs. Pages always end on a slot
+/// boundary, letting the next page start at the following slot. Events whose
+/// new decode still falls back to hex are left as they are and counted as failed.
+async fn reindex_events(
+    db: &Database,
+    event_decoder: &EventDecoder,
+    program_id: Option<&str>,
+    batch_size: usize,
+) -> Result<ReindexReport> {
+    let mut report = ReindexReport::default();
+    let mut start_slot = 0;
+
+    loop {
+        let mut filter = EventFilter::new();
+        filter.program_id = program_id.map(str::to_string);
+        filter.start_slot = Some(start_slot);
+
+        let mut page: Vec<EventRecord> = Vec::new();
+        let mut rows = db.stream_events(filter);
+        while let Some(event) = rows.try_next().await? {
+            if page.len() >= batch_size.max(1) && page.last().map(|e| e.slot) != Some(event.slot) {
+                break;
+            }
+            page.push(event);
+        }
+        drop(rows);
+
+        let Some(last_slot) = page.last().map(|e| e.slot) else {
+            break;
+        };
+        start_slot = last_slot as u64 + 1;
+
+        for event in page {
+            report.scanned += 1;
+            let Some(raw_hex) = event.raw_data.as_deref() else {
+                report.without_raw_data += 1;
+                continue;
+            };
+
+            let raw_bytes = match hex::decode(raw_hex) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    warn!("Event {} has invalid raw data: {}", event.id, e);
+                    report.failed += 1;
+                    continue;
+                }
+            };
+
+            match event_decoder.decode_event(
+                &event.program_id,
+                event.signature.as_str(),
+                &raw_bytes,
+            ) {
+                Ok(decoded) if decoded.data.get("decode_error").is_some() => {
+                    debug!("Event {} still falls back to hex", event.id);
+                    report.failed += 1;
+                }
+                Ok(decoded) => {
+                    if decoded.event_name != event.event_name || decoded.data != event.data {
+                        db.update_event_data(&event.id, &decoded.event_name, &decoded.data)
+                            .await?;
+                        report.changed += 1;
+                    }
                 }
                 Err(e) => {
-                    error!("Task panicked for signature {}: {}", signature, e);
+                    debug!("Failed to re-decode event {}: {}", event.id, e);
+                    report.failed += 1;
                 }
             }
         }
 
-        // Progress update every 100 signatures
-        if processed_count % 100 == 0 || processed_count >= total {
-            info!(
-                "Progress: {}/{} signatures processed, {} events found",
-                processed_count, total, events_count
-            );
-        }
+        info!(
+            "Reindexed {} event(s) through slot {}: {} changed, {} failed",
+            report.scanned, last_slot, report.changed, report.failed
+        );
     }
 
-    Ok(events_count)
+    Ok(report)
 }
 
-async fn process_single_signature(
-    rpc_client: &RpcClient,
-    signature: &str,
-    program_id_str: &str,
-    event_decoder: &EventDecoder,
-    db: &Database,
-    max_retries: u32,
-) -> Result<usize> {
-    // Parse signature
-    let sig = signature
-        .parse::<solana_sdk::signature::Signature>()
-        .map_err(|e| anyhow::anyhow!("Invalid signature: {}", e))?;
-
-    // Fetch transaction with retry
-    let transaction = retry_with_rate_limit(
-        || async {
-            rpc_client.get_transaction_with_config(
-                &sig,
-                RpcTransactionConfig {
-                    encoding: Some(solana_transaction_status::UiTransactionEncoding::Json),
-                    commitment: Some(CommitmentConfig::confirmed()),
-                    max_supported_transaction_version: Some(0),
-                },
-            )
-        },
-        max_retries,
+/// Fail if any loaded IDL has an event field type the decoder can't handle
+async fn run_validate_idls(cli: &Cli) -> Result<()> {
+    let idl_parser = load_idl_parser(&cli.idl_dir, cli.idl_bundle.as_deref()).await?;
+    idl_parser.validate_field_types()?;
+    info!(
+        "All field types in {} IDL(s) are supported",
+        idl_parser.get_idls().len()
+    );
+
+    Ok(())
+}
+
+/// Run the backend's maintenance pass on the events table
+async fn run_optimize(cli: &Cli) -> Result<()> {
+    let db = connect_db(cli).await?;
+    info!("Database connected: {}", cli.db_url);
+
+    let started = Instant::now();
+    db.optimize().await?;
+    info!("Optimized {} in {:?}", cli.table_name, started.elapsed());
+
+    Ok(())
+}
+
+/// Decode a single base64 event payload and print the event as JSON to stdout
+async fn run_decode(cli: &Cli, program: &str, data: &str) -> Result<()> {
+    let (event_decoder, _) = load_event_decoder(
+        &cli.idl_dir,
+        cli.idl_bundle.as_deref(),
+        &cli.program_prefixes,
+        cli.discriminator_schemes.as_deref(),
+        cli.event_name_case,
+    )
+    .await?;
+
+    let event = event_decoder
+        .with_decode_accounts(cli.decode_accounts)
+        .decode_base64(program, "(decode)", data)?;
+    println!("{}", serde_json::to_string_pretty(&event)?);
+
+    Ok(())
+}
+
+/// Print the event schema of every loaded IDL to stdout
+async fn run_schema(cli: &Cli, format: SchemaFormat) -> Result<()> {
+    let idl_parser = load_idl_parser(&cli.idl_dir, cli.idl_bundle.as_deref()).await?;
+
+    let schema = event_schema(&idl_parser);
+    match format {
+        SchemaFormat::Table => print!("{}", render_schema_table(&schema)),
+        SchemaFormat::Json => println!("{}", serde_json::to_string_pretty(&schema)?),
+    }
+
+    Ok(())
+}
+
+/// Print a summary of the stored events to stdout, leaving the schema as it is
+async fn run_stats(cli: &Cli, json: bool) -> Result<()> {
+    let db = Database::open(
+        &cli.db_url,
+        &cli.table_name,
+        cli.mongo_db_name.as_deref(),
+        false,
     )
-    .await
-    .map_err(|e| anyhow::anyhow!("Failed to fetch transaction: {}", e))?;
+    .await?;
+
+    let stats = db.event_stats().await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print!("{}", render_stats_table(&stats));
+    }
+
+    Ok(())
+}
+
+/// Render [`EventStats`] as a summary followed by per-program and per-event counts
+fn render_stats_table(stats: &EventStats) -> String {
+    let range = |min: Option<String>, max: Option<String>| match (min, max) {
+        (Some(min), Some(max)) => format!("{} - {}", min, max),
+        _ => "-".to_string(),
+    };
+    let bounds = &stats.bounds;
+    let summary = [
+        ("Total events", stats.total_events.to_string()),
+        (
+            "Slots",
+            range(
+                bounds.min_slot.map(|slot| slot.to_string()),
+                bounds.max_slot.map(|slot| slot.to_string()),
+            ),
+        ),
+        (
+            "Timestamps",
+            range(
+                bounds.min_timestamp.map(|ts| ts.to_rfc3339()),
+                bounds.max_timestamp.map(|ts| ts.to_rfc3339()),
+            ),
+        ),
+        (
+            "Database size",
+            stats
+                .size_bytes
+                .map_or("unknown".to_string(), |bytes| format!("{} bytes", bytes)),
+        ),
+    ];
+
+    let mut out = String::new();
+    for (label, value) in summary {
+        out.push_str(&format!("{:13}  {}\n", label, value));
+    }
+
+    for (heading, counts) in [
+        ("Program", &stats.events_per_program),
+        ("Event", &stats.events_per_event_name),
+    ] {
+        let name_width = counts
+            .iter()
+            .map(|count| count.name.len())
+            .chain([heading.len()])
+            .max()
+            .unwrap_or_default();
+        let count_width = counts
+            .iter()
+            .map(|count| count.count.to_string().len())
+            .chain(["Events".len()])
+            .max()
+            .unwrap_or_default();
+
+        out.push_str(&format!(
+            "\n{:name_width$}  {:>count_width$}\n",
+            heading, "Events"
+        ));
+        for count in counts {
+            out.push_str(&format!(
+                "{:name_width$}  {:>count_width$}\n",
+                count.name, count.count
+            ));
+        }
+    }
+
+    out
+}
+
+/// Programs, their events and fields as JSON, ordered by program ID
+fn event_schema(idl_parser: &IdlParser) -> serde_json::Value {
+    let mut program_ids: Vec<_> = idl_parser.get_idls().keys().collect();
+    program_ids.sort();
+
+    let programs = program_ids
+        .into_iter()
+        .map(|program_id| {
+            let idl = &idl_parser.get_idls()[program_id];
+            let events: Vec<_> = idl_parser
+                .resolved_events(program_id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|event| {
+                    let fields: Vec<_> = event
+                        .fields
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|field| {
+                            serde_json::json!({
+                                "name": field.name,
+                                "type": field.type_name(),
+                                "docs": field.docs.join(" "),
+                            })
+                        })
+                        .collect();
+                    serde_json::json!({
+                        "name": event.name,
+                        "docs": event.docs.join(" "),
+                        "fields": fields,
+                    })
+                })
+                .collect();
+
+            serde_json::json!({
+                "program_id": program_id,
+                "name": idl.name,
+                "events": events,
+            })
+        })
+        .collect();
+
+    serde_json::Value::Array(programs)
+}
+
+/// Render [`event_schema`] output as one aligned table per event
+fn render_schema_table(schema: &serde_json::Value) -> String {
+    let str_at = |value: &serde_json::Value, key: &str| -> String {
+        value[key].as_str().unwrap_or_default().to_string()
+    };
+
+    let mut out = String::new();
+    for program in schema.as_array().into_iter().flatten() {
+        out.push_str(&format!("Program {}", str_at(program, "program_id")));
+        if let Some(name) = program["name"].as_str() {
+            out.push_str(&format!(" ({})", name));
+        }
+        out.push('\n');
+
+        for event in program["events"].as_array().into_iter().flatten() {
+            out.push_str(&format!("\n  {}", str_at(event, "name")));
+            let docs = str_at(event, "docs");
+            if !docs.is_empty() {
+                out.push_str(&format!(" - {}", docs));
+            }
+            out.push('\n');
 
-    // Process transaction
-    match process_transaction(transaction, program_id_str, event_decoder, db).await {
-        Ok(processed) => Ok(processed.len()),
-        Err(e) => Err(anyhow::anyhow!("Failed to process transaction: {}", e)),
+            let fields: Vec<_> = event["fields"].as_array().into_iter().flatten().collect();
+            let name_width = fields.iter().map(|f| str_at(f, "name").len()).max();
+            let type_width = fields.iter().map(|f| str_at(f, "type").len()).max();
+            for field in &fields {
+                let line = format!(
+                    "    {:name_width$}  {:type_width$}  {}",
+                    str_at(field, "name"),
+                    str_at(field, "type"),
+                    str_at(field, "docs"),
+                    name_width = name_width.unwrap_or_default(),
+                    type_width = type_width.unwrap_or_default(),
+                );
+                out.push_str(line.trim_end());
+                out.push('\n');
+            }
+        }
+        out.push('\n');
     }
+
+    out
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use soltrace_core::{EventBounds, EventCount};
+
+    #[test]
+    fn test_progress_mode_falls_back_to_log_off_terminal() {
+        assert!(ProgressMode::None.reporter(true).is_none());
+        assert!(ProgressMode::Log.reporter(true).is_some());
+        assert!(ProgressMode::Bar.reporter(false).is_some());
+    }
+
+    #[test]
+    fn test_parse_signature_list() {
+        let first = solana_sdk::signature::Signature::from([1u8; 64]).to_string();
+        let second = solana_sdk::signature::Signature::from([2u8; 64]).to_string();
+        let contents =
+            format!("# incident 42\n{first}\n\n  {second}  \nnot-a-signature\n{first}\n");
+
+        let (signatures, invalid) = parse_signature_list(&contents);
+        assert_eq!(
+            signatures,
+            vec![
+                Signature::parse(&first).unwrap(),
+                Signature::parse(&second).unwrap()
+            ]
+        );
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].0, 5);
+        assert!(invalid[0].1.contains("not-a-signature"));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(2 * 60 * 60)));
+        assert!(parse_duration("2w").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_round_trips_as_signature_list() {
+        let signature =
+            Signature::parse(&solana_sdk::signature::Signature::from([3u8; 64]).to_string())
+                .unwrap();
+        let unprocessed = vec![signature];
+        let contents = checkpoint_contents(&unprocessed, &["Prog1".to_string()]);
+
+        let (signatures, invalid) = parse_signature_list(&contents);
+        assert_eq!(signatures, unprocessed);
+        assert!(invalid.is_empty());
+    }
+
+    #[test]
+    fn test_event_schema_table() {
+        let mut idl_parser = IdlParser::new();
+        idl_parser
+            .load_from_str(
+                r#"{
+                    "address": "Test111111111111111111111111111111",
+                    "name": "vault",
+                    "events": [{
+                        "name": "Deposit",
+                        "docs": ["Funds came in"],
+                        "fields": [
+                            {"name": "amount", "docs": ["In lamports"], "type": "u64"},
+                            {"name": "owner", "type": "pubkey"}
+                        ]
+                    }]
+                }"#,
+            )
+            .unwrap();
+
+        let schema = event_schema(&idl_parser);
+        assert_eq!(schema[0]["events"][0]["fields"][1]["docs"], "");
+
+        let expected = [
+            "Program Test111111111111111111111111111111 (vault)",
+            "",
+            "  Deposit - Funds came in",
+            "    amount  u64     In lamports",
+            "    owner   pubkey",
+            "",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(render_schema_table(&schema), expected);
+    }
+
+    #[test]
+    fn test_stats_table() {
+        let stats = EventStats {
+            total_events: 3,
+            events_per_program: vec![EventCount {
+                name: "Test111111111111111111111111111111".to_string(),
+                count: 3,
+            }],
+            events_per_event_name: vec![
+                EventCount {
+                    name: "vault_Deposit".to_string(),
+                    count: 2,
+                },
+                EventCount {
+                    name: "vault_Withdraw".to_string(),
+                    count: 1,
+                },
+            ],
+            bounds: EventBounds {
+                min_slot: Some(10),
+                max_slot: Some(30),
+                min_timestamp: "2024-01-01T00:00:00Z".parse().ok(),
+                max_timestamp: "2024-01-02T00:00:00Z".parse().ok(),
+            },
+            size_bytes: Some(4096),
+        };
+
+        let expected = [
+            "Total events   3",
+            "Slots          10 - 30",
+            "Timestamps     2024-01-01T00:00:00+00:00 - 2024-01-02T00:00:00+00:00",
+            "Database size  4096 bytes",
+            "",
+            "Program                             Events",
+            "Test111111111111111111111111111111       3",
+            "",
+            "Event           Events",
+            "vault_Deposit        2",
+            "vault_Withdraw       1",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(render_stats_table(&stats), expected);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["max_slot"], 30);
+        assert_eq!(json["events_per_event_name"][0]["name"], "vault_Deposit");
+    }
+
+    #[tokio::test]
+    async fn test_reindex_updates_changed_events() {
+        let program = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+        let mut idl_parser = IdlParser::new();
+        idl_parser
+            .load_from_str(&format!(
+                r#"{{
+                    "address": "{}",
+                    "events": [{{
+                        "name": "Deposit",
+                        "fields": [{{"name": "amount", "type": "u8"}}]
+                    }}]
+                }}"#,
+                program
+            ))
+            .unwrap();
+        let mut prefix_config = ProgramPrefixConfig::new();
+        prefix_config.add_mapping(program, "t");
+        let event_decoder = EventDecoder::new(idl_parser, prefix_config);
+        let discriminator = IdlParser::calculate_discriminator("Deposit");
+
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let stored = [
+            // (slot, stored data, raw amount): a stale row, an up-to-date one, one without raw bytes
+            (
+                5,
+                serde_json::json!({"hex": "09", "decode_error": "old IDL"}),
+                Some(9u8),
+            ),
+            (5, serde_json::json!({"amount": 4}), Some(4)),
+            (6, serde_json::json!({"amount": 1}), None),
+        ];
+        for (i, (slot, data, amount)) in stored.into_iter().enumerate() {
+            let signature = solana_sdk::signature::Signature::from([i as u8 + 1; 64]);
+            let event = soltrace_core::DecodedEvent {
+                event_name: "t_Deposit".to_string(),
+                data,
+                discriminator,
+            };
+            let raw = soltrace_core::types::RawEvent {
+                slot,
+                signature: Signature::parse(&signature.to_string()).unwrap(),
+                program_id: program.parse().unwrap(),
+                log: String::new(),
+                log_index: 0,
+                inner_instruction_index: None,
+                timestamp: Default::default(),
+                raw_data: amount.map(|amount| [&discriminator[..], &[amount]].concat()),
+                tx_failed: false,
+            };
+            db.insert_event(&event, &raw).await.unwrap();
+        }
+
+        // A batch size of 1 still reads both slot-5 events in one page
+        let report = reindex_events(&db, &event_decoder, Some(program), 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            ReindexReport {
+                scanned: 3,
+                changed: 1,
+                without_raw_data: 1,
+                failed: 0,
+            }
+        );
+
+        let events = db.get_events_by_name("t_Deposit").await.unwrap();
+        let mut amounts: Vec<_> = events.iter().map(|e| e.data["amount"].clone()).collect();
+        amounts.sort_by_key(|a| a.as_u64());
+        assert_eq!(amounts, vec![1, 4, 9]);
+
+        let other = reindex_events(
+            &db,
+            &event_decoder,
+            Some(&Pubkey::new_unique().to_string()),
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(other, ReindexReport::default());
+    }
+
     #[test]
     fn test_program_parsing() {
         let programs = "Prog1,Prog2,Prog3";
@@ -0,0 +1,358 @@
+use crate::{
+    error::Result,
+    queue::QueueEvent,
+    sink::EventSink,
+    types::{DecodedEvent, RawEvent, Slot},
+};
+use async_trait::async_trait;
+#[cfg(feature = "gzip")]
+use flate2::{write::GzEncoder, Compression};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{debug, error, info};
+
+/// Bytes of JSON a file holds before rotating unless [`FileSink::with_rotate_size`] says otherwise
+pub const DEFAULT_ROTATE_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Appends each event as a [`QueueEvent`] JSON line to rotating files in a directory,
+/// for cheap archives that can be re-loaded later
+///
+/// A file is rotated once it holds `rotate_size` bytes of JSON (counted before
+/// compression) or, with [`with_rotate_slots`](Self::with_rotate_slots), before it
+/// would span that many slots. The file being written is `events-<slot>.jsonl.part`;
+/// once closed it is renamed after the slot range it covers, e.g.
+/// `events-1000-1999.jsonl` (`.jsonl.gz` when gzipped). Call
+/// [`flush`](EventSink::flush) on shutdown to close the current file.
+pub struct FileSink {
+    dir: PathBuf,
+    rotate_size: u64,
+    rotate_slots: Option<u64>,
+    gzip: bool,
+    current: Mutex<Option<ArchiveFile>>,
+}
+
+/// The file currently being written and the slots it holds
+struct ArchiveFile {
+    path: PathBuf,
+    writer: ArchiveWriter,
+    first_slot: Slot,
+    last_slot: Slot,
+    bytes: u64,
+}
+
+enum ArchiveWriter {
+    Plain(BufWriter<File>),
+    #[cfg(feature = "gzip")]
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl ArchiveWriter {
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            ArchiveWriter::Plain(writer) => writer,
+            #[cfg(feature = "gzip")]
+            ArchiveWriter::Gzip(writer) => writer,
+        }
+    }
+
+    /// Write out buffered data and, for gzip, the stream trailer
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            ArchiveWriter::Plain(mut writer) => writer.flush(),
+            #[cfg(feature = "gzip")]
+            ArchiveWriter::Gzip(writer) => writer.finish()?.flush(),
+        }
+    }
+}
+
+impl FileSink {
+    /// Write files into `dir`, creating it if needed
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            rotate_size: DEFAULT_ROTATE_SIZE,
+            rotate_slots: None,
+            gzip: false,
+            current: Mutex::new(None),
+        })
+    }
+
+    /// Rotate once a file holds this many bytes of JSON
+    pub fn with_rotate_size(mut self, bytes: u64) -> Self {
+        self.rotate_size = bytes.max(1);
+        self
+    }
+
+    /// Also rotate before a file would span `slots` slots
+    pub fn with_rotate_slots(mut self, slots: Option<u64>) -> Self {
+        self.rotate_slots = slots.map(|slots| slots.max(1));
+        self
+    }
+
+    /// Gzip each file (named `.jsonl.gz`); writes fail unless built with the `gzip` feature
+    pub fn with_gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn extension(&self) -> &'static str {
+        if self.gzip {
+            ".jsonl.gz"
+        } else {
+            ".jsonl"
+        }
+    }
+
+    /// `dir/<stem><extension>`, or `dir/<stem>.<n><extension>` if that is taken
+    fn unused_path(&self, stem: &str, extension: &str) -> PathBuf {
+        let mut path = self.dir.join(format!("{}{}", stem, extension));
+        let mut n = 1;
+        while path.exists() {
+            path = self.dir.join(format!("{}.{}{}", stem, n, extension));
+            n += 1;
+        }
+        path
+    }
+
+    fn open(&self, slot: Slot) -> Result<ArchiveFile> {
+        if self.gzip && cfg!(not(feature = "gzip")) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "gzipped archives need soltrace built with the `gzip` feature",
+            )
+            .into());
+        }
+        let path = self.unused_path(
+            &format!("events-{}", slot),
+            &format!("{}.part", self.extension()),
+        );
+        let file = BufWriter::new(File::create(&path)?);
+        #[cfg(feature = "gzip")]
+        let writer = if self.gzip {
+            ArchiveWriter::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            ArchiveWriter::Plain(file)
+        };
+        #[cfg(not(feature = "gzip"))]
+        let writer = ArchiveWriter::Plain(file);
+        debug!("Opened archive file {}", path.display());
+
+        Ok(ArchiveFile {
+            path,
+            writer,
+            first_slot: slot,
+            last_slot: slot,
+            bytes: 0,
+        })
+    }
+
+    /// Finish the file and rename it after the slots it covers
+    fn close(&self, file: ArchiveFile) -> Result<PathBuf> {
+        file.writer.finish()?;
+        let path = self.unused_path(
+            &format!("events-{}-{}", file.first_slot, file.last_slot),
+            self.extension(),
+        );
+        std::fs::rename(&file.path, &path)?;
+        info!(
+            "Archived {} bytes of events to {}",
+            file.bytes,
+            path.display()
+        );
+        Ok(path)
+    }
+
+    /// Whether adding an event at `slot` would make the file span too many slots
+    fn spans_too_many_slots(&self, file: &ArchiveFile, slot: Slot) -> bool {
+        self.rotate_slots.is_some_and(|rotate_slots| {
+            let first = file.first_slot.min(slot);
+            let last = file.last_slot.max(slot);
+            last - first >= rotate_slots
+        })
+    }
+
+    fn write_lines<'a>(
+        &self,
+        events: impl Iterator<Item = (&'a DecodedEvent, &'a RawEvent)>,
+    ) -> Result<u64> {
+        let mut current = self.current.lock().unwrap();
+        let mut written = 0;
+
+        for (event, raw) in events {
+            let mut line = serde_json::to_string(&QueueEvent::from_decoded(event, raw))?;
+            line.push('\n');
+
+            if current
+                .as_ref()
+                .is_some_and(|file| self.spans_too_many_slots(file, raw.slot))
+            {
+                self.close(current.take().expect("checked above"))?;
+            }
+            let file = match current.as_mut() {
+                Some(file) => file,
+                None => current.insert(self.open(raw.slot)?),
+            };
+
+            file.writer.writer().write_all(line.as_bytes())?;
+            file.bytes += line.len() as u64;
+            file.first_slot = file.first_slot.min(raw.slot);
+            file.last_slot = file.last_slot.max(raw.slot);
+            written += 1;
+
+            if file.bytes >= self.rotate_size {
+                self.close(current.take().expect("just written"))?;
+            }
+        }
+
+        // Hand the lines to the OS so a crash loses as little as possible
+        if let Some(file) = current.as_mut() {
+            file.writer.writer().flush()?;
+        }
+        Ok(written)
+    }
+}
+
+#[async_trait]
+impl EventSink for FileSink {
+    async fn write(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<()> {
+        self.write_lines(std::iter::once((event, raw)))?;
+        Ok(())
+    }
+
+    async fn write_batch(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        self.write_lines(events.iter().map(|(event, raw)| (event, raw)))
+    }
+
+    /// Close the current file, so it is renamed after its slot range
+    async fn flush(&self) -> Result<()> {
+        let file = self.current.lock().unwrap().take();
+        if let Some(file) = file {
+            self.close(file)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FileSink {
+    fn drop(&mut self) {
+        let file = self.current.get_mut().map(Option::take).unwrap_or_default();
+        if let Some(file) = file {
+            if let Err(e) = self.close(file) {
+                error!("Failed to close archive file: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::event;
+
+    fn archive_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("soltrace-archive-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_file_sink_rotates_by_slot_and_size() {
+        let dir = archive_dir("rotate");
+        let sink = FileSink::new(&dir).unwrap().with_rotate_slots(Some(10));
+        let events: Vec<_> = [100, 105, 109, 110, 125]
+            .into_iter()
+            .map(|slot| event(slot, 0))
+            .collect();
+        assert_eq!(sink.write_batch(&events).await.unwrap(), 5);
+        assert_eq!(
+            file_names(&dir),
+            [
+                "events-100-109.jsonl",
+                "events-110-110.jsonl",
+                "events-125.jsonl.part"
+            ]
+        );
+
+        sink.flush().await.unwrap();
+        assert_eq!(
+            file_names(&dir),
+            [
+                "events-100-109.jsonl",
+                "events-110-110.jsonl",
+                "events-125-125.jsonl"
+            ]
+        );
+        let lines = std::fs::read_to_string(dir.join("events-100-109.jsonl")).unwrap();
+        let slots: Vec<_> = lines
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["slot"].clone())
+            .collect();
+        assert_eq!(slots, [100, 105, 109]);
+
+        // A one-byte budget closes the file after every event
+        let sized = FileSink::new(dir.join("sized"))
+            .unwrap()
+            .with_rotate_size(1);
+        sized.write_batch(&events[..2]).await.unwrap();
+        assert_eq!(
+            file_names(&dir.join("sized")),
+            ["events-100-100.jsonl", "events-105-105.jsonl"]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn test_file_sink_gzip() {
+        use std::io::Read;
+
+        let dir = archive_dir("gzip");
+        let sink = FileSink::new(&dir).unwrap().with_gzip(true);
+        let (event, raw) = event(7, 0);
+        sink.write(&event, &raw).await.unwrap();
+        drop(sink);
+
+        assert_eq!(file_names(&dir), ["events-7-7.jsonl.gz"]);
+        let mut contents = String::new();
+        flate2::read::GzDecoder::new(File::open(dir.join("events-7-7.jsonl.gz")).unwrap())
+            .read_to_string(&mut contents)
+            .unwrap();
+        let line: serde_json::Value = serde_json::from_str(contents.trim_end()).unwrap();
+        assert_eq!(line["event_name"], "tributary_Transfer");
+        assert_eq!(line["data"]["amount"], "7");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[tokio::test]
+    async fn test_file_sink_gzip_needs_feature() {
+        let dir = archive_dir("gzip-disabled");
+        let sink = FileSink::new(&dir).unwrap().with_gzip(true);
+        let (event, raw) = event(7, 0);
+        let err = sink.write(&event, &raw).await.unwrap_err();
+        assert!(err.to_string().contains("`gzip` feature"));
+        assert!(file_names(&dir).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1056 @@
+use crate::{
+    error::{Result, SoltraceError},
+    retry::{
+        concurrent_process_with_retry_stream, retry_with_rate_limit, CircuitBreaker, RateLimiter,
+    },
+    sink::{EventHook, EventSink, HookSink},
+    source::{TransactionSource, MAX_SIGNATURES_PER_PAGE},
+    types::Signature,
+    types::{DecodedEvent, RawEvent},
+    utils::{
+        decode_transaction_events, is_duplicate_error, is_missing_transaction_error,
+        process_transaction,
+    },
+    Database, EventDecoder, EventNameFilter, InstructionDecoder, Metrics,
+};
+use futures::StreamExt;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+/// Outcome of processing a batch of signatures
+#[derive(Debug, Clone, Default)]
+pub struct BackfillReport {
+    /// Signatures whose transactions were fetched and processed successfully
+    pub processed: Vec<Signature>,
+    /// Number of events stored
+    pub events: usize,
+    /// Number of signatures that failed after retries
+    pub failed: usize,
+    /// Number of signatures skipped because their transaction was pruned or not yet available
+    pub missing: usize,
+    /// Signatures left unprocessed because the deadline passed, in their original order
+    pub unprocessed: Vec<Signature>,
+}
+
+/// Receives progress updates while `Backfiller::process_signatures` runs
+pub trait ProgressReporter: Send + Sync {
+    /// Called before the first signature of a batch is processed
+    fn start(&self, _total: usize) {}
+
+    /// Called after each chunk of signatures completes
+    fn update(&self, processed: usize, total: usize, events: usize);
+
+    /// Called once every signature in the batch has been processed
+    fn finish(&self, _report: &BackfillReport) {}
+}
+
+/// Logs progress every 100 signatures (the default reporter)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogProgress;
+
+impl ProgressReporter for LogProgress {
+    fn update(&self, processed: usize, total: usize, events: usize) {
+        if processed.is_multiple_of(100) || processed >= total {
+            info!(
+                "Progress: {}/{} signatures processed, {} events found",
+                processed, total, events
+            );
+        }
+    }
+}
+
+/// Decoded `(event, raw, index)` triples waiting for a fast-load bulk insert
+type PendingEvents = Vec<(DecodedEvent, RawEvent)>;
+
+/// Historical indexer that fetches program transactions from a source and stores their events
+pub struct Backfiller {
+    source: Arc<dyn TransactionSource>,
+    event_decoder: Arc<EventDecoder>,
+    sink: Arc<dyn EventSink>,
+    event_filter: Arc<EventNameFilter>,
+    metrics: Arc<Metrics>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    progress: Option<Arc<dyn ProgressReporter>>,
+    store_raw: bool,
+    include_failed: bool,
+    fast_load: bool,
+    concurrency: usize,
+    max_retries: u32,
+    max_age: Option<Duration>,
+    deadline: Option<Instant>,
+    instructions: Option<(Arc<InstructionDecoder>, Arc<Database>)>,
+}
+
+impl Backfiller {
+    pub fn new(
+        source: Arc<dyn TransactionSource>,
+        event_decoder: Arc<EventDecoder>,
+        sink: Arc<dyn EventSink>,
+    ) -> Self {
+        Self {
+            source,
+            event_decoder,
+            sink,
+            event_filter: Arc::new(EventNameFilter::new()),
+            metrics: Arc::new(Metrics::new()),
+            rate_limiter: None,
+            circuit_breaker: None,
+            progress: Some(Arc::new(LogProgress)),
+            store_raw: false,
+            include_failed: false,
+            fast_load: false,
+            concurrency: 10,
+            max_retries: 3,
+            max_age: None,
+            deadline: None,
+            instructions: None,
+        }
+    }
+
+    /// Only store events allowed by this filter
+    pub fn with_event_filter(mut self, event_filter: EventNameFilter) -> Self {
+        self.event_filter = Arc::new(event_filter);
+        self
+    }
+
+    /// Record counters into a shared metrics instance
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Cap RPC requests per second across all tasks (0 = unlimited)
+    pub fn with_max_rps(mut self, max_rps: u32) -> Self {
+        self.rate_limiter = (max_rps > 0).then(|| Arc::new(RateLimiter::new(max_rps)));
+        self
+    }
+
+    /// Pause all transaction fetches while this breaker is open
+    pub fn with_circuit_breaker(mut self, circuit_breaker: Arc<CircuitBreaker>) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Report progress through this reporter (`None` = silent)
+    pub fn with_progress(mut self, progress: Option<Arc<dyn ProgressReporter>>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Store the pre-decode event bytes alongside decoded data
+    pub fn with_store_raw(mut self, store_raw: bool) -> Self {
+        self.store_raw = store_raw;
+        self
+    }
+
+    /// Keep events from failed transactions, marked `tx_failed`, instead of skipping them
+    pub fn with_include_failed(mut self, include_failed: bool) -> Self {
+        self.include_failed = include_failed;
+        self
+    }
+
+    /// Store each chunk of decoded events with one bulk insert (`COPY` on Postgres)
+    pub fn with_fast_load(mut self, fast_load: bool) -> Self {
+        self.fast_load = fast_load;
+        self
+    }
+
+    /// Also decode the program's instructions and store them in `db`'s instructions table
+    pub fn with_instructions(
+        mut self,
+        decoder: Arc<InstructionDecoder>,
+        db: Arc<Database>,
+    ) -> Self {
+        self.instructions = Some((decoder, db));
+        self
+    }
+
+    /// Run `hook` on each event after it has been written (see [`HookSink`])
+    pub fn with_on_event(mut self, hook: EventHook) -> Self {
+        self.sink = Arc::new(HookSink::new(self.sink, hook));
+        self
+    }
+
+    /// Number of transactions fetched concurrently
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Maximum retry attempts for failed RPC requests
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Stop fetching signatures once their block time is older than `max_age`
+    ///
+    /// Signatures are listed newest first, so this bounds a run to recent
+    /// history without knowing slots. Signatures without a block time are kept.
+    pub fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Stop starting new transaction fetches once `deadline` has passed
+    ///
+    /// Fetches already in flight finish, and the signatures never started are
+    /// returned in [`BackfillReport::unprocessed`] so the run can be resumed.
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Whether the deadline set with [`with_deadline`](Self::with_deadline) has passed
+    pub fn deadline_exceeded(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+    }
+
+    /// Count an RPC attempt made for `program_id` and its outcome
+    fn track_rpc<T>(&self, program_id: &str, result: Result<T>) -> Result<T> {
+        self.metrics
+            .record_program_rpc_call(program_id, result.is_err());
+        if let Err(e) = &result {
+            self.metrics.record_error(e);
+        }
+        result
+    }
+
+    /// Check that the address is an on-chain program (exists and is not owned by the System Program)
+    pub async fn is_program(&self, program_id: &Pubkey) -> Result<bool> {
+        let program = program_id.to_string();
+        let account = retry_with_rate_limit(
+            || async {
+                self.throttle().await;
+                self.track_rpc(&program, self.source.get_account(program_id).await)
+            },
+            self.max_retries,
+        )
+        .await
+        .map_err(|e| {
+            SoltraceError::SolanaClient(format!("Failed to fetch account {}: {}", program_id, e))
+        })?;
+
+        Ok(account.owner != solana_sdk_ids::system_program::ID)
+    }
+
+    /// Fetch up to `limit` signatures for a program, newest first
+    ///
+    /// `before` starts the search backwards from (excluding) that signature;
+    /// `until` stops once that signature is reached (excluding it). Requests are
+    /// paged [`MAX_SIGNATURES_PER_PAGE`] at a time until `limit` is reached or
+    /// the program has no older signatures, or (with
+    /// [`with_max_age`](Self::with_max_age)) they predate the cutoff.
+    pub async fn fetch_signatures(
+        &self,
+        program_id: &Pubkey,
+        before: Option<&Signature>,
+        until: Option<&Signature>,
+        limit: usize,
+    ) -> Result<Vec<Signature>> {
+        let program = program_id.to_string();
+        let mut signatures: Vec<Signature> = Vec::new();
+        let mut pages = 0;
+        let cutoff = self
+            .max_age
+            .map(|max_age| chrono::Utc::now().timestamp() - max_age.as_secs() as i64);
+        let mut too_old = 0;
+
+        while signatures.len() < limit {
+            let page_limit = (limit - signatures.len()).min(MAX_SIGNATURES_PER_PAGE);
+            // Continue from the oldest signature seen so far
+            let cursor = signatures.last().or(before);
+
+            let page = retry_with_rate_limit(
+                || async {
+                    self.throttle().await;
+                    self.track_rpc(
+                        &program,
+                        self.source
+                            .get_signatures_for_address(program_id, cursor, until, page_limit)
+                            .await,
+                    )
+                },
+                self.max_retries,
+            )
+            .await
+            .map_err(|e| {
+                SoltraceError::SolanaClient(format!(
+                    "Failed to get signatures for {}: {}",
+                    program_id, e
+                ))
+            })?;
+            pages += 1;
+
+            let exhausted = page.len() < page_limit;
+            let page_len = page.len();
+            let fetched = signatures.len();
+            signatures.extend(
+                page.into_iter()
+                    .take_while(|info| match (info.block_time, cutoff) {
+                        (Some(block_time), Some(cutoff)) => block_time >= cutoff,
+                        _ => true,
+                    })
+                    .map(|info| info.signature),
+            );
+            too_old = page_len - (signatures.len() - fetched);
+            if exhausted || too_old > 0 {
+                break;
+            }
+        }
+
+        if too_old > 0 {
+            info!(
+                "Skipped {} signature(s) for {} older than {:?}; stopped paginating",
+                too_old,
+                program_id,
+                self.max_age.unwrap_or_default()
+            );
+        }
+
+        info!(
+            "Fetched {} signature(s) for {} in {} page(s)",
+            signatures.len(),
+            program_id,
+            pages
+        );
+        Ok(signatures)
+    }
+
+    /// Fetch and process transactions for the given signatures concurrently
+    ///
+    /// Individual failures are logged and counted rather than aborting the batch.
+    /// Signatures whose transaction is missing are skipped without retrying.
+    /// Once the deadline passes no new fetches start, and the remaining
+    /// signatures are reported as unprocessed.
+    pub async fn process_signatures(
+        &self,
+        program_id: &str,
+        signatures: Vec<Signature>,
+    ) -> Result<BackfillReport> {
+        let total = signatures.len();
+        let mut processed_count: usize = 0;
+        let mut report = BackfillReport::default();
+        if let Some(progress) = &self.progress {
+            progress.start(total);
+        }
+
+        let fetch = |signature: Signature| async move {
+            let transaction =
+                match self.track_rpc(program_id, self.source.get_transaction(&signature).await) {
+                    Ok(transaction) => transaction,
+                    // A pruned or unknown transaction won't show up on retry
+                    Err(e) if is_missing_transaction_error(&e) => {
+                        return Ok((signature, None));
+                    }
+                    Err(e) => {
+                        return Err(SoltraceError::SolanaClient(format!(
+                            "Failed to fetch transaction {}: {}",
+                            signature, e
+                        )));
+                    }
+                };
+
+            // Processing errors aren't transient, so keep them out of the retry loop
+            let processed = self.process_transaction(program_id, transaction).await;
+            Ok((signature, Some(processed)))
+        };
+
+        // Keep `concurrency` fetches in flight, handling each result as it arrives;
+        // fast-load inserts and progress updates happen every `concurrency * 2` results.
+        // Signatures are taken in order, so when the deadline stops taking them the
+        // started ones are exactly the first `processed_count`
+        let mut results = std::pin::pin!(concurrent_process_with_retry_stream(
+            signatures
+                .iter()
+                .take_while(|_| !self.deadline_exceeded())
+                .cloned(),
+            self.concurrency,
+            self.max_retries,
+            self.rate_limiter.as_deref(),
+            self.circuit_breaker.as_deref(),
+            &fetch,
+        ));
+        let report_every = self.concurrency * 2;
+        let mut pending = PendingEvents::new();
+
+        while let Some(result) = results.next().await {
+            processed_count += 1;
+
+            match result {
+                Ok((signature, Some(Ok((event_count, events))))) => {
+                    report.events += event_count;
+                    report.processed.push(signature);
+                    pending.extend(events);
+                }
+                Ok((signature, None)) => {
+                    debug!("Transaction {} not found, skipping", signature);
+                    self.metrics.record_missing_transaction();
+                    report.missing += 1;
+                }
+                Ok((_, Some(Err(e @ SoltraceError::Transaction { kind, .. })))) => {
+                    // Transactions without logs are expected; anything else is a setup problem
+                    if kind.is_benign() {
+                        debug!("Skipping transaction: {}", e);
+                    } else {
+                        warn!("Failed to process transaction: {}", e);
+                        self.metrics.record_error(&e);
+                    }
+                    report.failed += 1;
+                }
+                Ok((signature, Some(Err(e)))) => {
+                    debug!("Failed to process signature {}: {}", signature, e);
+                    report.failed += 1;
+                }
+                Err(e) => {
+                    debug!("{}", e);
+                    report.failed += 1;
+                }
+            }
+
+            if processed_count.is_multiple_of(report_every) || processed_count == total {
+                if !pending.is_empty() {
+                    report.events += self
+                        .bulk_insert(program_id, std::mem::take(&mut pending))
+                        .await?;
+                }
+
+                if let Some(progress) = &self.progress {
+                    progress.update(processed_count, total, report.events);
+                }
+            }
+        }
+
+        if processed_count < total {
+            if !pending.is_empty() {
+                report.events += self.bulk_insert(program_id, pending).await?;
+            }
+            report.unprocessed = signatures[processed_count..].to_vec();
+            warn!(
+                "Deadline reached: {}/{} signatures processed, {} left",
+                processed_count,
+                total,
+                report.unprocessed.len()
+            );
+        }
+
+        if let Some(progress) = &self.progress {
+            progress.finish(&report);
+        }
+
+        Ok(report)
+    }
+
+    /// Store a transaction's events, or in fast-load mode decode them for the chunk's bulk insert
+    async fn process_transaction(
+        &self,
+        program_id: &str,
+        transaction: EncodedConfirmedTransactionWithStatusMeta,
+    ) -> Result<(usize, PendingEvents)> {
+        self.store_instructions(program_id, &transaction).await?;
+
+        if self.fast_load {
+            let events = decode_transaction_events(
+                &transaction,
+                program_id,
+                &self.event_decoder,
+                &self.event_filter,
+                &self.metrics,
+                self.store_raw,
+                self.include_failed,
+            )?;
+
+            return Ok((0, events));
+        }
+
+        let processed = process_transaction(
+            transaction,
+            program_id,
+            &self.event_decoder,
+            self.sink.as_ref(),
+            &self.event_filter,
+            &self.metrics,
+            self.store_raw,
+            self.include_failed,
+        )
+        .await?;
+
+        Ok((processed.len(), PendingEvents::new()))
+    }
+
+    /// Decode and store the transaction's instructions when `with_instructions` is set
+    async fn store_instructions(
+        &self,
+        program_id: &str,
+        transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    ) -> Result<()> {
+        let Some((decoder, db)) = &self.instructions else {
+            return Ok(());
+        };
+        let failed = transaction
+            .transaction
+            .meta
+            .as_ref()
+            .is_some_and(|meta| meta.err.is_some());
+        if failed && !self.include_failed {
+            return Ok(());
+        }
+
+        let instructions = decoder
+            .decode_transaction(transaction, program_id)
+            .map_err(|e| {
+                SoltraceError::EventDecode(format!("Failed to decode instructions: {}", e))
+            })?;
+        for (instruction, raw, index) in instructions {
+            match db.insert_instruction(&instruction, &raw, index).await {
+                Ok(_) => debug!(
+                    "Stored instruction {} ({})",
+                    instruction.name, raw.signature
+                ),
+                Err(e) if is_duplicate_error(&e) => {}
+                Err(e) => {
+                    return Err(SoltraceError::Database(format!(
+                        "Failed to store instruction {}: {}",
+                        instruction.name, e
+                    )))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Insert a chunk's decoded events at once, returning how many were new
+    async fn bulk_insert(&self, program_id: &str, events: PendingEvents) -> Result<usize> {
+        let insert_started = std::time::Instant::now();
+        let inserted = self.sink.write_batch(&events).await.map_err(|e| {
+            let e = SoltraceError::Database(format!(
+                "Failed to bulk insert {} events: {}",
+                events.len(),
+                e
+            ));
+            self.metrics.record_error(&e);
+            e
+        })? as usize;
+        self.metrics.record_insert_latency(insert_started.elapsed());
+
+        self.metrics.record_batch_insert(&events, inserted);
+        debug!(
+            "Bulk inserted {} of {} event(s) for {}",
+            inserted,
+            events.len(),
+            program_id
+        );
+
+        Ok(inserted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{source::MockSource, Database, IdlParser, ProgramPrefixConfig};
+    use base64::Engine;
+    use std::sync::atomic::Ordering;
+
+    const PROGRAM_ID: &str = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+
+    fn sig(byte: u8) -> Signature {
+        Signature::parse(&solana_sdk::signature::Signature::from([byte; 64]).to_string()).unwrap()
+    }
+
+    fn transfer_log(amount: u64) -> String {
+        let mut data = IdlParser::calculate_discriminator("Transfer").to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+        format!(
+            "Program data: {}",
+            base64::engine::general_purpose::STANDARD.encode(data)
+        )
+    }
+
+    /// Records (processed, total) pairs; `start` shows up as processed = 0
+    #[derive(Default)]
+    struct RecordingProgress {
+        updates: std::sync::Mutex<Vec<(usize, usize)>>,
+    }
+
+    impl ProgressReporter for RecordingProgress {
+        fn start(&self, total: usize) {
+            self.updates.lock().unwrap().push((0, total));
+        }
+
+        fn update(&self, processed: usize, total: usize, _events: usize) {
+            self.updates.lock().unwrap().push((processed, total));
+        }
+    }
+
+    fn transfer_decoder() -> Arc<EventDecoder> {
+        let mut idl_parser = IdlParser::new();
+        idl_parser
+            .load_from_str(&format!(
+                r#"{{
+                    "address": "{}",
+                    "name": "tributary",
+                    "events": [
+                        {{"name": "Transfer", "fields": [{{"name": "amount", "type": "u64"}}]}}
+                    ]
+                }}"#,
+                PROGRAM_ID
+            ))
+            .unwrap();
+        let mut prefix_config = ProgramPrefixConfig::new();
+        prefix_config.add_mapping(PROGRAM_ID, "tributary");
+        Arc::new(EventDecoder::new(idl_parser, prefix_config))
+    }
+
+    #[tokio::test]
+    async fn test_backfill_from_mock_source() {
+        let event_decoder = transfer_decoder();
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        let source = MockSource::new()
+            .with_program(program_id)
+            .with_logs(program_id, &sig(1), 10, vec![transfer_log(100)])
+            .with_logs(
+                program_id,
+                &sig(2),
+                11,
+                vec!["Program log: no event".into()],
+            )
+            .with_logs(program_id, &sig(3), 12, vec![transfer_log(300)]);
+
+        let progress = Arc::new(RecordingProgress::default());
+        let backfiller = Backfiller::new(Arc::new(source), event_decoder, db.clone())
+            .with_progress(Some(progress.clone()));
+        assert!(backfiller.is_program(&program_id).await.unwrap());
+
+        let signatures = backfiller
+            .fetch_signatures(&program_id, None, Some(&sig(1)), 10)
+            .await
+            .unwrap();
+        assert_eq!(signatures, vec![sig(3), sig(2)]);
+
+        let report = backfiller
+            .process_signatures(PROGRAM_ID, signatures)
+            .await
+            .unwrap();
+        assert_eq!(report.events, 1);
+        assert_eq!(report.processed.len(), 2);
+        assert_eq!(report.failed, 0);
+        assert_eq!(report.missing, 0);
+        assert_eq!(*progress.updates.lock().unwrap(), vec![(0, 2), (2, 2)]);
+
+        let metrics = backfiller.metrics();
+        // One account lookup, one signature page and two transaction fetches
+        assert_eq!(metrics.rpc_calls.load(Ordering::Relaxed), 4);
+        assert_eq!(metrics.rpc_failures.load(Ordering::Relaxed), 0);
+        assert_eq!(metrics.transactions_total.load(Ordering::Relaxed), 2);
+        assert_eq!(metrics.db_inserts.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.events_total.load(Ordering::Relaxed), 1);
+
+        let events = db.get_events_by_name("tributary_Transfer").await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].signature, sig(3));
+        assert_eq!(events[0].slot, 12);
+        assert_eq!(events[0].data["amount"], "300");
+    }
+
+    #[tokio::test]
+    async fn test_fast_load_matches_regular_inserts() {
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        let source = Arc::new(
+            MockSource::new()
+                .with_logs(program_id, &sig(1), 10, vec![transfer_log(100)])
+                .with_logs(
+                    program_id,
+                    &sig(2),
+                    11,
+                    vec![transfer_log(200), transfer_log(201)],
+                ),
+        );
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+
+        let report = Backfiller::new(source.clone(), transfer_decoder(), db.clone())
+            .with_fast_load(true)
+            .process_signatures(PROGRAM_ID, vec![sig(2), sig(1)])
+            .await
+            .unwrap();
+        assert_eq!(report.events, 3);
+        assert_eq!(report.processed.len(), 2);
+
+        // Event IDs match the regular path, so re-running it stores nothing new
+        Backfiller::new(source, transfer_decoder(), db.clone())
+            .process_signatures(PROGRAM_ID, vec![sig(2), sig(1)])
+            .await
+            .unwrap();
+        let events = db.get_events_by_name("tributary_Transfer").await.unwrap();
+        assert_eq!(events.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_multi_event_transaction_stores_every_event() {
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        let source = MockSource::new().with_logs(
+            program_id,
+            &sig(1),
+            10,
+            vec![transfer_log(100), transfer_log(100), transfer_log(200)],
+        );
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+
+        Backfiller::new(Arc::new(source), transfer_decoder(), db.clone())
+            .process_signatures(PROGRAM_ID, vec![sig(1)])
+            .await
+            .unwrap();
+
+        // Same signature, and even identical data, but each event is kept in log order
+        let events = db.get_events_by_slot_range(10, 10).await.unwrap();
+        let stored: Vec<_> = events
+            .iter()
+            .map(|e| (e.log_index, e.data["amount"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            stored,
+            vec![(Some(0), "100"), (Some(1), "100"), (Some(2), "200")]
+        );
+        assert!(events.iter().all(|e| e.signature == sig(1)));
+    }
+
+    #[tokio::test]
+    async fn test_two_programs_in_one_transaction_keep_their_events() {
+        const VAULT_ID: &str = "Vau1t11111111111111111111111111111111111111";
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        let vault_id: Pubkey = VAULT_ID.parse().unwrap();
+
+        let mut idl_parser = IdlParser::new();
+        for (address, name, event) in [
+            (PROGRAM_ID, "tributary", "Transfer"),
+            (VAULT_ID, "vault", "Deposit"),
+        ] {
+            idl_parser
+                .load_from_str(&format!(
+                    r#"{{
+                        "address": "{}",
+                        "name": "{}",
+                        "events": [
+                            {{"name": "{}", "fields": [{{"name": "amount", "type": "u64"}}]}}
+                        ]
+                    }}"#,
+                    address, name, event
+                ))
+                .unwrap();
+        }
+        let mut prefix_config = ProgramPrefixConfig::new();
+        prefix_config.add_mapping(PROGRAM_ID, "tributary");
+        prefix_config.add_mapping(VAULT_ID, "vault");
+        let decoder =
+            Arc::new(EventDecoder::new(idl_parser, prefix_config).with_decode_only_known(true));
+
+        let mut deposit = IdlParser::calculate_discriminator("Deposit").to_vec();
+        deposit.extend_from_slice(&7u64.to_le_bytes());
+        // Tributary emits an event, then CPIs into the vault, which emits its own
+        let logs = vec![
+            format!("Program {} invoke [1]", PROGRAM_ID),
+            transfer_log(100),
+            format!("Program {} invoke [2]", VAULT_ID),
+            format!(
+                "Program data: {}",
+                base64::engine::general_purpose::STANDARD.encode(deposit)
+            ),
+            format!("Program {} success", VAULT_ID),
+            format!("Program {} success", PROGRAM_ID),
+        ];
+        let source = Arc::new(
+            MockSource::new()
+                .with_logs(program_id, &sig(1), 10, logs.clone())
+                .with_logs(vault_id, &sig(1), 10, logs),
+        );
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+
+        // Each program is backfilled on its own, and re-running stores nothing new
+        for _ in 0..2 {
+            for program in [PROGRAM_ID, VAULT_ID] {
+                Backfiller::new(source.clone(), decoder.clone(), db.clone())
+                    .process_signatures(program, vec![sig(1)])
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let events = db.get_events_by_slot_range(10, 10).await.unwrap();
+        let stored: Vec<_> = events
+            .iter()
+            .map(|e| (e.event_name.as_str(), e.program_id.as_str(), e.log_index))
+            .collect();
+        assert_eq!(
+            stored,
+            vec![
+                ("tributary_Transfer", PROGRAM_ID, Some(1)),
+                ("vault_Deposit", VAULT_ID, Some(3)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_transactions_are_kept_only_when_included() {
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        // A rejected transaction that still logged an event before failing
+        let source = Arc::new(
+            MockSource::new()
+                .with_logs(program_id, &sig(1), 10, vec![transfer_log(100)])
+                .with_failure(&sig(1)),
+        );
+
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let report = Backfiller::new(source.clone(), transfer_decoder(), db.clone())
+            .process_signatures(PROGRAM_ID, vec![sig(1)])
+            .await
+            .unwrap();
+        assert_eq!(report.events, 0);
+        assert!(db
+            .get_events_by_slot_range(10, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let backfiller =
+            Backfiller::new(source, transfer_decoder(), db.clone()).with_include_failed(true);
+        let report = backfiller
+            .process_signatures(PROGRAM_ID, vec![sig(1)])
+            .await
+            .unwrap();
+        assert_eq!(report.events, 1);
+        assert_eq!(
+            backfiller
+                .metrics()
+                .transactions_failed
+                .load(Ordering::Relaxed),
+            1
+        );
+
+        let events = db.get_events_by_slot_range(10, 10).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].tx_failed);
+        assert_eq!(events[0].data["amount"], "100");
+    }
+
+    #[tokio::test]
+    async fn test_instructions_are_stored_when_enabled() {
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        let mut data = IdlParser::calculate_instruction_discriminator("pay").to_vec();
+        data.extend_from_slice(&250u64.to_le_bytes());
+        let source = MockSource::new()
+            .with_logs(program_id, &sig(1), 10, vec![transfer_log(250)])
+            .with_instruction(&sig(1), &[0; 8])
+            .with_instruction(&sig(1), &data);
+
+        let mut idl_parser = IdlParser::new();
+        idl_parser
+            .load_from_str(&format!(
+                r#"{{
+                    "address": "{}",
+                    "name": "tributary",
+                    "events": [],
+                    "instructions": [{{
+                        "name": "pay",
+                        "accounts": [{{"name": "program"}}],
+                        "args": [{{"name": "amount", "type": "u64"}}]
+                    }}]
+                }}"#,
+                PROGRAM_ID
+            ))
+            .unwrap();
+        let instruction_decoder = Arc::new(InstructionDecoder::new(&idl_parser).unwrap());
+
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let report = Backfiller::new(Arc::new(source), transfer_decoder(), db.clone())
+            .with_instructions(instruction_decoder, db.clone())
+            .process_signatures(PROGRAM_ID, vec![sig(1)])
+            .await
+            .unwrap();
+        assert_eq!(report.events, 1);
+
+        // The unknown first instruction is skipped
+        let instructions = db
+            .get_instructions_by_signature(sig(1).as_str())
+            .await
+            .unwrap();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].instruction_name, "pay");
+        assert_eq!(instructions[0].instruction_index, 1);
+        assert_eq!(instructions[0].slot, 10);
+        assert_eq!(instructions[0].data["amount"], "250");
+        assert_eq!(instructions[0].accounts["program"], PROGRAM_ID);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_signatures_paginates() {
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        // Oldest first, so `signatures` ends up newest first
+        let signatures: Vec<Signature> = (0..2300u16)
+            .map(|i| {
+                let mut bytes = [1u8; 64];
+                bytes[..2].copy_from_slice(&i.to_le_bytes());
+                Signature::parse(&solana_sdk::signature::Signature::from(bytes).to_string())
+                    .unwrap()
+            })
+            .collect();
+        let source = signatures
+            .iter()
+            .fold(MockSource::new(), |source, signature| {
+                source.with_missing_transaction(program_id, signature)
+            });
+        let newest_first: Vec<Signature> = signatures.into_iter().rev().collect();
+
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let backfiller = Backfiller::new(Arc::new(source), transfer_decoder(), db);
+
+        let fetched = backfiller
+            .fetch_signatures(&program_id, None, None, 2100)
+            .await
+            .unwrap();
+        assert_eq!(fetched, newest_first[..2100]);
+        assert_eq!(backfiller.metrics().rpc_calls.load(Ordering::Relaxed), 3);
+
+        // Stops at the end of history even when the limit is larger
+        let fetched = backfiller
+            .fetch_signatures(&program_id, Some(&newest_first[99]), None, 5000)
+            .await
+            .unwrap();
+        assert_eq!(fetched, newest_first[100..]);
+        assert_eq!(backfiller.metrics().rpc_calls.load(Ordering::Relaxed), 6);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_signatures_stops_at_max_age() {
+        const DAY: i64 = 86_400;
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        let now = chrono::Utc::now().timestamp();
+        // Oldest first: 10 and 8 days old, then 1 day and 1 hour old
+        let source = [(1, 10 * DAY), (2, 8 * DAY), (3, DAY), (4, 3600)]
+            .into_iter()
+            .fold(MockSource::new(), |source, (i, age)| {
+                source
+                    .with_logs(program_id, &sig(i), i as u64, vec![])
+                    .with_block_time(&sig(i), now - age)
+            });
+
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let backfiller = Backfiller::new(Arc::new(source), transfer_decoder(), db)
+            .with_max_age(Some(Duration::from_secs(7 * DAY as u64)));
+
+        let fetched = backfiller
+            .fetch_signatures(&program_id, None, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(fetched, vec![sig(4), sig(3)]);
+    }
+
+    #[tokio::test]
+    async fn test_missing_transactions_are_skipped_without_retry() {
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        let source = MockSource::new()
+            .with_logs(program_id, &sig(1), 10, vec![transfer_log(100)])
+            .with_missing_transaction(program_id, &sig(2));
+
+        let backfiller =
+            Backfiller::new(Arc::new(source), transfer_decoder(), db).with_max_retries(3);
+        let report = backfiller
+            .process_signatures(PROGRAM_ID, vec![sig(2), sig(1)])
+            .await
+            .unwrap();
+        assert_eq!(report.processed, vec![sig(1)]);
+        assert_eq!(report.missing, 1);
+        assert_eq!(report.failed, 0);
+
+        let metrics = backfiller.metrics();
+        // One fetch per signature: the missing one is not retried
+        assert_eq!(metrics.rpc_calls.load(Ordering::Relaxed), 2);
+        assert_eq!(metrics.transactions_missing.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_deadline_leaves_signatures_unprocessed() {
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        let source = Arc::new(
+            MockSource::new()
+                .with_logs(program_id, &sig(1), 10, vec![transfer_log(100)])
+                .with_logs(program_id, &sig(2), 11, vec![transfer_log(200)]),
+        );
+
+        let expired = Backfiller::new(source.clone(), transfer_decoder(), db.clone())
+            .with_deadline(Some(Instant::now()));
+        assert!(expired.deadline_exceeded());
+        let report = expired
+            .process_signatures(PROGRAM_ID, vec![sig(2), sig(1)])
+            .await
+            .unwrap();
+        assert!(report.processed.is_empty());
+        assert_eq!(report.unprocessed, vec![sig(2), sig(1)]);
+        assert_eq!(expired.metrics().rpc_calls.load(Ordering::Relaxed), 0);
+
+        let report = Backfiller::new(source, transfer_decoder(), db)
+            .with_deadline(Some(Instant::now() + Duration::from_secs(60)))
+            .process_signatures(PROGRAM_ID, report.unprocessed)
+            .await
+            .unwrap();
+        assert_eq!(report.events, 2);
+        assert!(report.unprocessed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_decode_only_known_skips_unknown_discriminators() {
+        let program_id: Pubkey = PROGRAM_ID.parse().unwrap();
+        let data_log = |data: Vec<u8>| {
+            format!(
+                "Program data: {}",
+                base64::engine::general_purpose::STANDARD.encode(data)
+            )
+        };
+        // A known event, an event missing from the IDL and a truncated known event
+        let mut unknown = IdlParser::calculate_discriminator("Mint").to_vec();
+        unknown.extend_from_slice(&[1, 2, 3]);
+        let mut truncated = IdlParser::calculate_discriminator("Transfer").to_vec();
+        truncated.extend_from_slice(&[1, 2]);
+        let logs = vec![transfer_log(5), data_log(unknown), data_log(truncated)];
+
+        for (decode_only_known, failures) in [(false, 2), (true, 1)] {
+            let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+            let source = MockSource::new().with_logs(program_id, &sig(1), 10, logs.clone());
+            let decoder = (*transfer_decoder())
+                .clone()
+                .with_decode_only_known(decode_only_known);
+
+            let backfiller = Backfiller::new(Arc::new(source), Arc::new(decoder), db.clone());
+            backfiller
+                .process_signatures(PROGRAM_ID, vec![sig(1)])
+                .await
+                .unwrap();
+            // Both Transfers are stored, the truncated one as a hex fallback
+            let stored = db.get_events_by_name("tributary_Transfer").await.unwrap();
+            assert_eq!(stored.len(), 2);
+
+            let metrics = backfiller.metrics();
+            assert_eq!(metrics.decode_failures.load(Ordering::Relaxed), failures);
+        }
+    }
+}
@@ -1,18 +1,64 @@
 use crate::error::{Result, SoltraceError};
 use std::sync::Arc;
 
-use super::DatabaseBackend;
+use super::{DatabaseBackend, DEFAULT_TABLE_NAME};
 
 /// Create a database backend based on the URL scheme
 pub async fn create_backend(database_url: &str) -> Result<Arc<dyn DatabaseBackend>> {
+    create_backend_with_table(database_url, DEFAULT_TABLE_NAME).await
+}
+
+/// Create a database backend that stores events in `table` (a collection for MongoDB)
+pub async fn create_backend_with_table(
+    database_url: &str,
+    table: &str,
+) -> Result<Arc<dyn DatabaseBackend>> {
+    create_backend_with_options(database_url, table, None).await
+}
+
+/// Like [`create_backend_with_table`], also choosing the MongoDB database
+///
+/// `mongo_db_name` overrides the database in a `mongodb://` URL path; SQL
+/// backends ignore it.
+pub async fn create_backend_with_options(
+    database_url: &str,
+    table: &str,
+    mongo_db_name: Option<&str>,
+) -> Result<Arc<dyn DatabaseBackend>> {
+    open_backend(database_url, table, mongo_db_name, true).await
+}
+
+/// Like [`create_backend_with_options`], skipping migrations unless `run_migrations`
+///
+/// For read replicas and consumers without DDL privileges; the schema must
+/// already have been created by a writer that ran them.
+pub async fn open_backend(
+    database_url: &str,
+    table: &str,
+    mongo_db_name: Option<&str>,
+    run_migrations: bool,
+) -> Result<Arc<dyn DatabaseBackend>> {
     if database_url.starts_with("sqlite:") {
-        let backend = super::sqlite::SqliteBackend::new(database_url).await?;
+        let backend =
+            super::sqlite::SqliteBackend::connect(database_url, table, run_migrations).await?;
         Ok(Arc::new(backend))
     } else if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
-        let backend = super::postgres::PostgresBackend::new(database_url).await?;
+        let backend = super::postgres::PostgresBackend::connect(
+            database_url,
+            table,
+            super::postgres::PostgresPoolOptions::default(),
+            run_migrations,
+        )
+        .await?;
         Ok(Arc::new(backend))
     } else if database_url.starts_with("mongodb://") || database_url.starts_with("mongodb+srv://") {
-        let backend = super::mongodb::MongoDbBackend::new(database_url).await?;
+        let backend = super::mongodb::MongoDbBackend::connect(
+            database_url,
+            mongo_db_name,
+            table,
+            run_migrations,
+        )
+        .await?;
         Ok(Arc::new(backend))
     } else {
         Err(SoltraceError::Database(format!(
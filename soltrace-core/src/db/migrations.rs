@@ -0,0 +1,71 @@
+/// A versioned schema change, recorded in `schema_migrations` once applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+}
+
+/// Every schema migration, in the order they are applied
+///
+/// Each step is idempotent (`IF NOT EXISTS`, or checking for the column first),
+/// so tables created before versioning existed are adopted by replaying them.
+/// Append new migrations here and implement them in each SQL backend.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create events table and indexes",
+    },
+    Migration {
+        version: 2,
+        description: "add program_id column and program/slot index",
+    },
+    Migration {
+        version: 3,
+        description: "add raw_data column",
+    },
+    Migration {
+        version: 4,
+        description: "add discriminator column and index",
+    },
+    Migration {
+        version: 5,
+        description: "add log_index column",
+    },
+    Migration {
+        version: 6,
+        description: "add tx_failed column",
+    },
+    Migration {
+        version: 7,
+        description: "create instructions table",
+    },
+    Migration {
+        version: 8,
+        description: "add data_compressed and compressed columns",
+    },
+    Migration {
+        version: 9,
+        description: "make signature/program_id/log_index unique",
+    },
+];
+
+/// Migrations not in `applied`, in order
+pub(crate) fn pending(applied: &[i64]) -> impl Iterator<Item = &'static Migration> + '_ {
+    MIGRATIONS
+        .iter()
+        .filter(move |migration| !applied.contains(&migration.version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_ordered_and_pending_skips_applied() {
+        assert!(MIGRATIONS.windows(2).all(|w| w[0].version < w[1].version));
+
+        let versions: Vec<_> = pending(&[1, 3]).map(|m| m.version).collect();
+        assert_eq!(versions, vec![2, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(pending(&[]).count(), MIGRATIONS.len());
+    }
+}
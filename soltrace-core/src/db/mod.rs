@@ -1,15 +1,55 @@
 use crate::{
-    error::Result,
-    types::{DecodedEvent, RawEvent, Slot},
+    error::{Result, SoltraceError},
+    types::{DecodedEvent, DecodedInstruction, EventDiscriminator, RawEvent, Signature, Slot},
 };
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use futures::stream::BoxStream;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
-pub fn generate_event_id(signature: &str, index: usize, event_type: &str) -> [u8; 32] {
+/// Table (or MongoDB collection) events are stored in unless configured otherwise
+pub const DEFAULT_TABLE_NAME: &str = "events";
+
+/// Most events a streaming list query returns when no limit is given
+pub const DEFAULT_STREAM_LIMIT: usize = 10_000;
+
+/// Name for an index on `table`; the default table keeps its unprefixed index names
+pub(crate) fn index_name(table: &str, index: &str) -> String {
+    if table == DEFAULT_TABLE_NAME {
+        format!("idx_{}", index)
+    } else {
+        format!("{}_idx_{}", table, index)
+    }
+}
+
+/// Table decoded instructions are stored in alongside `table`
+///
+/// The default `events` table keeps its instructions in `instructions`; any
+/// other table gets a `{table}_instructions` companion.
+pub fn instructions_table_name(table: &str) -> String {
+    if table == DEFAULT_TABLE_NAME {
+        "instructions".to_string()
+    } else {
+        format!("{}_instructions", table)
+    }
+}
+
+/// Primary key of an event (or instruction) row
+///
+/// Hashes the unique `(signature, program_id, index)` key together with the name,
+/// so two programs decoding the same log line never collide on the ID.
+pub fn generate_event_id(
+    signature: &str,
+    program_id: &str,
+    index: usize,
+    event_type: &str,
+) -> [u8; 32] {
     let mut hasher = Sha256::new();
-    hasher.update(format!("{}_{}_{}", signature, index, event_type));
+    hasher.update(format!(
+        "{}_{}_{}_{}",
+        signature, program_id, index, event_type
+    ));
     let result = hasher.finalize();
     let mut bytes = [0u8; 32];
     bytes.copy_from_slice(&result);
@@ -20,25 +60,306 @@ pub fn event_id_to_hex(id: &[u8; 32]) -> String {
     hex::encode(id)
 }
 
+/// Uppercase hex form of a discriminator, as stored in the `discriminator` column
+pub(crate) fn discriminator_to_hex(discriminator: &EventDiscriminator) -> String {
+    hex::encode_upper(discriminator)
+}
+
+/// Normalize a user-supplied discriminator (any case, optional `0x`) to its stored form
+pub(crate) fn parse_discriminator_hex(discriminator_hex: &str) -> Result<String> {
+    let digits = discriminator_hex.trim().trim_start_matches("0x");
+    let bytes = hex::decode(digits).map_err(|e| {
+        SoltraceError::Database(format!(
+            "Invalid discriminator '{}': {}",
+            discriminator_hex, e
+        ))
+    })?;
+    let discriminator: EventDiscriminator = bytes.try_into().map_err(|_| {
+        SoltraceError::Database(format!(
+            "Invalid discriminator '{}': expected 8 bytes",
+            discriminator_hex
+        ))
+    })?;
+    Ok(discriminator_to_hex(&discriminator))
+}
+
+/// Parse the `compress=` database URL option: `zstd` compresses `data`, `none` doesn't
+pub(crate) fn parse_compress_option(value: &str) -> Result<bool> {
+    match value {
+        "zstd" if cfg!(feature = "zstd") => Ok(true),
+        "zstd" => Err(SoltraceError::Database(
+            "compress=zstd needs soltrace built with the `zstd` feature".to_string(),
+        )),
+        "none" => Ok(false),
+        other => Err(SoltraceError::Database(format!(
+            "Unsupported compress option '{}': expected zstd or none",
+            other
+        ))),
+    }
+}
+
+/// An event's `data` as zstd-compressed JSON, for the `data_compressed` column
+#[cfg(feature = "zstd")]
+pub(crate) fn compress_data(data: &serde_json::Value) -> Result<Vec<u8>> {
+    Ok(zstd::encode_all(serde_json::to_vec(data)?.as_slice(), 0)?)
+}
+
+#[cfg(not(feature = "zstd"))]
+pub(crate) fn compress_data(_data: &serde_json::Value) -> Result<Vec<u8>> {
+    Err(zstd_disabled())
+}
+
+/// Read back [`compress_data`] output
+#[cfg(feature = "zstd")]
+pub(crate) fn decompress_data(bytes: &[u8]) -> Result<serde_json::Value> {
+    Ok(serde_json::from_slice(&zstd::decode_all(bytes)?)?)
+}
+
+#[cfg(not(feature = "zstd"))]
+pub(crate) fn decompress_data(_bytes: &[u8]) -> Result<serde_json::Value> {
+    Err(zstd_disabled())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_disabled() -> SoltraceError {
+    SoltraceError::Database(
+        "Event data is stored zstd-compressed, but soltrace was built without the `zstd` feature"
+            .to_string(),
+    )
+}
+
 /// Event record stored in the database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventRecord {
     pub id: String,
     pub slot: i64,
-    pub signature: String,
+    pub signature: Signature,
+    pub program_id: String,
     pub event_name: String,
     pub data: serde_json::Value,
+    /// Hex-encoded pre-decode event bytes (only when stored with `--store-raw`)
+    pub raw_data: Option<String>,
+    /// Uppercase hex discriminator the event matched (unset for rows stored before it was recorded)
+    #[serde(default)]
+    pub discriminator: Option<String>,
     pub timestamp: DateTime<Utc>,
+    /// Position among the transaction's events (unset for rows stored before it was recorded)
+    #[serde(default)]
+    pub log_index: Option<i64>,
+    /// Whether the emitting transaction failed (only stored with `--include-failed`)
+    #[serde(default)]
+    pub tx_failed: bool,
+}
+
+impl EventRecord {
+    /// Deserialize the decoded event data into a typed struct
+    ///
+    /// `u64`, `u128`, `i64` and `i128` values are stored as JSON strings so they keep
+    /// full precision; declare those fields as `String` or parse them with a
+    /// `#[serde(deserialize_with = ...)]` helper.
+    pub fn data_as<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(T::deserialize(&self.data)?)
+    }
+}
+
+/// Decoded instruction record stored in the database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstructionRecord {
+    pub id: String,
+    pub slot: i64,
+    pub signature: Signature,
+    pub program_id: String,
+    pub instruction_name: String,
+    pub data: serde_json::Value,
+    /// Account pubkeys by IDL account name
+    pub accounts: serde_json::Value,
+    /// Position among the transaction's instructions, inner instructions included
+    pub instruction_index: i64,
+    pub timestamp: DateTime<Utc>,
+    pub tx_failed: bool,
+}
+
+/// Filter for streaming events; unset fields match every event
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub program_id: Option<String>,
+    pub event_name: Option<String>,
+    pub start_slot: Option<Slot>,
+    pub end_slot: Option<Slot>,
+    /// Stop after this many events; applied by the database, not the stream
+    pub limit: Option<usize>,
+}
+
+/// Where [`DatabaseBackend::get_events_since`] resumes reading
+///
+/// Events are ordered by `(timestamp, signature, program_id, log_index)`. Block
+/// times have one-second resolution, so a page can end partway through the events
+/// of one timestamp. The cursor therefore records the last row read, not just its time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventCursor {
+    pub timestamp: DateTime<Utc>,
+    /// `(signature, program_id, log_index)` of the last row read at `timestamp`;
+    /// `None` skips every event at `timestamp`
+    pub after: Option<(String, String, i64)>,
+}
+
+impl EventCursor {
+    /// Start with the events newer than `timestamp`
+    pub fn after_time(timestamp: DateTime<Utc>) -> Self {
+        Self {
+            timestamp,
+            after: None,
+        }
+    }
+
+    /// Continue with the events after `record`
+    ///
+    /// Rows stored before `log_index` was recorded sort as position -1.
+    pub fn after_event(record: &EventRecord) -> Self {
+        Self {
+            timestamp: record.timestamp,
+            after: Some((
+                record.signature.to_string(),
+                record.program_id.clone(),
+                record.log_index.unwrap_or(-1),
+            )),
+        }
+    }
+}
+
+/// Event field [`DatabaseBackend::count_events_by`] groups on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventCountField {
+    ProgramId,
+    EventName,
+}
+
+impl EventCountField {
+    /// Column (or document field) name
+    pub fn column(&self) -> &'static str {
+        match self {
+            EventCountField::ProgramId => "program_id",
+            EventCountField::EventName => "event_name",
+        }
+    }
+}
+
+/// Number of events sharing one value of an [`EventCountField`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventCount {
+    pub name: String,
+    pub count: u64,
+}
+
+/// Lowest and highest slot and timestamp over the stored events; all `None` when there are none
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventBounds {
+    pub min_slot: Option<Slot>,
+    pub max_slot: Option<Slot>,
+    pub min_timestamp: Option<DateTime<Utc>>,
+    pub max_timestamp: Option<DateTime<Utc>>,
+}
+
+/// Summary of the stored events, as printed by `soltrace-backfill stats`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventStats {
+    pub total_events: u64,
+    /// Events per program, most first
+    pub events_per_program: Vec<EventCount>,
+    /// Events per (prefixed) event name, most first
+    pub events_per_event_name: Vec<EventCount>,
+    #[serde(flatten)]
+    pub bounds: EventBounds,
+    /// Bytes on disk, if the backend can tell
+    pub size_bytes: Option<u64>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match events from this program
+    pub fn with_program_id(mut self, program_id: &str) -> Self {
+        self.program_id = Some(program_id.to_string());
+        self
+    }
+
+    /// Only match events with this (prefixed) name
+    pub fn with_event_name(mut self, event_name: &str) -> Self {
+        self.event_name = Some(event_name.to_string());
+        self
+    }
+
+    /// Only match events within an inclusive slot range
+    pub fn with_slot_range(mut self, start_slot: Slot, end_slot: Slot) -> Self {
+        self.start_slot = Some(start_slot);
+        self.end_slot = Some(end_slot);
+        self
+    }
+
+    /// Only return the first `limit` matching events
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
 }
 
 /// Trait defining the database backend interface
 #[async_trait]
 pub trait DatabaseBackend: Send + Sync {
-    /// Run database migrations/schema setup
-    async fn run_migrations(&self) -> Result<()>;
+    /// Run database migrations/schema setup, returning the migrations this call applied
+    async fn run_migrations(&self) -> Result<Vec<Migration>>;
+
+    /// Migrations applied when this backend connected
+    fn applied_migrations(&self) -> Vec<Migration> {
+        Vec::new()
+    }
 
     /// Store a decoded event
-    async fn insert_event(&self, event: &DecodedEvent, raw: &RawEvent, index: usize) -> Result<String>;
+    ///
+    /// Fails with [`SoltraceError::Duplicate`] when an event with the same id
+    /// (signature, log position and name) is already stored.
+    async fn insert_event(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<String>;
+
+    /// Store many `(event, raw)` pairs, skipping events that already exist
+    ///
+    /// Returns the number of events inserted. Backends without a faster path
+    /// insert one event at a time, skipping [`SoltraceError::Duplicate`] errors.
+    async fn bulk_insert(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        let mut inserted = 0;
+        for (event, raw) in events {
+            match self.insert_event(event, raw).await {
+                Ok(_) => inserted += 1,
+                Err(e) if crate::utils::is_duplicate_error(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(inserted)
+    }
+
+    /// Store `(event, raw)` pairs in one database transaction: all or nothing
+    ///
+    /// Events that already exist are skipped; any other failure rolls back the
+    /// whole batch. Returns the number of events inserted.
+    async fn insert_events_atomic(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64>;
+
+    /// Store a decoded instruction at position `index` in its transaction
+    ///
+    /// Fails with [`SoltraceError::Duplicate`] when the transaction's instruction
+    /// at that position is already stored.
+    async fn insert_instruction(
+        &self,
+        instruction: &DecodedInstruction,
+        raw: &RawEvent,
+        index: usize,
+    ) -> Result<String>;
+
+    /// Get a transaction's stored instructions in execution order
+    async fn get_instructions_by_signature(
+        &self,
+        signature: &str,
+    ) -> Result<Vec<InstructionRecord>>;
 
     /// Get events by slot range
     async fn get_events_by_slot_range(
@@ -47,11 +368,72 @@ pub trait DatabaseBackend: Send + Sync {
         end_slot: Slot,
     ) -> Result<Vec<EventRecord>>;
 
+    /// Get events for one program within an inclusive slot range
+    async fn get_events_by_program_and_slot_range(
+        &self,
+        program_id: &str,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<EventRecord>>;
+
+    /// Get up to `limit` events after `cursor`, oldest first
+    ///
+    /// Pass [`EventCursor::after_event`] of the last returned event back in to tail the table.
+    async fn get_events_since(
+        &self,
+        cursor: &EventCursor,
+        limit: usize,
+    ) -> Result<Vec<EventRecord>>;
+
     /// Get events by event name
     async fn get_events_by_name(&self, event_name: &str) -> Result<Vec<EventRecord>>;
 
+    /// Get events by their 8-byte discriminator, given as hex (case-insensitive, optional `0x`)
+    async fn get_events_by_discriminator(
+        &self,
+        discriminator_hex: &str,
+    ) -> Result<Vec<EventRecord>>;
+
     /// Check if an event already exists (by signature)
     async fn event_exists(&self, signature: &str) -> Result<bool>;
+
+    /// Highest slot of any stored event for one program, `None` if it has none
+    async fn get_latest_slot(&self, program_id: &str) -> Result<Option<Slot>>;
+
+    /// Highest slot of any stored event across all programs, `None` if there are none
+    async fn get_global_latest_slot(&self) -> Result<Option<Slot>>;
+
+    /// The `limit` newest events, by slot descending with ties broken by id descending
+    async fn get_recent_events(&self, limit: usize) -> Result<Vec<EventRecord>>;
+
+    /// Reclaim space and refresh planner statistics, e.g. after deleting many events
+    async fn optimize(&self) -> Result<()>;
+
+    /// Round-trip a trivial query to check the database is reachable
+    async fn ping(&self) -> Result<()>;
+
+    /// Replace the decoded name and data of an existing event
+    async fn update_event_data(
+        &self,
+        id: &str,
+        event_name: &str,
+        data: &serde_json::Value,
+    ) -> Result<()>;
+
+    /// Stream events matching a filter in ascending slot order without buffering
+    fn stream_events(&self, filter: EventFilter) -> BoxStream<'_, Result<EventRecord>>;
+
+    /// Number of stored events
+    async fn count_events(&self) -> Result<u64>;
+
+    /// Number of stored events per distinct value of `field`, most first (ties by name)
+    async fn count_events_by(&self, field: EventCountField) -> Result<Vec<EventCount>>;
+
+    /// Lowest and highest slot and timestamp over the stored events
+    async fn event_bounds(&self) -> Result<EventBounds>;
+
+    /// Bytes the events take on disk, `None` if the backend can't tell
+    async fn storage_size(&self) -> Result<Option<u64>>;
 }
 
 /// Database wrapper that holds a dynamic backend
@@ -63,16 +445,88 @@ pub struct Database {
 impl Database {
     /// Create a new database instance by parsing the URL scheme
     pub async fn new(database_url: &str) -> Result<Self> {
-        let backend = crate::db::factory::create_backend(database_url).await?;
+        Self::with_table(database_url, DEFAULT_TABLE_NAME).await
+    }
+
+    /// Wrap an already constructed backend
+    pub fn from_backend(backend: std::sync::Arc<dyn DatabaseBackend>) -> Self {
+        Self { backend }
+    }
+
+    /// Create a database instance storing events in `table` instead of `events`
+    ///
+    /// Lets several deployments share one database; the name is validated
+    /// with [`crate::validation::validate_table_name`].
+    pub async fn with_table(database_url: &str, table: &str) -> Result<Self> {
+        Self::with_options(database_url, table, None).await
+    }
+
+    /// Like [`with_table`](Self::with_table), also choosing the MongoDB database
+    /// when the URL has no path (see [`create_backend_with_options`])
+    pub async fn with_options(
+        database_url: &str,
+        table: &str,
+        mongo_db_name: Option<&str>,
+    ) -> Result<Self> {
+        Self::open(database_url, table, mongo_db_name, true).await
+    }
+
+    /// Like [`with_options`](Self::with_options), running migrations only if `run_migrations`
+    ///
+    /// Pass `false` to connect to e.g. a read-only Postgres replica. The schema must
+    /// already exist: a writer (the indexer, or `soltrace-live init`) has to have run
+    /// the migrations first.
+    pub async fn open(
+        database_url: &str,
+        table: &str,
+        mongo_db_name: Option<&str>,
+        run_migrations: bool,
+    ) -> Result<Self> {
+        let backend =
+            crate::db::factory::open_backend(database_url, table, mongo_db_name, run_migrations)
+                .await?;
         Ok(Self { backend })
     }
 
-    pub async fn run_migrations(&self) -> Result<()> {
+    pub async fn run_migrations(&self) -> Result<Vec<Migration>> {
         self.backend.run_migrations().await
     }
 
-    pub async fn insert_event(&self, event: &DecodedEvent, raw: &RawEvent, index: usize) -> Result<String> {
-        self.backend.insert_event(event, raw, index).await
+    /// Migrations applied when the database was opened (empty if it was up to date)
+    pub fn applied_migrations(&self) -> Vec<Migration> {
+        self.backend.applied_migrations()
+    }
+
+    pub async fn insert_event(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<String> {
+        self.backend.insert_event(event, raw).await
+    }
+
+    /// Store a batch (e.g. one transaction's events) atomically, so a failure
+    /// part-way through leaves none of them stored
+    pub async fn insert_events_atomic(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        self.backend.insert_events_atomic(events).await
+    }
+
+    pub async fn bulk_insert(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        self.backend.bulk_insert(events).await
+    }
+
+    pub async fn insert_instruction(
+        &self,
+        instruction: &DecodedInstruction,
+        raw: &RawEvent,
+        index: usize,
+    ) -> Result<String> {
+        self.backend
+            .insert_instruction(instruction, raw, index)
+            .await
+    }
+
+    pub async fn get_instructions_by_signature(
+        &self,
+        signature: &str,
+    ) -> Result<Vec<InstructionRecord>> {
+        self.backend.get_instructions_by_signature(signature).await
     }
 
     pub async fn get_events_by_slot_range(
@@ -85,18 +539,131 @@ impl Database {
             .await
     }
 
+    pub async fn get_events_by_program_and_slot_range(
+        &self,
+        program_id: &str,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<EventRecord>> {
+        self.backend
+            .get_events_by_program_and_slot_range(program_id, start_slot, end_slot)
+            .await
+    }
+
+    pub async fn get_events_since(
+        &self,
+        cursor: &EventCursor,
+        limit: usize,
+    ) -> Result<Vec<EventRecord>> {
+        self.backend.get_events_since(cursor, limit).await
+    }
+
     pub async fn get_events_by_name(&self, event_name: &str) -> Result<Vec<EventRecord>> {
         self.backend.get_events_by_name(event_name).await
     }
 
+    pub async fn get_events_by_discriminator(
+        &self,
+        discriminator_hex: &str,
+    ) -> Result<Vec<EventRecord>> {
+        self.backend
+            .get_events_by_discriminator(discriminator_hex)
+            .await
+    }
+
     pub async fn event_exists(&self, signature: &str) -> Result<bool> {
         self.backend.event_exists(signature).await
     }
+
+    pub async fn get_latest_slot(&self, program_id: &str) -> Result<Option<Slot>> {
+        self.backend.get_latest_slot(program_id).await
+    }
+
+    pub async fn get_global_latest_slot(&self) -> Result<Option<Slot>> {
+        self.backend.get_global_latest_slot().await
+    }
+
+    /// The `limit` newest events, newest first (e.g. for a dashboard's front page)
+    pub async fn get_recent_events(&self, limit: usize) -> Result<Vec<EventRecord>> {
+        self.backend.get_recent_events(limit).await
+    }
+
+    pub async fn optimize(&self) -> Result<()> {
+        self.backend.optimize().await
+    }
+
+    /// Check the database is reachable (e.g. for liveness probes)
+    pub async fn ping(&self) -> Result<()> {
+        self.backend.ping().await
+    }
+
+    pub async fn update_event_data(
+        &self,
+        id: &str,
+        event_name: &str,
+        data: &serde_json::Value,
+    ) -> Result<()> {
+        self.backend.update_event_data(id, event_name, data).await
+    }
+
+    pub async fn count_events(&self) -> Result<u64> {
+        self.backend.count_events().await
+    }
+
+    pub async fn count_events_by(&self, field: EventCountField) -> Result<Vec<EventCount>> {
+        self.backend.count_events_by(field).await
+    }
+
+    pub async fn event_bounds(&self) -> Result<EventBounds> {
+        self.backend.event_bounds().await
+    }
+
+    pub async fn storage_size(&self) -> Result<Option<u64>> {
+        self.backend.storage_size().await
+    }
+
+    /// Totals, per-program and per-event-name counts, slot and time ranges, and size
+    pub async fn event_stats(&self) -> Result<EventStats> {
+        Ok(EventStats {
+            total_events: self.count_events().await?,
+            events_per_program: self.count_events_by(EventCountField::ProgramId).await?,
+            events_per_event_name: self.count_events_by(EventCountField::EventName).await?,
+            bounds: self.event_bounds().await?,
+            size_bytes: self.storage_size().await?,
+        })
+    }
+
+    /// Stream events matching a filter; prefer this over the collecting
+    /// queries for large result sets
+    pub fn stream_events(&self, filter: EventFilter) -> BoxStream<'_, Result<EventRecord>> {
+        self.backend.stream_events(filter)
+    }
+
+    /// Stream up to `limit` (default [`DEFAULT_STREAM_LIMIT`]) of a program's events,
+    /// in ascending slot order
+    ///
+    /// Rows come from a database cursor as the stream is polled, so an API handler
+    /// can send them as chunked JSON without holding the whole result set.
+    pub fn get_events_by_program_stream(
+        &self,
+        program_id: &str,
+        limit: Option<usize>,
+    ) -> BoxStream<'_, Result<EventRecord>> {
+        self.stream_events(
+            EventFilter::new()
+                .with_program_id(program_id)
+                .with_limit(limit.unwrap_or(DEFAULT_STREAM_LIMIT)),
+        )
+    }
 }
 
 pub mod factory;
+pub mod migrations;
 pub mod mongodb;
 pub mod postgres;
 pub mod sqlite;
 
-pub use factory::create_backend;
+pub use factory::{
+    create_backend, create_backend_with_options, create_backend_with_table, open_backend,
+};
+pub use migrations::{Migration, MIGRATIONS};
@@ -1,23 +1,62 @@
 use crate::{
-    db::{event_id_to_hex, generate_event_id, DatabaseBackend, EventRecord},
+    db::{
+        discriminator_to_hex, event_id_to_hex, generate_event_id, instructions_table_name,
+        parse_discriminator_hex, DatabaseBackend, EventBounds, EventCount, EventCountField,
+        EventCursor, EventFilter, EventRecord, InstructionRecord, Migration, DEFAULT_TABLE_NAME,
+    },
     error::{Result, SoltraceError},
-    types::{DecodedEvent, RawEvent, Slot},
+    types::{DecodedEvent, DecodedInstruction, RawEvent, Signature, Slot},
+    validation::{validate_mongo_db_name, validate_table_name},
 };
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use mongodb::{bson, bson::doc, options::IndexOptions, Client, Collection, IndexModel};
 use serde::{Deserialize, Serialize};
 
+/// A count or size from an aggregation or command result, whichever BSON number type it came back as
+fn bson_to_u64(value: Option<&bson::Bson>) -> Option<u64> {
+    match value? {
+        bson::Bson::Int32(n) => Some(*n as u64),
+        bson::Bson::Int64(n) => Some(*n as u64),
+        bson::Bson::Double(n) => Some(*n as u64),
+        _ => None,
+    }
+}
+
+/// A `$min`/`$max` timestamp; events store theirs as RFC 3339 strings
+fn bson_to_timestamp(value: Option<&bson::Bson>) -> Result<Option<DateTime<Utc>>> {
+    match value {
+        Some(bson::Bson::String(ts)) => DateTime::parse_from_rfc3339(ts)
+            .map(|ts| Some(ts.with_timezone(&Utc)))
+            .map_err(|e| SoltraceError::Database(format!("Invalid timestamp: {}", e))),
+        Some(bson::Bson::DateTime(ts)) => {
+            Ok(DateTime::from_timestamp_millis(ts.timestamp_millis()))
+        }
+        _ => Ok(None),
+    }
+}
+
 /// MongoDB document structure for events
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct EventDocument {
     #[serde(rename = "_id")]
     id: String,
     slot: i64,
-    signature: String,
+    signature: Signature,
+    #[serde(default)]
+    program_id: String,
     event_name: String,
     data: bson::Document,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raw_data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    discriminator: Option<String>,
     timestamp: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    log_index: Option<i64>,
+    #[serde(default)]
+    tx_failed: bool,
 }
 
 impl From<EventDocument> for EventRecord {
@@ -26,51 +65,157 @@ impl From<EventDocument> for EventRecord {
             id: doc.id,
             slot: doc.slot,
             signature: doc.signature,
+            program_id: doc.program_id,
             event_name: doc.event_name,
             data: bson::Bson::Document(doc.data).into(),
+            raw_data: doc.raw_data,
+            discriminator: doc.discriminator,
+            timestamp: doc.timestamp,
+            log_index: doc.log_index,
+            tx_failed: doc.tx_failed,
+        }
+    }
+}
+
+/// MongoDB document structure for decoded instructions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InstructionDocument {
+    #[serde(rename = "_id")]
+    id: String,
+    slot: i64,
+    signature: Signature,
+    program_id: String,
+    instruction_name: String,
+    data: bson::Document,
+    accounts: bson::Document,
+    instruction_index: i64,
+    timestamp: DateTime<Utc>,
+    tx_failed: bool,
+}
+
+impl From<InstructionDocument> for InstructionRecord {
+    fn from(doc: InstructionDocument) -> Self {
+        InstructionRecord {
+            id: doc.id,
+            slot: doc.slot,
+            signature: doc.signature,
+            program_id: doc.program_id,
+            instruction_name: doc.instruction_name,
+            data: bson::Bson::Document(doc.data).into(),
+            accounts: bson::Bson::Document(doc.accounts).into(),
+            instruction_index: doc.instruction_index,
             timestamp: doc.timestamp,
+            tx_failed: doc.tx_failed,
         }
     }
 }
 
+/// Build the document stored for an event
+fn event_document(event: &DecodedEvent, raw: &RawEvent) -> Result<EventDocument> {
+    let id_bytes = generate_event_id(
+        raw.signature.as_str(),
+        &raw.program_id.to_string(),
+        raw.log_index as usize,
+        &event.event_name,
+    );
+    let data = bson::to_document(&event.data).map_err(|e| {
+        SoltraceError::Database(format!("Failed to convert event data to BSON: {}", e))
+    })?;
+
+    Ok(EventDocument {
+        id: event_id_to_hex(&id_bytes),
+        slot: raw.slot as i64,
+        signature: raw.signature.clone(),
+        program_id: raw.program_id.to_string(),
+        event_name: event.event_name.clone(),
+        data,
+        raw_data: raw.raw_data.as_ref().map(hex::encode),
+        discriminator: Some(discriminator_to_hex(&event.discriminator)),
+        timestamp: raw.timestamp,
+        log_index: Some(raw.log_index as i64),
+        tx_failed: raw.tx_failed,
+    })
+}
+
 /// MongoDB database backend
 #[derive(Clone)]
 pub struct MongoDbBackend {
     collection: Collection<EventDocument>,
+    instructions: Collection<InstructionDocument>,
 }
 
 impl MongoDbBackend {
     pub async fn new(database_url: &str) -> Result<Self> {
-        tracing::info!("Connecting to MongoDB database");
+        Self::with_table(database_url, DEFAULT_TABLE_NAME).await
+    }
 
-        // Parse URL to extract database name
-        let parsed = url::Url::parse(database_url)
-            .map_err(|e| SoltraceError::Database(format!("Invalid MongoDB URL: {}", e)))?;
+    /// Connect, storing events in the `collection` collection instead of `events`
+    pub async fn with_table(database_url: &str, collection: &str) -> Result<Self> {
+        Self::with_database(database_url, None, collection).await
+    }
+
+    /// Connect to `database` (or the one in the URL path, or `soltrace`), storing
+    /// events in `collection`
+    ///
+    /// `mongodb+srv://` URLs often carry no path, so pass `database` on shared
+    /// clusters rather than relying on the fallback.
+    pub async fn with_database(
+        database_url: &str,
+        database: Option<&str>,
+        collection: &str,
+    ) -> Result<Self> {
+        Self::connect(database_url, database, collection, true).await
+    }
 
-        let db_name = parsed
-            .path_segments()
-            .and_then(|mut s| s.next())
-            .filter(|s| !s.is_empty())
-            .unwrap_or("soltrace");
+    /// Like [`with_database`](Self::with_database), creating indexes only if `run_migrations`
+    pub(crate) async fn connect(
+        database_url: &str,
+        database: Option<&str>,
+        collection: &str,
+        run_migrations: bool,
+    ) -> Result<Self> {
+        validate_table_name(collection)?;
+        let (db_name, source) = resolve_database_name(database_url, database)?;
+        tracing::info!(
+            "Connecting to MongoDB database '{}' ({}), collection '{}'",
+            db_name,
+            source,
+            collection
+        );
 
         let client = Client::with_uri_str(database_url)
             .await
             .map_err(|e| SoltraceError::Database(format!("Failed to connect to MongoDB: {}", e)))?;
 
-        let db = client.database(db_name);
-        let collection = db.collection::<EventDocument>("events");
+        let db = client.database(&db_name);
+        let instructions =
+            db.collection::<InstructionDocument>(&instructions_table_name(collection));
+        let collection = db.collection::<EventDocument>(collection);
 
-        let backend = Self { collection };
-        backend.run_migrations().await?;
+        let backend = Self {
+            collection,
+            instructions,
+        };
+        if run_migrations {
+            backend.run_migrations().await?;
+        }
 
         Ok(backend)
     }
 
     async fn create_indexes(&self) -> Result<()> {
-        // Signature unique index
+        self.drop_legacy_signature_indexes().await?;
+
+        // One document per program and log line in a transaction; documents stored
+        // before log_index existed are left out rather than colliding
         let signature_index = IndexModel::builder()
-            .keys(doc! { "signature": 1 })
-            .options(IndexOptions::builder().unique(true).build())
+            .keys(doc! { "signature": 1, "program_id": 1, "log_index": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .partial_filter_expression(doc! { "log_index": { "$exists": true } })
+                    .build(),
+            )
             .build();
 
         // Slot index
@@ -82,51 +227,263 @@ impl MongoDbBackend {
         // Timestamp index
         let timestamp_index = IndexModel::builder().keys(doc! { "timestamp": 1 }).build();
 
+        // Program + slot index for per-program slot range queries
+        let program_slot_index = IndexModel::builder()
+            .keys(doc! { "program_id": 1, "slot": 1 })
+            .build();
+
+        // Discriminator index for lookups when the event name is unknown
+        let discriminator_index = IndexModel::builder()
+            .keys(doc! { "discriminator": 1 })
+            .build();
+
         self.collection
             .create_indexes(vec![
                 signature_index,
                 slot_index,
                 event_name_index,
                 timestamp_index,
+                program_slot_index,
+                discriminator_index,
             ])
             .await
             .map_err(|e| SoltraceError::Database(format!("Failed to create indexes: {}", e)))?;
 
+        // One document per instruction position in a transaction
+        let instruction_index = IndexModel::builder()
+            .keys(doc! { "signature": 1, "instruction_index": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        let instruction_program_slot_index = IndexModel::builder()
+            .keys(doc! { "program_id": 1, "slot": 1 })
+            .build();
+
+        self.instructions
+            .create_indexes(vec![instruction_index, instruction_program_slot_index])
+            .await
+            .map_err(|e| {
+                SoltraceError::Database(format!("Failed to create instruction indexes: {}", e))
+            })?;
+
+        Ok(())
+    }
+
+    /// Drop the unique indexes older versions created: `signature` alone, which
+    /// allowed only one event per transaction, and `signature` + `log_index`,
+    /// which didn't tell programs apart
+    async fn drop_legacy_signature_indexes(&self) -> Result<()> {
+        use mongodb::error::ErrorKind;
+
+        for name in ["signature_1", "signature_1_log_index_1"] {
+            match self.collection.drop_index(name).await {
+                Ok(()) => tracing::info!("Dropped legacy unique index {}", name),
+                Err(e) => match e.kind.as_ref() {
+                    // NamespaceNotFound (26) or IndexNotFound (27): nothing to drop
+                    ErrorKind::Command(c) if c.code == 26 || c.code == 27 => {}
+                    _ => {
+                        return Err(SoltraceError::Database(format!(
+                            "Failed to drop legacy index {}: {}",
+                            name, e
+                        )))
+                    }
+                },
+            }
+        }
         Ok(())
     }
 }
 
+/// Database used when neither an override nor the URL path names one
+pub const DEFAULT_MONGO_DB_NAME: &str = "soltrace";
+
+/// Pick the database name and say where it came from: the override, the URL
+/// path, or [`DEFAULT_MONGO_DB_NAME`]
+fn resolve_database_name(
+    database_url: &str,
+    database: Option<&str>,
+) -> Result<(String, &'static str)> {
+    let parsed = url::Url::parse(database_url)
+        .map_err(|e| SoltraceError::Database(format!("Invalid MongoDB URL: {}", e)))?;
+
+    let from_path = parsed
+        .path_segments()
+        .and_then(|mut s| s.next())
+        .filter(|s| !s.is_empty());
+
+    let (name, source) = match (database, from_path) {
+        (Some(name), _) => (name, "--mongo-db-name"),
+        (None, Some(name)) => (name, "URL path"),
+        (None, None) => (DEFAULT_MONGO_DB_NAME, "default"),
+    };
+    validate_mongo_db_name(name)?;
+
+    Ok((name.to_string(), source))
+}
+
+/// Whether a write failed on a unique index (`E11000`)
+fn is_duplicate_key_error(error: &mongodb::error::Error) -> bool {
+    use mongodb::error::{ErrorKind, WriteFailure};
+
+    matches!(
+        error.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(write_error)) if write_error.code == 11000
+    )
+}
+
 #[async_trait]
 impl DatabaseBackend for MongoDbBackend {
-    async fn run_migrations(&self) -> Result<()> {
+    async fn run_migrations(&self) -> Result<Vec<Migration>> {
+        // Documents are schemaless, so only indexes need creating
         self.create_indexes().await?;
         tracing::info!("MongoDB migrations completed");
-        Ok(())
+        Ok(Vec::new())
     }
 
-    async fn insert_event(&self, event: &DecodedEvent, raw: &RawEvent, index: usize) -> Result<String> {
-        let id_bytes = generate_event_id(&raw.signature, index, &event.event_name);
-        let event_id = event_id_to_hex(&id_bytes);
+    async fn insert_event(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<String> {
+        let doc = event_document(event, raw)?;
+        let event_id = doc.id.clone();
 
-        let data_doc = bson::to_document(&event.data).map_err(|e| {
-            SoltraceError::Database(format!("Failed to convert event data to BSON: {}", e))
+        self.collection.insert_one(doc).await.map_err(|e| {
+            if is_duplicate_key_error(&e) {
+                SoltraceError::Duplicate(event_id.clone())
+            } else {
+                SoltraceError::Database(format!("Failed to insert event: {}", e))
+            }
         })?;
 
-        let doc = EventDocument {
-            id: event_id.clone(),
+        Ok(event_id)
+    }
+
+    /// Needs a replica set or sharded cluster, since standalone servers have no transactions
+    async fn insert_events_atomic(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        let db_error = |action: &str, e: mongodb::error::Error| {
+            SoltraceError::Database(format!("Failed to {}: {}", action, e))
+        };
+        let mut docs = Vec::with_capacity(events.len());
+        for (event, raw) in events {
+            let doc = event_document(event, raw)?;
+            if !docs.iter().any(|d: &EventDocument| d.id == doc.id) {
+                docs.push(doc);
+            }
+        }
+        if docs.is_empty() {
+            return Ok(0);
+        }
+
+        let mut session = self
+            .collection
+            .client()
+            .start_session()
+            .await
+            .map_err(|e| db_error("start session", e))?;
+        session
+            .start_transaction()
+            .await
+            .map_err(|e| db_error("start transaction", e))?;
+
+        // A duplicate key error would abort the transaction, so leave out stored events
+        let ids: Vec<&str> = docs.iter().map(|doc| doc.id.as_str()).collect();
+        let mut cursor = self
+            .collection
+            .clone_with_type::<bson::Document>()
+            .find(doc! { "_id": { "$in": ids } })
+            .projection(doc! { "_id": 1 })
+            .session(&mut session)
+            .await
+            .map_err(|e| db_error("find stored events", e))?;
+        let mut stored = Vec::new();
+        while let Some(found) = cursor.next(&mut session).await {
+            let found = found.map_err(|e| db_error("find stored events", e))?;
+            if let Ok(id) = found.get_str("_id") {
+                stored.push(id.to_string());
+            }
+        }
+        docs.retain(|doc| !stored.contains(&doc.id));
+
+        let inserted = docs.len() as u64;
+        if !docs.is_empty() {
+            // Dropping the session on error aborts the transaction
+            self.collection
+                .insert_many(docs)
+                .session(&mut session)
+                .await
+                .map_err(|e| db_error("insert events", e))?;
+        }
+        session
+            .commit_transaction()
+            .await
+            .map_err(|e| db_error("commit transaction", e))?;
+
+        Ok(inserted)
+    }
+
+    async fn insert_instruction(
+        &self,
+        instruction: &DecodedInstruction,
+        raw: &RawEvent,
+        index: usize,
+    ) -> Result<String> {
+        let id_bytes = generate_event_id(
+            raw.signature.as_str(),
+            &raw.program_id.to_string(),
+            index,
+            &instruction.name,
+        );
+        let instruction_id = event_id_to_hex(&id_bytes);
+
+        let to_document = |value: &serde_json::Value| {
+            bson::to_document(value).map_err(|e| {
+                SoltraceError::Database(format!("Failed to convert instruction to BSON: {}", e))
+            })
+        };
+        let doc = InstructionDocument {
+            id: instruction_id.clone(),
             slot: raw.slot as i64,
             signature: raw.signature.clone(),
-            event_name: event.event_name.clone(),
-            data: data_doc,
+            program_id: raw.program_id.to_string(),
+            instruction_name: instruction.name.clone(),
+            data: to_document(&instruction.data)?,
+            accounts: to_document(&instruction.accounts)?,
+            instruction_index: index as i64,
             timestamp: raw.timestamp,
+            tx_failed: raw.tx_failed,
         };
 
-        self.collection
-            .insert_one(doc)
+        self.instructions.insert_one(doc).await.map_err(|e| {
+            if is_duplicate_key_error(&e) {
+                SoltraceError::Duplicate(instruction_id.clone())
+            } else {
+                SoltraceError::Database(format!("Failed to insert instruction: {}", e))
+            }
+        })?;
+
+        Ok(instruction_id)
+    }
+
+    async fn get_instructions_by_signature(
+        &self,
+        signature: &str,
+    ) -> Result<Vec<InstructionRecord>> {
+        let mut cursor = self
+            .instructions
+            .find(doc! { "signature": signature })
+            .sort(doc! { "instruction_index": 1 })
             .await
-            .map_err(|e| SoltraceError::Database(format!("Failed to insert event: {}", e)))?;
+            .map_err(|e| SoltraceError::Database(format!("Failed to query instructions: {}", e)))?;
 
-        Ok(event_id)
+        let mut instructions = Vec::new();
+        while cursor
+            .advance()
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to advance cursor: {}", e)))?
+        {
+            let doc = cursor.deserialize_current().map_err(|e| {
+                SoltraceError::Database(format!("Failed to deserialize instruction: {}", e))
+            })?;
+            instructions.push(doc.into());
+        }
+
+        Ok(instructions)
     }
 
     async fn get_events_by_slot_range(
@@ -144,7 +501,97 @@ impl DatabaseBackend for MongoDbBackend {
         let mut cursor = self
             .collection
             .find(filter)
-            .sort(doc! { "slot": 1 })
+            .sort(doc! { "slot": 1, "signature": 1, "log_index": 1 })
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to query events: {}", e)))?;
+
+        let mut events = Vec::new();
+        while cursor
+            .advance()
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to advance cursor: {}", e)))?
+        {
+            let doc = cursor.deserialize_current().map_err(|e| {
+                SoltraceError::Database(format!("Failed to deserialize event: {}", e))
+            })?;
+            events.push(doc.into());
+        }
+
+        Ok(events)
+    }
+
+    async fn get_events_by_program_and_slot_range(
+        &self,
+        program_id: &str,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<EventRecord>> {
+        let filter = doc! {
+            "program_id": program_id,
+            "slot": {
+                "$gte": start_slot as i64,
+                "$lte": end_slot as i64
+            }
+        };
+
+        let mut cursor = self
+            .collection
+            .find(filter)
+            .sort(doc! { "slot": 1, "signature": 1, "log_index": 1 })
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to query events: {}", e)))?;
+
+        let mut events = Vec::new();
+        while cursor
+            .advance()
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to advance cursor: {}", e)))?
+        {
+            let doc = cursor.deserialize_current().map_err(|e| {
+                SoltraceError::Database(format!("Failed to deserialize event: {}", e))
+            })?;
+            events.push(doc.into());
+        }
+
+        Ok(events)
+    }
+
+    async fn get_events_since(
+        &self,
+        cursor: &EventCursor,
+        limit: usize,
+    ) -> Result<Vec<EventRecord>> {
+        // Compare against the same representation the documents were stored with
+        let timestamp = bson::to_bson(&cursor.timestamp).map_err(|e| {
+            SoltraceError::Database(format!("Failed to convert timestamp to BSON: {}", e))
+        })?;
+        let filter = match &cursor.after {
+            None => doc! { "timestamp": { "$gt": timestamp } },
+            // A missing log_index sorts lowest, so it never follows a resumed position
+            Some((signature, program_id, log_index)) => doc! {
+                "$or": [
+                    { "timestamp": { "$gt": timestamp.clone() } },
+                    { "timestamp": timestamp.clone(), "signature": { "$gt": signature } },
+                    {
+                        "timestamp": timestamp.clone(),
+                        "signature": signature,
+                        "program_id": { "$gt": program_id },
+                    },
+                    {
+                        "timestamp": timestamp,
+                        "signature": signature,
+                        "program_id": program_id,
+                        "log_index": { "$gt": log_index },
+                    },
+                ]
+            },
+        };
+
+        let mut cursor = self
+            .collection
+            .find(filter)
+            .sort(doc! { "timestamp": 1, "signature": 1, "program_id": 1, "log_index": 1 })
+            .limit(limit as i64)
             .await
             .map_err(|e| SoltraceError::Database(format!("Failed to query events: {}", e)))?;
 
@@ -169,7 +616,35 @@ impl DatabaseBackend for MongoDbBackend {
         let mut cursor = self
             .collection
             .find(filter)
-            .sort(doc! { "slot": -1 })
+            .sort(doc! { "slot": -1, "signature": -1, "log_index": -1 })
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to query events: {}", e)))?;
+
+        let mut events = Vec::new();
+        while cursor
+            .advance()
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to advance cursor: {}", e)))?
+        {
+            let doc = cursor.deserialize_current().map_err(|e| {
+                SoltraceError::Database(format!("Failed to deserialize event: {}", e))
+            })?;
+            events.push(doc.into());
+        }
+
+        Ok(events)
+    }
+
+    async fn get_events_by_discriminator(
+        &self,
+        discriminator_hex: &str,
+    ) -> Result<Vec<EventRecord>> {
+        let filter = doc! { "discriminator": parse_discriminator_hex(discriminator_hex)? };
+
+        let mut cursor = self
+            .collection
+            .find(filter)
+            .sort(doc! { "slot": -1, "signature": -1, "log_index": -1 })
             .await
             .map_err(|e| SoltraceError::Database(format!("Failed to query events: {}", e)))?;
 
@@ -199,4 +674,240 @@ impl DatabaseBackend for MongoDbBackend {
 
         Ok(count > 0)
     }
+
+    async fn get_latest_slot(&self, program_id: &str) -> Result<Option<Slot>> {
+        let latest = self
+            .collection
+            .find_one(doc! { "program_id": program_id })
+            .sort(doc! { "slot": -1 })
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to query events: {}", e)))?;
+
+        Ok(latest.map(|doc| doc.slot as Slot))
+    }
+
+    async fn get_global_latest_slot(&self) -> Result<Option<Slot>> {
+        let latest = self
+            .collection
+            .find_one(doc! {})
+            .sort(doc! { "slot": -1 })
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to query events: {}", e)))?;
+
+        Ok(latest.map(|doc| doc.slot as Slot))
+    }
+
+    async fn get_recent_events(&self, limit: usize) -> Result<Vec<EventRecord>> {
+        let mut cursor = self
+            .collection
+            .find(doc! {})
+            .sort(doc! { "slot": -1, "signature": -1, "log_index": -1 })
+            .limit(limit as i64)
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to query events: {}", e)))?;
+
+        let mut events = Vec::new();
+        while cursor
+            .advance()
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to advance cursor: {}", e)))?
+        {
+            let doc = cursor.deserialize_current().map_err(|e| {
+                SoltraceError::Database(format!("Failed to deserialize event: {}", e))
+            })?;
+            events.push(doc.into());
+        }
+
+        Ok(events)
+    }
+
+    /// Runs `compact`, which needs the `compact` privilege (unavailable on shared Atlas tiers)
+    async fn optimize(&self) -> Result<()> {
+        let namespace = self.collection.namespace();
+        self.collection
+            .client()
+            .database(&namespace.db)
+            .run_command(doc! { "compact": namespace.coll })
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to compact collection: {}", e)))?;
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let namespace = self.collection.namespace();
+        self.collection
+            .client()
+            .database(&namespace.db)
+            .run_command(doc! { "ping": 1 })
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to ping MongoDB: {}", e)))?;
+        Ok(())
+    }
+
+    async fn count_events(&self) -> Result<u64> {
+        self.collection
+            .count_documents(doc! {})
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to count events: {}", e)))
+    }
+
+    async fn count_events_by(&self, field: EventCountField) -> Result<Vec<EventCount>> {
+        let pipeline = vec![
+            doc! { "$group": { "_id": format!("${}", field.column()), "count": { "$sum": 1 } } },
+            doc! { "$sort": { "count": -1, "_id": 1 } },
+        ];
+        let mut cursor = self
+            .collection
+            .aggregate(pipeline)
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to count events: {}", e)))?;
+
+        let mut counts = Vec::new();
+        while let Some(group) = cursor
+            .try_next()
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to advance cursor: {}", e)))?
+        {
+            counts.push(EventCount {
+                name: group.get_str("_id").unwrap_or_default().to_string(),
+                count: bson_to_u64(group.get("count")).unwrap_or_default(),
+            });
+        }
+
+        Ok(counts)
+    }
+
+    async fn event_bounds(&self) -> Result<EventBounds> {
+        let pipeline = vec![doc! { "$group": {
+            "_id": null,
+            "min_slot": { "$min": "$slot" },
+            "max_slot": { "$max": "$slot" },
+            "min_timestamp": { "$min": "$timestamp" },
+            "max_timestamp": { "$max": "$timestamp" },
+        } }];
+        let bounds = self
+            .collection
+            .aggregate(pipeline)
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to query events: {}", e)))?
+            .try_next()
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to advance cursor: {}", e)))?;
+
+        let Some(bounds) = bounds else {
+            return Ok(EventBounds::default());
+        };
+        Ok(EventBounds {
+            min_slot: bson_to_u64(bounds.get("min_slot")),
+            max_slot: bson_to_u64(bounds.get("max_slot")),
+            min_timestamp: bson_to_timestamp(bounds.get("min_timestamp"))?,
+            max_timestamp: bson_to_timestamp(bounds.get("max_timestamp"))?,
+        })
+    }
+
+    /// `totalSize` from `collStats` (storage plus indexes), or `storageSize` on servers without it
+    async fn storage_size(&self) -> Result<Option<u64>> {
+        let namespace = self.collection.namespace();
+        let stats = self
+            .collection
+            .client()
+            .database(&namespace.db)
+            .run_command(doc! { "collStats": namespace.coll })
+            .await
+            .map_err(|e| {
+                SoltraceError::Database(format!("Failed to read collection stats: {}", e))
+            })?;
+
+        Ok(bson_to_u64(
+            stats.get("totalSize").or_else(|| stats.get("storageSize")),
+        ))
+    }
+
+    async fn update_event_data(
+        &self,
+        id: &str,
+        event_name: &str,
+        data: &serde_json::Value,
+    ) -> Result<()> {
+        let data_doc = bson::to_document(data).map_err(|e| {
+            SoltraceError::Database(format!("Failed to convert event data to BSON: {}", e))
+        })?;
+
+        self.collection
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": { "event_name": event_name, "data": data_doc } },
+            )
+            .await
+            .map_err(|e| SoltraceError::Database(format!("Failed to update event: {}", e)))?;
+
+        Ok(())
+    }
+
+    fn stream_events(&self, filter: EventFilter) -> BoxStream<'_, Result<EventRecord>> {
+        let mut query = doc! {};
+        if let Some(program_id) = filter.program_id {
+            query.insert("program_id", program_id);
+        }
+        if let Some(event_name) = filter.event_name {
+            query.insert("event_name", event_name);
+        }
+        let mut slot_range = doc! {};
+        if let Some(start_slot) = filter.start_slot {
+            slot_range.insert("$gte", start_slot as i64);
+        }
+        if let Some(end_slot) = filter.end_slot {
+            slot_range.insert("$lte", end_slot as i64);
+        }
+        if !slot_range.is_empty() {
+            query.insert("slot", slot_range);
+        }
+
+        let mut find = self
+            .collection
+            .find(query)
+            .sort(doc! { "slot": 1, "signature": 1, "log_index": 1 });
+        if let Some(limit) = filter.limit {
+            find = find.limit(limit as i64);
+        }
+
+        stream::once(async move {
+            find.await
+                .map_err(|e| SoltraceError::Database(format!("Failed to query events: {}", e)))
+        })
+        .map_ok(|cursor| {
+            cursor.map(|doc| {
+                doc.map(EventRecord::from).map_err(|e| {
+                    SoltraceError::Database(format!("Failed to deserialize event: {}", e))
+                })
+            })
+        })
+        .try_flatten()
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_database_name() {
+        let (name, source) = resolve_database_name(
+            "mongodb+srv://user:pw@cluster.example.net/?retryWrites=true",
+            None,
+        )
+        .unwrap();
+        assert_eq!((name.as_str(), source), (DEFAULT_MONGO_DB_NAME, "default"));
+
+        let (name, source) =
+            resolve_database_name("mongodb://localhost:27017/indexer", None).unwrap();
+        assert_eq!((name.as_str(), source), ("indexer", "URL path"));
+
+        let (name, _) =
+            resolve_database_name("mongodb://localhost:27017/indexer", Some("tenant_a")).unwrap();
+        assert_eq!(name, "tenant_a");
+
+        assert!(resolve_database_name("mongodb://localhost:27017", Some("bad.name")).is_err());
+    }
 }
@@ -1,21 +1,65 @@
 use crate::{
-    db::{event_id_to_hex, generate_event_id, DatabaseBackend, EventRecord},
-    error::Result,
-    types::{DecodedEvent, RawEvent, Slot},
+    db::{
+        compress_data, decompress_data, discriminator_to_hex, event_id_to_hex, generate_event_id,
+        index_name, instructions_table_name, migrations, parse_compress_option,
+        parse_discriminator_hex, DatabaseBackend, EventBounds, EventCount, EventCountField,
+        EventCursor, EventFilter, EventRecord, InstructionRecord, Migration, DEFAULT_TABLE_NAME,
+    },
+    error::{Result, SoltraceError},
+    types::{DecodedEvent, DecodedInstruction, RawEvent, Signature, Slot},
+    validation::validate_table_name,
 };
 use async_trait::async_trait;
-use chrono::DateTime;
+use chrono::{DateTime, Utc};
+use futures::stream::{BoxStream, StreamExt};
 use sqlx::Row;
 
 /// SQLite database backend
 #[derive(Clone)]
 pub struct SqliteBackend {
     pool: sqlx::sqlite::SqlitePool,
+    table: String,
+    /// `stream_events` query, kept here so the stream can borrow it
+    stream_query: String,
+    applied_migrations: Vec<Migration>,
+    /// Store `data` (and instruction `accounts`) as indented JSON
+    pretty_json: bool,
+    /// Store event `data` zstd-compressed in `data_compressed`
+    compress_data: bool,
 }
 
 impl SqliteBackend {
     pub async fn new(database_url: &str) -> Result<Self> {
+        Self::with_table(database_url, DEFAULT_TABLE_NAME).await
+    }
+
+    /// Connect, storing events in `table` instead of `events`
+    ///
+    /// The URL may end in `?json=pretty` to store JSON indented for reading in the
+    /// `sqlite3` CLI (see [`with_pretty_json`](Self::with_pretty_json)), and in
+    /// `?compress=zstd` to compress event data (see
+    /// [`with_compressed_data`](Self::with_compressed_data)); join both with `&`.
+    pub async fn with_table(database_url: &str, table: &str) -> Result<Self> {
+        Self::connect(database_url, table, true).await
+    }
+
+    /// Like [`with_table`](Self::with_table), leaving the schema alone unless `run_migrations`
+    pub(crate) async fn connect(
+        database_url: &str,
+        table: &str,
+        run_migrations: bool,
+    ) -> Result<Self> {
+        validate_table_name(table)?;
+
         let db_path = database_url.trim_start_matches("sqlite:");
+        let (db_path, query) = db_path.split_once('?').unwrap_or((db_path, ""));
+        let (pretty_json, compress_data) = Self::parse_url_options(query)?;
+        if Self::is_in_memory(db_path) {
+            let db = Self::in_memory_with_table(table, run_migrations).await?;
+            return Ok(db
+                .with_pretty_json(pretty_json)
+                .with_compressed_data(compress_data));
+        }
         tracing::info!("Database path: {}", db_path);
 
         if let Some(parent) = std::path::Path::new(db_path).parent() {
@@ -33,66 +77,516 @@ impl SqliteBackend {
 
         let pool = sqlx::sqlite::SqlitePool::connect_with(options).await?;
 
-        let db = Self { pool };
-        db.run_migrations().await?;
+        let mut db = Self::from_pool(pool, table)
+            .with_pretty_json(pretty_json)
+            .with_compressed_data(compress_data);
+        if run_migrations {
+            db.applied_migrations = db.run_migrations().await?;
+        }
+
+        Ok(db)
+    }
+
+    /// Store JSON columns indented (`true`) or compact (`false`, the default)
+    ///
+    /// Only changes how the text looks; reads parse either form.
+    pub fn with_pretty_json(mut self, pretty_json: bool) -> Self {
+        self.pretty_json = pretty_json;
+        self
+    }
+
+    /// Store event `data` as zstd-compressed JSON in `data_compressed` (`true`), or
+    /// as JSON text in `data` (`false`, the default)
+    ///
+    /// Compressed rows keep JSON `null` in `data`, so SQL filtering on the JSON
+    /// doesn't see their fields; reads decompress them transparently.
+    pub fn with_compressed_data(mut self, compress_data: bool) -> Self {
+        self.compress_data = compress_data;
+        self
+    }
+
+    /// Parse the URL options after `?` (`json=pretty|compact`, `compress=zstd|none`)
+    /// into `(pretty_json, compress_data)`
+    fn parse_url_options(query: &str) -> Result<(bool, bool)> {
+        let (mut pretty_json, mut compress_data) = (false, false);
+        for option in query.split('&').filter(|option| !option.is_empty()) {
+            match option.split_once('=') {
+                Some(("json", "pretty")) => pretty_json = true,
+                Some(("json", "compact")) => pretty_json = false,
+                Some(("compress", value)) => compress_data = parse_compress_option(value)?,
+                _ => {
+                    return Err(SoltraceError::Database(format!(
+                        "Unsupported SQLite URL option '{}': expected json=pretty, json=compact, compress=zstd or compress=none",
+                        option
+                    )))
+                }
+            }
+        }
+        Ok((pretty_json, compress_data))
+    }
+
+    /// Open a private in-memory database (`sqlite::memory:`), e.g. for tests
+    ///
+    /// Every connection to `:memory:` gets its own empty database, so the pool
+    /// holds exactly one connection and never recycles it.
+    pub async fn new_in_memory() -> Result<Self> {
+        Self::in_memory_with_table(DEFAULT_TABLE_NAME, true).await
+    }
+
+    async fn in_memory_with_table(table: &str, run_migrations: bool) -> Result<Self> {
+        tracing::info!("Connecting to in-memory database");
+        let options = sqlx::sqlite::SqliteConnectOptions::new().in_memory(true);
+
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
+
+        let mut db = Self::from_pool(pool, table);
+        if run_migrations {
+            db.applied_migrations = db.run_migrations().await?;
+        }
 
         Ok(db)
     }
 
+    fn from_pool(pool: sqlx::sqlite::SqlitePool, table: &str) -> Self {
+        let stream_query = format!(
+            r#"
+            SELECT id, slot, signature, program_id, event_name, data, data_compressed, compressed, raw_data, discriminator, timestamp, log_index, tx_failed FROM {}
+            WHERE (?1 IS NULL OR program_id = ?1)
+              AND (?2 IS NULL OR event_name = ?2)
+              AND (?3 IS NULL OR slot >= ?3)
+              AND (?4 IS NULL OR slot <= ?4)
+            ORDER BY slot ASC, signature, log_index
+            LIMIT COALESCE(?5, -1)
+        "#,
+            table
+        );
+
+        Self {
+            pool,
+            table: table.to_string(),
+            stream_query,
+            applied_migrations: Vec::new(),
+            pretty_json: false,
+            compress_data: false,
+        }
+    }
+
+    fn is_in_memory(db_path: &str) -> bool {
+        let db_path = db_path.trim_start_matches("//");
+        db_path == ":memory:" || db_path.starts_with(":memory:?")
+    }
+
     fn parse_timestamp(ts_str: &str) -> Result<chrono::DateTime<chrono::Utc>> {
         DateTime::parse_from_rfc3339(ts_str)
             .map(|dt| dt.into())
-            .map_err(|e| crate::error::SoltraceError::Database(format!("Invalid timestamp: {}", e)))
+            .map_err(|e| SoltraceError::Database(format!("Invalid timestamp: {}", e)))
+    }
+
+    fn row_to_event_record(row: sqlx::sqlite::SqliteRow) -> Result<EventRecord> {
+        let id_bytes: Vec<u8> = row.get("id");
+        Ok(EventRecord {
+            id: hex::encode(&id_bytes),
+            slot: row.get("slot"),
+            signature: Signature::parse(row.get::<&str, _>("signature"))?,
+            program_id: row.get("program_id"),
+            event_name: row.get("event_name"),
+            data: if row.get("compressed") {
+                decompress_data(row.get::<&[u8], _>("data_compressed"))?
+            } else {
+                serde_json::from_str(row.get::<String, _>("data").as_str())?
+            },
+            raw_data: row.get("raw_data"),
+            discriminator: row.get("discriminator"),
+            timestamp: Self::parse_timestamp(row.get::<String, _>("timestamp").as_str())?,
+            log_index: row.get("log_index"),
+            tx_failed: row.get("tx_failed"),
+        })
+    }
+
+    fn row_to_instruction_record(row: sqlx::sqlite::SqliteRow) -> Result<InstructionRecord> {
+        let id_bytes: Vec<u8> = row.get("id");
+        Ok(InstructionRecord {
+            id: hex::encode(&id_bytes),
+            slot: row.get("slot"),
+            signature: Signature::parse(row.get::<&str, _>("signature"))?,
+            program_id: row.get("program_id"),
+            instruction_name: row.get("instruction_name"),
+            data: serde_json::from_str(row.get::<String, _>("data").as_str())?,
+            accounts: serde_json::from_str(row.get::<String, _>("accounts").as_str())?,
+            instruction_index: row.get("instruction_index"),
+            timestamp: Self::parse_timestamp(row.get::<String, _>("timestamp").as_str())?,
+            tx_failed: row.get("tx_failed"),
+        })
+    }
+
+    /// `INSERT` of one event row, followed by `conflict` (e.g. `ON CONFLICT DO NOTHING`)
+    fn insert_event_sql(&self, conflict: &str) -> String {
+        format!(
+            r#"
+            INSERT INTO {} (id, slot, signature, program_id, event_name, data, raw_data, discriminator, timestamp, log_index, tx_failed, data_compressed, compressed)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+            {}
+        "#,
+            self.table, conflict
+        )
+    }
+
+    /// Serialize a JSON column, indented when `pretty_json` is set
+    fn json_text(&self, value: &serde_json::Value) -> Result<String> {
+        Ok(if self.pretty_json {
+            serde_json::to_string_pretty(value)?
+        } else {
+            serde_json::to_string(value)?
+        })
+    }
+
+    /// The `data`, `data_compressed` and `compressed` column values for event data
+    fn data_columns(&self, data: &serde_json::Value) -> Result<(String, Option<Vec<u8>>, bool)> {
+        if self.compress_data {
+            // JSON null keeps `data` NOT NULL without duplicating the payload
+            Ok(("null".to_string(), Some(compress_data(data)?), true))
+        } else {
+            Ok((self.json_text(data)?, None, false))
+        }
+    }
+
+    /// Bind an event's columns to an [`insert_event_sql`](Self::insert_event_sql) query
+    fn bind_event<'q>(
+        &self,
+        query: sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>>,
+        id_bytes: &'q [u8],
+        event: &'q DecodedEvent,
+        raw: &'q RawEvent,
+    ) -> Result<sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>>> {
+        let (data, data_compressed, compressed) = self.data_columns(&event.data)?;
+        Ok(query
+            .bind(id_bytes)
+            .bind(raw.slot as i64)
+            .bind(raw.signature.as_str())
+            .bind(raw.program_id.to_string())
+            .bind(&event.event_name)
+            .bind(data)
+            .bind(raw.raw_data.as_ref().map(hex::encode))
+            .bind(discriminator_to_hex(&event.discriminator))
+            .bind(raw.timestamp.to_rfc3339())
+            .bind(raw.log_index as i64)
+            .bind(raw.tx_failed)
+            .bind(data_compressed)
+            .bind(compressed))
+    }
+
+    /// Add a column to the events table if an older schema lacks it
+    async fn ensure_column(&self, column: &str, definition: &str) -> Result<()> {
+        let exists: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2")
+                .bind(&self.table)
+                .bind(column)
+                .fetch_one(&self.pool)
+                .await?;
+
+        if exists == 0 {
+            tracing::info!("Adding column '{}' to {} table", column, self.table);
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                self.table, column, definition
+            ))
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply one schema migration; every step must be safe to re-run
+    async fn apply_migration(&self, migration: &Migration) -> Result<()> {
+        let table = &self.table;
+        match migration.version {
+            1 => {
+                sqlx::query(&format!(
+                    r#"
+                    CREATE TABLE IF NOT EXISTS {table} (
+                        id BLOB PRIMARY KEY,
+                        slot INTEGER NOT NULL,
+                        signature TEXT NOT NULL,
+                        event_name TEXT NOT NULL,
+                        data TEXT NOT NULL,
+                        timestamp TEXT NOT NULL
+                    );
+
+                    CREATE INDEX IF NOT EXISTS {} ON {table}(slot);
+                    CREATE INDEX IF NOT EXISTS {} ON {table}(event_name);
+                    CREATE INDEX IF NOT EXISTS {} ON {table}(timestamp);
+                    CREATE INDEX IF NOT EXISTS {} ON {table}(signature);
+                "#,
+                    index_name(table, "slot"),
+                    index_name(table, "event_name"),
+                    index_name(table, "timestamp"),
+                    index_name(table, "signature"),
+                ))
+                .execute(&self.pool)
+                .await?;
+            }
+            2 => {
+                self.ensure_column("program_id", "TEXT NOT NULL DEFAULT ''")
+                    .await?;
+                sqlx::query(&format!(
+                    "CREATE INDEX IF NOT EXISTS {} ON {}(program_id, slot)",
+                    index_name(table, "program_slot"),
+                    table
+                ))
+                .execute(&self.pool)
+                .await?;
+            }
+            3 => self.ensure_column("raw_data", "TEXT").await?,
+            4 => {
+                self.ensure_column("discriminator", "TEXT").await?;
+                sqlx::query(&format!(
+                    "CREATE INDEX IF NOT EXISTS {} ON {}(discriminator)",
+                    index_name(table, "discriminator"),
+                    table
+                ))
+                .execute(&self.pool)
+                .await?;
+            }
+            5 => self.ensure_column("log_index", "INTEGER").await?,
+            6 => {
+                self.ensure_column("tx_failed", "INTEGER NOT NULL DEFAULT 0")
+                    .await?
+            }
+            7 => {
+                let instructions = instructions_table_name(table);
+                sqlx::query(&format!(
+                    r#"
+                    CREATE TABLE IF NOT EXISTS {instructions} (
+                        id BLOB PRIMARY KEY,
+                        slot INTEGER NOT NULL,
+                        signature TEXT NOT NULL,
+                        program_id TEXT NOT NULL,
+                        instruction_name TEXT NOT NULL,
+                        data TEXT NOT NULL,
+                        accounts TEXT NOT NULL,
+                        instruction_index INTEGER NOT NULL,
+                        timestamp TEXT NOT NULL,
+                        tx_failed INTEGER NOT NULL DEFAULT 0,
+                        UNIQUE (signature, instruction_index)
+                    );
+
+                    CREATE INDEX IF NOT EXISTS {} ON {instructions}(program_id, slot);
+                "#,
+                    index_name(&instructions, "program_slot"),
+                ))
+                .execute(&self.pool)
+                .await?;
+            }
+            8 => {
+                self.ensure_column("data_compressed", "BLOB").await?;
+                self.ensure_column("compressed", "INTEGER NOT NULL DEFAULT 0")
+                    .await?
+            }
+            // Rows stored before log_index existed keep it NULL, which never conflicts
+            9 => {
+                sqlx::query(&format!(
+                    "DROP INDEX IF EXISTS {}",
+                    index_name(table, "signature_log_index")
+                ))
+                .execute(&self.pool)
+                .await?;
+                sqlx::query(&format!(
+                    "CREATE UNIQUE INDEX IF NOT EXISTS {} ON {}(signature, program_id, log_index)",
+                    index_name(table, "signature_program_log_index"),
+                    table
+                ))
+                .execute(&self.pool)
+                .await?;
+            }
+            version => {
+                return Err(SoltraceError::Database(format!(
+                    "SQLite backend has no migration {}",
+                    version
+                )))
+            }
+        }
+
+        Ok(())
     }
 }
 
+/// Whether an insert hit the primary key or a unique index
+///
+/// SQLite reports these as extended result codes 1555 (`SQLITE_CONSTRAINT_PRIMARYKEY`)
+/// and 2067 (`SQLITE_CONSTRAINT_UNIQUE`).
+fn is_unique_violation(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "1555" || code == "2067")
+}
+
 #[async_trait]
 impl DatabaseBackend for SqliteBackend {
-    async fn run_migrations(&self) -> Result<()> {
+    async fn run_migrations(&self) -> Result<Vec<Migration>> {
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS events (
-                id BLOB PRIMARY KEY,
-                slot INTEGER NOT NULL,
-                signature TEXT NOT NULL,
-                event_name TEXT NOT NULL,
-                data TEXT NOT NULL,
-                timestamp TEXT NOT NULL
-            );
-
-            CREATE INDEX IF NOT EXISTS idx_slot ON events(slot);
-            CREATE INDEX IF NOT EXISTS idx_event_name ON events(event_name);
-            CREATE INDEX IF NOT EXISTS idx_timestamp ON events(timestamp);
-            CREATE INDEX IF NOT EXISTS idx_signature ON events(signature);
+            CREATE TABLE IF NOT EXISTS schema_migrations (
+                table_name TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                description TEXT NOT NULL,
+                applied_at TEXT NOT NULL,
+                PRIMARY KEY (table_name, version)
+            )
         "#,
         )
         .execute(&self.pool)
         .await?;
 
+        let applied: Vec<i64> =
+            sqlx::query_scalar("SELECT version FROM schema_migrations WHERE table_name = ?1")
+                .bind(&self.table)
+                .fetch_all(&self.pool)
+                .await?;
+
+        let mut newly_applied = Vec::new();
+        for migration in migrations::pending(&applied) {
+            self.apply_migration(migration).await?;
+            sqlx::query(
+                "INSERT OR IGNORE INTO schema_migrations (table_name, version, description, applied_at) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .bind(&self.table)
+            .bind(migration.version)
+            .bind(migration.description)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+            tracing::info!(
+                "Applied migration {} to {}: {}",
+                migration.version,
+                self.table,
+                migration.description
+            );
+            newly_applied.push(*migration);
+        }
+
         tracing::info!("SQLite migrations completed");
-        Ok(())
+        Ok(newly_applied)
     }
 
-    async fn insert_event(&self, event: &DecodedEvent, raw: &RawEvent, index: usize) -> Result<String> {
-        let id_bytes = generate_event_id(&raw.signature, index, &event.event_name);
+    fn applied_migrations(&self) -> Vec<Migration> {
+        self.applied_migrations.clone()
+    }
+
+    async fn insert_event(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<String> {
+        let id_bytes = generate_event_id(
+            raw.signature.as_str(),
+            &raw.program_id.to_string(),
+            raw.log_index as usize,
+            &event.event_name,
+        );
         let event_id = event_id_to_hex(&id_bytes);
 
-        sqlx::query(
+        let sql = self.insert_event_sql("");
+        self.bind_event(sqlx::query(&sql), &id_bytes, event, raw)?
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                if is_unique_violation(&e) {
+                    SoltraceError::Duplicate(event_id.clone())
+                } else {
+                    e.into()
+                }
+            })?;
+
+        Ok(event_id)
+    }
+
+    async fn insert_events_atomic(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        let sql = self.insert_event_sql("ON CONFLICT DO NOTHING");
+        let mut tx = self.pool.begin().await?;
+        let mut inserted = 0;
+        for (event, raw) in events {
+            let id_bytes = generate_event_id(
+                raw.signature.as_str(),
+                &raw.program_id.to_string(),
+                raw.log_index as usize,
+                &event.event_name,
+            );
+            // Returning early drops `tx`, which rolls the batch back
+            inserted += self
+                .bind_event(sqlx::query(&sql), &id_bytes, event, raw)?
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        tx.commit().await?;
+
+        Ok(inserted)
+    }
+
+    async fn insert_instruction(
+        &self,
+        instruction: &DecodedInstruction,
+        raw: &RawEvent,
+        index: usize,
+    ) -> Result<String> {
+        let id_bytes = generate_event_id(
+            raw.signature.as_str(),
+            &raw.program_id.to_string(),
+            index,
+            &instruction.name,
+        );
+        let instruction_id = event_id_to_hex(&id_bytes);
+
+        sqlx::query(&format!(
             r#"
-            INSERT OR IGNORE INTO events (id, slot, signature, event_name, data, timestamp)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            INSERT INTO {} (id, slot, signature, program_id, instruction_name, data, accounts, instruction_index, timestamp, tx_failed)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
         "#,
-        )
+            instructions_table_name(&self.table)
+        ))
         .bind(&id_bytes[..])
         .bind(raw.slot as i64)
-        .bind(&raw.signature)
-        .bind(&event.event_name)
-        .bind(serde_json::to_string(&event.data)?)
+        .bind(raw.signature.as_str())
+        .bind(raw.program_id.to_string())
+        .bind(&instruction.name)
+        .bind(self.json_text(&instruction.data)?)
+        .bind(self.json_text(&instruction.accounts)?)
+        .bind(index as i64)
         .bind(raw.timestamp.to_rfc3339())
+        .bind(raw.tx_failed)
         .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            if is_unique_violation(&e) {
+                SoltraceError::Duplicate(instruction_id.clone())
+            } else {
+                e.into()
+            }
+        })?;
+
+        Ok(instruction_id)
+    }
+
+    async fn get_instructions_by_signature(
+        &self,
+        signature: &str,
+    ) -> Result<Vec<InstructionRecord>> {
+        let rows = sqlx::query(&format!(
+            "SELECT id, slot, signature, program_id, instruction_name, data, accounts, instruction_index, timestamp, tx_failed FROM {} WHERE signature = ?1 ORDER BY instruction_index",
+            instructions_table_name(&self.table)
+        ))
+        .bind(signature)
+        .fetch_all(&self.pool)
         .await?;
 
-        Ok(event_id)
+        rows.into_iter()
+            .map(Self::row_to_instruction_record)
+            .collect()
     }
 
     async fn get_events_by_slot_range(
@@ -100,60 +594,1041 @@ impl DatabaseBackend for SqliteBackend {
         start_slot: Slot,
         end_slot: Slot,
     ) -> Result<Vec<EventRecord>> {
-        let rows = sqlx::query(
-            "SELECT id, slot, signature, event_name, data, timestamp FROM events WHERE slot >= ?1 AND slot <= ?2 ORDER BY slot ASC",
-        )
+        let rows = sqlx::query(&format!(
+            "SELECT id, slot, signature, program_id, event_name, data, data_compressed, compressed, raw_data, discriminator, timestamp, log_index, tx_failed FROM {} WHERE slot >= ?1 AND slot <= ?2 ORDER BY slot ASC, signature, log_index",
+            self.table
+        ))
         .bind(start_slot as i64)
         .bind(end_slot as i64)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut events = Vec::new();
-        for row in rows {
-            let id_bytes: Vec<u8> = row.get("id");
-            events.push(EventRecord {
-                id: hex::encode(&id_bytes),
-                slot: row.get("slot"),
-                signature: row.get("signature"),
-                event_name: row.get("event_name"),
-                data: serde_json::from_str(row.get::<String, _>("data").as_str())?,
-                timestamp: Self::parse_timestamp(row.get::<String, _>("timestamp").as_str())?,
-            });
-        }
+        rows.into_iter().map(Self::row_to_event_record).collect()
+    }
 
-        Ok(events)
+    async fn get_events_by_program_and_slot_range(
+        &self,
+        program_id: &str,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Vec<EventRecord>> {
+        let rows = sqlx::query(&format!(
+            "SELECT id, slot, signature, program_id, event_name, data, data_compressed, compressed, raw_data, discriminator, timestamp, log_index, tx_failed FROM {} WHERE program_id = ?1 AND slot >= ?2 AND slot <= ?3 ORDER BY slot ASC, signature, log_index",
+            self.table
+        ))
+        .bind(program_id)
+        .bind(start_slot as i64)
+        .bind(end_slot as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(Self::row_to_event_record).collect()
+    }
+
+    async fn get_events_since(
+        &self,
+        cursor: &EventCursor,
+        limit: usize,
+    ) -> Result<Vec<EventRecord>> {
+        // Timestamps are stored as UTC RFC 3339 text, which sorts chronologically.
+        // Without a row to resume after the row comparison is NULL, skipping the instant.
+        let after = cursor.after.as_ref();
+        let rows = sqlx::query(&format!(
+            "SELECT id, slot, signature, program_id, event_name, data, data_compressed, compressed, raw_data, discriminator, timestamp, log_index, tx_failed FROM {} WHERE timestamp > ?1 OR (timestamp = ?1 AND (signature, program_id, COALESCE(log_index, -1)) > (?2, ?3, ?4)) ORDER BY timestamp ASC, signature, program_id, COALESCE(log_index, -1) LIMIT ?5",
+            self.table
+        ))
+        .bind(cursor.timestamp.to_rfc3339())
+        .bind(after.map(|(signature, _, _)| signature.as_str()))
+        .bind(after.map(|(_, program_id, _)| program_id.as_str()))
+        .bind(after.map(|(_, _, log_index)| *log_index))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(Self::row_to_event_record).collect()
     }
 
     async fn get_events_by_name(&self, event_name: &str) -> Result<Vec<EventRecord>> {
-        let rows = sqlx::query(
-            "SELECT id, slot, signature, event_name, data, timestamp FROM events WHERE event_name = ?1 ORDER BY slot DESC",
-        )
+        let rows = sqlx::query(&format!(
+            "SELECT id, slot, signature, program_id, event_name, data, data_compressed, compressed, raw_data, discriminator, timestamp, log_index, tx_failed FROM {} WHERE event_name = ?1 ORDER BY slot DESC, signature DESC, log_index DESC",
+            self.table
+        ))
         .bind(event_name)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut events = Vec::new();
-        for row in rows {
-            let id_bytes: Vec<u8> = row.get("id");
-            events.push(EventRecord {
-                id: hex::encode(&id_bytes),
-                slot: row.get("slot"),
-                signature: row.get("signature"),
-                event_name: row.get("event_name"),
-                data: serde_json::from_str(row.get::<String, _>("data").as_str())?,
-                timestamp: Self::parse_timestamp(row.get::<String, _>("timestamp").as_str())?,
-            });
-        }
+        rows.into_iter().map(Self::row_to_event_record).collect()
+    }
 
-        Ok(events)
+    async fn get_events_by_discriminator(
+        &self,
+        discriminator_hex: &str,
+    ) -> Result<Vec<EventRecord>> {
+        let rows = sqlx::query(&format!(
+            "SELECT id, slot, signature, program_id, event_name, data, data_compressed, compressed, raw_data, discriminator, timestamp, log_index, tx_failed FROM {} WHERE discriminator = ?1 ORDER BY slot DESC, signature DESC, log_index DESC",
+            self.table
+        ))
+        .bind(parse_discriminator_hex(discriminator_hex)?)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(Self::row_to_event_record).collect()
     }
 
     async fn event_exists(&self, signature: &str) -> Result<bool> {
-        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM events WHERE signature = ?1")
-            .bind(signature)
+        let count: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {} WHERE signature = ?1",
+            self.table
+        ))
+        .bind(signature)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    async fn get_latest_slot(&self, program_id: &str) -> Result<Option<Slot>> {
+        let slot: Option<i64> = sqlx::query_scalar(&format!(
+            "SELECT MAX(slot) FROM {} WHERE program_id = ?1",
+            self.table
+        ))
+        .bind(program_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(slot.map(|slot| slot as Slot))
+    }
+
+    async fn get_global_latest_slot(&self) -> Result<Option<Slot>> {
+        let slot: Option<i64> =
+            sqlx::query_scalar(&format!("SELECT MAX(slot) FROM {}", self.table))
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(slot.map(|slot| slot as Slot))
+    }
+
+    async fn get_recent_events(&self, limit: usize) -> Result<Vec<EventRecord>> {
+        let rows = sqlx::query(&format!(
+            "SELECT id, slot, signature, program_id, event_name, data, data_compressed, compressed, raw_data, discriminator, timestamp, log_index, tx_failed FROM {} ORDER BY slot DESC, signature DESC, log_index DESC LIMIT ?1",
+            self.table
+        ))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(Self::row_to_event_record).collect()
+    }
+
+    /// `VACUUM` rebuilds the whole file (not just this table) so deleted pages are released
+    async fn optimize(&self) -> Result<()> {
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+        sqlx::query("PRAGMA optimize").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn count_events(&self) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", self.table))
             .fetch_one(&self.pool)
             .await?;
 
-        Ok(count > 0)
+        Ok(count as u64)
+    }
+
+    async fn count_events_by(&self, field: EventCountField) -> Result<Vec<EventCount>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(&format!(
+            "SELECT {column}, COUNT(*) AS count FROM {} GROUP BY {column} ORDER BY count DESC, {column}",
+            self.table,
+            column = field.column()
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(name, count)| EventCount {
+                name,
+                count: count as u64,
+            })
+            .collect())
+    }
+
+    async fn event_bounds(&self) -> Result<EventBounds> {
+        let row = sqlx::query(&format!(
+            "SELECT MIN(slot) AS min_slot, MAX(slot) AS max_slot, MIN(timestamp) AS min_timestamp, MAX(timestamp) AS max_timestamp FROM {}",
+            self.table
+        ))
+        .fetch_one(&self.pool)
+        .await?;
+        let timestamp = |column: &str| {
+            row.get::<Option<String>, _>(column)
+                .as_deref()
+                .map(Self::parse_timestamp)
+                .transpose()
+        };
+        let slot = |column: &str| row.get::<Option<i64>, _>(column).map(|slot| slot as Slot);
+
+        Ok(EventBounds {
+            min_slot: slot("min_slot"),
+            max_slot: slot("max_slot"),
+            min_timestamp: timestamp("min_timestamp")?,
+            max_timestamp: timestamp("max_timestamp")?,
+        })
+    }
+
+    /// Size of the whole database file, not just this table
+    async fn storage_size(&self) -> Result<Option<u64>> {
+        let bytes: i64 = sqlx::query_scalar(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(Some(bytes as u64))
+    }
+
+    async fn update_event_data(
+        &self,
+        id: &str,
+        event_name: &str,
+        data: &serde_json::Value,
+    ) -> Result<()> {
+        let id_bytes = hex::decode(id)
+            .map_err(|e| SoltraceError::Database(format!("Invalid event id '{}': {}", id, e)))?;
+        let (data, data_compressed, compressed) = self.data_columns(data)?;
+
+        sqlx::query(&format!(
+            "UPDATE {} SET event_name = ?1, data = ?2, data_compressed = ?3, compressed = ?4 WHERE id = ?5",
+            self.table
+        ))
+        .bind(event_name)
+        .bind(data)
+        .bind(data_compressed)
+        .bind(compressed)
+        .bind(&id_bytes[..])
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    fn stream_events(&self, filter: EventFilter) -> BoxStream<'_, Result<EventRecord>> {
+        sqlx::query(&self.stream_query)
+            .bind(filter.program_id)
+            .bind(filter.event_name)
+            .bind(filter.start_slot.map(|s| s as i64))
+            .bind(filter.end_slot.map(|s| s as i64))
+            .bind(filter.limit.map(|l| l as i64))
+            .fetch(&self.pool)
+            .map(|row| Self::row_to_event_record(row?))
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use futures::TryStreamExt;
+
+    fn signature() -> Signature {
+        Signature::parse(
+            "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_in_memory_url_detection() {
+        assert!(SqliteBackend::is_in_memory(":memory:"));
+        assert!(SqliteBackend::is_in_memory("//:memory:"));
+        assert!(!SqliteBackend::is_in_memory("./soltrace.db"));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_insert_and_read() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+
+        let event = DecodedEvent {
+            event_name: "tributary_PaymentRecord".to_string(),
+            data: serde_json::json!({"amount": "1000"}),
+            discriminator: [0; 8],
+        };
+        let raw = RawEvent {
+            slot: 42,
+            signature: signature(),
+            program_id: "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ"
+                .parse()
+                .unwrap(),
+            log: "Program data: AQID".to_string(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: Some(vec![1, 2, 3]),
+            tx_failed: false,
+        };
+        db.insert_event(&event, &raw).await.unwrap();
+
+        assert!(db.event_exists(signature().as_str()).await.unwrap());
+
+        let events = db
+            .get_events_by_name("tributary_PaymentRecord")
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].slot, 42);
+        assert_eq!(events[0].data["amount"], "1000");
+        assert_eq!(events[0].raw_data.as_deref(), Some("010203"));
+
+        let streamed: Vec<_> = db
+            .stream_events(EventFilter::new().with_slot_range(40, 50))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(streamed.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_insert_is_typed() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let event = DecodedEvent {
+            event_name: "tributary_PaymentRecord".to_string(),
+            data: serde_json::json!({}),
+            discriminator: [0; 8],
+        };
+        let raw = RawEvent {
+            slot: 42,
+            signature: signature(),
+            program_id: solana_sdk::pubkey::Pubkey::default(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+
+        let id = db.insert_event(&event, &raw).await.unwrap();
+        match db.insert_event(&event, &raw).await {
+            Err(SoltraceError::Duplicate(duplicate)) => assert_eq!(duplicate, id),
+            other => panic!("expected a duplicate error, got {:?}", other),
+        }
+
+        // Bulk inserts skip the duplicate and count only the new event
+        let next = RawEvent {
+            log_index: 1,
+            ..raw.clone()
+        };
+        let batch = vec![(event.clone(), raw), (event, next)];
+        assert_eq!(db.bulk_insert(&batch).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_insert_events_atomic_rolls_back() {
+        let backend = SqliteBackend::new_in_memory().await.unwrap();
+        // Make any event named "boom" fail mid-batch
+        sqlx::query(
+            "CREATE TRIGGER boom BEFORE INSERT ON events WHEN NEW.event_name = 'boom' \
+             BEGIN SELECT RAISE(ABORT, 'boom'); END",
+        )
+        .execute(&backend.pool)
+        .await
+        .unwrap();
+        let db = Database::from_backend(std::sync::Arc::new(backend));
+
+        let event = |name: &str| DecodedEvent {
+            event_name: name.to_string(),
+            data: serde_json::json!({}),
+            discriminator: [0; 8],
+        };
+        let raw = RawEvent {
+            slot: 9,
+            signature: signature(),
+            program_id: "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ"
+                .parse()
+                .unwrap(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+
+        let at = |log_index| RawEvent {
+            log_index,
+            ..raw.clone()
+        };
+
+        let failing = vec![(event("Deposit"), at(0)), (event("boom"), at(1))];
+        assert!(db.insert_events_atomic(&failing).await.is_err());
+        assert!(!db.event_exists(signature().as_str()).await.unwrap());
+
+        let batch = vec![(event("Deposit"), at(0)), (event("Withdraw"), at(1))];
+        assert_eq!(db.insert_events_atomic(&batch).await.unwrap(), 2);
+        // Stored events are skipped rather than failing the batch
+        assert_eq!(db.insert_events_atomic(&batch).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_pretty_json_url_option() {
+        assert!(SqliteBackend::new("sqlite::memory:?json=fancy")
+            .await
+            .is_err());
+        let backend = SqliteBackend::new("sqlite::memory:?json=pretty")
+            .await
+            .unwrap();
+
+        let event = DecodedEvent {
+            event_name: "tributary_Transfer".to_string(),
+            data: serde_json::json!({"amount": "5"}),
+            discriminator: [0; 8],
+        };
+        let raw = RawEvent {
+            slot: 3,
+            signature: signature(),
+            program_id: "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ"
+                .parse()
+                .unwrap(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+        backend.insert_event(&event, &raw).await.unwrap();
+
+        let text: String = sqlx::query_scalar("SELECT data FROM events")
+            .fetch_one(&backend.pool)
+            .await
+            .unwrap();
+        assert_eq!(text, "{\n  \"amount\": \"5\"\n}");
+        let stored = backend
+            .get_events_by_name("tributary_Transfer")
+            .await
+            .unwrap();
+        assert_eq!(stored[0].data, event.data);
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[tokio::test]
+    async fn test_compressed_data_needs_zstd_feature() {
+        let err = SqliteBackend::new("sqlite::memory:?compress=zstd")
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("`zstd` feature"));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[tokio::test]
+    async fn test_compressed_data_url_option() {
+        assert!(SqliteBackend::new("sqlite::memory:?compress=lz4")
+            .await
+            .is_err());
+        let backend = SqliteBackend::new("sqlite::memory:?json=pretty&compress=zstd")
+            .await
+            .unwrap();
+
+        let event = DecodedEvent {
+            event_name: "tributary_Transfer".to_string(),
+            data: serde_json::json!({"amount": "5", "memo": "x".repeat(1000)}),
+            discriminator: [0; 8],
+        };
+        let raw = RawEvent {
+            slot: 3,
+            signature: signature(),
+            program_id: "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ"
+                .parse()
+                .unwrap(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+        backend.insert_event(&event, &raw).await.unwrap();
+
+        let (text, compressed, bytes): (String, bool, Vec<u8>) =
+            sqlx::query_as("SELECT data, compressed, data_compressed FROM events")
+                .fetch_one(&backend.pool)
+                .await
+                .unwrap();
+        assert_eq!(text, "null");
+        assert!(compressed);
+        assert!(bytes.len() < 100);
+        let stored = backend
+            .get_events_by_name("tributary_Transfer")
+            .await
+            .unwrap();
+        assert_eq!(stored[0].data, event.data);
+
+        // Re-decoded data is compressed too
+        let data = serde_json::json!({"amount": "6"});
+        backend
+            .update_event_data(&stored[0].id, "tributary_Transfer", &data)
+            .await
+            .unwrap();
+        let stored: Vec<_> = backend
+            .stream_events(EventFilter::default())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(stored[0].as_ref().unwrap().data, data);
+    }
+
+    #[tokio::test]
+    async fn test_instructions_insert_and_read() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let instruction = DecodedInstruction {
+            name: "createPayment".to_string(),
+            data: serde_json::json!({"amount": "7"}),
+            accounts: serde_json::json!({"payer": "Payer111"}),
+        };
+        let raw = RawEvent {
+            slot: 42,
+            signature: signature(),
+            program_id: solana_sdk::pubkey::Pubkey::default(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+
+        db.insert_instruction(&instruction, &raw, 2).await.unwrap();
+        db.insert_instruction(&instruction, &raw, 0).await.unwrap();
+        assert!(matches!(
+            db.insert_instruction(&instruction, &raw, 0).await,
+            Err(SoltraceError::Duplicate(_))
+        ));
+
+        let stored = db
+            .get_instructions_by_signature(signature().as_str())
+            .await
+            .unwrap();
+        let positions: Vec<_> = stored.iter().map(|i| i.instruction_index).collect();
+        assert_eq!(positions, vec![0, 2]);
+        assert_eq!(stored[0].instruction_name, "createPayment");
+        assert_eq!(stored[0].data["amount"], "7");
+        assert_eq!(stored[0].accounts["payer"], "Payer111");
+        // Instructions live in their own table
+        assert!(!db.event_exists(signature().as_str()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_events_in_one_transaction_keep_log_order() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let raw = RawEvent {
+            slot: 42,
+            signature: signature(),
+            program_id: solana_sdk::pubkey::Pubkey::default(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+        let event = |name: &str| DecodedEvent {
+            event_name: name.to_string(),
+            data: serde_json::json!({}),
+            discriminator: [0; 8],
+        };
+
+        let second = RawEvent {
+            log_index: 1,
+            ..raw.clone()
+        };
+        db.insert_event(&event("tributary_Second"), &second)
+            .await
+            .unwrap();
+        db.insert_event(&event("tributary_First"), &raw)
+            .await
+            .unwrap();
+
+        let events = db.get_events_by_slot_range(42, 42).await.unwrap();
+        let order: Vec<_> = events
+            .iter()
+            .map(|e| (e.event_name.as_str(), e.log_index))
+            .collect();
+        assert_eq!(
+            order,
+            vec![("tributary_First", Some(0)), ("tributary_Second", Some(1))]
+        );
+
+        // One event per program and log position, whatever its name
+        assert!(matches!(
+            db.insert_event(&event("tributary_Other"), &raw).await,
+            Err(SoltraceError::Duplicate(_))
+        ));
+
+        // A second program sharing the prefix decodes the same line to the same name
+        let other_program = RawEvent {
+            program_id: solana_sdk::pubkey::Pubkey::new_unique(),
+            ..raw.clone()
+        };
+        db.insert_event(&event("tributary_First"), &other_program)
+            .await
+            .unwrap();
+        assert!(matches!(
+            db.insert_event(&event("tributary_First"), &other_program)
+                .await,
+            Err(SoltraceError::Duplicate(_))
+        ));
+        assert_eq!(db.get_events_by_slot_range(42, 42).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_events_by_program_and_slot_range() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let tributary = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+        let other = "11111111111111111111111111111111";
+
+        let event = DecodedEvent {
+            event_name: "tributary_PaymentRecord".to_string(),
+            data: serde_json::json!({}),
+            discriminator: [0; 8],
+        };
+        let placements = [(tributary, 10), (other, 11), (tributary, 12), (tributary, 30)];
+        for (index, (program_id, slot)) in placements.into_iter().enumerate() {
+            let raw = RawEvent {
+                slot,
+                signature: signature(),
+                program_id: program_id.parse().unwrap(),
+                log: String::new(),
+                log_index: index as u32,
+                inner_instruction_index: None,
+                timestamp: chrono::Utc::now(),
+                raw_data: None,
+                tx_failed: false,
+            };
+            db.insert_event(&event, &raw).await.unwrap();
+        }
+
+        let events = db
+            .get_events_by_program_and_slot_range(tributary, 10, 20)
+            .await
+            .unwrap();
+        let slots: Vec<_> = events.iter().map(|e| e.slot).collect();
+        assert_eq!(slots, vec![10, 12]);
+        assert!(events.iter().all(|e| e.program_id == tributary));
+
+        let streamed: Vec<EventRecord> = db
+            .get_events_by_program_stream(tributary, Some(2))
+            .try_collect()
+            .await
+            .unwrap();
+        let slots: Vec<_> = streamed.iter().map(|e| e.slot).collect();
+        assert_eq!(slots, vec![10, 12]);
+        let streamed: Vec<EventRecord> = db
+            .get_events_by_program_stream(tributary, None)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(streamed.len(), 3);
+
+        let limited: Vec<EventRecord> = db
+            .stream_events(EventFilter::new().with_limit(3))
+            .try_collect()
+            .await
+            .unwrap();
+        let slots: Vec<_> = limited.iter().map(|e| e.slot).collect();
+        assert_eq!(slots, vec![10, 11, 12]);
+    }
+
+    #[tokio::test]
+    async fn test_events_since() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let event = DecodedEvent {
+            event_name: "tributary_PaymentRecord".to_string(),
+            data: serde_json::json!({}),
+            discriminator: [0; 8],
+        };
+
+        let start = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        // Inserted out of order, one with sub-second precision, three sharing an instant
+        let offsets_ms = [3000, 0, 1500, 1000, 2000, 1000, 1000];
+        for (index, offset_ms) in offsets_ms.into_iter().enumerate() {
+            let raw = RawEvent {
+                slot: index as u64,
+                signature: signature(),
+                program_id: solana_sdk::pubkey::Pubkey::default(),
+                log: String::new(),
+                log_index: index as u32,
+                inner_instruction_index: None,
+                timestamp: start + chrono::Duration::milliseconds(offset_ms),
+                raw_data: None,
+                tx_failed: false,
+            };
+            db.insert_event(&event, &raw).await.unwrap();
+        }
+
+        let mut cursor = EventCursor::after_time(start);
+        let mut pages = Vec::new();
+        loop {
+            let events = db.get_events_since(&cursor, 2).await.unwrap();
+            let Some(last) = events.last() else {
+                break;
+            };
+            // Continue from the last event seen, even partway through an instant
+            cursor = EventCursor::after_event(last);
+            pages.push(events.iter().map(|e| e.slot).collect::<Vec<_>>());
+        }
+        assert_eq!(pages, vec![vec![3, 5], vec![6, 2], vec![4, 0]]);
+
+        // Only events strictly newer than a bare timestamp
+        let cursor = EventCursor::after_time(start + chrono::Duration::seconds(1));
+        let events = db.get_events_since(&cursor, 10).await.unwrap();
+        let slots: Vec<_> = events.iter().map(|e| e.slot).collect();
+        assert_eq!(slots, vec![2, 4, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_events_by_discriminator() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let discriminators = [[0xab; 8], [0x01; 8], [0xab; 8]];
+        for (index, discriminator) in discriminators.into_iter().enumerate() {
+            let event = DecodedEvent {
+                event_name: format!("Event{}", index),
+                data: serde_json::json!({}),
+                discriminator,
+            };
+            let raw = RawEvent {
+                slot: index as u64,
+                signature: signature(),
+                program_id: solana_sdk::pubkey::Pubkey::default(),
+                log: String::new(),
+                log_index: index as u32,
+                inner_instruction_index: None,
+                timestamp: chrono::Utc::now(),
+                raw_data: None,
+                tx_failed: false,
+            };
+            db.insert_event(&event, &raw).await.unwrap();
+        }
+
+        let events = db
+            .get_events_by_discriminator("0xabababababababab")
+            .await
+            .unwrap();
+        let slots: Vec<_> = events.iter().map(|e| e.slot).collect();
+        assert_eq!(slots, vec![2, 0]);
+        assert_eq!(events[0].discriminator.as_deref(), Some("ABABABABABABABAB"));
+
+        assert_eq!(
+            db.get_events_by_discriminator("0101010101010101")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(db.get_events_by_discriminator("abab").await.is_err());
+        assert!(db.get_events_by_discriminator("not hex").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_latest_slot_per_program() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let program = solana_sdk::pubkey::Pubkey::new_unique();
+        for (index, slot) in [7u64, 42, 9].into_iter().enumerate() {
+            let event = DecodedEvent {
+                event_name: "Event".to_string(),
+                data: serde_json::json!({}),
+                discriminator: [0; 8],
+            };
+            let raw = RawEvent {
+                slot,
+                signature: signature(),
+                program_id: program,
+                log: String::new(),
+                log_index: index as u32,
+                inner_instruction_index: None,
+                timestamp: chrono::Utc::now(),
+                raw_data: None,
+                tx_failed: false,
+            };
+            db.insert_event(&event, &raw).await.unwrap();
+        }
+
+        assert_eq!(
+            db.get_latest_slot(&program.to_string()).await.unwrap(),
+            Some(42)
+        );
+        assert_eq!(
+            db.get_latest_slot("11111111111111111111111111111111")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(db.get_global_latest_slot().await.unwrap(), Some(42));
+    }
+
+    #[tokio::test]
+    async fn test_event_stats() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let empty = db.event_stats().await.unwrap();
+        assert_eq!(empty.total_events, 0);
+        assert!(empty.events_per_program.is_empty());
+        assert_eq!(empty.bounds, EventBounds::default());
+        assert!(empty.size_bytes.unwrap() > 0);
+
+        let tributary = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+        let other = "11111111111111111111111111111111";
+        let start = "2024-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let placements = [
+            (tributary, "tributary_Deposit", 12),
+            (other, "other_Swap", 30),
+            (tributary, "tributary_Withdraw", 10),
+            (tributary, "tributary_Deposit", 20),
+        ];
+        for (index, (program_id, event_name, slot)) in placements.into_iter().enumerate() {
+            let event = DecodedEvent {
+                event_name: event_name.to_string(),
+                data: serde_json::json!({}),
+                discriminator: [0; 8],
+            };
+            let raw = RawEvent {
+                slot,
+                signature: signature(),
+                program_id: program_id.parse().unwrap(),
+                log: String::new(),
+                log_index: index as u32,
+                inner_instruction_index: None,
+                timestamp: start + chrono::Duration::seconds(slot as i64),
+                raw_data: None,
+                tx_failed: false,
+            };
+            db.insert_event(&event, &raw).await.unwrap();
+        }
+
+        let stats = db.event_stats().await.unwrap();
+        assert_eq!(stats.total_events, 4);
+        let count = |name: &str, count| EventCount {
+            name: name.to_string(),
+            count,
+        };
+        assert_eq!(
+            stats.events_per_program,
+            vec![count(tributary, 3), count(other, 1)]
+        );
+        assert_eq!(
+            stats.events_per_event_name,
+            vec![
+                count("tributary_Deposit", 2),
+                count("other_Swap", 1),
+                count("tributary_Withdraw", 1)
+            ]
+        );
+        assert_eq!(stats.bounds.min_slot, Some(10));
+        assert_eq!(stats.bounds.max_slot, Some(30));
+        assert_eq!(
+            stats.bounds.min_timestamp,
+            Some(start + chrono::Duration::seconds(10))
+        );
+        assert_eq!(
+            stats.bounds.max_timestamp,
+            Some(start + chrono::Duration::seconds(30))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_data_as_typed_struct() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Transfer {
+            amount: String,
+            bps: u16,
+            memo: Option<String>,
+        }
+
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let event = DecodedEvent {
+            event_name: "tributary_Transfer".to_string(),
+            data: serde_json::json!({ "amount": "18446744073709551615", "bps": 250, "memo": null }),
+            discriminator: [0; 8],
+        };
+        let raw = RawEvent {
+            slot: 1,
+            signature: signature(),
+            program_id: solana_sdk::pubkey::Pubkey::default(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+        db.insert_event(&event, &raw).await.unwrap();
+
+        let record = db
+            .get_events_by_name("tributary_Transfer")
+            .await
+            .unwrap()
+            .remove(0);
+        let transfer: Transfer = record.data_as().unwrap();
+        assert_eq!(
+            transfer,
+            Transfer {
+                amount: "18446744073709551615".to_string(),
+                bps: 250,
+                memo: None,
+            }
+        );
+        assert_eq!(transfer.amount.parse::<u64>().unwrap(), u64::MAX);
+        assert!(record.data_as::<Vec<u8>>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_optimize() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let event = DecodedEvent {
+            event_name: "Event".to_string(),
+            data: serde_json::json!({}),
+            discriminator: [0; 8],
+        };
+        let raw = RawEvent {
+            slot: 1,
+            signature: signature(),
+            program_id: solana_sdk::pubkey::Pubkey::default(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+        db.insert_event(&event, &raw).await.unwrap();
+
+        db.optimize().await.unwrap();
+        assert!(db.event_exists(raw.signature.as_str()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_tables_are_isolated() {
+        let path = std::env::temp_dir().join(format!("soltrace-tables-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite:{}", path.display());
+
+        let default = Database::new(&url).await.unwrap();
+        let tenant = Database::with_table(&url, "tenant_a_events").await.unwrap();
+        assert!(Database::with_table(&url, "events; DROP TABLE events")
+            .await
+            .is_err());
+
+        let event = DecodedEvent {
+            event_name: "tributary_PaymentRecord".to_string(),
+            data: serde_json::json!({}),
+            discriminator: [0; 8],
+        };
+        let raw = RawEvent {
+            slot: 7,
+            signature: signature(),
+            program_id: solana_sdk::pubkey::Pubkey::default(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+        tenant.insert_event(&event, &raw).await.unwrap();
+
+        assert!(tenant.event_exists(signature().as_str()).await.unwrap());
+        assert!(!default.event_exists(signature().as_str()).await.unwrap());
+        let streamed: Vec<_> = tenant
+            .stream_events(EventFilter::new())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(streamed.len(), 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_connect_without_migrations() {
+        let path =
+            std::env::temp_dir().join(format!("soltrace-no-migrate-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite:{}", path.display());
+
+        // Nothing creates the table, so reads fail instead of running DDL
+        let reader = Database::open(&url, DEFAULT_TABLE_NAME, None, false)
+            .await
+            .unwrap();
+        assert!(reader.applied_migrations().is_empty());
+        assert!(reader.get_global_latest_slot().await.is_err());
+
+        let writer = Database::new(&url).await.unwrap();
+        assert!(!writer.applied_migrations().is_empty());
+        assert_eq!(reader.get_global_latest_slot().await.unwrap(), None);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_migrations_upgrade_legacy_table() {
+        let path =
+            std::env::temp_dir().join(format!("soltrace-migrations-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite:{}", path.display());
+
+        // A table created before program_id, raw_data and versioning existed
+        let legacy = sqlx::sqlite::SqlitePool::connect_with(
+            sqlx::sqlite::SqliteConnectOptions::new()
+                .filename(&path)
+                .create_if_missing(true),
+        )
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TABLE events (id BLOB PRIMARY KEY, slot INTEGER NOT NULL, signature TEXT NOT NULL, event_name TEXT NOT NULL, data TEXT NOT NULL, timestamp TEXT NOT NULL)",
+        )
+        .execute(&legacy)
+        .await
+        .unwrap();
+        legacy.close().await;
+
+        let db = Database::new(&url).await.unwrap();
+        let versions: Vec<_> = db.applied_migrations().iter().map(|m| m.version).collect();
+        assert_eq!(versions, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        let raw = RawEvent {
+            slot: 1,
+            signature: signature(),
+            program_id: solana_sdk::pubkey::Pubkey::default(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: Some(vec![0xab]),
+            tx_failed: false,
+        };
+        let event = DecodedEvent {
+            event_name: "tributary_PaymentRecord".to_string(),
+            data: serde_json::json!({}),
+            discriminator: [0; 8],
+        };
+        db.insert_event(&event, &raw).await.unwrap();
+        let events: Vec<_> = db
+            .stream_events(EventFilter::default())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(events[0].raw_data.as_deref(), Some("ab"));
+
+        // Reopening finds everything applied
+        let db = Database::new(&url).await.unwrap();
+        assert!(db.applied_migrations().is_empty());
+        assert!(db.run_migrations().await.unwrap().is_empty());
+
+        let _ = std::fs::remove_file(&path);
     }
 }
@@ -1,3 +1,4 @@
+use crate::types::Slot;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, SoltraceError>;
@@ -13,6 +14,9 @@ pub enum SoltraceError {
     #[error("Database error: {0}")]
     Database(String),
 
+    #[error("Event already stored: {0}")]
+    Duplicate(String),
+
     #[error("SQLx error: {0}")]
     Sqlx(#[from] sqlx::Error),
 
@@ -28,9 +32,50 @@ pub enum SoltraceError {
     #[error("Event not found in IDL: {0}")]
     EventNotFound(String),
 
+    #[error("Instruction not found in IDL: {0}")]
+    InstructionNotFound(String),
+
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
     #[error("Discriminator mismatch")]
     DiscriminatorMismatch,
 
     #[error("Solana client error: {0}")]
     SolanaClient(String),
+
+    #[error("Webhook error: {0}")]
+    Webhook(String),
+
+    #[error("Transaction {} at slot {slot}: {kind}", .signature.as_deref().unwrap_or("<unknown>"))]
+    Transaction {
+        /// `None` when the signature itself couldn't be read
+        signature: Option<String>,
+        slot: Slot,
+        kind: TransactionErrorKind,
+    },
+}
+
+/// Why a transaction's events couldn't be read
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionErrorKind {
+    #[error("no metadata")]
+    NoMetadata,
+
+    #[error("no logs")]
+    NoLogs,
+
+    #[error("no signature")]
+    NoSignature,
+
+    #[error("not JSON-encoded; only JSON transactions are supported")]
+    UnsupportedEncoding,
+}
+
+impl TransactionErrorKind {
+    /// Whether some transactions are simply like this (no logs), as opposed to a
+    /// configuration problem such as fetching an unsupported encoding
+    pub fn is_benign(self) -> bool {
+        matches!(self, Self::NoLogs)
+    }
 }
@@ -1,14 +1,19 @@
 use crate::{
     error::{Result, SoltraceError},
     idl::IdlParser,
-    idl_event::IdlEventDecoder,
-    types::{DecodedEvent, IdlEventDefinition, ProgramPrefixConfig},
+    idl_event::{DecodeOptions, IdlEventDecoder},
+    types::{DecodedEvent, EventNameCase, IdlEventDefinition, ProgramPrefixConfig},
 };
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 
 #[derive(Clone)]
 pub struct EventDecoder {
     idl_parser: IdlParser,
     prefix_config: ProgramPrefixConfig,
+    name_case: EventNameCase,
+    decode_options: DecodeOptions,
+    decode_only_known: bool,
+    decode_accounts: bool,
 }
 
 impl EventDecoder {
@@ -16,54 +21,125 @@ impl EventDecoder {
         Self {
             idl_parser,
             prefix_config,
+            name_case: EventNameCase::default(),
+            decode_options: DecodeOptions::default(),
+            decode_only_known: false,
+            decode_accounts: false,
         }
     }
 
+    /// Convert decoded event names (prefix included) to this casing
+    pub fn with_name_case(mut self, name_case: EventNameCase) -> Self {
+        self.name_case = name_case;
+        self
+    }
+
+    /// Decode event fields with these options (e.g. lenient UTF-8 handling)
+    pub fn with_decode_options(mut self, decode_options: DecodeOptions) -> Self {
+        self.decode_options = decode_options;
+        self
+    }
+
+    /// Quietly skip data whose discriminator matches no loaded event
+    ///
+    /// Such data is usually another program's (or an unindexed event), so
+    /// [`is_ignored`](Self::is_ignored) lets callers drop it without counting a
+    /// decode failure.
+    pub fn with_decode_only_known(mut self, decode_only_known: bool) -> Self {
+        self.decode_only_known = decode_only_known;
+        self
+    }
+
+    /// Fall back to the IDL's account discriminators when no event matches
+    ///
+    /// Some programs log account snapshots as `Program data:`, prefixed with the
+    /// account's discriminator; these decode with the account's fields and name.
+    pub fn with_decode_accounts(mut self, decode_accounts: bool) -> Self {
+        self.decode_accounts = decode_accounts;
+        self
+    }
+
+    /// Whether a [`decode_event`](Self::decode_event) error should be dropped
+    /// silently rather than counted as a failure
+    pub fn is_ignored(&self, error: &SoltraceError) -> bool {
+        self.decode_only_known && matches!(error, SoltraceError::EventNotFound(_))
+    }
+
+    /// The IDLs this decoder was built from
+    pub fn idl_parser(&self) -> &IdlParser {
+        &self.idl_parser
+    }
+
     /// Decode an Anchor event from raw data bytes
     ///
     /// Anchor event format:
     /// - 8 bytes: discriminator (sha256("event:<name>")[..8])
     /// - Remaining bytes: borsh-encoded event data
+    ///
+    /// Programs configured with another [`DiscriminatorScheme`](crate::types::DiscriminatorScheme)
+    /// are matched on that scheme's discriminator, whatever its length. With
+    /// [`with_decode_accounts`](Self::with_decode_accounts), data matching no event
+    /// is tried against the IDL's accounts. Data matching neither fails with
+    /// [`SoltraceError::EventNotFound`].
     pub fn decode_event(
         &self,
         program_id: &str,
         signature: &str,
         data: &[u8],
     ) -> Result<DecodedEvent> {
-        if data.len() < 8 {
+        if data.is_empty() {
             return Err(SoltraceError::EventDecode(
-                "Event data too short (< 8 bytes)".to_string(),
+                "Event data is empty".to_string(),
             ));
         }
 
-        let discriminator: [u8; 8] = data[..8].try_into().unwrap();
-        let event_data = &data[8..];
-
         // Find event definition by discriminator
-        let event_def = self
+        let (event_def, discriminator_len) = self
             .idl_parser
-            .find_event_by_discriminator(program_id, &discriminator)
+            .find_event_for_data(program_id, data)
+            .or_else(|| {
+                self.decode_accounts
+                    .then(|| self.idl_parser.find_account_for_data(program_id, data))
+                    .flatten()
+            })
             .ok_or_else(|| {
-                SoltraceError::EventDecode(format!(
-                    "No event found with discriminator: {:02x?}",
-                    discriminator
+                SoltraceError::EventNotFound(format!(
+                    "no event with discriminator {:02x?}",
+                    &data[..data.len().min(8)]
                 ))
             })?;
 
+        let mut discriminator = [0u8; 8];
+        discriminator[..discriminator_len].copy_from_slice(&data[..discriminator_len]);
+        let event_data = &data[discriminator_len..];
+
         // Decode the event data using IDL-based decoder
         let decoded = self.decode_event_data(program_id, signature, &event_def, event_data)?;
 
-        // Prefix event name with program prefix
+        // Prefix event name with program prefix, then apply the configured casing
         let prefix = self.prefix_config.get_prefix(program_id);
         let prefixed_event_name = format!("{}_{}", prefix, event_def.name);
 
         Ok(DecodedEvent {
-            event_name: prefixed_event_name,
+            event_name: self.name_case.apply(&prefixed_event_name),
             data: decoded,
             discriminator,
         })
     }
 
+    /// Decode the base64 payload of a `Program data:` log line, e.g. copied from an explorer
+    pub fn decode_base64(
+        &self,
+        program_id: &str,
+        signature: &str,
+        b64: &str,
+    ) -> Result<DecodedEvent> {
+        let data = STANDARD
+            .decode(b64.trim())
+            .map_err(|e| SoltraceError::EventDecode(format!("Invalid base64 event data: {}", e)))?;
+        self.decode_event(program_id, signature, &data)
+    }
+
     /// Decode event data using IDL-based borsh deserialization
     fn decode_event_data(
         &self,
@@ -84,7 +160,7 @@ impl EventDecoder {
             .unwrap_or(&empty_types);
 
         // Use new IDL-based decoder
-        match IdlEventDecoder::decode(data, fields, types) {
+        match IdlEventDecoder::decode_with_options(data, fields, types, &self.decode_options) {
             Ok(decoded) => Ok(decoded),
             Err(e) => {
                 // Log detailed warning for decode failure
@@ -117,6 +193,175 @@ impl EventDecoder {
 mod tests {
     use super::*;
 
+    fn decoder_with_scheme(scheme: &str) -> EventDecoder {
+        let mut idl_parser = IdlParser::new();
+        idl_parser
+            .add_discriminator_schemes_from_string(&format!(
+                "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ:{}",
+                scheme
+            ))
+            .unwrap();
+        idl_parser
+            .load_from_str(
+                r#"{
+                    "address": "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ",
+                    "events": [
+                        {"name": "Deposit", "discriminator": [7, 1], "fields": [{"name": "amount", "type": "u8"}]},
+                        {"name": "Withdraw", "discriminator": [9, 1], "fields": [{"name": "amount", "type": "u8"}]}
+                    ]
+                }"#,
+            )
+            .unwrap();
+        let mut prefix_config = ProgramPrefixConfig::new();
+        prefix_config.add_mapping("TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ", "t");
+        EventDecoder::new(idl_parser, prefix_config)
+    }
+
+    #[test]
+    fn test_decode_with_custom_discriminator_schemes() {
+        const PROGRAM: &str = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+
+        // A one-byte tag followed by the payload
+        let event = decoder_with_scheme("custom:1")
+            .decode_event(PROGRAM, "sig", &[9, 42])
+            .unwrap();
+        assert_eq!(event.event_name, "t_Withdraw");
+        assert_eq!(event.data["amount"], 42);
+        assert_eq!(event.discriminator, [9, 0, 0, 0, 0, 0, 0, 0]);
+
+        // The full declared discriminator
+        let event = decoder_with_scheme("declared")
+            .decode_event(PROGRAM, "sig", &[7, 1, 5])
+            .unwrap();
+        assert_eq!(event.event_name, "t_Deposit");
+        assert_eq!(event.data["amount"], 5);
+
+        // Raw struct discriminators
+        let mut data = IdlParser::calculate_account_discriminator("Deposit").to_vec();
+        data.push(3);
+        let event = decoder_with_scheme("anchor-account")
+            .decode_event(PROGRAM, "sig", &data)
+            .unwrap();
+        assert_eq!(event.event_name, "t_Deposit");
+        assert_eq!(event.data["amount"], 3);
+
+        // The Anchor event discriminator no longer matches under another scheme
+        let mut data = IdlParser::calculate_discriminator("Deposit").to_vec();
+        data.push(3);
+        assert!(decoder_with_scheme("custom:1")
+            .decode_event(PROGRAM, "sig", &data)
+            .is_err());
+    }
+
+    #[test]
+    fn test_decode_account_snapshots() {
+        const PROGRAM: &str = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+        let mut idl_parser = IdlParser::new();
+        idl_parser
+            .load_from_str(
+                r#"{
+                    "address": "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ",
+                    "events": [{"name": "Deposit", "fields": [{"name": "amount", "type": "u8"}]}],
+                    "accounts": [
+                        {"name": "Vault", "type": {"kind": "struct", "fields": [{"name": "balance", "type": "u64"}]}},
+                        {"name": "Config", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8]}
+                    ],
+                    "types": [
+                        {"name": "Config", "type": {"kind": "struct", "fields": [{"name": "paused", "type": "bool"}]}}
+                    ]
+                }"#,
+            )
+            .unwrap();
+        let mut prefix_config = ProgramPrefixConfig::new();
+        prefix_config.add_mapping(PROGRAM, "t");
+        let decoder = EventDecoder::new(idl_parser, prefix_config);
+
+        let mut vault = IdlParser::calculate_account_discriminator("Vault").to_vec();
+        vault.extend_from_slice(&500u64.to_le_bytes());
+        assert!(matches!(
+            decoder.decode_event(PROGRAM, "sig", &vault),
+            Err(SoltraceError::EventNotFound(_))
+        ));
+
+        let decoder = decoder.with_decode_accounts(true);
+        let event = decoder.decode_event(PROGRAM, "sig", &vault).unwrap();
+        assert_eq!(event.event_name, "t_Vault");
+        assert_eq!(event.data["balance"], "500");
+
+        // Anchor >= 0.30: declared discriminator, fields in `types`
+        let event = decoder
+            .decode_event(PROGRAM, "sig", &[1, 2, 3, 4, 5, 6, 7, 8, 1])
+            .unwrap();
+        assert_eq!(event.event_name, "t_Config");
+        assert_eq!(event.data["paused"], true);
+
+        // Events still take precedence
+        let mut deposit = IdlParser::calculate_discriminator("Deposit").to_vec();
+        deposit.push(9);
+        let event = decoder.decode_event(PROGRAM, "sig", &deposit).unwrap();
+        assert_eq!(event.event_name, "t_Deposit");
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        const PROGRAM: &str = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+        let decoder = decoder_with_scheme("declared");
+
+        let b64 = STANDARD.encode([7, 1, 5]);
+        let event = decoder
+            .decode_base64(PROGRAM, "sig", &format!(" {}\n", b64))
+            .unwrap();
+        assert_eq!(event.event_name, "t_Deposit");
+        assert_eq!(event.data["amount"], 5);
+
+        assert!(matches!(
+            decoder.decode_base64(PROGRAM, "sig", "not base64!"),
+            Err(SoltraceError::EventDecode(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_untagged_data() {
+        const PROGRAM: &str = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+
+        // Without a discriminator the payload starts at byte 0 and is the first event
+        let event = decoder_with_scheme("custom:0")
+            .decode_event(PROGRAM, "sig", &[42])
+            .unwrap();
+        assert_eq!(event.event_name, "t_Deposit");
+        assert_eq!(event.data["amount"], 42);
+        assert_eq!(event.discriminator, [0; 8]);
+
+        // Leading bytes that aren't a discriminator are skipped
+        let event = decoder_with_scheme("untagged:2")
+            .decode_event(PROGRAM, "sig", &[0xff, 0xee, 7])
+            .unwrap();
+        assert_eq!(event.event_name, "t_Deposit");
+        assert_eq!(event.data["amount"], 7);
+        assert_eq!(event.discriminator, [0xff, 0xee, 0, 0, 0, 0, 0, 0]);
+        assert!(decoder_with_scheme("untagged:2")
+            .decode_event(PROGRAM, "sig", &[1])
+            .is_err());
+    }
+
+    #[test]
+    fn test_decode_applies_event_name_case() {
+        const PROGRAM: &str = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+
+        for (case, expected) in [
+            (EventNameCase::AsIs, "t_Withdraw"),
+            (EventNameCase::Snake, "t_withdraw"),
+            (EventNameCase::Camel, "tWithdraw"),
+            (EventNameCase::Kebab, "t-withdraw"),
+        ] {
+            let event = decoder_with_scheme("declared")
+                .with_name_case(case)
+                .decode_event(PROGRAM, "sig", &[9, 1, 42])
+                .unwrap();
+            assert_eq!(event.event_name, expected);
+        }
+    }
+
     #[test]
     fn test_decode_empty_data() {
         let idl_parser = IdlParser::new();
@@ -0,0 +1,99 @@
+/// Include/exclude filter applied to prefixed event names before storage
+///
+/// Patterns match the full event name exactly, or by prefix when they end
+/// with a `*` wildcard (e.g. `tributary_Price*`). Exclude patterns always win
+/// over include patterns; an empty include list allows every event.
+#[derive(Debug, Clone, Default)]
+pub struct EventNameFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl EventNameFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a filter from comma-separated include/exclude pattern lists
+    pub fn from_patterns(include: Option<&str>, exclude: Option<&str>) -> Self {
+        Self {
+            include: include.map(parse_patterns).unwrap_or_default(),
+            exclude: exclude.map(parse_patterns).unwrap_or_default(),
+        }
+    }
+
+    /// Add an include pattern
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include.push(pattern.to_string());
+        self
+    }
+
+    /// Add an exclude pattern
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(pattern.to_string());
+        self
+    }
+
+    /// Whether no patterns are configured (every event is allowed)
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Check whether an event with this (prefixed) name should be stored
+    pub fn is_allowed(&self, event_name: &str) -> bool {
+        if self.exclude.iter().any(|p| pattern_matches(p, event_name)) {
+            return false;
+        }
+
+        self.include.is_empty() || self.include.iter().any(|p| pattern_matches(p, event_name))
+    }
+}
+
+fn parse_patterns(patterns: &str) -> Vec<String> {
+    patterns
+        .split(',')
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_string())
+        .collect()
+}
+
+fn pattern_matches(pattern: &str, event_name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => event_name.starts_with(prefix),
+        None => pattern == event_name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_filter_allows_everything() {
+        let filter = EventNameFilter::new();
+        assert!(filter.is_empty());
+        assert!(filter.is_allowed("tributary_PaymentRecord"));
+    }
+
+    #[test]
+    fn test_include_exact_and_wildcard() {
+        let filter = EventNameFilter::from_patterns(
+            Some("tributary_PaymentRecord, tributary_Policy*"),
+            None,
+        );
+        assert!(filter.is_allowed("tributary_PaymentRecord"));
+        assert!(filter.is_allowed("tributary_PolicyCreated"));
+        assert!(!filter.is_allowed("tributary_PriceUpdate"));
+    }
+
+    #[test]
+    fn test_exclude_wins_over_include() {
+        let filter = EventNameFilter::new()
+            .include("tributary_*")
+            .exclude("tributary_Price*");
+        assert!(filter.is_allowed("tributary_PaymentRecord"));
+        assert!(!filter.is_allowed("tributary_PriceUpdate"));
+        assert!(!filter.is_allowed("other_PaymentRecord"));
+    }
+}
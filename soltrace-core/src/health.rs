@@ -0,0 +1,315 @@
+use crate::{
+    error::Result,
+    metrics::{HealthCheck, HealthStatus},
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+/// Largest request head we bother reading; probes send a handful of bytes
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// How long a client gets to send its request head by default
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Events returned by `GET /events/recent` without a `limit`
+pub const DEFAULT_RECENT_EVENTS: usize = 100;
+
+/// Largest `limit` accepted by `GET /events/recent`
+pub const MAX_RECENT_EVENTS: usize = 1000;
+
+/// Minimal HTTP server exposing `GET /health` and `GET /metrics` for probes and scrapers
+///
+/// When the health check has a database, `GET /events/recent?limit=N` returns the
+/// newest events (see [`Database::get_recent_events`](crate::db::Database::get_recent_events)).
+pub struct HealthServer {
+    listener: TcpListener,
+    health: Arc<HealthCheck>,
+    request_timeout: Duration,
+}
+
+impl HealthServer {
+    /// Bind to `addr` (e.g. `0.0.0.0:8080`)
+    pub async fn bind(addr: &str, health: HealthCheck) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            health: Arc::new(health),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+        })
+    }
+
+    /// Drop connections that haven't sent their request head within `timeout`
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> Result<std::net::SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Accept connections until the task is dropped
+    pub async fn serve(self) -> Result<()> {
+        info!("Health server listening on {}", self.local_addr()?);
+
+        loop {
+            // Accept errors (e.g. running out of file descriptors) are transient
+            let (stream, peer) = match self.listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Health server failed to accept a connection: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let health = self.health.clone();
+            let request_timeout = self.request_timeout;
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, &health, request_timeout).await {
+                    debug!("Health request from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    health: &HealthCheck,
+    request_timeout: Duration,
+) -> Result<()> {
+    let head = tokio::time::timeout(request_timeout, read_request_head(&mut stream))
+        .await
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "request head not received in time",
+            )
+        })??;
+
+    let request = String::from_utf8_lossy(&head);
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let (status, body) = match (method, path) {
+        ("GET", "/health") => {
+            let result = health.health_check().await;
+            let status = match result.status {
+                HealthStatus::Healthy | HealthStatus::Degraded => "200 OK",
+                HealthStatus::Unhealthy => "503 Service Unavailable",
+            };
+            (status, result.to_json())
+        }
+        ("GET", "/metrics") => ("200 OK", health.metrics().snapshot().await.to_json()),
+        ("GET", "/events/recent") => recent_events(health, query).await,
+        ("GET", _) => ("404 Not Found", serde_json::json!({ "error": "not found" })),
+        _ => (
+            "405 Method Not Allowed",
+            serde_json::json!({ "error": "method not allowed" }),
+        ),
+    };
+
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
+
+/// Read up to the blank line ending the request head (or `MAX_REQUEST_BYTES`)
+async fn read_request_head(stream: &mut TcpStream) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; MAX_REQUEST_BYTES];
+    let mut len = 0;
+
+    // Only the request line matters, but read the whole head so the client isn't reset
+    while len < buf.len() {
+        let n = stream.read(&mut buf[len..]).await?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+        if buf[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+    }
+
+    buf.truncate(len);
+    Ok(buf)
+}
+
+/// `GET /events/recent`: the newest events, `limit` (default 100, at most 1000) of them
+async fn recent_events(health: &HealthCheck, query: &str) -> (&'static str, serde_json::Value) {
+    let Some(database) = health.database() else {
+        return (
+            "404 Not Found",
+            serde_json::json!({ "error": "no database configured" }),
+        );
+    };
+
+    let limit = match query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("limit="))
+    {
+        None => DEFAULT_RECENT_EVENTS,
+        Some(limit) => match limit.parse::<usize>() {
+            Ok(limit) if (1..=MAX_RECENT_EVENTS).contains(&limit) => limit,
+            _ => {
+                return (
+                    "400 Bad Request",
+                    serde_json::json!({
+                        "error": format!("limit must be between 1 and {}", MAX_RECENT_EVENTS)
+                    }),
+                )
+            }
+        },
+    };
+
+    match database.get_recent_events(limit).await {
+        Ok(events) => ("200 OK", serde_json::json!({ "events": events })),
+        Err(e) => {
+            debug!("Failed to read recent events: {}", e);
+            (
+                "500 Internal Server Error",
+                serde_json::json!({ "error": "failed to read events" }),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Metrics;
+
+    async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_health_server_routes() {
+        let metrics = Arc::new(Metrics::new());
+        let server = HealthServer::bind(
+            "127.0.0.1:0",
+            HealthCheck::new(metrics.clone()).with_max_reconnections(1),
+        )
+        .await
+        .unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.serve());
+
+        let response = get(addr, "/health").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\"status\":\"healthy\""));
+
+        metrics.record_transaction(false);
+        let response = get(addr, "/metrics?format=json").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\"transactions_total\":1"));
+
+        assert!(get(addr, "/nope").await.starts_with("HTTP/1.1 404"));
+
+        // More than twice the reconnection budget is unhealthy
+        for _ in 0..3 {
+            metrics.record_ws_reconnection();
+        }
+        let response = get(addr, "/health").await;
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(response.contains("\"status\":\"unhealthy\""));
+
+        // No database, so no events
+        assert!(get(addr, "/events/recent")
+            .await
+            .starts_with("HTTP/1.1 404"));
+    }
+
+    #[tokio::test]
+    async fn test_silent_client_is_dropped() {
+        let metrics = Arc::new(Metrics::new());
+        let server = HealthServer::bind("127.0.0.1:0", HealthCheck::new(metrics))
+            .await
+            .unwrap()
+            .with_request_timeout(Duration::from_millis(50));
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.serve());
+
+        // A client that never sends its request is closed without a response...
+        let mut silent = TcpStream::connect(addr).await.unwrap();
+        let mut response = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), silent.read_to_end(&mut response))
+            .await
+            .expect("silent connection should be closed")
+            .unwrap();
+        assert!(response.is_empty());
+
+        // ...and others are still served
+        assert!(get(addr, "/health").await.starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[tokio::test]
+    async fn test_recent_events_route() {
+        let db = Arc::new(crate::Database::new("sqlite::memory:").await.unwrap());
+        let signature = solana_sdk::signature::Signature::from([4u8; 64]).to_string();
+        for slot in [5, 9, 7] {
+            let event = crate::DecodedEvent {
+                event_name: "tributary_Transfer".to_string(),
+                data: serde_json::json!({}),
+                discriminator: [0; 8],
+            };
+            let raw = crate::types::RawEvent {
+                slot,
+                signature: crate::types::Signature::parse(&signature).unwrap(),
+                program_id: solana_sdk::pubkey::Pubkey::default(),
+                log: String::new(),
+                log_index: slot as u32,
+                inner_instruction_index: None,
+                timestamp: chrono::Utc::now(),
+                raw_data: None,
+                tx_failed: false,
+            };
+            db.insert_event(&event, &raw).await.unwrap();
+        }
+
+        let health = HealthCheck::new(Arc::new(Metrics::new())).with_database(db);
+        let server = HealthServer::bind("127.0.0.1:0", health).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.serve());
+
+        let response = get(addr, "/events/recent?limit=2").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        let body: serde_json::Value =
+            serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        let slots: Vec<_> = body["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| event["slot"].as_i64().unwrap())
+            .collect();
+        assert_eq!(slots, vec![9, 7]);
+
+        assert!(get(addr, "/events/recent?limit=0")
+            .await
+            .starts_with("HTTP/1.1 400"));
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
     error::{Result, SoltraceError},
-    types::{EventDiscriminator, IdlEventDefinition, ParsedIdl},
+    idl_event::IdlEventDecoder,
+    types::{
+        DiscriminatorScheme, EventDiscriminator, EventNameCase, IdlEventDefinition, ParsedIdl,
+    },
 };
 use anchor_lang::solana_program::hash::hash;
 use std::collections::HashMap;
@@ -8,34 +11,341 @@ use std::collections::HashMap;
 #[derive(Clone)]
 pub struct IdlParser {
     idls: HashMap<String, ParsedIdl>, // program_id -> ParsedIdl
+    schemes: HashMap<String, DiscriminatorScheme>, // program_id -> non-default scheme
 }
 
 impl IdlParser {
     pub fn new() -> Self {
         Self {
             idls: HashMap::new(),
+            schemes: HashMap::new(),
         }
     }
 
-    /// Load an IDL from a JSON file
-    pub fn load_from_file(&mut self, path: &str) -> Result<()> {
-        let content = std::fs::read_to_string(path)?;
-        let idl: ParsedIdl = serde_json::from_str(&content)
-            .map_err(|e| SoltraceError::IdlParse(format!("Failed to parse IDL JSON: {}", e)))?;
+    /// Use a non-Anchor discriminator scheme for a program's events
+    pub fn set_discriminator_scheme(
+        &mut self,
+        program_id: &str,
+        scheme: DiscriminatorScheme,
+    ) -> Result<()> {
+        if let Some(idl) = self.idls.get(program_id) {
+            Self::check_discriminator_collisions(idl, scheme)?;
+        }
+        self.schemes.insert(program_id.to_string(), scheme);
+        Ok(())
+    }
 
-        self.idls.insert(idl.address.clone(), idl);
+    /// Set discriminator schemes from a comma-separated string
+    /// Format: "id1:declared,id2:custom:1"
+    pub fn add_discriminator_schemes_from_string(&mut self, schemes_str: &str) -> Result<()> {
+        for mapping in schemes_str.split(',') {
+            let mapping = mapping.trim();
+            if mapping.is_empty() {
+                continue;
+            }
+            let (program_id, scheme) = mapping.split_once(':').ok_or_else(|| {
+                SoltraceError::InvalidIdl(format!(
+                    "Invalid discriminator scheme mapping '{}': expected program_id:scheme",
+                    mapping
+                ))
+            })?;
+            self.set_discriminator_scheme(program_id.trim(), scheme.parse()?)?;
+        }
         Ok(())
     }
 
+    /// Discriminator scheme used for a program (Anchor events unless configured)
+    pub fn discriminator_scheme(&self, program_id: &str) -> DiscriminatorScheme {
+        self.schemes.get(program_id).copied().unwrap_or_default()
+    }
+
+    /// Load an IDL from a JSON file (`.json`, `.json.gz` or `.json.zst`)
+    pub fn load_from_file(&mut self, path: &str) -> Result<()> {
+        let content = Self::read_idl_file(path)?;
+        self.load_from_str(&content)
+    }
+
+    /// Whether a file name looks like an IDL this parser can load
+    pub fn is_idl_file(path: &str) -> bool {
+        path.ends_with(".json") || path.ends_with(".json.gz") || path.ends_with(".json.zst")
+    }
+
+    /// Read an IDL file, decompressing it based on its extension
+    fn read_idl_file(path: &str) -> Result<String> {
+        if path.ends_with(".gz") || path.ends_with(".zst") {
+            return Self::read_compressed_idl_file(path);
+        }
+
+        Ok(std::fs::read_to_string(path)?)
+    }
+
+    /// Decompress a `.gz` or `.zst` IDL, failing clearly when built without its codec
+    fn read_compressed_idl_file(path: &str) -> Result<String> {
+        if path.ends_with(".gz") {
+            Self::read_gzip_idl_file(path)
+        } else {
+            Self::read_zstd_idl_file(path)
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    fn read_gzip_idl_file(path: &str) -> Result<String> {
+        use std::io::Read;
+
+        let mut content = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(path)?).read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    fn read_gzip_idl_file(path: &str) -> Result<String> {
+        Err(Self::missing_codec(path, "gzip"))
+    }
+
+    #[cfg(feature = "zstd")]
+    fn read_zstd_idl_file(path: &str) -> Result<String> {
+        use std::io::Read;
+
+        let mut content = String::new();
+        zstd::stream::read::Decoder::new(std::fs::File::open(path)?)?
+            .read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    fn read_zstd_idl_file(path: &str) -> Result<String> {
+        Err(Self::missing_codec(path, "zstd"))
+    }
+
+    #[cfg(not(all(feature = "gzip", feature = "zstd")))]
+    fn missing_codec(path: &str, feature: &str) -> SoltraceError {
+        SoltraceError::InvalidIdl(format!(
+            "IDL {} is {}-compressed, but soltrace was built without the `{}` feature (or `compressed-idl`)",
+            path, feature, feature
+        ))
+    }
+
     /// Load an IDL from a JSON string
     pub fn load_from_str(&mut self, json: &str) -> Result<()> {
-        let idl: ParsedIdl = serde_json::from_str(json)
-            .map_err(|e| SoltraceError::IdlParse(format!("Failed to parse IDL JSON: {}", e)))?;
+        let idl = Self::parse_idl(json)?;
+        self.insert_idl(idl)
+    }
+
+    /// Load every IDL from a bundle file mapping program ID to IDL
+    ///
+    /// The bundle is a JSON object `{ "<program_id>": {<idl>}, ... }`; the map
+    /// key is used as the program address even if the inner IDL disagrees.
+    /// Returns the number of IDLs loaded.
+    pub fn load_from_bundle(&mut self, path: &str) -> Result<usize> {
+        let content = Self::read_idl_file(path)?;
+        let bundle: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&content)
+            .map_err(|e| SoltraceError::IdlParse(format!("Failed to parse IDL bundle: {}", e)))?;
+
+        let count = bundle.len();
+        for (program_id, mut idl) in bundle {
+            let Some(fields) = idl.as_object_mut() else {
+                return Err(SoltraceError::InvalidIdl(format!(
+                    "IDL bundle entry for {} is not an object",
+                    program_id
+                )));
+            };
+            fields.insert(
+                "address".to_string(),
+                serde_json::Value::String(program_id.clone()),
+            );
+
+            let idl = Self::parse_idl_value(idl).map_err(|e| {
+                SoltraceError::IdlParse(format!("Bundle entry {}: {}", program_id, e))
+            })?;
+            self.insert_idl(idl)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Check and store a parsed IDL
+    fn insert_idl(&mut self, idl: ParsedIdl) -> Result<()> {
+        Self::check_discriminator_collisions(&idl, self.discriminator_scheme(&idl.address))?;
+        Self::check_account_discriminators(&idl)?;
 
         self.idls.insert(idl.address.clone(), idl);
         Ok(())
     }
 
+    /// Parse IDL JSON
+    fn parse_idl(json: &str) -> Result<ParsedIdl> {
+        let value = serde_json::from_str(json)
+            .map_err(|e| SoltraceError::IdlParse(format!("Failed to parse IDL JSON: {}", e)))?;
+        Self::parse_idl_value(value)
+    }
+
+    /// Parse an IDL, resolving the program address from `metadata.address`
+    /// when the top-level `address` is missing (Anchor < 0.30 layout)
+    fn parse_idl_value(mut value: serde_json::Value) -> Result<ParsedIdl> {
+        Self::resolve_generic_lengths(&mut value);
+        let mut idl: ParsedIdl = serde_json::from_value(value)
+            .map_err(|e| SoltraceError::IdlParse(format!("Failed to parse IDL JSON: {}", e)))?;
+
+        if idl.address.trim().is_empty() {
+            idl.address = idl
+                .metadata
+                .as_ref()
+                .and_then(|m| m.address.as_deref())
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    SoltraceError::InvalidIdl(format!(
+                        "IDL '{}' has no program address (expected `address` or `metadata.address`)",
+                        idl.name.as_deref().unwrap_or("<unnamed>")
+                    ))
+                })?;
+        }
+
+        Ok(idl)
+    }
+
+    /// Replace `{"array": [T, {"generic": "N"}]}` lengths naming an IDL constant with its value
+    ///
+    /// Anchor 0.30 writes const-generic array lengths by name. Names that aren't
+    /// a numeric constant are left as they are for the decoder to reject.
+    fn resolve_generic_lengths(idl: &mut serde_json::Value) {
+        let constants: HashMap<String, u64> = idl
+            .get("constants")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|constant| {
+                let name = constant.get("name")?.as_str()?;
+                Some((
+                    name.to_string(),
+                    Self::constant_length(constant.get("value")?)?,
+                ))
+            })
+            .collect();
+        if !constants.is_empty() {
+            Self::substitute_generic_lengths(idl, &constants);
+        }
+    }
+
+    fn substitute_generic_lengths(value: &mut serde_json::Value, constants: &HashMap<String, u64>) {
+        match value {
+            serde_json::Value::Object(obj) => {
+                if let Some(length) = obj
+                    .get_mut("array")
+                    .and_then(|array| array.as_array_mut())
+                    .and_then(|array| array.get_mut(1))
+                {
+                    let resolved = length
+                        .get("generic")
+                        .and_then(|name| name.as_str())
+                        .and_then(|name| constants.get(name));
+                    if let Some(&resolved) = resolved {
+                        *length = resolved.into();
+                    }
+                }
+                for value in obj.values_mut() {
+                    Self::substitute_generic_lengths(value, constants);
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for value in items {
+                    Self::substitute_generic_lengths(value, constants);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// A constant's value as a length: a number, or a string like `"8"`, `"1_024"` or `"8usize"`
+    fn constant_length(value: &serde_json::Value) -> Option<u64> {
+        if let Some(length) = value.as_u64() {
+            return Some(length);
+        }
+        let digits: String = value
+            .as_str()?
+            .trim()
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '_')
+            .filter(|c| *c != '_')
+            .collect();
+        digits.parse().ok()
+    }
+
+    /// Reject IDLs where two events share a discriminator (or one is a prefix of
+    /// another), since only the first would ever be matched when decoding, or where
+    /// a discriminator is longer than a decoded event can carry
+    fn check_discriminator_collisions(idl: &ParsedIdl, scheme: DiscriminatorScheme) -> Result<()> {
+        let tagged: Vec<(&str, Vec<u8>)> = idl
+            .events
+            .iter()
+            .filter_map(|e| Some((e.name.as_str(), Self::event_discriminator(e, scheme)?)))
+            .collect();
+
+        for (i, (name, discriminator)) in tagged.iter().enumerate() {
+            Self::check_discriminator_len("Event", name, discriminator, &idl.address)?;
+            for (existing, other) in &tagged[..i] {
+                if discriminator.starts_with(other) || other.starts_with(discriminator) {
+                    return Err(SoltraceError::InvalidIdl(format!(
+                        "Events '{}' and '{}' in program {} have the same discriminator",
+                        existing, name, idl.address
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reject declared account discriminators that don't fit in a decoded event
+    fn check_account_discriminators(idl: &ParsedIdl) -> Result<()> {
+        let accounts = idl.accounts.as_ref().and_then(|a| a.as_array());
+        for account in accounts.into_iter().flatten() {
+            let name = account.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            let len = account
+                .get("discriminator")
+                .and_then(|d| d.as_array())
+                .map_or(0, |d| d.len());
+            if len > std::mem::size_of::<EventDiscriminator>() {
+                return Err(SoltraceError::InvalidIdl(format!(
+                    "Account '{}' in program {} has a {}-byte discriminator, at most 8 are supported",
+                    name, idl.address, len
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_discriminator_len(
+        kind: &str,
+        name: &str,
+        discriminator: &[u8],
+        program_id: &str,
+    ) -> Result<()> {
+        if discriminator.len() > std::mem::size_of::<EventDiscriminator>() {
+            return Err(SoltraceError::InvalidIdl(format!(
+                "{} '{}' in program {} has a {}-byte discriminator, at most 8 are supported",
+                kind,
+                name,
+                program_id,
+                discriminator.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Drop a program's IDL (e.g. when its file is deleted), returning whether one was loaded
+    ///
+    /// A discriminator scheme configured for the program is kept for when it's reloaded.
+    pub fn remove_idl(&mut self, program_id: &str) -> bool {
+        self.idls.remove(program_id).is_some()
+    }
+
+    /// Drop every loaded IDL, keeping configured discriminator schemes
+    pub fn clear(&mut self) {
+        self.idls.clear();
+    }
+
     /// Get all loaded IDLs
     pub fn get_idls(&self) -> &HashMap<String, ParsedIdl> {
         &self.idls
@@ -56,6 +366,49 @@ impl IdlParser {
         discriminator
     }
 
+    /// Calculate the discriminator Anchor gives an account struct
+    /// Anchor uses: sha256("account:<struct_name>")[..8]
+    pub fn calculate_account_discriminator(struct_name: &str) -> EventDiscriminator {
+        let preimage = format!("account:{}", struct_name);
+        let hash = hash(preimage.as_bytes());
+        let mut discriminator = [0u8; 8];
+        discriminator.copy_from_slice(&hash.to_bytes()[..8]);
+        discriminator
+    }
+
+    /// Calculate the discriminator Anchor gives an instruction
+    /// Anchor uses: sha256("global:<snake_case_name>")[..8]
+    pub fn calculate_instruction_discriminator(instruction_name: &str) -> EventDiscriminator {
+        let preimage = format!("global:{}", EventNameCase::Snake.apply(instruction_name));
+        let hash = hash(preimage.as_bytes());
+        let mut discriminator = [0u8; 8];
+        discriminator.copy_from_slice(&hash.to_bytes()[..8]);
+        discriminator
+    }
+
+    /// Discriminator bytes for an event under a scheme, or `None` when the scheme
+    /// needs a declared discriminator the IDL doesn't provide or doesn't tag events
+    pub fn event_discriminator(
+        event: &IdlEventDefinition,
+        scheme: DiscriminatorScheme,
+    ) -> Option<Vec<u8>> {
+        match scheme {
+            DiscriminatorScheme::AnchorEvent => {
+                Some(Self::calculate_discriminator(&event.name).to_vec())
+            }
+            DiscriminatorScheme::AnchorAccount => {
+                Some(Self::calculate_account_discriminator(&event.name).to_vec())
+            }
+            DiscriminatorScheme::Declared => event.discriminator.clone().filter(|d| !d.is_empty()),
+            DiscriminatorScheme::Custom(len) => event
+                .discriminator
+                .as_ref()
+                .filter(|d| d.len() >= len)
+                .map(|d| d[..len].to_vec()),
+            DiscriminatorScheme::Untagged(_) => None,
+        }
+    }
+
     /// Find event name by discriminator
     pub fn find_event_by_discriminator(
         &self,
@@ -63,14 +416,142 @@ impl IdlParser {
         discriminator: &[u8],
     ) -> Option<IdlEventDefinition> {
         let idl = self.idls.get(program_id)?;
-        let event = idl
-            .events
-            .iter()
-            .find(|e| Self::calculate_discriminator(&e.name).as_slice() == discriminator)?;
+        let scheme = self.discriminator_scheme(program_id);
+        let event = match scheme {
+            DiscriminatorScheme::Untagged(skip) if discriminator.len() == skip => {
+                idl.events.first()?
+            }
+            _ => idl.events.iter().find(|e| {
+                Self::event_discriminator(e, scheme).is_some_and(|d| d == discriminator)
+            })?,
+        };
+
+        Some(Self::resolve_event_fields(idl, event))
+    }
 
+    /// Find the event whose discriminator prefixes `data`
+    ///
+    /// Returns the event together with the discriminator length, so callers can
+    /// split off the payload whatever scheme the program uses.
+    pub fn find_event_for_data(
+        &self,
+        program_id: &str,
+        data: &[u8],
+    ) -> Option<(IdlEventDefinition, usize)> {
+        let idl = self.idls.get(program_id)?;
+        let scheme = self.discriminator_scheme(program_id);
+        if let DiscriminatorScheme::Untagged(skip) = scheme {
+            let event = idl.events.first().filter(|_| data.len() >= skip)?;
+            return Some((Self::resolve_event_fields(idl, event), skip));
+        }
+        idl.events.iter().find_map(|e| {
+            let discriminator = Self::event_discriminator(e, scheme)?;
+            data.starts_with(&discriminator)
+                .then(|| (Self::resolve_event_fields(idl, e), discriminator.len()))
+        })
+    }
+
+    /// Find the account struct whose Anchor discriminator prefixes `data`
+    ///
+    /// For programs that log account snapshots as `Program data:`. The account is
+    /// returned as an event definition (fields from the account entry or, for
+    /// Anchor >= 0.30, its `types` entry) with the discriminator length.
+    pub fn find_account_for_data(
+        &self,
+        program_id: &str,
+        data: &[u8],
+    ) -> Option<(IdlEventDefinition, usize)> {
+        let idl = self.idls.get(program_id)?;
+        let accounts = idl.accounts.as_ref()?.as_array()?;
+        accounts.iter().find_map(|account| {
+            let mut account: IdlEventDefinition = serde_json::from_value(account.clone()).ok()?;
+            let discriminator = account
+                .discriminator
+                .clone()
+                .filter(|d| !d.is_empty())
+                .unwrap_or_else(|| Self::calculate_account_discriminator(&account.name).to_vec());
+            if !data.starts_with(&discriminator) {
+                return None;
+            }
+            // Anchor < 0.30 nests the fields under `type`
+            if account.fields.is_none() {
+                account.fields = account
+                    .r#type
+                    .as_ref()
+                    .and_then(|t| t.get("fields"))
+                    .and_then(|fields| serde_json::from_value(fields.clone()).ok());
+            }
+            Some((
+                Self::resolve_event_fields(idl, &account),
+                discriminator.len(),
+            ))
+        })
+    }
+
+    /// Every event of a program with its fields resolved, in IDL order
+    pub fn resolved_events(&self, program_id: &str) -> Option<Vec<IdlEventDefinition>> {
+        let idl = self.idls.get(program_id)?;
+        Some(
+            idl.events
+                .iter()
+                .map(|event| Self::resolve_event_fields(idl, event))
+                .collect(),
+        )
+    }
+
+    /// Check every event field, and the types it references, against what the decoder supports
+    ///
+    /// Fails with one error listing every unsupported field type, so a typo in a
+    /// hand-edited IDL stops startup instead of failing each event at runtime.
+    pub fn validate_field_types(&self) -> Result<()> {
+        let mut program_ids: Vec<_> = self.idls.keys().collect();
+        program_ids.sort();
+
+        let mut problems = Vec::new();
+        for program_id in program_ids {
+            let idl = &self.idls[program_id];
+            let types = idl.types.as_deref().unwrap_or_default();
+            for event in &idl.events {
+                let event = Self::resolve_event_fields(idl, event);
+                for field in event.fields.iter().flatten() {
+                    for problem in IdlEventDecoder::unsupported_types(&field.field_type, types) {
+                        problems.push(format!(
+                            "{} {}.{}: {}",
+                            program_id, event.name, field.name, problem
+                        ));
+                    }
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(SoltraceError::InvalidIdl(format!(
+            "{} unsupported field type(s):\n  {}",
+            problems.len(),
+            problems.join("\n  ")
+        )))
+    }
+
+    /// An event's docs, falling back to those on its `types` entry (Anchor >= 0.30)
+    fn event_docs(event: &IdlEventDefinition, type_def: &serde_json::Value) -> Vec<String> {
+        if !event.docs.is_empty() {
+            return event.docs.clone();
+        }
+
+        type_def
+            .get("docs")
+            .and_then(|docs| serde_json::from_value(docs.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Fill in an event's fields from the IDL `types` section when the event
+    /// itself doesn't carry them (Anchor >= 0.30 layout)
+    fn resolve_event_fields(idl: &ParsedIdl, event: &IdlEventDefinition) -> IdlEventDefinition {
         // If event has fields, return it directly
         if event.fields.is_some() {
-            return Some(event.clone());
+            return event.clone();
         }
 
         // Otherwise, look for event definition in the types array
@@ -91,11 +572,15 @@ impl IdlParser {
                                                     fields.clone()
                                                 ) {
                                                     Ok(fields_vec) => {
-                                                        return Some(IdlEventDefinition {
+                                                        return IdlEventDefinition {
                                                             name: event.name.clone(),
+                                                            discriminator: event
+                                                                .discriminator
+                                                                .clone(),
                                                             fields: Some(fields_vec),
                                                             r#type: Some(type_obj.clone()),
-                                                        });
+                                                            docs: Self::event_docs(event, type_def),
+                                                        };
                                                     }
                                                     Err(e) => {
                                                         eprintln!(
@@ -116,7 +601,7 @@ impl IdlParser {
         }
 
         // Fallback: return the event as-is (no fields)
-        Some(event.clone())
+        event.clone()
     }
 }
 
@@ -178,6 +663,413 @@ mod tests {
         assert_eq!(fields[1].field_type, "pubkey");
     }
 
+    #[test]
+    fn test_validate_field_types() {
+        let idl_json = r#"{
+            "address": "Test111111111111111111111111111111",
+            "events": [
+                {"name": "Good", "fields": [{"name": "amount", "type": "u64"}]},
+                {"name": "Bad", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8]}
+            ],
+            "types": [
+                {
+                    "name": "Bad",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            {"name": "amount", "type": "u62"},
+                            {"name": "owner", "type": {"option": "pubky"}}
+                        ]
+                    }
+                }
+            ]
+        }"#;
+
+        let mut parser = IdlParser::new();
+        parser.load_from_str(idl_json).unwrap();
+
+        let err = parser.validate_field_types().unwrap_err().to_string();
+        assert!(err.contains("2 unsupported field type(s)"), "{}", err);
+        assert!(
+            err.contains("Bad.amount: unsupported type 'u62'"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("Bad.owner: unsupported type 'pubky'"),
+            "{}",
+            err
+        );
+        assert!(!err.contains("Good"), "{}", err);
+
+        let mut parser = IdlParser::new();
+        parser
+            .load_from_str(&idl_json.replace("u62", "u64").replace("pubky", "pubkey"))
+            .unwrap();
+        assert!(parser.validate_field_types().is_ok());
+    }
+
+    #[test]
+    fn test_array_of_defined_struct_with_generic_length() {
+        let idl_json = r#"{
+            "address": "Test111111111111111111111111111111",
+            "constants": [{"name": "MAX_FEES", "type": "usize", "value": "2"}],
+            "events": [{"name": "FeesSet", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8]}],
+            "types": [
+                {
+                    "name": "FeesSet",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            {"name": "fees", "type": {"array": [{"defined": {"name": "Fee"}}, {"generic": "MAX_FEES"}]}},
+                            {"name": "tiers", "type": {"array": ["u16", {"generic": "TIERS"}]}}
+                        ]
+                    }
+                },
+                {
+                    "name": "Fee",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [{"name": "bps", "type": "u16"}, {"name": "flat", "type": "u8"}]
+                    }
+                }
+            ]
+        }"#;
+
+        let mut parser = IdlParser::new();
+        parser.load_from_str(idl_json).unwrap();
+        let idl = &parser.get_idls()["Test111111111111111111111111111111"];
+        let types = idl.types.as_deref().unwrap();
+        let fields = parser.resolved_events(&idl.address).unwrap()[0]
+            .fields
+            .clone()
+            .unwrap();
+
+        let fees = IdlEventDecoder::decode(&[10, 0, 1, 20, 0, 2], &fields[..1], types).unwrap();
+        assert_eq!(
+            fees["fees"],
+            serde_json::json!([{"bps": 10, "flat": 1}, {"bps": 20, "flat": 2}])
+        );
+
+        // A length that names no IDL constant can't be decoded
+        let err = IdlEventDecoder::decode(&[0; 4], &fields[1..], types)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("TIERS"), "{}", err);
+        let err = parser.validate_field_types().unwrap_err().to_string();
+        assert!(err.contains("FeesSet.tiers"), "{}", err);
+        assert!(!err.contains("FeesSet.fees"), "{}", err);
+    }
+
+    #[test]
+    fn test_resolved_events_carry_docs() {
+        let idl_json = r#"{
+            "address": "Test111111111111111111111111111111",
+            "events": [
+                {"name": "Deposit", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8]},
+                {
+                    "name": "Legacy",
+                    "docs": ["Emitted by old versions"],
+                    "fields": [{"name": "value", "type": "u8"}]
+                }
+            ],
+            "types": [
+                {
+                    "name": "Deposit",
+                    "docs": ["A user deposited funds"],
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            {"name": "amount", "docs": ["In lamports"], "type": "u64"},
+                            {"name": "memo", "type": {"option": "string"}}
+                        ]
+                    }
+                }
+            ]
+        }"#;
+
+        let mut parser = IdlParser::new();
+        parser.load_from_str(idl_json).unwrap();
+
+        let events = parser
+            .resolved_events("Test111111111111111111111111111111")
+            .unwrap();
+        assert_eq!(events[0].docs, vec!["A user deposited funds"]);
+        let fields = events[0].fields.as_ref().unwrap();
+        assert_eq!(fields[0].docs, vec!["In lamports"]);
+        assert!(fields[1].docs.is_empty());
+        assert_eq!(events[1].docs, vec!["Emitted by old versions"]);
+        assert!(parser.resolved_events("Unknown").is_none());
+    }
+
+    #[test]
+    fn test_legacy_idl_metadata_address() {
+        let idl_json = r#"{
+            "version": "0.1.0",
+            "name": "legacy_program",
+            "events": [
+                {
+                    "name": "Transfer",
+                    "fields": [{"name": "amount", "type": "u64", "index": false}]
+                }
+            ],
+            "metadata": {
+                "address": "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ"
+            }
+        }"#;
+
+        let mut parser = IdlParser::new();
+        parser.load_from_str(idl_json).unwrap();
+
+        assert!(parser
+            .get_idls()
+            .contains_key("TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ"));
+        assert!(!parser.get_idls().contains_key(""));
+        assert!(parser
+            .get_events("TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ")
+            .is_some());
+    }
+
+    #[test]
+    fn test_remove_idl() {
+        let program_id = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+        let idl_json = format!(
+            r#"{{
+                "address": "{}",
+                "name": "tributary",
+                "events": [{{"name": "Transfer", "fields": []}}]
+            }}"#,
+            program_id
+        );
+        let mut parser = IdlParser::new();
+        parser.load_from_str(&idl_json).unwrap();
+        let discriminator = IdlParser::calculate_discriminator("Transfer");
+        assert!(parser
+            .find_event_by_discriminator(program_id, &discriminator)
+            .is_some());
+
+        assert!(parser.remove_idl(program_id));
+        assert!(!parser.remove_idl(program_id));
+        assert!(parser
+            .find_event_by_discriminator(program_id, &discriminator)
+            .is_none());
+        assert!(parser.get_idls().is_empty());
+
+        parser.load_from_str(&idl_json).unwrap();
+        parser.clear();
+        assert!(parser.get_idls().is_empty());
+    }
+
+    #[test]
+    fn test_idl_without_address_is_rejected() {
+        let idl_json = r#"{
+            "name": "anonymous",
+            "events": []
+        }"#;
+
+        let mut parser = IdlParser::new();
+        let err = parser.load_from_str(idl_json).unwrap_err();
+        assert!(err.to_string().contains("no program address"));
+        assert!(parser.get_idls().is_empty());
+    }
+
+    #[test]
+    fn test_load_from_bundle() {
+        let bundle = r#"{
+            "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ": {
+                "address": "11111111111111111111111111111111",
+                "name": "tributary",
+                "events": [{"name": "Transfer", "fields": []}]
+            },
+            "Test111111111111111111111111111111": {
+                "name": "legacy",
+                "events": [{"name": "Deposit", "fields": []}, {"name": "Withdraw", "fields": []}]
+            }
+        }"#;
+        let path =
+            std::env::temp_dir().join(format!("soltrace-bundle-{}.json", std::process::id()));
+        std::fs::write(&path, bundle).unwrap();
+
+        let mut parser = IdlParser::new();
+        let loaded = parser.load_from_bundle(path.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded, 2);
+        // The bundle key wins over the inner address
+        assert!(!parser
+            .get_idls()
+            .contains_key("11111111111111111111111111111111"));
+        assert_eq!(
+            parser
+                .get_events("TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ")
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            parser
+                .get_events("Test111111111111111111111111111111")
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_discriminator_collision_is_rejected() {
+        let idl: ParsedIdl = serde_json::from_str(
+            r#"{
+                "address": "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ",
+                "events": [
+                    {"name": "Deposit", "discriminator": [1, 2], "fields": []},
+                    {"name": "Withdraw", "discriminator": [1, 3], "fields": []}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(
+            IdlParser::check_discriminator_collisions(&idl, DiscriminatorScheme::AnchorEvent)
+                .is_ok()
+        );
+        assert!(
+            IdlParser::check_discriminator_collisions(&idl, DiscriminatorScheme::Declared).is_ok()
+        );
+
+        // Truncated to a single byte both tags are 0x01
+        let err = IdlParser::check_discriminator_collisions(&idl, DiscriminatorScheme::Custom(1))
+            .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("'Deposit'"));
+        assert!(message.contains("'Withdraw'"));
+
+        // Duplicate event names are the collision that happens in practice
+        let mut parser = IdlParser::new();
+        let err = parser
+            .load_from_str(
+                r#"{
+                    "address": "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ",
+                    "events": [
+                        {"name": "Transfer", "fields": []},
+                        {"name": "Transfer", "fields": []}
+                    ]
+                }"#,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("same discriminator"));
+        assert!(parser.get_idls().is_empty());
+    }
+
+    #[test]
+    fn test_long_declared_discriminator_is_rejected() {
+        let idl = r#"{
+            "address": "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ",
+            "events": [
+                {"name": "Deposit", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8, 9], "fields": []}
+            ]
+        }"#;
+
+        // Only the declared scheme uses the bytes, so the IDL loads under Anchor's
+        let mut parser = IdlParser::new();
+        parser.load_from_str(idl).unwrap();
+        let err = parser
+            .set_discriminator_scheme(
+                "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ",
+                DiscriminatorScheme::Declared,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("9-byte discriminator"));
+
+        let mut parser = IdlParser::new();
+        parser
+            .set_discriminator_scheme(
+                "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ",
+                DiscriminatorScheme::Declared,
+            )
+            .unwrap();
+        assert!(parser.load_from_str(idl).is_err());
+        assert!(parser.get_idls().is_empty());
+
+        let err = parser
+            .load_from_str(
+                r#"{
+                    "address": "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ",
+                    "events": [],
+                    "accounts": [
+                        {"name": "Vault", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]}
+                    ]
+                }"#,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("Account 'Vault'"));
+    }
+
+    #[cfg(feature = "compressed-idl")]
+    #[test]
+    fn test_load_compressed_idl_files() {
+        use crate::{EventDecoder, ProgramPrefixConfig};
+        use std::io::Write;
+
+        let idl_json = r#"{
+            "address": "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ",
+            "name": "tributary",
+            "events": [
+                {"name": "Transfer", "fields": [{"name": "amount", "type": "u64"}]}
+            ]
+        }"#;
+
+        let dir = std::env::temp_dir();
+        let gz_path = dir.join(format!("soltrace-idl-{}.json.gz", std::process::id()));
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&gz_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(idl_json.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let zst_path = dir.join(format!("soltrace-idl-{}.json.zst", std::process::id()));
+        std::fs::write(&zst_path, zstd::encode_all(idl_json.as_bytes(), 0).unwrap()).unwrap();
+
+        let mut zst_parser = IdlParser::new();
+        zst_parser
+            .load_from_file(zst_path.to_str().unwrap())
+            .unwrap();
+        assert!(zst_parser
+            .get_events("TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ")
+            .is_some());
+
+        let mut parser = IdlParser::new();
+        parser.load_from_file(gz_path.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&gz_path);
+        let _ = std::fs::remove_file(&zst_path);
+
+        let mut prefix_config = ProgramPrefixConfig::new();
+        prefix_config.add_mapping("TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ", "tributary");
+        let decoder = EventDecoder::new(parser, prefix_config);
+
+        let mut data = IdlParser::calculate_discriminator("Transfer").to_vec();
+        data.extend_from_slice(&42u64.to_le_bytes());
+        let event = decoder
+            .decode_event("TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ", "sig", &data)
+            .unwrap();
+        assert_eq!(event.event_name, "tributary_Transfer");
+        assert_eq!(event.data["amount"], "42");
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn test_compressed_idl_needs_its_feature() {
+        let path =
+            std::env::temp_dir().join(format!("soltrace-idl-{}.json.gz", std::process::id()));
+        std::fs::write(&path, [0x1f, 0x8b]).unwrap();
+
+        let err = IdlParser::new()
+            .load_from_file(path.to_str().unwrap())
+            .unwrap_err();
+        let _ = std::fs::remove_file(&path);
+        assert!(err.to_string().contains("without the `gzip` feature"));
+    }
+
     #[test]
     fn test_payment_record_fields_from_idl() {
         let idl_json = r#"{
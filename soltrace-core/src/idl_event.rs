@@ -2,19 +2,113 @@ use crate::{
     error::{Result, SoltraceError},
     types::IdlField,
 };
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use serde_json::Value;
 
+/// What to do with `string` fields that aren't valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidUtf8 {
+    /// Fail the event (it is then stored with the hex fallback)
+    #[default]
+    Error,
+    /// Replace invalid sequences with U+FFFD
+    Lossy,
+    /// Emit `{"hex": "<bytes>", "_invalid_utf8": true}` in place of the string
+    Hex,
+}
+
+/// How `bytes` fields are rendered as JSON strings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BytesEncoding {
+    /// Lowercase hex; fixed `[u8; N]` arrays stay arrays of numbers
+    #[default]
+    Hex,
+    /// Standard base64, also applied to fixed `[u8; N]` arrays
+    Base64,
+}
+
+impl BytesEncoding {
+    fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            BytesEncoding::Hex => hex::encode(bytes),
+            BytesEncoding::Base64 => STANDARD.encode(bytes),
+        }
+    }
+}
+
+/// How `vec`, `string` and `bytes` lengths are prefixed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthEncoding {
+    /// Borsh 4-byte little-endian u32
+    #[default]
+    Borsh,
+    /// Solana compact-u16 ("shortvec"): 1-3 bytes, 7 bits per byte
+    ShortVec,
+}
+
+/// Options for [`IdlEventDecoder::decode_with_options`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecodeOptions {
+    pub invalid_utf8: InvalidUtf8,
+    pub bytes_encoding: BytesEncoding,
+    /// Render fixed `[u8; N]` arrays as strings in this encoding; when unset they are
+    /// arrays of numbers, or base64 if `bytes_encoding` is base64
+    pub byte_array_encoding: Option<BytesEncoding>,
+    pub length_encoding: LengthEncoding,
+}
+
+impl DecodeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle invalid UTF-8 in `string` fields this way instead of failing
+    pub fn with_invalid_utf8(mut self, invalid_utf8: InvalidUtf8) -> Self {
+        self.invalid_utf8 = invalid_utf8;
+        self
+    }
+
+    /// Encode byte fields this way
+    pub fn with_bytes_encoding(mut self, bytes_encoding: BytesEncoding) -> Self {
+        self.bytes_encoding = bytes_encoding;
+        self
+    }
+
+    /// Encode fixed `[u8; N]` arrays as a single string instead of an array of numbers
+    pub fn with_byte_array_encoding(mut self, byte_array_encoding: BytesEncoding) -> Self {
+        self.byte_array_encoding = Some(byte_array_encoding);
+        self
+    }
+
+    /// Read length prefixes this way
+    pub fn with_length_encoding(mut self, length_encoding: LengthEncoding) -> Self {
+        self.length_encoding = length_encoding;
+        self
+    }
+}
+
 /// IDL-based event decoder using anchor_lang utilities
 pub struct IdlEventDecoder;
 
 impl IdlEventDecoder {
     /// Decode event data using IDL field definitions and anchor_lang's borsh utilities
     pub fn decode(data: &[u8], fields: &[IdlField], types: &[serde_json::Value]) -> Result<Value> {
+        Self::decode_with_options(data, fields, types, &DecodeOptions::default())
+    }
+
+    /// Like [`IdlEventDecoder::decode`], with non-default [`DecodeOptions`]
+    pub fn decode_with_options(
+        data: &[u8],
+        fields: &[IdlField],
+        types: &[serde_json::Value],
+        options: &DecodeOptions,
+    ) -> Result<Value> {
         let mut result = serde_json::Map::new();
         let mut offset = 0;
 
         for field in fields {
-            let (value, bytes_read) = Self::decode_field(data, offset, &field.field_type, types)?;
+            let (value, bytes_read) =
+                Self::decode_field(data, offset, &field.field_type, types, options)?;
             result.insert(field.name.clone(), value);
             offset += bytes_read;
         }
@@ -30,23 +124,190 @@ impl IdlEventDecoder {
         Ok(Value::Object(result))
     }
 
+    /// Describe every part of `field_type` (and the types it references) the decoder can't handle
+    pub fn unsupported_types(field_type: &Value, types: &[serde_json::Value]) -> Vec<String> {
+        let mut problems = Vec::new();
+        Self::check_type(field_type, types, &mut Vec::new(), &mut problems);
+        problems
+    }
+
+    /// Mirror of `decode_field`'s dispatch that records problems instead of reading data
+    fn check_type(
+        field_type: &Value,
+        types: &[serde_json::Value],
+        visiting: &mut Vec<String>,
+        problems: &mut Vec<String>,
+    ) {
+        if let Some(obj) = field_type.as_object() {
+            if let Some(array) = obj.get("array") {
+                match array.as_array().map(Vec::as_slice) {
+                    Some([inner, size]) if size.is_u64() => {
+                        Self::check_type(inner, types, visiting, problems)
+                    }
+                    Some([_, size]) if size.get("generic").is_some() => problems.push(format!(
+                        "array length {} is not an IDL constant",
+                        size["generic"]
+                    )),
+                    _ => problems.push(format!("invalid array type {}", array)),
+                }
+            } else if let Some(inner) = obj.get("option").or_else(|| obj.get("vec")) {
+                Self::check_type(inner, types, visiting, problems);
+            } else if let Some(defined) = obj.get("defined") {
+                match defined
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .or_else(|| defined.as_str())
+                {
+                    Some(type_name) => Self::check_defined(type_name, types, visiting, problems),
+                    None => problems.push(format!("invalid defined type {}", defined)),
+                }
+            } else {
+                problems.push(format!("unsupported complex type {}", field_type));
+            }
+            return;
+        }
+
+        let Some(type_str) = field_type.as_str() else {
+            problems.push(format!("invalid field type {}", field_type));
+            return;
+        };
+
+        match type_str {
+            "bool" | "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64"
+            | "i128" | "string" | "bytes" => {}
+            t if Self::is_pubkey(t) => {}
+            t if t.starts_with("option<") && t.ends_with('>') => {
+                Self::check_type(&Value::from(&t[7..t.len() - 1]), types, visiting, problems)
+            }
+            t if t.starts_with("vec<") && t.ends_with('>') => {
+                Self::check_type(&Value::from(&t[4..t.len() - 1]), types, visiting, problems)
+            }
+            t if t.starts_with('[') && t.contains(';') => {
+                let parts: Vec<&str> = t[1..t.len() - 1].split(';').collect();
+                match parts.as_slice() {
+                    [inner, len] if len.trim().parse::<usize>().is_ok() => {
+                        Self::check_type(&Value::from(inner.trim()), types, visiting, problems)
+                    }
+                    _ => problems.push(format!("invalid array type '{}'", t)),
+                }
+            }
+            t if Self::find_type(t, types).is_some() => {
+                Self::check_defined(t, types, visiting, problems)
+            }
+            t => problems.push(format!("unsupported type '{}'", t)),
+        }
+    }
+
+    /// Check an IDL-defined struct or enum, once per type even if it is recursive
+    fn check_defined(
+        type_name: &str,
+        types: &[serde_json::Value],
+        visiting: &mut Vec<String>,
+        problems: &mut Vec<String>,
+    ) {
+        if visiting.iter().any(|name| name == type_name) {
+            return;
+        }
+
+        let Some(type_def) = Self::find_type(type_name, types) else {
+            if !Self::is_pubkey(type_name) {
+                problems.push(format!("type '{}' not found in IDL", type_name));
+            }
+            return;
+        };
+        match Self::resolve_alias(type_name, types) {
+            Ok(Some(target)) => {
+                visiting.push(type_name.to_string());
+                Self::check_type(target, types, visiting, problems);
+                visiting.pop();
+                return;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                problems.push(e.to_string());
+                return;
+            }
+        }
+        let Some(type_obj) = type_def.get("type").and_then(|t| t.as_object()) else {
+            problems.push(format!("type '{}' has no 'type' field", type_name));
+            return;
+        };
+
+        // Named fields as the decoder reads them: objects with a `type`
+        let fields: Vec<(String, &Value)> = match type_obj.get("kind").and_then(|k| k.as_str()) {
+            Some("struct") => match type_obj.get("fields").and_then(|f| f.as_array()) {
+                Some(fields) => fields.iter().map(|f| (type_name.to_string(), f)).collect(),
+                None => {
+                    problems.push(format!("struct '{}' has no fields", type_name));
+                    return;
+                }
+            },
+            Some("enum") => match type_obj.get("variants").and_then(|v| v.as_array()) {
+                Some(variants) => variants
+                    .iter()
+                    .flat_map(|variant| {
+                        let owner = format!(
+                            "{}::{}",
+                            type_name,
+                            variant.get("name").and_then(|n| n.as_str()).unwrap_or("?")
+                        );
+                        variant
+                            .get("fields")
+                            .and_then(|f| f.as_array())
+                            .into_iter()
+                            .flatten()
+                            .map(move |f| (owner.clone(), f))
+                    })
+                    .collect(),
+                None => {
+                    problems.push(format!("enum '{}' has no variants", type_name));
+                    return;
+                }
+            },
+            Some(kind) => {
+                problems.push(format!(
+                    "unsupported type kind '{}' for '{}'",
+                    kind, type_name
+                ));
+                return;
+            }
+            None => {
+                problems.push(format!("type '{}' has no 'kind'", type_name));
+                return;
+            }
+        };
+
+        visiting.push(type_name.to_string());
+        for (owner, field) in fields {
+            match field.get("type") {
+                Some(field_type) => Self::check_type(field_type, types, visiting, problems),
+                None => problems.push(format!(
+                    "{} has a field without a name and type (tuple fields are not supported)",
+                    owner
+                )),
+            }
+        }
+        visiting.pop();
+    }
+
     /// Decode a single field using borsh format
     fn decode_field(
         data: &[u8],
         offset: usize,
         field_type: &serde_json::Value,
         types: &[serde_json::Value],
+        options: &DecodeOptions,
     ) -> Result<(Value, usize)> {
         let data = &data[offset..];
 
         // Handle complex types (objects like {"array": ["u8", 64]})
         if let Some(obj) = field_type.as_object() {
-            return Self::decode_complex_type(data, obj, types);
+            return Self::decode_complex_type(data, obj, types, options);
         }
 
         // Simple string type
         if let Some(type_str) = field_type.as_str() {
-            return Self::decode_simple_type(data, type_str, types);
+            return Self::decode_simple_type(data, type_str, types, options);
         }
 
         Err(SoltraceError::EventDecode(format!(
@@ -59,6 +320,7 @@ impl IdlEventDecoder {
         data: &[u8],
         field_type: &str,
         types: &[serde_json::Value],
+        options: &DecodeOptions,
     ) -> Result<(Value, usize)> {
         match field_type {
             // Boolean
@@ -85,11 +347,11 @@ impl IdlEventDecoder {
             }
 
             // Signed integers
-            "i8" => Self::read_le_bytes::<i8>(data, 1).map(|(v, n)| (Value::Number(v.into()), n)),
-            "i16" => Self::read_le_bytes::<i16>(data, 2).map(|(v, n)| (Value::Number(v.into()), n)),
-            "i32" => Self::read_le_bytes::<i32>(data, 4).map(|(v, n)| (Value::Number(v.into()), n)),
+            "i8" => Self::read_le_signed::<i8>(data, 1).map(|(v, n)| (Value::Number(v.into()), n)),
+            "i16" => Self::read_le_signed::<i16>(data, 2).map(|(v, n)| (Value::Number(v.into()), n)),
+            "i32" => Self::read_le_signed::<i32>(data, 4).map(|(v, n)| (Value::Number(v.into()), n)),
             "i64" => {
-                let (v, n) = Self::read_le_bytes::<i64>(data, 8)?;
+                let (v, n) = Self::read_le_signed::<i64>(data, 8)?;
                 Ok((Value::String(v.to_string()), n))
             }
             "i128" => {
@@ -98,13 +360,10 @@ impl IdlEventDecoder {
             }
 
             // String
-            "string" => {
-                let (s, n) = Self::decode_string(data)?;
-                Ok((Value::String(s), n))
-            }
+            "string" => Self::decode_string(data, options),
 
             // PublicKey (32 bytes)
-            "publicKey" | "pubkey" | "Pubkey" => {
+            t if Self::is_pubkey(t) => {
                 if data.len() < 32 {
                     return Err(SoltraceError::EventDecode(
                         "Not enough data for Pubkey".to_string(),
@@ -117,32 +376,19 @@ impl IdlEventDecoder {
 
             // Byte arrays
             "bytes" => {
-                let (bytes, n) = Self::decode_bytes(data)?;
-                Ok((Value::String(hex::encode(&bytes)), n))
+                let (bytes, n) = Self::decode_bytes(data, options)?;
+                Ok((Value::String(options.bytes_encoding.encode(&bytes)), n))
             }
 
             // Option<T>
             t if t.starts_with("option<") && t.ends_with(">") => {
-                if data.is_empty() {
-                    return Err(SoltraceError::EventDecode(
-                        "Unexpected end of data for option".to_string(),
-                    ));
-                }
-                let is_some = data[0] != 0;
-                if is_some {
-                    let inner_type = &t[7..t.len() - 1];
-                    let (value, bytes_read) =
-                        Self::decode_field(&data[1..], 0, &serde_json::json!(inner_type), types)?;
-                    Ok((value, 1 + bytes_read))
-                } else {
-                    Ok((Value::Null, 1))
-                }
+                Self::decode_option(data, &Value::from(&t[7..t.len() - 1]), types, options)
             }
 
             // Vec<T>
             t if t.starts_with("vec<") && t.ends_with(">") => {
-                let inner_type = &t[4..t.len() - 1];
-                let (arr, bytes_read) = Self::decode_vec(data, inner_type, types)?;
+                let inner_type = Value::from(&t[4..t.len() - 1]);
+                let (arr, bytes_read) = Self::decode_vec(data, &inner_type, types, options)?;
                 Ok((Value::Array(arr), bytes_read))
             }
 
@@ -155,24 +401,17 @@ impl IdlEventDecoder {
                         t
                     )));
                 }
-                let inner_type = parts[0].trim();
+                let inner_type = Value::from(parts[0].trim());
                 let len: usize = parts[1].trim().parse().map_err(|_| {
                     SoltraceError::EventDecode(format!("Invalid array length: {}", parts[1]))
                 })?;
 
-                let mut arr = Vec::with_capacity(len);
-                let mut total_bytes = 0;
-                for _ in 0..len {
-                    let (value, bytes_read) = Self::decode_field(
-                        &data[total_bytes..],
-                        0,
-                        &serde_json::json!(inner_type),
-                        types,
-                    )?;
-                    arr.push(value);
-                    total_bytes += bytes_read;
-                }
-                Ok((Value::Array(arr), total_bytes))
+                Self::decode_fixed_array(data, &inner_type, len, types, options)
+            }
+
+            // Bare name of a type defined in the IDL (e.g. the `Fee` in `vec<Fee>`)
+            t if Self::find_type(t, types).is_some() => {
+                Self::decode_defined_type(data, t, types, options)
             }
 
             // Unknown type
@@ -187,38 +426,51 @@ impl IdlEventDecoder {
         data: &[u8],
         obj: &serde_json::Map<String, serde_json::Value>,
         types: &[serde_json::Value],
+        options: &DecodeOptions,
     ) -> Result<(Value, usize)> {
-        // Handle array type: {"array": ["u8", 64]}
+        // Handle array type: {"array": ["u8", 64]} or {"array": [{"defined": ...}, 4]}
         if let Some(array) = obj.get("array") {
             if let Some(arr) = array.as_array() {
                 if arr.len() == 2 {
-                    if let Some(inner_type) = arr[0].as_str() {
-                        if let Some(size) = arr[1].as_u64() {
-                            return Self::decode_fixed_array(
-                                data,
-                                inner_type,
-                                size as usize,
-                                types,
-                            );
-                        }
+                    if let Some(size) = arr[1].as_u64() {
+                        return Self::decode_fixed_array(
+                            data,
+                            &arr[0],
+                            size as usize,
+                            types,
+                            options,
+                        );
+                    }
+                    // Lengths naming an IDL constant were resolved when the IDL was loaded
+                    if let Some(name) = arr[1].get("generic") {
+                        return Err(SoltraceError::EventDecode(format!(
+                            "Array length {} is a const generic with no matching IDL constant",
+                            name
+                        )));
                     }
                 }
             }
         }
 
-        // Handle option type: {"option": "u32"}
-        if let Some(option) = obj.get("option") {
-            if let Some(inner_type) = option.as_str() {
-                return Self::decode_option(data, inner_type, types);
-            }
+        // Handle option type: {"option": "u32"} or {"option": {"defined": ...}}
+        if let Some(inner_type) = obj.get("option") {
+            return Self::decode_option(data, inner_type, types, options);
         }
 
-        // Handle defined type: {"defined": {"name": "SomeType"}}
+        // Handle vec type: {"vec": "u8"} or {"vec": {"defined": ...}}
+        if let Some(inner_type) = obj.get("vec") {
+            let (arr, bytes_read) = Self::decode_vec(data, inner_type, types, options)?;
+            return Ok((Value::Array(arr), bytes_read));
+        }
+
+        // Handle defined type: {"defined": {"name": "SomeType"}} or legacy {"defined": "SomeType"}
         if let Some(defined) = obj.get("defined") {
-            if let Some(name) = defined.get("name") {
-                if let Some(type_name) = name.as_str() {
-                    return Self::decode_defined_type(data, type_name, types);
-                }
+            let type_name = defined
+                .get("name")
+                .and_then(|n| n.as_str())
+                .or_else(|| defined.as_str());
+            if let Some(type_name) = type_name {
+                return Self::decode_defined_type(data, type_name, types, options);
             }
         }
 
@@ -230,15 +482,35 @@ impl IdlEventDecoder {
 
     fn decode_fixed_array(
         data: &[u8],
-        inner_type: &str,
+        inner_type: &Value,
         size: usize,
         types: &[serde_json::Value],
+        options: &DecodeOptions,
     ) -> Result<(Value, usize)> {
+        // Single-byte elements are read straight from the buffer
+        if let Some(inner @ ("u8" | "i8")) = inner_type.as_str() {
+            let bytes = data.get(..size).ok_or_else(|| {
+                SoltraceError::EventDecode(format!("Not enough data for [{}; {}]", inner, size))
+            })?;
+
+            let base64_bytes = options.bytes_encoding == BytesEncoding::Base64;
+            let string_encoding = options
+                .byte_array_encoding
+                .or(base64_bytes.then_some(BytesEncoding::Base64));
+            let value = match (inner, string_encoding) {
+                ("u8", Some(encoding)) => Value::String(encoding.encode(bytes)),
+                ("u8", None) => bytes.iter().map(|&b| Value::from(b)).collect(),
+                _ => bytes.iter().map(|&b| Value::from(b as i8)).collect(),
+            };
+            return Ok((value, size));
+        }
+
         let mut arr = Vec::with_capacity(size);
         let mut offset = 0;
 
         for _ in 0..size {
-            let (value, bytes_read) = Self::decode_simple_type(&data[offset..], inner_type, types)?;
+            let (value, bytes_read) =
+                Self::decode_field(&data[offset..], 0, inner_type, types, options)?;
             arr.push(value);
             offset += bytes_read;
         }
@@ -246,18 +518,105 @@ impl IdlEventDecoder {
         Ok((Value::Array(arr), offset))
     }
 
-    /// Decode a defined type (enum or struct) from IDL types array
+    /// Look up a type definition by name in the IDL types array
+    fn find_type<'a>(type_name: &str, types: &'a [serde_json::Value]) -> Option<&'a Value> {
+        types
+            .iter()
+            .find(|t| t.get("name").and_then(|n| n.as_str()) == Some(type_name))
+    }
+
+    /// Whether `type_name` is one of the spellings IDLs use for a public key
+    ///
+    /// Anchor 0.30 writes `pubkey`, older IDLs `publicKey`, and some generators emit
+    /// `{"defined": {"name": "Pubkey"}}` without defining the type.
+    fn is_pubkey(type_name: &str) -> bool {
+        matches!(type_name, "publicKey" | "pubkey" | "Pubkey")
+    }
+
+    /// The aliased type when `type_def` is an alias rather than a struct or enum
+    ///
+    /// Covers primitives (`"type": "u64"`), containers (`"type": {"vec": "u8"}`) and
+    /// Anchor's `{"kind": "type", "alias": ...}`.
+    fn alias_target(type_def: &Value) -> Option<&Value> {
+        let ty = type_def.get("type")?;
+        if ty.is_string() {
+            return Some(ty);
+        }
+        match ty.get("kind").and_then(|k| k.as_str()) {
+            Some("type") => ty.get("alias"),
+            Some(_) => None,
+            None => ["array", "option", "vec", "defined"]
+                .iter()
+                .any(|key| ty.get(key).is_some())
+                .then_some(ty),
+        }
+    }
+
+    /// Name of the IDL type `field_type` refers to, if it is a defined type
+    fn defined_name<'a>(field_type: &'a Value, types: &[serde_json::Value]) -> Option<&'a str> {
+        match field_type.get("defined") {
+            Some(defined) => defined
+                .get("name")
+                .and_then(|n| n.as_str())
+                .or_else(|| defined.as_str()),
+            None => field_type
+                .as_str()
+                .filter(|t| Self::find_type(t, types).is_some()),
+        }
+    }
+
+    /// Follow `type_name` through aliases of aliases to the type it stands for
+    ///
+    /// Returns `None` when `type_name` is a struct or enum, and an error on alias cycles.
+    fn resolve_alias<'a>(
+        type_name: &str,
+        types: &'a [serde_json::Value],
+    ) -> Result<Option<&'a Value>> {
+        let mut seen = vec![type_name];
+        let Some(mut target) = Self::find_type(type_name, types).and_then(Self::alias_target)
+        else {
+            return Ok(None);
+        };
+
+        while let Some((name, next)) = Self::defined_name(target, types).and_then(|name| {
+            Self::find_type(name, types)
+                .and_then(Self::alias_target)
+                .map(|next| (name, next))
+        }) {
+            if seen.contains(&name) {
+                return Err(SoltraceError::EventDecode(format!(
+                    "type alias cycle: {} -> {}",
+                    seen.join(" -> "),
+                    name
+                )));
+            }
+            seen.push(name);
+            target = next;
+        }
+
+        Ok(Some(target))
+    }
+
+    /// Decode a defined type (enum, struct or alias) from IDL types array
     fn decode_defined_type(
         data: &[u8],
         type_name: &str,
         types: &[serde_json::Value],
+        options: &DecodeOptions,
     ) -> Result<(Value, usize)> {
-        let type_def = types
-            .iter()
-            .find(|t| t.get("name").and_then(|n| n.as_str()) == Some(type_name))
-            .ok_or_else(|| {
-                SoltraceError::EventDecode(format!("Type '{}' not found in IDL", type_name))
-            })?;
+        let Some(type_def) = Self::find_type(type_name, types) else {
+            if Self::is_pubkey(type_name) {
+                return Self::decode_simple_type(data, type_name, types, options);
+            }
+            return Err(SoltraceError::EventDecode(format!(
+                "Type '{}' not found in IDL",
+                type_name
+            )));
+        };
+
+        if let Some(target) = Self::resolve_alias(type_name, types)? {
+            return Self::decode_field(data, 0, target, types, options);
+        }
 
         let type_obj = type_def
             .get("type")
@@ -274,8 +633,8 @@ impl IdlEventDecoder {
             })?;
 
         match kind {
-            "enum" => Self::decode_enum(data, type_obj, types),
-            "struct" => Self::decode_struct(data, type_obj, types),
+            "enum" => Self::decode_enum(data, type_obj, types, options),
+            "struct" => Self::decode_struct(data, type_obj, types, options),
             _ => Err(SoltraceError::EventDecode(format!(
                 "Unsupported type kind '{}': {}",
                 kind, type_name
@@ -288,6 +647,7 @@ impl IdlEventDecoder {
         data: &[u8],
         type_obj: &serde_json::Map<String, serde_json::Value>,
         types: &[serde_json::Value],
+        options: &DecodeOptions,
     ) -> Result<(Value, usize)> {
         if data.is_empty() {
             return Err(SoltraceError::EventDecode(
@@ -331,7 +691,7 @@ impl IdlEventDecoder {
                 })?;
 
                 let (value, bytes_read) =
-                    Self::decode_field(&data[offset..], 0, field_type, types)?;
+                    Self::decode_field(&data[offset..], 0, field_type, types, options)?;
                 result.insert(field_name.to_string(), value);
                 offset += bytes_read;
             }
@@ -345,6 +705,7 @@ impl IdlEventDecoder {
         data: &[u8],
         type_obj: &serde_json::Map<String, serde_json::Value>,
         types: &[serde_json::Value],
+        options: &DecodeOptions,
     ) -> Result<(Value, usize)> {
         let fields = type_obj
             .get("fields")
@@ -364,7 +725,8 @@ impl IdlEventDecoder {
                 SoltraceError::EventDecode(format!("Field '{}' has no type", field_name))
             })?;
 
-            let (value, bytes_read) = Self::decode_field(&data[offset..], 0, field_type, types)?;
+            let (value, bytes_read) =
+                Self::decode_field(&data[offset..], 0, field_type, types, options)?;
             result.insert(field_name.to_string(), value);
             offset += bytes_read;
         }
@@ -372,24 +734,30 @@ impl IdlEventDecoder {
         Ok((Value::Object(result), offset))
     }
 
-    /// Decode an option type for complex types
+    /// Decode an option type (shared by the `option<T>` and `{"option": T}` forms)
+    ///
+    /// `inner_type` may be any type value (primitive, defined, array, vec or
+    /// another option). Borsh only allows a 0 (None) or 1 (Some) tag.
     fn decode_option(
         data: &[u8],
-        inner_type: &str,
+        inner_type: &Value,
         types: &[serde_json::Value],
+        options: &DecodeOptions,
     ) -> Result<(Value, usize)> {
-        if data.is_empty() {
-            return Err(SoltraceError::EventDecode(
+        match data.first() {
+            None => Err(SoltraceError::EventDecode(
                 "Unexpected end of data for option".to_string(),
-            ));
-        }
-        let is_some = data[0] != 0;
-        if is_some {
-            let (value, bytes_read) =
-                Self::decode_field(&data[1..], 0, &serde_json::json!(inner_type), types)?;
-            Ok((value, 1 + bytes_read))
-        } else {
-            Ok((Value::Null, 1))
+            )),
+            Some(0) => Ok((Value::Null, 1)),
+            Some(1) => {
+                let (value, bytes_read) =
+                    Self::decode_field(&data[1..], 0, inner_type, types, options)?;
+                Ok((value, 1 + bytes_read))
+            }
+            Some(tag) => Err(SoltraceError::EventDecode(format!(
+                "Invalid option tag {} (expected 0 or 1)",
+                tag
+            ))),
         }
     }
 
@@ -410,6 +778,24 @@ impl IdlEventDecoder {
             .map_err(|_| SoltraceError::EventDecode("Integer conversion failed".to_string()))
     }
 
+    /// Read little-endian bytes into a signed integer type, sign-extending negatives
+    fn read_le_signed<T: TryFrom<i128>>(data: &[u8], size: usize) -> Result<(T, usize)> {
+        if data.len() < size {
+            return Err(SoltraceError::EventDecode(
+                "Not enough data for integer".to_string(),
+            ));
+        }
+
+        let fill = if data[size - 1] & 0x80 != 0 { 0xff } else { 0 };
+        let mut bytes = [fill; 16];
+        bytes[..size].copy_from_slice(&data[..size]);
+        let value = i128::from_le_bytes(bytes);
+
+        T::try_from(value)
+            .map(|v| (v, size))
+            .map_err(|_| SoltraceError::EventDecode("Integer conversion failed".to_string()))
+    }
+
     /// Read i128 (signed 128-bit integer)
     fn read_i128(data: &[u8]) -> Result<(i128, usize)> {
         if data.len() < 16 {
@@ -421,70 +807,108 @@ impl IdlEventDecoder {
         Ok((i128::from_le_bytes(bytes), 16))
     }
 
-    /// Decode borsh string (4-byte length prefix + content)
-    fn decode_string(data: &[u8]) -> Result<(String, usize)> {
-        if data.len() < 4 {
-            return Err(SoltraceError::EventDecode(
-                "Not enough data for string length".to_string(),
-            ));
+    /// Read a length prefix, returning (length, prefix size)
+    fn read_length(data: &[u8], what: &str, options: &DecodeOptions) -> Result<(usize, usize)> {
+        match options.length_encoding {
+            LengthEncoding::Borsh => {
+                if data.len() < 4 {
+                    return Err(SoltraceError::EventDecode(format!(
+                        "Not enough data for {} length",
+                        what
+                    )));
+                }
+                let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+                Ok((len, 4))
+            }
+            LengthEncoding::ShortVec => Self::read_short_vec(data, what),
         }
+    }
 
-        let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    /// Read a compact-u16 length, rejecting overflow and non-canonical encodings
+    fn read_short_vec(data: &[u8], what: &str) -> Result<(usize, usize)> {
+        let mut len = 0usize;
+        for i in 0..3 {
+            let byte = *data.get(i).ok_or_else(|| {
+                SoltraceError::EventDecode(format!("Not enough data for {} length", what))
+            })?;
+            len |= ((byte & 0x7f) as usize) << (7 * i);
 
-        if data.len() < 4 + len {
-            return Err(SoltraceError::EventDecode(
-                "Not enough data for string content".to_string(),
-            ));
+            if byte & 0x80 == 0 {
+                if i > 0 && byte == 0 {
+                    return Err(SoltraceError::EventDecode(format!(
+                        "Non-canonical shortvec length for {}",
+                        what
+                    )));
+                }
+                if len > u16::MAX as usize {
+                    break;
+                }
+                return Ok((len, i + 1));
+            }
         }
 
-        let s = String::from_utf8(data[4..4 + len].to_vec())
-            .map_err(|e| SoltraceError::EventDecode(format!("Invalid UTF-8: {}", e)))?;
-
-        Ok((s, 4 + len))
+        Err(SoltraceError::EventDecode(format!(
+            "Shortvec length for {} overflows u16",
+            what
+        )))
     }
 
-    /// Decode borsh bytes (4-byte length prefix + content)
-    fn decode_bytes(data: &[u8]) -> Result<(Vec<u8>, usize)> {
-        if data.len() < 4 {
+    /// Decode a string (length prefix + UTF-8 content)
+    fn decode_string(data: &[u8], options: &DecodeOptions) -> Result<(Value, usize)> {
+        let (len, prefix) = Self::read_length(data, "string", options)?;
+
+        if data.len() < prefix + len {
             return Err(SoltraceError::EventDecode(
-                "Not enough data for bytes length".to_string(),
+                "Not enough data for string content".to_string(),
             ));
         }
 
-        let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let bytes = &data[prefix..prefix + len];
+        let value = match (std::str::from_utf8(bytes), options.invalid_utf8) {
+            (Ok(s), _) => Value::String(s.to_string()),
+            (Err(e), InvalidUtf8::Error) => {
+                return Err(SoltraceError::EventDecode(format!("Invalid UTF-8: {}", e)));
+            }
+            (Err(_), InvalidUtf8::Lossy) => {
+                Value::String(String::from_utf8_lossy(bytes).into_owned())
+            }
+            (Err(_), InvalidUtf8::Hex) => serde_json::json!({
+                "hex": hex::encode(bytes),
+                "_invalid_utf8": true,
+            }),
+        };
 
-        if data.len() < 4 + len {
+        Ok((value, prefix + len))
+    }
+
+    /// Decode bytes (length prefix + content)
+    fn decode_bytes(data: &[u8], options: &DecodeOptions) -> Result<(Vec<u8>, usize)> {
+        let (len, prefix) = Self::read_length(data, "bytes", options)?;
+
+        if data.len() < prefix + len {
             return Err(SoltraceError::EventDecode(
                 "Not enough data for bytes content".to_string(),
             ));
         }
 
-        Ok((data[4..4 + len].to_vec(), 4 + len))
+        Ok((data[prefix..prefix + len].to_vec(), prefix + len))
     }
 
     /// Decode a vector of elements
     fn decode_vec(
         data: &[u8],
-        inner_type: &str,
+        inner_type: &Value,
         types: &[serde_json::Value],
+        options: &DecodeOptions,
     ) -> Result<(Vec<Value>, usize)> {
-        if data.len() < 4 {
-            return Err(SoltraceError::EventDecode(
-                "Not enough data for vec length".to_string(),
-            ));
-        }
-
-        let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
-        let mut result = Vec::with_capacity(len);
-        let mut total_bytes = 4;
+        let (len, prefix) = Self::read_length(data, "vec", options)?;
+        // The length comes from the event, so don't trust it for the allocation
+        let mut result = Vec::with_capacity(len.min(data.len() - prefix));
+        let mut total_bytes = prefix;
 
         for _ in 0..len {
-            let (value, bytes_read) = Self::decode_field(
-                &data[total_bytes..],
-                0,
-                &serde_json::json!(inner_type),
-                types,
-            )?;
+            let (value, bytes_read) =
+                Self::decode_field(&data[total_bytes..], 0, inner_type, types, options)?;
             result.push(value);
             total_bytes += bytes_read;
         }
@@ -504,12 +928,36 @@ mod tests {
         let fields = vec![IdlField {
             name: "amount".to_string(),
             field_type: serde_json::json!("u64"),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
         assert_eq!(result["amount"], "42");
     }
 
+    #[test]
+    fn test_decode_negative_integers() {
+        let mut data = (-1i8).to_le_bytes().to_vec();
+        data.extend_from_slice(&(-300i16).to_le_bytes());
+        data.extend_from_slice(&(-70000i32).to_le_bytes());
+        data.extend_from_slice(&i64::MIN.to_le_bytes());
+
+        let fields: Vec<IdlField> = [("a", "i8"), ("b", "i16"), ("c", "i32"), ("d", "i64")]
+            .iter()
+            .map(|(name, ty)| IdlField {
+                name: name.to_string(),
+                field_type: serde_json::json!(ty),
+                docs: Vec::new(),
+            })
+            .collect();
+
+        let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
+        assert_eq!(result["a"], -1);
+        assert_eq!(result["b"], -300);
+        assert_eq!(result["c"], -70000);
+        assert_eq!(result["d"], i64::MIN.to_string());
+    }
+
     #[test]
     fn test_decode_pubkey() {
         let pubkey = solana_sdk::pubkey::Pubkey::new_unique();
@@ -517,12 +965,157 @@ mod tests {
         let fields = vec![IdlField {
             name: "owner".to_string(),
             field_type: serde_json::json!("publicKey"),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
         assert_eq!(result["owner"], pubkey.to_string());
     }
 
+    #[test]
+    fn test_decode_vec_of_strings() {
+        let strings = ["a", "", "attribute: rare"];
+        let mut data = (strings.len() as u32).to_le_bytes().to_vec();
+        for s in strings {
+            data.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            data.extend_from_slice(s.as_bytes());
+        }
+        data.extend_from_slice(&7u16.to_le_bytes());
+
+        let fields = vec![
+            IdlField {
+                name: "attributes".to_string(),
+                field_type: serde_json::json!("vec<string>"),
+                docs: Vec::new(),
+            },
+            IdlField {
+                name: "after".to_string(),
+                field_type: serde_json::json!("u16"),
+                docs: Vec::new(),
+            },
+        ];
+
+        // The field after the vec only decodes if every string's length was accounted for
+        let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
+        assert_eq!(result["attributes"], serde_json::json!(strings));
+        assert_eq!(result["after"], 7);
+
+        let object_form = vec![IdlField {
+            name: "attributes".to_string(),
+            field_type: serde_json::json!({"vec": "string"}),
+            docs: Vec::new(),
+        }];
+        let result = IdlEventDecoder::decode(&data[..data.len() - 2], &object_form, &[]).unwrap();
+        assert_eq!(result["attributes"], serde_json::json!(strings));
+
+        let array_form = vec![IdlField {
+            name: "attributes".to_string(),
+            field_type: serde_json::json!({"array": ["string", 3]}),
+            docs: Vec::new(),
+        }];
+        let result = IdlEventDecoder::decode(&data[4..data.len() - 2], &array_form, &[]).unwrap();
+        assert_eq!(result["attributes"], serde_json::json!(strings));
+
+        // A corrupt element count fails instead of allocating for it
+        let mut corrupt = u32::MAX.to_le_bytes().to_vec();
+        corrupt.extend_from_slice(&data[4..]);
+        assert!(IdlEventDecoder::decode(&corrupt, &object_form, &[]).is_err());
+    }
+
+    #[test]
+    fn test_decode_object_form_vec_of_integers() {
+        let mut data = 3u32.to_le_bytes().to_vec();
+        for amount in [1u64, 2, u64::MAX] {
+            data.extend_from_slice(&amount.to_le_bytes());
+        }
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.push(9);
+
+        let fields = vec![
+            IdlField {
+                name: "amounts".to_string(),
+                field_type: serde_json::json!({"vec": "u64"}),
+                docs: Vec::new(),
+            },
+            IdlField {
+                name: "empty".to_string(),
+                field_type: serde_json::json!({"vec": "i16"}),
+                docs: Vec::new(),
+            },
+            IdlField {
+                name: "after".to_string(),
+                field_type: serde_json::json!("u8"),
+                docs: Vec::new(),
+            },
+        ];
+
+        let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
+        assert_eq!(
+            result["amounts"],
+            serde_json::json!(["1", "2", u64::MAX.to_string()])
+        );
+        assert_eq!(result["empty"], serde_json::json!([]));
+        assert_eq!(result["after"], 9);
+        assert!(result.get("decode_error").is_none());
+    }
+
+    #[test]
+    fn test_decode_pubkey_collections() {
+        let keys: Vec<_> = (0..3)
+            .map(|_| solana_sdk::pubkey::Pubkey::new_unique())
+            .collect();
+        let expected: Vec<_> = keys.iter().map(|k| k.to_string()).collect();
+
+        let mut vec_data = (keys.len() as u32).to_le_bytes().to_vec();
+        let mut array_data = Vec::new();
+        for key in &keys {
+            vec_data.extend_from_slice(key.as_ref());
+            array_data.extend_from_slice(key.as_ref());
+        }
+
+        for field_type in [
+            serde_json::json!("vec<pubkey>"),
+            serde_json::json!("vec<publicKey>"),
+            serde_json::json!({"vec": "pubkey"}),
+            serde_json::json!({"vec": {"defined": {"name": "Pubkey"}}}),
+            serde_json::json!({"vec": {"defined": "Pubkey"}}),
+        ] {
+            let fields = vec![IdlField {
+                name: "accounts".to_string(),
+                field_type: field_type.clone(),
+                docs: Vec::new(),
+            }];
+            let result = IdlEventDecoder::decode(&vec_data, &fields, &[]).unwrap();
+            assert_eq!(
+                result["accounts"],
+                serde_json::json!(expected),
+                "{}",
+                field_type
+            );
+            assert!(IdlEventDecoder::unsupported_types(&field_type, &[]).is_empty());
+        }
+
+        for field_type in [
+            serde_json::json!("[pubkey; 3]"),
+            serde_json::json!({"array": ["pubkey", 3]}),
+            serde_json::json!({"array": [{"defined": {"name": "Pubkey"}}, 3]}),
+        ] {
+            let fields = vec![IdlField {
+                name: "accounts".to_string(),
+                field_type: field_type.clone(),
+                docs: Vec::new(),
+            }];
+            let result = IdlEventDecoder::decode(&array_data, &fields, &[]).unwrap();
+            assert_eq!(
+                result["accounts"],
+                serde_json::json!(expected),
+                "{}",
+                field_type
+            );
+            assert!(IdlEventDecoder::unsupported_types(&field_type, &[]).is_empty());
+        }
+    }
+
     #[test]
     fn test_decode_string() {
         let s = "Hello, World!";
@@ -532,18 +1125,56 @@ mod tests {
         let fields = vec![IdlField {
             name: "message".to_string(),
             field_type: serde_json::json!("string"),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
         assert_eq!(result["message"], s);
     }
 
+    #[test]
+    fn test_decode_string_with_invalid_utf8() {
+        // "ab" followed by the first two bytes of a three-byte sequence ("€" is e2 82 ac)
+        let content = [b'a', b'b', 0xe2, 0x82];
+        let mut data = (content.len() as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(&content);
+        data.push(7);
+
+        let fields = vec![
+            IdlField {
+                name: "message".to_string(),
+                field_type: serde_json::json!("string"),
+                docs: Vec::new(),
+            },
+            IdlField {
+                name: "after".to_string(),
+                field_type: serde_json::json!("u8"),
+                docs: Vec::new(),
+            },
+        ];
+
+        // Strict by default
+        assert!(IdlEventDecoder::decode(&data, &fields, &[]).is_err());
+
+        let options = DecodeOptions::new().with_invalid_utf8(InvalidUtf8::Lossy);
+        let result = IdlEventDecoder::decode_with_options(&data, &fields, &[], &options).unwrap();
+        assert_eq!(result["message"], "ab\u{fffd}");
+        assert_eq!(result["after"], 7);
+
+        let options = DecodeOptions::new().with_invalid_utf8(InvalidUtf8::Hex);
+        let result = IdlEventDecoder::decode_with_options(&data, &fields, &[], &options).unwrap();
+        assert_eq!(result["message"]["hex"], "6162e282");
+        assert_eq!(result["message"]["_invalid_utf8"], true);
+        assert_eq!(result["after"], 7);
+    }
+
     #[test]
     fn test_decode_bool() {
         let data = vec![1u8]; // true
         let fields = vec![IdlField {
             name: "active".to_string(),
             field_type: serde_json::json!("bool"),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
@@ -563,10 +1194,12 @@ mod tests {
             IdlField {
                 name: "amount".to_string(),
                 field_type: serde_json::json!("u64"),
+                docs: Vec::new(),
             },
             IdlField {
                 name: "owner".to_string(),
                 field_type: serde_json::json!("publicKey"),
+                docs: Vec::new(),
             },
         ];
 
@@ -584,6 +1217,7 @@ mod tests {
         let fields = vec![IdlField {
             name: "data".to_string(),
             field_type: serde_json::json!("vec<u8>"),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
@@ -600,6 +1234,7 @@ mod tests {
         let fields = vec![IdlField {
             name: "value".to_string(),
             field_type: serde_json::json!("option<u64>"),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
@@ -614,12 +1249,83 @@ mod tests {
         let fields = vec![IdlField {
             name: "value".to_string(),
             field_type: serde_json::json!("option<u64>"),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
         assert!(result["value"].is_null());
     }
 
+    #[test]
+    fn test_decode_option_objects() {
+        let types = vec![serde_json::json!({
+            "name": "Fee",
+            "type": {
+                "kind": "struct",
+                "fields": [
+                    {"name": "bps", "type": "u16"},
+                    {"name": "recipient", "type": "pubkey"}
+                ]
+            }
+        })];
+        let fields: Vec<IdlField> = [
+            (
+                "fee",
+                serde_json::json!({"option": {"defined": {"name": "Fee"}}}),
+            ),
+            (
+                "owner",
+                serde_json::json!({"option": {"defined": {"name": "Pubkey"}}}),
+            ),
+            ("tiers", serde_json::json!({"vec": {"option": "u8"}})),
+            ("pair", serde_json::json!({"array": [{"option": "u16"}, 2]})),
+        ]
+        .into_iter()
+        .map(|(name, field_type)| IdlField {
+            name: name.to_string(),
+            field_type,
+            docs: Vec::new(),
+        })
+        .collect();
+        let recipient = solana_sdk::pubkey::Pubkey::new_unique();
+
+        // Some(Fee), None, [Some(7), None], [None, Some(300)]
+        let mut data = vec![1u8];
+        data.extend_from_slice(&25u16.to_le_bytes());
+        data.extend_from_slice(recipient.as_ref());
+        data.push(0);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[1, 7, 0]);
+        data.push(0);
+        data.push(1);
+        data.extend_from_slice(&300u16.to_le_bytes());
+
+        let result = IdlEventDecoder::decode(&data, &fields, &types).unwrap();
+        assert_eq!(result["fee"]["bps"], 25);
+        assert_eq!(result["fee"]["recipient"], recipient.to_string());
+        assert!(result["owner"].is_null());
+        assert_eq!(result["tiers"], serde_json::json!([7, null]));
+        assert_eq!(result["pair"], serde_json::json!([null, 300]));
+
+        // None, Some(Pubkey), [], [Some(1), Some(2)]
+        let mut data = vec![0u8, 1];
+        data.extend_from_slice(recipient.as_ref());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&[1, 1, 0, 1, 2, 0]);
+
+        let result = IdlEventDecoder::decode(&data, &fields, &types).unwrap();
+        assert!(result["fee"].is_null());
+        assert_eq!(result["owner"], recipient.to_string());
+        assert_eq!(result["tiers"], serde_json::json!([]));
+        assert_eq!(result["pair"], serde_json::json!([1, 2]));
+        assert!(fields
+            .iter()
+            .all(|f| IdlEventDecoder::unsupported_types(&f.field_type, &types).is_empty()));
+
+        // Tags other than 0 and 1 are corrupt data, not Some
+        assert!(IdlEventDecoder::decode(&[2, 25, 0], &fields[..1], &types).is_err());
+    }
+
     #[test]
     fn test_decode_fixed_array() {
         // array<u8, 64>
@@ -628,6 +1334,7 @@ mod tests {
         let fields = vec![IdlField {
             name: "memo".to_string(),
             field_type: serde_json::json!({"array": ["u8", 4]}),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
@@ -640,6 +1347,168 @@ mod tests {
         assert_eq!(arr[3], 4);
     }
 
+    #[test]
+    fn test_decode_bytes_encodings() {
+        let content = [0xde, 0xad, 0xbe, 0xef, 0x01];
+        let mut data = (content.len() as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(&content);
+        data.extend_from_slice(&content[..4]);
+
+        let fields = vec![
+            IdlField {
+                name: "blob".to_string(),
+                field_type: serde_json::json!("bytes"),
+                docs: Vec::new(),
+            },
+            IdlField {
+                name: "tag".to_string(),
+                field_type: serde_json::json!({"array": ["u8", 4]}),
+                docs: Vec::new(),
+            },
+        ];
+
+        let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
+        assert_eq!(result["blob"], "deadbeef01");
+        assert_eq!(result["tag"], serde_json::json!([0xde, 0xad, 0xbe, 0xef]));
+
+        let options = DecodeOptions::new().with_bytes_encoding(BytesEncoding::Base64);
+        let result = IdlEventDecoder::decode_with_options(&data, &fields, &[], &options).unwrap();
+        assert_eq!(result["blob"], "3q2+7wE=");
+        assert_eq!(result["tag"], "3q2+7w==");
+    }
+
+    #[test]
+    fn test_decode_byte_array_encoding() {
+        let data = [0xde, 0xad, 0xbe, 0xef, 0xff, 0x01];
+        let fields = vec![
+            IdlField {
+                name: "tag".to_string(),
+                field_type: serde_json::json!({"array": ["u8", 4]}),
+                docs: Vec::new(),
+            },
+            IdlField {
+                name: "deltas".to_string(),
+                field_type: serde_json::json!("[i8; 2]"),
+                docs: Vec::new(),
+            },
+        ];
+
+        let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
+        assert_eq!(result["tag"], serde_json::json!([0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(result["deltas"], serde_json::json!([-1, 1]));
+
+        let options = DecodeOptions::new().with_byte_array_encoding(BytesEncoding::Hex);
+        let result = IdlEventDecoder::decode_with_options(&data, &fields, &[], &options).unwrap();
+        assert_eq!(result["tag"], "deadbeef");
+        assert_eq!(result["deltas"], serde_json::json!([-1, 1]));
+
+        let options = DecodeOptions::new().with_byte_array_encoding(BytesEncoding::Base64);
+        let result = IdlEventDecoder::decode_with_options(&data, &fields, &[], &options).unwrap();
+        assert_eq!(result["tag"], "3q2+7w==");
+
+        assert!(IdlEventDecoder::decode(&data[..3], &fields[..1], &[]).is_err());
+    }
+
+    #[test]
+    fn test_decode_short_vec_lengths() {
+        let options = DecodeOptions::new().with_length_encoding(LengthEncoding::ShortVec);
+        let field = |name: &str, ty: &str| IdlField {
+            name: name.to_string(),
+            field_type: serde_json::json!(ty),
+            docs: Vec::new(),
+        };
+
+        // 1-byte length: vec<u8> of 3 elements, then a 2-byte string
+        let data = [3, 7, 8, 9, 2, b'h', b'i'];
+        let fields = vec![field("items", "vec<u8>"), field("name", "string")];
+        let result = IdlEventDecoder::decode_with_options(&data, &fields, &[], &options).unwrap();
+        assert_eq!(result["items"], serde_json::json!([7, 8, 9]));
+        assert_eq!(result["name"], "hi");
+
+        // 2-byte length: 0x80 0x01 = 128
+        let mut data = vec![0x80, 0x01];
+        data.extend(std::iter::repeat_n(0xab, 128));
+        let result =
+            IdlEventDecoder::decode_with_options(&data, &[field("blob", "bytes")], &[], &options)
+                .unwrap();
+        assert_eq!(result["blob"], "ab".repeat(128));
+
+        // 3-byte length: 0x80 0x80 0x01 = 16384
+        let mut data = vec![0x80, 0x80, 0x01];
+        data.extend(std::iter::repeat_n(0, 16384));
+        let result =
+            IdlEventDecoder::decode_with_options(&data, &[field("blob", "bytes")], &[], &options)
+                .unwrap();
+        assert_eq!(result["blob"].as_str().unwrap().len(), 2 * 16384);
+
+        // Overflow past u16, non-canonical and truncated prefixes are rejected
+        for bad in [&[0xff, 0xff, 0x04][..], &[0x80, 0x00], &[0x80]] {
+            assert!(IdlEventDecoder::decode_with_options(
+                bad,
+                &[field("blob", "bytes")],
+                &[],
+                &options
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_unsupported_types() {
+        let types = vec![
+            serde_json::json!({
+                "name": "Fee",
+                "type": {"kind": "struct", "fields": [
+                    {"name": "bps", "type": "u62"},
+                    {"name": "next", "type": {"option": {"defined": {"name": "Fee"}}}}
+                ]}
+            }),
+            serde_json::json!({
+                "name": "Side",
+                "type": {"kind": "enum", "variants": [
+                    {"name": "Bid"},
+                    {"name": "Ask", "fields": ["u64"]}
+                ]}
+            }),
+        ];
+
+        for supported in [
+            serde_json::json!("u64"),
+            serde_json::json!("vec<option<pubkey>>"),
+            serde_json::json!("[u8; 32]"),
+            serde_json::json!({"array": ["i128", 4]}),
+            serde_json::json!({"vec": {"option": "string"}}),
+            serde_json::json!("bytes"),
+        ] {
+            assert!(
+                IdlEventDecoder::unsupported_types(&supported, &[]).is_empty(),
+                "{}",
+                supported
+            );
+        }
+
+        assert_eq!(
+            IdlEventDecoder::unsupported_types(&serde_json::json!("vec<pubky>"), &[]),
+            vec!["unsupported type 'pubky'"]
+        );
+        assert_eq!(
+            IdlEventDecoder::unsupported_types(&serde_json::json!({"defined": "Fee"}), &types),
+            vec!["unsupported type 'u62'"]
+        );
+        assert_eq!(
+            IdlEventDecoder::unsupported_types(&serde_json::json!("vec<Side>"), &types),
+            vec!["Side::Ask has a field without a name and type (tuple fields are not supported)"]
+        );
+        assert_eq!(
+            IdlEventDecoder::unsupported_types(&serde_json::json!({"defined": "Missing"}), &types),
+            vec!["type 'Missing' not found in IDL"]
+        );
+        assert_eq!(
+            IdlEventDecoder::unsupported_types(&serde_json::json!("[u8; n]"), &[]),
+            vec!["invalid array type '[u8; n]'"]
+        );
+    }
+
     #[test]
     fn test_decode_enum() {
         // Define a simple enum type
@@ -671,6 +1540,7 @@ mod tests {
         let fields = vec![IdlField {
             name: "simple_enum".to_string(),
             field_type: serde_json::json!({"defined": {"name": "SimpleEnum"}}),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &types).unwrap();
@@ -680,6 +1550,103 @@ mod tests {
         assert_eq!(obj.get("value").unwrap(), "42");
     }
 
+    #[test]
+    fn test_decode_type_aliases() {
+        let types = vec![
+            serde_json::json!({"name": "Amount", "type": "u64"}),
+            serde_json::json!({"name": "Payload", "type": {"vec": "u8"}}),
+            serde_json::json!({
+                "name": "Total",
+                "type": {"kind": "type", "alias": {"defined": {"name": "Amount"}}}
+            }),
+            serde_json::json!({"name": "Loop", "type": {"defined": {"name": "Loop"}}}),
+        ];
+        let field = |name: &str, field_type: serde_json::Value| IdlField {
+            name: name.to_string(),
+            field_type,
+            docs: Vec::new(),
+        };
+        let fields = vec![
+            field("amount", serde_json::json!({"defined": {"name": "Amount"}})),
+            field("payload", serde_json::json!("Payload")),
+            field("total", serde_json::json!({"defined": "Total"})),
+        ];
+
+        let mut data = 5u64.to_le_bytes().to_vec();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[7, 8]);
+        data.extend_from_slice(&9u64.to_le_bytes());
+
+        let result = IdlEventDecoder::decode(&data, &fields, &types).unwrap();
+        assert_eq!(result["amount"], serde_json::json!("5"));
+        assert_eq!(result["payload"], serde_json::json!([7, 8]));
+        assert_eq!(result["total"], serde_json::json!("9"));
+
+        let cycle = serde_json::json!({"defined": "Loop"});
+        assert!(IdlEventDecoder::decode(&data, &[field("x", cycle.clone())], &types).is_err());
+        assert_eq!(IdlEventDecoder::unsupported_types(&cycle, &types).len(), 1);
+        assert!(IdlEventDecoder::unsupported_types(&fields[2].field_type, &types).is_empty());
+    }
+
+    #[test]
+    fn test_decode_vec_of_defined_struct() {
+        let types = vec![serde_json::json!({
+            "name": "Fee",
+            "type": {
+                "kind": "struct",
+                "fields": [
+                    {"name": "recipient", "type": "pubkey"},
+                    {"name": "bps", "type": "u16"}
+                ]
+            }
+        })];
+
+        let recipients = [
+            solana_sdk::pubkey::Pubkey::new_unique(),
+            solana_sdk::pubkey::Pubkey::new_unique(),
+        ];
+        let mut data = 2u32.to_le_bytes().to_vec();
+        for (i, recipient) in recipients.iter().enumerate() {
+            data.extend_from_slice(recipient.as_ref());
+            data.extend_from_slice(&(100u16 * (i as u16 + 1)).to_le_bytes());
+        }
+        // Trailing vec<option<pubkey>>: [Some(recipients[0]), None]
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(recipients[0].as_ref());
+        data.push(0);
+
+        for fee_type in [
+            serde_json::json!({"vec": {"defined": {"name": "Fee"}}}),
+            serde_json::json!("vec<Fee>"),
+        ] {
+            let fields = vec![
+                IdlField {
+                    name: "fees".to_string(),
+                    field_type: fee_type,
+                    docs: Vec::new(),
+                },
+                IdlField {
+                    name: "delegates".to_string(),
+                    field_type: serde_json::json!({"vec": {"option": "pubkey"}}),
+                    docs: Vec::new(),
+                },
+            ];
+
+            let result = IdlEventDecoder::decode(&data, &fields, &types).unwrap();
+            let fees = result["fees"].as_array().unwrap();
+            assert_eq!(fees.len(), 2);
+            assert_eq!(fees[0]["recipient"], recipients[0].to_string());
+            assert_eq!(fees[0]["bps"], 100);
+            assert_eq!(fees[1]["recipient"], recipients[1].to_string());
+            assert_eq!(fees[1]["bps"], 200);
+            assert_eq!(
+                result["delegates"],
+                serde_json::json!([recipients[0].to_string(), null])
+            );
+        }
+    }
+
     #[test]
     fn test_decode_option_complex() {
         // Test complex option format: {"option": "u32"}
@@ -690,12 +1657,54 @@ mod tests {
         let fields = vec![IdlField {
             name: "optional_value".to_string(),
             field_type: serde_json::json!({"option": "u32"}),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
         assert_eq!(result["optional_value"], 42);
     }
 
+    #[test]
+    fn test_string_and_object_forms_decode_identically() {
+        let mut data = vec![1u8];
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+        data.extend_from_slice(&(-5i64).to_le_bytes());
+        data.extend_from_slice(&(-6i64).to_le_bytes());
+
+        let string_form = vec![
+            IdlField {
+                name: "maybe".to_string(),
+                field_type: serde_json::json!("option<u64>"),
+                docs: Vec::new(),
+            },
+            IdlField {
+                name: "pair".to_string(),
+                field_type: serde_json::json!("[i64; 2]"),
+                docs: Vec::new(),
+            },
+        ];
+        let object_form = vec![
+            IdlField {
+                name: "maybe".to_string(),
+                field_type: serde_json::json!({"option": "u64"}),
+                docs: Vec::new(),
+            },
+            IdlField {
+                name: "pair".to_string(),
+                field_type: serde_json::json!({"array": ["i64", 2]}),
+                docs: Vec::new(),
+            },
+        ];
+
+        let from_string = IdlEventDecoder::decode(&data, &string_form, &[]).unwrap();
+        let from_object = IdlEventDecoder::decode(&data, &object_form, &[]).unwrap();
+
+        assert_eq!(from_string, from_object);
+        // 64-bit integers are always emitted as strings to avoid JSON precision loss
+        assert_eq!(from_string["maybe"], u64::MAX.to_string());
+        assert_eq!(from_string["pair"], serde_json::json!(["-5", "-6"]));
+    }
+
     #[test]
     fn test_decode_option_complex_none() {
         // Test complex option format: {"option": "u32"}
@@ -705,6 +1714,7 @@ mod tests {
         let fields = vec![IdlField {
             name: "optional_value".to_string(),
             field_type: serde_json::json!({"option": "u32"}),
+            docs: Vec::new(),
         }];
 
         let result = IdlEventDecoder::decode(&data, &fields, &[]).unwrap();
@@ -0,0 +1,368 @@
+use crate::{
+    error::{Result, SoltraceError},
+    idl::IdlParser,
+    idl_event::{DecodeOptions, IdlEventDecoder},
+    types::{DecodedInstruction, IdlInstruction, IdlInstructionAccount, RawEvent, Signature},
+};
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiCompiledInstruction,
+    UiInstruction, UiMessage,
+};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// A program's instructions with their discriminators, and the IDL types they use
+#[derive(Clone)]
+struct ProgramInstructions {
+    instructions: Vec<(Vec<u8>, IdlInstruction)>,
+    types: Vec<serde_json::Value>,
+}
+
+/// Decodes program instructions from transaction data using the IDL `instructions` array
+///
+/// Instruction data is an 8-byte discriminator followed by the borsh-encoded
+/// arguments, which are decoded like event fields.
+#[derive(Clone)]
+pub struct InstructionDecoder {
+    programs: HashMap<String, ProgramInstructions>,
+    decode_options: DecodeOptions,
+}
+
+impl InstructionDecoder {
+    /// Index every loaded IDL's instructions; fails if an `instructions` array is malformed
+    pub fn new(idl_parser: &IdlParser) -> Result<Self> {
+        let mut programs = HashMap::new();
+        for (program_id, idl) in idl_parser.get_idls() {
+            let Some(value) = &idl.instructions else {
+                continue;
+            };
+            let instructions: Vec<IdlInstruction> =
+                serde_json::from_value(value.clone()).map_err(|e| {
+                    SoltraceError::InvalidIdl(format!(
+                        "Invalid instructions in IDL for {}: {}",
+                        program_id, e
+                    ))
+                })?;
+
+            let instructions = instructions
+                .into_iter()
+                .map(|instruction| {
+                    let discriminator = instruction
+                        .discriminator
+                        .clone()
+                        .filter(|d| !d.is_empty())
+                        .unwrap_or_else(|| {
+                            IdlParser::calculate_instruction_discriminator(&instruction.name)
+                                .to_vec()
+                        });
+                    (discriminator, instruction)
+                })
+                .collect();
+            programs.insert(
+                program_id.clone(),
+                ProgramInstructions {
+                    instructions,
+                    types: idl.types.clone().unwrap_or_default(),
+                },
+            );
+        }
+
+        Ok(Self {
+            programs,
+            decode_options: DecodeOptions::default(),
+        })
+    }
+
+    /// Decode instruction arguments with these options (e.g. lenient UTF-8 handling)
+    pub fn with_decode_options(mut self, decode_options: DecodeOptions) -> Self {
+        self.decode_options = decode_options;
+        self
+    }
+
+    /// Decode one instruction's data, naming `accounts` (its account pubkeys, in order)
+    /// after the IDL's account list
+    ///
+    /// Data matching no instruction fails with [`SoltraceError::InstructionNotFound`].
+    /// Arguments that don't decode are kept as hex with a `decode_error`, as for events.
+    pub fn decode(
+        &self,
+        program_id: &str,
+        data: &[u8],
+        accounts: &[String],
+    ) -> Result<DecodedInstruction> {
+        let (discriminator, instruction) = self
+            .programs
+            .get(program_id)
+            .and_then(|program| {
+                program
+                    .instructions
+                    .iter()
+                    .find(|(discriminator, _)| data.starts_with(discriminator))
+            })
+            .ok_or_else(|| {
+                SoltraceError::InstructionNotFound(format!(
+                    "no instruction with discriminator {:02x?}",
+                    &data[..data.len().min(8)]
+                ))
+            })?;
+        let args = &data[discriminator.len()..];
+        let types = &self.programs[program_id].types;
+
+        let data = match IdlEventDecoder::decode_with_options(
+            args,
+            &instruction.args,
+            types,
+            &self.decode_options,
+        ) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to decode instruction '{}' (program_id: {}): {}. Fallback to hex encoding",
+                    instruction.name,
+                    program_id,
+                    e
+                );
+                serde_json::json!({
+                    "hex": hex::encode_upper(args),
+                    "length": args.len(),
+                    "decode_error": e.to_string(),
+                })
+            }
+        };
+
+        let names = IdlInstructionAccount::flatten(&instruction.accounts);
+        let mut named = serde_json::Map::new();
+        for (name, pubkey) in names.iter().zip(accounts) {
+            named.insert(name.clone(), pubkey.clone().into());
+        }
+        if accounts.len() > names.len() {
+            named.insert(
+                "remaining_accounts".to_string(),
+                accounts[names.len()..].to_vec().into(),
+            );
+        }
+
+        Ok(DecodedInstruction {
+            name: instruction.name.clone(),
+            data,
+            accounts: serde_json::Value::Object(named),
+        })
+    }
+
+    /// Decode every instruction `program_id` ran in a transaction, top-level and inner (CPI)
+    ///
+    /// Each comes back with its position among all of the transaction's
+    /// instructions, counting each top-level instruction followed by its inner
+    /// ones; that position is the `index` to store it under. Instructions matching
+    /// no IDL instruction are skipped.
+    pub fn decode_transaction(
+        &self,
+        transaction: &EncodedConfirmedTransactionWithStatusMeta,
+        program_id: &str,
+    ) -> Result<Vec<(DecodedInstruction, RawEvent, usize)>> {
+        let EncodedTransaction::Json(ui_tx) = &transaction.transaction.transaction else {
+            return Err(SoltraceError::EventDecode(
+                "Only JSON-encoded transactions are supported".to_string(),
+            ));
+        };
+        let UiMessage::Raw(message) = &ui_tx.message else {
+            return Err(SoltraceError::EventDecode(
+                "Only raw (not jsonParsed) transaction messages are supported".to_string(),
+            ));
+        };
+        let signature = Signature::parse(ui_tx.signatures.first().ok_or_else(|| {
+            SoltraceError::EventDecode("Transaction has no signature".to_string())
+        })?)?;
+
+        // Versioned transactions append the accounts they loaded from lookup tables
+        let mut account_keys = message.account_keys.clone();
+        let meta = transaction.transaction.meta.as_ref();
+        if let Some(OptionSerializer::Some(loaded)) = meta.map(|m| &m.loaded_addresses) {
+            account_keys.extend(loaded.writable.iter().cloned());
+            account_keys.extend(loaded.readonly.iter().cloned());
+        }
+        let mut inner_instructions: HashMap<usize, &Vec<UiInstruction>> = HashMap::new();
+        if let Some(OptionSerializer::Some(inner)) = meta.map(|m| &m.inner_instructions) {
+            for group in inner {
+                inner_instructions.insert(group.index as usize, &group.instructions);
+            }
+        }
+
+        let raw = RawEvent {
+            slot: transaction.slot,
+            signature,
+            program_id: program_id.parse().unwrap_or_default(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: transaction
+                .block_time
+                .and_then(|bt| chrono::DateTime::from_timestamp(bt, 0))
+                .unwrap_or_else(chrono::Utc::now),
+            raw_data: None,
+            tx_failed: meta.is_some_and(|m| m.err.is_some()),
+        };
+
+        let mut decoded = Vec::new();
+        let mut position = 0;
+        for (index, instruction) in message.instructions.iter().enumerate() {
+            let inner = inner_instructions
+                .get(&index)
+                .into_iter()
+                .flat_map(|instructions| instructions.iter())
+                .filter_map(|instruction| match instruction {
+                    UiInstruction::Compiled(compiled) => Some(compiled),
+                    UiInstruction::Parsed(_) => None,
+                });
+
+            for compiled in std::iter::once(instruction).chain(inner) {
+                if let Some(instruction) =
+                    self.decode_compiled(compiled, &account_keys, program_id)?
+                {
+                    decoded.push((instruction, raw.clone(), position));
+                }
+                position += 1;
+            }
+        }
+
+        Ok(decoded)
+    }
+
+    /// Decode a compiled instruction if `program_id` ran it and the IDL knows it
+    fn decode_compiled(
+        &self,
+        instruction: &UiCompiledInstruction,
+        account_keys: &[String],
+        program_id: &str,
+    ) -> Result<Option<DecodedInstruction>> {
+        if account_keys
+            .get(instruction.program_id_index as usize)
+            .map(String::as_str)
+            != Some(program_id)
+        {
+            return Ok(None);
+        }
+
+        let data = bs58::decode(&instruction.data)
+            .into_vec()
+            .map_err(|e| SoltraceError::EventDecode(format!("Invalid instruction data: {}", e)))?;
+        let accounts: Vec<String> = instruction
+            .accounts
+            .iter()
+            .filter_map(|&index| account_keys.get(index as usize).cloned())
+            .collect();
+
+        match self.decode(program_id, &data, &accounts) {
+            Ok(decoded) => Ok(Some(decoded)),
+            Err(SoltraceError::InstructionNotFound(e)) => {
+                debug!("Skipping instruction: {}", e);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM_ID: &str = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+
+    fn decoder() -> InstructionDecoder {
+        let mut idl_parser = IdlParser::new();
+        idl_parser
+            .load_from_str(&format!(
+                r#"{{
+                    "address": "{}",
+                    "name": "tributary",
+                    "events": [],
+                    "instructions": [{{
+                        "name": "createPayment",
+                        "accounts": [
+                            {{"name": "payer"}},
+                            {{"name": "vault", "accounts": [{{"name": "state"}}]}}
+                        ],
+                        "args": [{{"name": "amount", "type": "u64"}}]
+                    }}]
+                }}"#,
+                PROGRAM_ID
+            ))
+            .unwrap();
+        InstructionDecoder::new(&idl_parser).unwrap()
+    }
+
+    #[test]
+    fn test_decode_transaction_instructions() {
+        let decoder = decoder();
+        let mut data = IdlParser::calculate_instruction_discriminator("create_payment").to_vec();
+        data.extend_from_slice(&7u64.to_le_bytes());
+        let data = bs58::encode(&data).into_string();
+        let unknown = bs58::encode([9u8; 8]).into_string();
+
+        let signature = solana_sdk::signature::Signature::from([5u8; 64]).to_string();
+        let transaction: EncodedConfirmedTransactionWithStatusMeta =
+            serde_json::from_value(serde_json::json!({
+                "slot": 12,
+                "blockTime": 1_700_000_000,
+                "transaction": {
+                    "signatures": [signature],
+                    "message": {
+                        "header": {
+                            "numRequiredSignatures": 1,
+                            "numReadonlySignedAccounts": 0,
+                            "numReadonlyUnsignedAccounts": 1
+                        },
+                        "accountKeys": ["Payer111", "State111", "Extra111", PROGRAM_ID],
+                        "recentBlockhash": "11111111111111111111111111111111",
+                        "instructions": [
+                            {"programIdIndex": 3, "accounts": [0, 1, 2], "data": data},
+                            {"programIdIndex": 3, "accounts": [], "data": unknown}
+                        ]
+                    }
+                },
+                "meta": {
+                    "err": null,
+                    "status": { "Ok": null },
+                    "fee": 5000,
+                    "preBalances": [],
+                    "postBalances": [],
+                    "innerInstructions": [{
+                        "index": 1,
+                        "instructions": [
+                            {"programIdIndex": 3, "accounts": [0], "data": data}
+                        ]
+                    }]
+                }
+            }))
+            .unwrap();
+
+        let decoded = decoder
+            .decode_transaction(&transaction, PROGRAM_ID)
+            .unwrap();
+        // The unknown top-level instruction is skipped but keeps its position
+        let positions: Vec<_> = decoded.iter().map(|(_, _, index)| *index).collect();
+        assert_eq!(positions, vec![0, 2]);
+
+        let (instruction, raw, _) = &decoded[0];
+        assert_eq!(instruction.name, "createPayment");
+        assert_eq!(instruction.data["amount"], "7");
+        assert_eq!(
+            instruction.accounts,
+            serde_json::json!({
+                "payer": "Payer111",
+                "vault.state": "State111",
+                "remaining_accounts": ["Extra111"]
+            })
+        );
+        assert_eq!(raw.slot, 12);
+        assert_eq!(raw.signature.as_str(), signature);
+        assert!(!raw.tx_failed);
+
+        assert!(matches!(
+            decoder.decode(PROGRAM_ID, &[1, 2, 3], &[]),
+            Err(SoltraceError::InstructionNotFound(_))
+        ));
+    }
+}
@@ -1,31 +1,71 @@
+pub mod archive;
+pub mod backfill;
 pub mod db;
 pub mod error;
 pub mod event;
+pub mod filter;
+pub mod health;
 pub mod idl;
 pub mod idl_event;
+pub mod instruction;
 pub mod metrics;
 pub mod queue;
 pub mod retry;
+pub mod sink;
+pub mod source;
+#[cfg(test)]
+mod test_support;
 pub mod types;
 pub mod utils;
 pub mod validation;
+pub mod webhook;
 
-pub use db::{Database, DatabaseBackend, EventRecord};
-pub use error::{Result, SoltraceError};
+pub use archive::{FileSink, DEFAULT_ROTATE_SIZE};
+pub use backfill::{BackfillReport, Backfiller, LogProgress, ProgressReporter};
+pub use db::postgres::PostgresPoolOptions;
+pub use db::{
+    Database, DatabaseBackend, EventBounds, EventCount, EventCountField, EventCursor, EventFilter,
+    EventRecord, EventStats, InstructionRecord, Migration, DEFAULT_STREAM_LIMIT,
+};
+pub use error::{Result, SoltraceError, TransactionErrorKind};
 pub use event::EventDecoder;
+pub use filter::EventNameFilter;
+pub use health::HealthServer;
 pub use idl::IdlParser;
-pub use idl_event::IdlEventDecoder;
-pub use metrics::{HealthCheck, HealthStatus, Metrics, MetricsSnapshot};
+pub use idl_event::{BytesEncoding, DecodeOptions, IdlEventDecoder, InvalidUtf8, LengthEncoding};
+pub use instruction::InstructionDecoder;
+pub use metrics::{
+    HealthCheck, HealthCheckResult, HealthStatus, LatencyHistogram, LatencyPercentiles, Metrics,
+    MetricsSnapshot,
+};
 pub use queue::{EventQueue, QueueEvent};
 #[cfg(feature = "kafka")]
 pub use queue::kafka::{KafkaConfig, KafkaProducer};
-pub use retry::{concurrent_process, process_batches, retry_with_backoff, retry_with_rate_limit};
-pub use types::DecodedEvent;
-pub use types::{EventDiscriminator, ProgramId, ProgramPrefixConfig, Slot};
-pub use utils::{extract_event_from_log, load_idls, process_transaction};
+#[cfg(feature = "sqs")]
+pub use queue::sqs::{SqsConfig, SqsProducer};
+pub use retry::{
+    concurrent_process, concurrent_process_with_retry, concurrent_process_with_retry_stream,
+    process_batches, retry_with_backoff, retry_with_rate_limit, CircuitBreaker, RateLimiter,
+};
+pub use sink::{
+    DedupSink, EventHook, EventSink, FanoutSink, HookSink, OverlapSink, SampledSink, SinkKind,
+    StdoutJsonSink,
+};
+pub use source::{MockSource, SignatureInfo, TransactionSource};
+pub use types::{Cluster, DecodedEvent, DecodedInstruction};
+pub use types::{
+    DiscriminatorScheme, EventDiscriminator, EventNameCase, ProgramAliases, ProgramId,
+    ProgramPrefixConfig, Signature, Slot,
+};
+pub use utils::{
+    env_file_from_args, extract_event_from_log, extract_events_from_logs, load_idls,
+    process_transaction, LogEvent,
+};
 pub use validation::{
-    validate_program_id, validate_program_ids, validate_rpc_url, validate_ws_url,
+    derive_ws_url, parse_commitment, validate_commitment, validate_program_id,
+    validate_program_ids, validate_rpc_url, validate_ws_url, CommitmentMap,
 };
+pub use webhook::{WebhookSink, DEFAULT_WEBHOOK_CONCURRENCY};
 
 // Re-export anchor_lang types for users who want to define their own events
 pub use anchor_lang::Discriminator;
@@ -1,8 +1,18 @@
+use crate::db::Database;
+use crate::types::{DecodedEvent, ProgramAliases, RawEvent, Slot};
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
-use tracing::{debug, info};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Default cap on distinct keys in `events_by_program` and `events_by_type`
+pub const DEFAULT_MAX_METRIC_KEYS: usize = 5000;
+
+/// Key that counts events whose program or type arrived after the cap was reached
+pub const OTHER_METRIC_KEY: &str = "__other__";
 
 /// Metrics for tracking indexer performance
 #[derive(Debug)]
@@ -17,12 +27,20 @@ pub struct Metrics {
     pub transactions_total: AtomicU64,
     /// Number of failed transactions
     pub transactions_failed: AtomicU64,
+    /// Number of signatures skipped because their transaction was not found
+    pub transactions_missing: AtomicU64,
     /// Number of WebSocket reconnections
     pub ws_reconnections: AtomicU64,
+    /// Number of WebSocket connections found stale by a keepalive probe
+    pub ws_stale_connections: AtomicU64,
     /// Number of RPC calls made
     pub rpc_calls: AtomicU64,
     /// Number of RPC call failures
     pub rpc_failures: AtomicU64,
+    /// Number of RPC calls made on behalf of each program ID
+    pub rpc_calls_by_program: Arc<tokio::sync::RwLock<HashMap<String, u64>>>,
+    /// Number of RPC call failures by program ID
+    pub rpc_failures_by_program: Arc<tokio::sync::RwLock<HashMap<String, u64>>>,
     /// Processing start time
     pub start_time: Instant,
     /// Number of database insertions
@@ -33,6 +51,32 @@ pub struct Metrics {
     pub duplicate_events: AtomicU64,
     /// Number of events that failed to decode
     pub decode_failures: AtomicU64,
+    /// Number of decode failures by program ID
+    pub decode_failures_by_program: Arc<tokio::sync::RwLock<HashMap<String, u64>>>,
+    /// Number of events skipped by include/exclude filters
+    pub events_filtered: AtomicU64,
+    /// Largest slot gap between stored events and the chain tip, measured at startup
+    pub slot_gap: AtomicU64,
+    /// Number of events a webhook dropped after exhausting its retries
+    pub webhook_dropped: AtomicU64,
+    /// Number of events left unstored by `--sample-rate`
+    pub events_sampled_out: AtomicU64,
+    /// Number of `Program data:` payloads that looked cut off and couldn't be reassembled
+    pub events_truncated: AtomicU64,
+    /// Time spent decoding each event
+    pub decode_latency: LatencyHistogram,
+    /// Time spent inserting each event into the database
+    pub insert_latency: LatencyHistogram,
+    /// When an event was last stored
+    pub last_event_at: Mutex<Option<DateTime<Utc>>>,
+    /// When the last pipeline error happened, and its message
+    pub last_error: Mutex<Option<(DateTime<Utc>, String)>>,
+    /// Distinct keys kept per breakdown map before new keys go to [`OTHER_METRIC_KEY`]
+    max_metric_keys: usize,
+    /// Whether the key cap has been hit (and warned about) yet
+    metric_keys_capped: Arc<AtomicBool>,
+    /// Names that label programs in `events_by_program` instead of their IDs
+    program_aliases: ProgramAliases,
 }
 
 impl Default for Metrics {
@@ -49,38 +93,85 @@ impl Metrics {
             events_by_type: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
             transactions_total: AtomicU64::new(0),
             transactions_failed: AtomicU64::new(0),
+            transactions_missing: AtomicU64::new(0),
             ws_reconnections: AtomicU64::new(0),
+            ws_stale_connections: AtomicU64::new(0),
             rpc_calls: AtomicU64::new(0),
             rpc_failures: AtomicU64::new(0),
+            rpc_calls_by_program: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            rpc_failures_by_program: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
             start_time: Instant::now(),
             db_inserts: AtomicU64::new(0),
             db_insert_failures: AtomicU64::new(0),
             duplicate_events: AtomicU64::new(0),
             decode_failures: AtomicU64::new(0),
+            decode_failures_by_program: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            events_filtered: AtomicU64::new(0),
+            slot_gap: AtomicU64::new(0),
+            webhook_dropped: AtomicU64::new(0),
+            events_sampled_out: AtomicU64::new(0),
+            events_truncated: AtomicU64::new(0),
+            decode_latency: LatencyHistogram::new(),
+            insert_latency: LatencyHistogram::new(),
+            last_event_at: Mutex::new(None),
+            last_error: Mutex::new(None),
+            max_metric_keys: DEFAULT_MAX_METRIC_KEYS,
+            metric_keys_capped: Arc::new(AtomicBool::new(false)),
+            program_aliases: ProgramAliases::new(),
         }
     }
 
+    /// Keep at most `max` distinct program IDs and event types each; events for
+    /// further keys are counted under [`OTHER_METRIC_KEY`]
+    pub fn with_max_metric_keys(mut self, max: usize) -> Self {
+        self.max_metric_keys = max;
+        self
+    }
+
+    /// Label programs in `events_by_program` by these aliases rather than their IDs
+    pub fn with_program_aliases(mut self, program_aliases: ProgramAliases) -> Self {
+        self.program_aliases = program_aliases;
+        self
+    }
+
+    pub fn program_aliases(&self) -> &ProgramAliases {
+        &self.program_aliases
+    }
+
     /// Record a processed event
     pub fn record_event(&self, program_id: &str, event_type: &str) {
         self.events_total.fetch_add(1, Ordering::Relaxed);
+        *self.last_event_at.lock().unwrap() = Some(Utc::now());
 
         // Update program counter
-        let program_id = program_id.to_string();
+        let program_id = self.program_aliases.label(program_id).to_string();
         let events_by_program = self.events_by_program.clone();
+        let max_keys = self.max_metric_keys;
+        let capped = self.metric_keys_capped.clone();
         tokio::spawn(async move {
             let mut map = events_by_program.write().await;
-            *map.entry(program_id).or_insert(0) += 1;
+            count_capped(&mut map, program_id, max_keys, &capped, "events_by_program");
         });
 
         // Update event type counter
         let event_type = event_type.to_string();
         let events_by_type = self.events_by_type.clone();
+        let capped = self.metric_keys_capped.clone();
         tokio::spawn(async move {
             let mut map = events_by_type.write().await;
-            *map.entry(event_type).or_insert(0) += 1;
+            count_capped(&mut map, event_type, max_keys, &capped, "events_by_type");
         });
     }
 
+    /// List a program in the per-program breakdown even before it produces events
+    pub async fn register_program(&self, program_id: &str) {
+        self.events_by_program
+            .write()
+            .await
+            .entry(self.program_aliases.label(program_id).to_string())
+            .or_insert(0);
+    }
+
     /// Record a transaction
     pub fn record_transaction(&self, failed: bool) {
         self.transactions_total.fetch_add(1, Ordering::Relaxed);
@@ -89,11 +180,21 @@ impl Metrics {
         }
     }
 
+    /// Record a signature whose transaction was pruned or not yet available
+    pub fn record_missing_transaction(&self) {
+        self.transactions_missing.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Record a WebSocket reconnection
     pub fn record_ws_reconnection(&self) {
         self.ws_reconnections.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Record a WebSocket connection that failed its keepalive probe
+    pub fn record_ws_stale_connection(&self) {
+        self.ws_stale_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Record an RPC call
     pub fn record_rpc_call(&self, failed: bool) {
         self.rpc_calls.fetch_add(1, Ordering::Relaxed);
@@ -102,6 +203,27 @@ impl Metrics {
         }
     }
 
+    /// Record an RPC call made for `program_id`, so its health can be judged on its own
+    pub fn record_program_rpc_call(&self, program_id: &str, failed: bool) {
+        self.record_rpc_call(failed);
+
+        let program_id = program_id.to_string();
+        let rpc_calls_by_program = self.rpc_calls_by_program.clone();
+        let rpc_failures_by_program = self.rpc_failures_by_program.clone();
+        tokio::spawn(async move {
+            *rpc_calls_by_program
+                .write()
+                .await
+                .entry(program_id.clone())
+                .or_insert(0) += 1;
+            *rpc_failures_by_program
+                .write()
+                .await
+                .entry(program_id)
+                .or_insert(0) += u64::from(failed);
+        });
+    }
+
     /// Record a database insert
     pub fn record_db_insert(&self, failed: bool, duplicate: bool) {
         if failed {
@@ -115,9 +237,80 @@ impl Metrics {
         }
     }
 
-    /// Record a decode failure
-    pub fn record_decode_failure(&self) {
+    /// Record a batch write of `events` that reported `inserted` of them as new
+    ///
+    /// The count doesn't say which events were the duplicates, so events are only
+    /// broken down by program and type when all or none of the batch was new;
+    /// otherwise just the totals move.
+    pub fn record_batch_insert(&self, events: &[(DecodedEvent, RawEvent)], inserted: usize) {
+        let inserted = inserted.min(events.len());
+        let duplicates = events.len() - inserted;
+        self.db_inserts
+            .fetch_add(inserted as u64, Ordering::Relaxed);
+        self.duplicate_events
+            .fetch_add(duplicates as u64, Ordering::Relaxed);
+
+        if duplicates == 0 {
+            for (event, raw) in events {
+                self.record_event(&raw.program_id.to_string(), &event.event_name);
+            }
+        } else if inserted > 0 {
+            self.events_total
+                .fetch_add(inserted as u64, Ordering::Relaxed);
+            *self.last_event_at.lock().unwrap() = Some(Utc::now());
+        }
+    }
+
+    /// Record an event of `program_id` that failed to decode
+    pub fn record_decode_failure(&self, program_id: &str) {
         self.decode_failures.fetch_add(1, Ordering::Relaxed);
+
+        let program_id = program_id.to_string();
+        let decode_failures_by_program = self.decode_failures_by_program.clone();
+        tokio::spawn(async move {
+            let mut map = decode_failures_by_program.write().await;
+            *map.entry(program_id).or_insert(0) += 1;
+        });
+    }
+
+    /// Record an event skipped by the event name filter
+    pub fn record_filtered_event(&self) {
+        self.events_filtered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record the slot gap between stored events and the chain tip
+    pub fn record_slot_gap(&self, gap: u64) {
+        self.slot_gap.store(gap, Ordering::Relaxed);
+    }
+
+    /// Record an event a webhook gave up on
+    pub fn record_webhook_dropped(&self) {
+        self.webhook_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record an event that sampling left unstored
+    pub fn record_sampled_out(&self) {
+        self.events_sampled_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record an event payload that was cut off in the logs
+    pub fn record_truncated_event(&self) {
+        self.events_truncated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a pipeline error (failed store, RPC call, decode or connection) for alerting
+    pub fn record_error(&self, message: impl std::fmt::Display) {
+        *self.last_error.lock().unwrap() = Some((Utc::now(), message.to_string()));
+    }
+
+    /// Record how long decoding a single event took
+    pub fn record_decode_latency(&self, elapsed: Duration) {
+        self.decode_latency.record(elapsed);
+    }
+
+    /// Record how long inserting a single event took
+    pub fn record_insert_latency(&self, elapsed: Duration) {
+        self.insert_latency.record(elapsed);
     }
 
     /// Get events per second
@@ -139,6 +332,10 @@ impl Metrics {
     pub async fn snapshot(&self) -> MetricsSnapshot {
         let events_by_program = self.events_by_program.read().await.clone();
         let events_by_type = self.events_by_type.read().await.clone();
+        let decode_failures_by_program = self.decode_failures_by_program.read().await.clone();
+        let rpc_calls_by_program = self.rpc_calls_by_program.read().await.clone();
+        let rpc_failures_by_program = self.rpc_failures_by_program.read().await.clone();
+        let last_error = self.last_error.lock().unwrap().clone();
 
         MetricsSnapshot {
             events_total: self.events_total.load(Ordering::Relaxed),
@@ -146,15 +343,30 @@ impl Metrics {
             events_by_type,
             transactions_total: self.transactions_total.load(Ordering::Relaxed),
             transactions_failed: self.transactions_failed.load(Ordering::Relaxed),
+            transactions_missing: self.transactions_missing.load(Ordering::Relaxed),
             ws_reconnections: self.ws_reconnections.load(Ordering::Relaxed),
+            ws_stale_connections: self.ws_stale_connections.load(Ordering::Relaxed),
             rpc_calls: self.rpc_calls.load(Ordering::Relaxed),
             rpc_failures: self.rpc_failures.load(Ordering::Relaxed),
+            rpc_calls_by_program,
+            rpc_failures_by_program,
             uptime_seconds: self.uptime_seconds(),
             events_per_second: self.events_per_second(),
             db_inserts: self.db_inserts.load(Ordering::Relaxed),
             db_insert_failures: self.db_insert_failures.load(Ordering::Relaxed),
             duplicate_events: self.duplicate_events.load(Ordering::Relaxed),
             decode_failures: self.decode_failures.load(Ordering::Relaxed),
+            decode_failures_by_program,
+            events_filtered: self.events_filtered.load(Ordering::Relaxed),
+            slot_gap: self.slot_gap.load(Ordering::Relaxed),
+            webhook_dropped: self.webhook_dropped.load(Ordering::Relaxed),
+            events_sampled_out: self.events_sampled_out.load(Ordering::Relaxed),
+            events_truncated: self.events_truncated.load(Ordering::Relaxed),
+            decode_latency_ms: self.decode_latency.percentiles(),
+            insert_latency_ms: self.insert_latency.percentiles(),
+            last_event_at: *self.last_event_at.lock().unwrap(),
+            last_error_at: last_error.as_ref().map(|(at, _)| *at),
+            last_error_message: last_error.map(|(_, message)| message),
         }
     }
 
@@ -170,8 +382,23 @@ impl Metrics {
             snapshot.rpc_calls,
             snapshot.rpc_failures
         );
-        debug!("Events by program: {:?}", snapshot.events_by_program);
-        debug!("Events by type: {:?}", snapshot.events_by_type);
+        for line in snapshot.breakdown() {
+            info!("{}", line);
+        }
+    }
+
+    /// Spawn a task that logs the summary every `interval` until aborted
+    pub fn spawn_summary_logger(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let metrics = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately; nothing to report yet
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                metrics.log_summary().await;
+            }
+        })
     }
 }
 
@@ -183,18 +410,63 @@ pub struct MetricsSnapshot {
     pub events_by_type: HashMap<String, u64>,
     pub transactions_total: u64,
     pub transactions_failed: u64,
+    pub transactions_missing: u64,
     pub ws_reconnections: u64,
+    pub ws_stale_connections: u64,
     pub rpc_calls: u64,
     pub rpc_failures: u64,
+    pub rpc_calls_by_program: HashMap<String, u64>,
+    pub rpc_failures_by_program: HashMap<String, u64>,
     pub uptime_seconds: u64,
     pub events_per_second: f64,
     pub db_inserts: u64,
     pub db_insert_failures: u64,
     pub duplicate_events: u64,
     pub decode_failures: u64,
+    pub decode_failures_by_program: HashMap<String, u64>,
+    pub events_filtered: u64,
+    pub slot_gap: u64,
+    pub webhook_dropped: u64,
+    pub events_sampled_out: u64,
+    pub events_truncated: u64,
+    pub decode_latency_ms: LatencyPercentiles,
+    pub insert_latency_ms: LatencyPercentiles,
+    pub last_event_at: Option<DateTime<Utc>>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_error_message: Option<String>,
 }
 
 impl MetricsSnapshot {
+    /// Per-program event and decode failure counts, then per-event-name counts, as log lines
+    pub fn breakdown(&self) -> Vec<String> {
+        let mut programs: Vec<&String> = self
+            .events_by_program
+            .keys()
+            .chain(self.decode_failures_by_program.keys())
+            .collect();
+        programs.sort();
+        programs.dedup();
+
+        let mut lines = vec!["Events by program:".to_string()];
+        for program in programs {
+            lines.push(format!(
+                "  {}: {} event(s), {} decode failure(s)",
+                program,
+                self.events_by_program.get(program).unwrap_or(&0),
+                self.decode_failures_by_program.get(program).unwrap_or(&0)
+            ));
+        }
+
+        let mut event_names: Vec<_> = self.events_by_type.iter().collect();
+        event_names.sort();
+        lines.push("Events by name:".to_string());
+        for (event_name, count) in event_names {
+            lines.push(format!("  {}: {}", event_name, count));
+        }
+
+        lines
+    }
+
     /// Export as JSON
     pub fn to_json(&self) -> serde_json::Value {
         serde_json::json!({
@@ -203,21 +475,274 @@ impl MetricsSnapshot {
             "events_by_type": self.events_by_type,
             "transactions_total": self.transactions_total,
             "transactions_failed": self.transactions_failed,
+            "transactions_missing": self.transactions_missing,
             "ws_reconnections": self.ws_reconnections,
+            "ws_stale_connections": self.ws_stale_connections,
             "rpc_calls": self.rpc_calls,
             "rpc_failures": self.rpc_failures,
+            "rpc_calls_by_program": self.rpc_calls_by_program,
+            "rpc_failures_by_program": self.rpc_failures_by_program,
             "uptime_seconds": self.uptime_seconds,
             "events_per_second": self.events_per_second,
             "db_inserts": self.db_inserts,
             "db_insert_failures": self.db_insert_failures,
             "duplicate_events": self.duplicate_events,
             "decode_failures": self.decode_failures,
+            "decode_failures_by_program": self.decode_failures_by_program,
+            "events_filtered": self.events_filtered,
+            "slot_gap": self.slot_gap,
+            "webhook_dropped": self.webhook_dropped,
+            "events_sampled_out": self.events_sampled_out,
+            "events_truncated": self.events_truncated,
+            "decode_latency_ms": self.decode_latency_ms.to_json(),
+            "insert_latency_ms": self.insert_latency_ms.to_json(),
+            "last_event_at": self.last_event_at.map(|at| at.to_rfc3339()),
+            "last_error_at": self.last_error_at.map(|at| at.to_rfc3339()),
+            "last_error_message": self.last_error_message,
         })
     }
+
+    /// Export in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        let counters = [
+            ("events_total", self.events_total),
+            ("transactions_total", self.transactions_total),
+            ("transactions_failed", self.transactions_failed),
+            ("transactions_missing", self.transactions_missing),
+            ("ws_reconnections", self.ws_reconnections),
+            ("ws_stale_connections", self.ws_stale_connections),
+            ("rpc_calls", self.rpc_calls),
+            ("rpc_failures", self.rpc_failures),
+            ("db_inserts", self.db_inserts),
+            ("db_insert_failures", self.db_insert_failures),
+            ("duplicate_events", self.duplicate_events),
+            ("decode_failures", self.decode_failures),
+            ("events_filtered", self.events_filtered),
+            ("webhook_dropped", self.webhook_dropped),
+            ("events_sampled_out", self.events_sampled_out),
+            ("events_truncated", self.events_truncated),
+        ];
+        for (name, value) in counters {
+            out.push_str(&format!(
+                "# TYPE soltrace_{name} counter\nsoltrace_{name} {value}\n"
+            ));
+        }
+
+        out.push_str(&format!(
+            "# TYPE soltrace_uptime_seconds gauge\nsoltrace_uptime_seconds {}\n",
+            self.uptime_seconds
+        ));
+        out.push_str(&format!(
+            "# TYPE soltrace_events_per_second gauge\nsoltrace_events_per_second {}\n",
+            self.events_per_second
+        ));
+        out.push_str(&format!(
+            "# TYPE soltrace_slot_gap gauge\nsoltrace_slot_gap {}\n",
+            self.slot_gap
+        ));
+        let timestamps = [
+            ("last_event_timestamp_seconds", self.last_event_at),
+            ("last_error_timestamp_seconds", self.last_error_at),
+        ];
+        for (name, at) in timestamps {
+            if let Some(at) = at {
+                out.push_str(&format!(
+                    "# TYPE soltrace_{name} gauge\nsoltrace_{name} {}\n",
+                    at.timestamp()
+                ));
+            }
+        }
+
+        let labeled = [
+            ("events_by_program", "program", &self.events_by_program),
+            ("events_by_type", "event_type", &self.events_by_type),
+            (
+                "decode_failures_by_program",
+                "program",
+                &self.decode_failures_by_program,
+            ),
+            (
+                "rpc_calls_by_program",
+                "program",
+                &self.rpc_calls_by_program,
+            ),
+            (
+                "rpc_failures_by_program",
+                "program",
+                &self.rpc_failures_by_program,
+            ),
+        ];
+        for (name, label, values) in labeled {
+            out.push_str(&format!("# TYPE soltrace_{name} counter\n"));
+            let mut values: Vec<_> = values.iter().collect();
+            values.sort();
+            for (key, value) in values {
+                out.push_str(&format!(
+                    "soltrace_{name}{{{label}=\"{}\"}} {value}\n",
+                    escape_label(key)
+                ));
+            }
+        }
+
+        let latencies = [
+            ("decode_latency_ms", &self.decode_latency_ms),
+            ("insert_latency_ms", &self.insert_latency_ms),
+        ];
+        for (name, latency) in latencies {
+            out.push_str(&format!("# TYPE soltrace_{name} summary\n"));
+            for (quantile, value) in [
+                ("0.5", latency.p50),
+                ("0.9", latency.p90),
+                ("0.99", latency.p99),
+            ] {
+                out.push_str(&format!(
+                    "soltrace_{name}{{quantile=\"{quantile}\"}} {value}\n"
+                ));
+            }
+            out.push_str(&format!("soltrace_{name}_count {}\n", latency.count));
+        }
+
+        out
+    }
 }
 
-/// Health check status
-#[derive(Debug, Clone, Copy, PartialEq)]
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Count one event under `key`, or under [`OTHER_METRIC_KEY`] once `map` holds
+/// `max_keys` other keys, so unbounded program IDs or event names can't grow it forever
+fn count_capped(
+    map: &mut HashMap<String, u64>,
+    key: String,
+    max_keys: usize,
+    capped: &AtomicBool,
+    metric: &str,
+) {
+    let key = if map.contains_key(&key) || map.len() < max_keys {
+        key
+    } else {
+        if !capped.swap(true, Ordering::Relaxed) {
+            warn!(
+                "{} reached {} distinct keys; counting new keys under '{}'",
+                metric, max_keys, OTHER_METRIC_KEY
+            );
+        }
+        OTHER_METRIC_KEY.to_string()
+    };
+    *map.entry(key).or_insert(0) += 1;
+}
+
+/// Upper bounds (milliseconds) of the latency histogram buckets
+const LATENCY_BUCKETS_MS: [f64; 16] = [
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0,
+    10000.0,
+];
+
+/// Lock-free bucketed latency histogram
+///
+/// Percentiles resolve to the upper bound of the bucket containing them, which
+/// is precise enough to tell a slow RPC from a slow database.
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    /// One counter per bucket, plus a final overflow bucket
+    buckets: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+    count: AtomicU64,
+    max_us: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            count: AtomicU64::new(0),
+            max_us: AtomicU64::new(0),
+        }
+    }
+
+    /// Record a single observation
+    pub fn record(&self, elapsed: Duration) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| ms <= bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.max_us
+            .fetch_max(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Estimate the latency (ms) below which `quantile` (0.0..=1.0) of observations fall
+    pub fn percentile(&self, quantile: f64) -> f64 {
+        let count = self.count.load(Ordering::Relaxed);
+        if count == 0 {
+            return 0.0;
+        }
+
+        let max_ms = self.max_us.load(Ordering::Relaxed) as f64 / 1000.0;
+        let rank = ((quantile * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= rank {
+                // Never report more than the slowest observation
+                return LATENCY_BUCKETS_MS
+                    .get(i)
+                    .map_or(max_ms, |&bound| bound.min(max_ms));
+            }
+        }
+
+        max_ms
+    }
+
+    pub fn percentiles(&self) -> LatencyPercentiles {
+        LatencyPercentiles {
+            count: self.count.load(Ordering::Relaxed),
+            p50: self.percentile(0.50),
+            p90: self.percentile(0.90),
+            p99: self.percentile(0.99),
+            max: self.max_us.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+}
+
+/// Latency percentiles in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyPercentiles {
+    pub count: u64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl LatencyPercentiles {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "count": self.count,
+            "p50": self.p50,
+            "p90": self.p90,
+            "p99": self.p99,
+            "max": self.max,
+        })
+    }
+}
+
+/// Health check status, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum HealthStatus {
     Healthy,
     Degraded,
@@ -237,16 +762,20 @@ impl std::fmt::Display for HealthStatus {
 /// Health check for the indexer
 pub struct HealthCheck {
     metrics: Arc<Metrics>,
+    database: Option<Arc<Database>>,
     max_reconnections: u64,
     max_failure_rate: f64,
+    max_event_age: Option<Duration>,
 }
 
 impl HealthCheck {
     pub fn new(metrics: Arc<Metrics>) -> Self {
         Self {
             metrics,
+            database: None,
             max_reconnections: 10,
             max_failure_rate: 0.5, // 50% failure rate
+            max_event_age: None,
         }
     }
 
@@ -262,6 +791,28 @@ impl HealthCheck {
         self
     }
 
+    /// Mark as degraded when no event has been stored for longer than this
+    /// (counted from startup until the first event)
+    pub fn with_max_event_age(mut self, max_age: Duration) -> Self {
+        self.max_event_age = Some(max_age);
+        self
+    }
+
+    /// Ping this database on every check, and report its newest indexed slot as a
+    /// freshness indicator
+    pub fn with_database(mut self, database: Arc<Database>) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    pub fn database(&self) -> Option<&Arc<Database>> {
+        self.database.as_ref()
+    }
+
     /// Check current health status
     pub fn check(&self) -> HealthStatus {
         let reconnections = self.metrics.ws_reconnections.load(Ordering::Relaxed);
@@ -286,20 +837,107 @@ impl HealthCheck {
             return HealthStatus::Degraded;
         }
 
+        if self.is_stale() {
+            return HealthStatus::Degraded;
+        }
+
         HealthStatus::Healthy
     }
 
+    /// Whether no event has been stored within `max_event_age`
+    fn is_stale(&self) -> bool {
+        let Some(max_age) = self.max_event_age else {
+            return false;
+        };
+        let age = match *self.metrics.last_event_at.lock().unwrap() {
+            Some(at) => (Utc::now() - at).to_std().unwrap_or_default(),
+            None => self.metrics.start_time.elapsed(),
+        };
+        age > max_age
+    }
+
+    /// Each program's status from its own RPC failure rate, so one failing program
+    /// isn't hidden by healthy ones
+    pub fn program_statuses(&self, snapshot: &MetricsSnapshot) -> HashMap<String, HealthStatus> {
+        snapshot
+            .rpc_calls_by_program
+            .iter()
+            .map(|(program, &calls)| {
+                let failures = snapshot.rpc_failures_by_program.get(program).copied();
+                let failure_rate = failures.unwrap_or(0) as f64 / calls.max(1) as f64;
+                let status = if failure_rate > self.max_failure_rate {
+                    HealthStatus::Degraded
+                } else {
+                    HealthStatus::Healthy
+                };
+                (program.clone(), status)
+            })
+            .collect()
+    }
+
     /// Get health check result with details
+    ///
+    /// The status is the worst of the overall check and every program's own status.
+    /// An attached database that doesn't answer a ping makes the indexer unhealthy.
     pub async fn health_check(&self) -> HealthCheckResult {
-        let status = self.check();
         let snapshot = self.metrics.snapshot().await;
+        let programs = self.program_statuses(&snapshot);
+        let mut status = programs
+            .values()
+            .copied()
+            .fold(self.check(), HealthStatus::max);
+
+        let db_reachable = match &self.database {
+            Some(database) => Some(match database.ping().await {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Database health ping failed: {}", e);
+                    false
+                }
+            }),
+            None => None,
+        };
+        if db_reachable == Some(false) {
+            status = HealthStatus::Unhealthy;
+        }
+
+        // Informational only: a failing query doesn't change the status
+        let latest_indexed_slot = match &self.database {
+            Some(database) if db_reachable == Some(true) => {
+                database.get_global_latest_slot().await.unwrap_or_else(|e| {
+                    debug!("Failed to read the latest indexed slot: {}", e);
+                    None
+                })
+            }
+            _ => None,
+        };
+
+        let mut degraded_programs: Vec<_> = programs
+            .iter()
+            .filter(|(_, status)| **status != HealthStatus::Healthy)
+            .map(|(program, _)| self.metrics.program_aliases().display(program))
+            .collect();
+        degraded_programs.sort();
 
         HealthCheckResult {
             status,
             metrics: snapshot,
+            programs,
+            latest_indexed_slot,
+            db_reachable,
             message: match status {
                 HealthStatus::Healthy => "All systems operational".to_string(),
+                HealthStatus::Degraded if !degraded_programs.is_empty() => {
+                    format!("Degraded program(s): {}", degraded_programs.join(", "))
+                }
+                HealthStatus::Degraded if self.is_stale() => format!(
+                    "No events stored in the last {:?}",
+                    self.max_event_age.unwrap_or_default()
+                ),
                 HealthStatus::Degraded => "System performance degraded".to_string(),
+                HealthStatus::Unhealthy if db_reachable == Some(false) => {
+                    "Database unreachable".to_string()
+                }
                 HealthStatus::Unhealthy => "System unhealthy".to_string(),
             },
         }
@@ -311,7 +949,13 @@ impl HealthCheck {
 pub struct HealthCheckResult {
     pub status: HealthStatus,
     pub metrics: MetricsSnapshot,
+    /// Status of each program that has made RPC calls
+    pub programs: HashMap<String, HealthStatus>,
     pub message: String,
+    /// Highest slot of any stored event, when a database is attached
+    pub latest_indexed_slot: Option<Slot>,
+    /// Whether the attached database answered a ping (`None` without a database)
+    pub db_reachable: Option<bool>,
 }
 
 impl HealthCheckResult {
@@ -320,6 +964,13 @@ impl HealthCheckResult {
         serde_json::json!({
             "status": self.status.to_string(),
             "message": self.message,
+            "latest_indexed_slot": self.latest_indexed_slot,
+            "db_reachable": self.db_reachable,
+            "programs": self
+                .programs
+                .iter()
+                .map(|(program, status)| (program.clone(), status.to_string()))
+                .collect::<HashMap<_, _>>(),
             "metrics": self.metrics.to_json(),
         })
     }
@@ -345,6 +996,88 @@ mod tests {
         tokio::time::sleep(std::time::Duration::from_millis(10)).await;
     }
 
+    #[tokio::test]
+    async fn test_batch_insert_only_breaks_down_unambiguous_batches() {
+        let metrics = Metrics::new();
+        let program_id = solana_sdk::pubkey::Pubkey::new_unique();
+        let pair = |event_name: &str, log_index: u32| {
+            let event = DecodedEvent {
+                event_name: event_name.to_string(),
+                data: serde_json::json!({}),
+                discriminator: [0; 8],
+            };
+            let raw = RawEvent {
+                slot: 1,
+                signature: crate::types::Signature::parse(
+                    &solana_sdk::signature::Signature::from([1u8; 64]).to_string(),
+                )
+                .unwrap(),
+                program_id,
+                log: String::new(),
+                log_index,
+                inner_instruction_index: None,
+                timestamp: Utc::now(),
+                raw_data: None,
+                tx_failed: false,
+            };
+            (event, raw)
+        };
+        let batch = vec![pair("Deposit", 0), pair("Withdraw", 1)];
+
+        metrics.record_batch_insert(&batch, 2);
+        metrics.record_batch_insert(&batch, 0);
+        // One of the two was new, but which one is unknown
+        metrics.record_batch_insert(&batch, 1);
+        // Wait for the async hashmap updates to complete
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        assert_eq!(metrics.events_total.load(Ordering::Relaxed), 3);
+        assert_eq!(metrics.db_inserts.load(Ordering::Relaxed), 3);
+        assert_eq!(metrics.duplicate_events.load(Ordering::Relaxed), 3);
+        let by_type = metrics.events_by_type.read().await.clone();
+        assert_eq!(by_type["Deposit"], 1);
+        assert_eq!(by_type["Withdraw"], 1);
+        let by_program = metrics.events_by_program.read().await.clone();
+        assert_eq!(by_program[&program_id.to_string()], 2);
+    }
+
+    #[tokio::test]
+    async fn test_metric_keys_are_capped() {
+        let metrics = Metrics::new().with_max_metric_keys(2);
+        for event_type in ["A", "B", "C", "D", "A"] {
+            metrics.record_event("program1", event_type);
+        }
+        // Wait for the async hashmap updates to complete
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        let by_type = metrics.events_by_type.read().await.clone();
+        assert_eq!(by_type.len(), 3);
+        assert_eq!(by_type["A"], 2);
+        assert_eq!(by_type[OTHER_METRIC_KEY], 2);
+        assert_eq!(metrics.events_by_program.read().await["program1"], 5);
+    }
+
+    #[tokio::test]
+    async fn test_program_aliases_label_events_by_program() {
+        assert!(ProgramAliases::from_string("program1").is_err());
+        let aliases = ProgramAliases::from_string("program1=vault, program3 = pool").unwrap();
+        assert_eq!(aliases.display("program1"), "vault (program1)");
+        assert_eq!(aliases.display("program2"), "program2");
+
+        let metrics = Metrics::new().with_program_aliases(aliases);
+        metrics.register_program("program3").await;
+        metrics.record_event("program1", "Deposit");
+        metrics.record_event("program2", "Deposit");
+        // Wait for the async hashmap updates to complete
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        let by_program = metrics.events_by_program.read().await.clone();
+        assert_eq!(by_program["vault"], 1);
+        assert_eq!(by_program["program2"], 1);
+        assert_eq!(by_program["pool"], 0);
+        assert!(!by_program.contains_key("program1"));
+    }
+
     #[test]
     fn test_metrics_record_transaction() {
         let metrics = Metrics::new();
@@ -354,6 +1087,73 @@ mod tests {
         assert_eq!(metrics.transactions_failed.load(Ordering::Relaxed), 1);
     }
 
+    #[test]
+    fn test_metrics_record_filtered_event() {
+        let metrics = Metrics::new();
+        metrics.record_filtered_event();
+        metrics.record_filtered_event();
+        assert_eq!(metrics.events_filtered.load(Ordering::Relaxed), 2);
+        assert_eq!(metrics.events_total.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_latency_histogram_percentiles() {
+        let histogram = LatencyHistogram::new();
+        assert_eq!(histogram.percentile(0.5), 0.0);
+
+        for _ in 0..98 {
+            histogram.record(Duration::from_micros(800));
+        }
+        histogram.record(Duration::from_millis(40));
+        histogram.record(Duration::from_millis(20_000));
+
+        let percentiles = histogram.percentiles();
+        assert_eq!(percentiles.count, 100);
+        assert_eq!(percentiles.p50, 1.0);
+        assert_eq!(percentiles.p99, 50.0);
+        assert_eq!(percentiles.max, 20_000.0);
+        assert_eq!(histogram.percentile(1.0), 20_000.0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_prometheus_output() {
+        let metrics = Metrics::new();
+        metrics.record_transaction(false);
+        metrics.record_ws_stale_connection();
+        metrics.record_decode_latency(Duration::from_micros(300));
+        metrics.record_insert_latency(Duration::from_millis(3));
+
+        let output = metrics.snapshot().await.to_prometheus();
+        assert!(output.contains("soltrace_transactions_total 1\n"));
+        assert!(output.contains("soltrace_ws_stale_connections 1\n"));
+        assert!(output.contains("soltrace_decode_latency_ms{quantile=\"0.5\"} 0.3\n"));
+        assert!(output.contains("soltrace_insert_latency_ms{quantile=\"0.99\"} 3\n"));
+        assert!(output.contains("soltrace_insert_latency_ms_count 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_breakdown() {
+        let metrics = Metrics::new();
+        metrics.register_program("quiet").await;
+        metrics.record_event("busy", "busy_Transfer");
+        metrics.record_event("busy", "busy_Transfer");
+        metrics.record_decode_failure("broken");
+        // Wait for the async hashmap updates to complete
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        assert_eq!(
+            metrics.snapshot().await.breakdown(),
+            vec![
+                "Events by program:",
+                "  broken: 0 event(s), 1 decode failure(s)",
+                "  busy: 2 event(s), 0 decode failure(s)",
+                "  quiet: 0 event(s), 0 decode failure(s)",
+                "Events by name:",
+                "  busy_Transfer: 2",
+            ]
+        );
+    }
+
     #[test]
     fn test_health_check_healthy() {
         let metrics = Arc::new(Metrics::new());
@@ -385,4 +1185,106 @@ mod tests {
         let health = HealthCheck::new(metrics).with_max_failure_rate(0.5);
         assert_eq!(health.check(), HealthStatus::Degraded);
     }
+
+    #[tokio::test]
+    async fn test_health_check_flags_stale_events_and_reports_last_error() {
+        let metrics = Arc::new(Metrics::new());
+        let health =
+            HealthCheck::new(metrics.clone()).with_max_event_age(Duration::from_secs(3600));
+        assert_eq!(health.check(), HealthStatus::Healthy);
+
+        *metrics.last_event_at.lock().unwrap() = Some(Utc::now() - chrono::Duration::hours(2));
+        let result = health.health_check().await;
+        assert_eq!(result.status, HealthStatus::Degraded);
+        assert_eq!(result.message, "No events stored in the last 3600s");
+
+        metrics.record_event("program1", "Transfer");
+        metrics.record_error("Failed to store event: disk full");
+        let result = health.health_check().await;
+        assert_eq!(result.status, HealthStatus::Healthy);
+        let json = result.to_json();
+        assert!(json["metrics"]["last_event_at"].is_string());
+        assert!(json["metrics"]["last_error_at"].is_string());
+        assert_eq!(
+            json["metrics"]["last_error_message"],
+            "Failed to store event: disk full"
+        );
+        assert!(result
+            .metrics
+            .to_prometheus()
+            .contains("# TYPE soltrace_last_error_timestamp_seconds gauge\n"));
+    }
+
+    #[tokio::test]
+    async fn test_health_check_isolates_programs() {
+        let metrics = Arc::new(Metrics::new());
+        for _ in 0..3 {
+            metrics.record_program_rpc_call("noisy", true);
+        }
+        for _ in 0..10 {
+            metrics.record_program_rpc_call("quiet", false);
+        }
+        metrics.record_program_rpc_call("steady", false);
+        // Wait for the async hashmap updates to complete
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        // 3 failures in 14 calls is fine overall, but not for the program that failed
+        let health = HealthCheck::new(metrics).with_max_failure_rate(0.5);
+        assert_eq!(health.check(), HealthStatus::Healthy);
+        let result = health.health_check().await;
+        assert_eq!(result.programs["noisy"], HealthStatus::Degraded);
+        assert_eq!(result.programs["quiet"], HealthStatus::Healthy);
+        assert_eq!(result.programs["steady"], HealthStatus::Healthy);
+        assert_eq!(result.status, HealthStatus::Degraded);
+        assert_eq!(result.message, "Degraded program(s): noisy");
+        assert_eq!(result.to_json()["programs"]["quiet"], "healthy");
+        assert_eq!(result.metrics.rpc_failures_by_program["quiet"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_latest_indexed_slot() {
+        let metrics = Arc::new(Metrics::new());
+        let health = HealthCheck::new(metrics.clone());
+        assert_eq!(
+            health.health_check().await.to_json()["latest_indexed_slot"],
+            serde_json::Value::Null
+        );
+        assert_eq!(health.health_check().await.db_reachable, None);
+
+        let database = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let health = HealthCheck::new(metrics).with_database(database.clone());
+        let result = health.health_check().await;
+        assert_eq!(result.latest_indexed_slot, None);
+        assert_eq!(result.db_reachable, Some(true));
+        assert_eq!(result.status, HealthStatus::Healthy);
+        assert_eq!(result.to_json()["db_reachable"], true);
+
+        let signature = solana_sdk::signature::Signature::from([3u8; 64]).to_string();
+        for (slot, program_id) in [
+            (12, solana_sdk::pubkey::Pubkey::new_unique()),
+            (30, solana_sdk::pubkey::Pubkey::new_unique()),
+        ] {
+            let event = crate::types::DecodedEvent {
+                event_name: "tributary_Transfer".to_string(),
+                data: serde_json::json!({}),
+                discriminator: [0; 8],
+            };
+            let raw = crate::types::RawEvent {
+                slot,
+                signature: crate::types::Signature::parse(&signature).unwrap(),
+                program_id,
+                log: String::new(),
+                log_index: slot as u32,
+                inner_instruction_index: None,
+                timestamp: chrono::Utc::now(),
+                raw_data: None,
+                tx_failed: false,
+            };
+            database.insert_event(&event, &raw).await.unwrap();
+        }
+
+        let result = health.health_check().await;
+        assert_eq!(result.latest_indexed_slot, Some(30));
+        assert_eq!(result.to_json()["latest_indexed_slot"], 30);
+    }
 }
@@ -1,31 +1,42 @@
+use crate::{
+    db::discriminator_to_hex,
+    types::{DecodedEvent, RawEvent, Slot},
+};
 use async_trait::async_trait;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "kafka")]
 pub mod kafka;
+#[cfg(feature = "sqs")]
+pub mod sqs;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct QueueEvent {
     pub event_name: String,
     pub signature: String,
     pub program_id: String,
+    pub slot: Slot,
+    /// Uppercase hex discriminator the event matched, as stored in the database
+    pub discriminator: String,
     pub data: serde_json::Value,
     pub timestamp: String,
+    /// Set for events kept from a failed transaction; omitted otherwise
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tx_failed: bool,
 }
 
 impl QueueEvent {
-    pub fn new(
-        event_name: String,
-        signature: String,
-        program_id: String,
-        data: serde_json::Value,
-    ) -> Self {
+    /// Build the queue message for a decoded event, mirroring what the database stores
+    pub fn from_decoded(event: &DecodedEvent, raw: &RawEvent) -> Self {
         Self {
-            event_name,
-            signature,
-            program_id,
-            data,
-            timestamp: chrono::Utc::now().to_rfc3339(),
+            event_name: event.event_name.clone(),
+            signature: raw.signature.to_string(),
+            program_id: raw.program_id.to_string(),
+            slot: raw.slot,
+            discriminator: discriminator_to_hex(&event.discriminator),
+            data: event.data.clone(),
+            timestamp: raw.timestamp.to_rfc3339(),
+            tx_failed: raw.tx_failed,
         }
     }
 }
@@ -35,3 +46,43 @@ pub trait EventQueue: Send + Sync {
     async fn send(&self, event: &QueueEvent) -> anyhow::Result<()>;
     async fn flush(&self) -> anyhow::Result<()>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Signature;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn test_from_decoded_round_trip() {
+        let signature = solana_sdk::signature::Signature::from([3u8; 64]).to_string();
+        let raw = RawEvent {
+            slot: 1234,
+            signature: Signature::parse(&signature).unwrap(),
+            program_id: Pubkey::new_unique(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+        let event = DecodedEvent {
+            event_name: "Transfer".to_string(),
+            data: serde_json::json!({ "amount": 42 }),
+            discriminator: [1, 2, 3, 4, 5, 6, 7, 8],
+        };
+
+        let queued = QueueEvent::from_decoded(&event, &raw);
+        assert_eq!(queued.signature, signature);
+        assert_eq!(queued.program_id, raw.program_id.to_string());
+        assert_eq!(queued.slot, 1234);
+        assert_eq!(queued.discriminator, "0102030405060708");
+        assert_eq!(queued.timestamp, raw.timestamp.to_rfc3339());
+
+        let json = serde_json::to_string(&queued).unwrap();
+        assert!(!json.contains("tx_failed"));
+        let parsed: QueueEvent = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, queued);
+    }
+}
@@ -0,0 +1,206 @@
+use super::{EventQueue, QueueEvent};
+use crate::retry::retry_with_backoff;
+use async_trait::async_trait;
+use aws_sdk_sqs::error::DisplayErrorContext;
+use aws_sdk_sqs::types::SendMessageBatchRequestEntry;
+use aws_sdk_sqs::Client;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Most messages SQS accepts in one `SendMessageBatch` call
+pub const SQS_MAX_BATCH: usize = 10;
+
+/// Retries for a throttled or failed batch, with exponential backoff
+const SQS_SEND_RETRIES: u32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct SqsConfig {
+    pub queue_url: String,
+    /// AWS region; the SDK's default chain (`AWS_REGION`, profile, IMDS) is used when unset
+    pub region: Option<String>,
+}
+
+impl SqsConfig {
+    pub fn new(queue_url: String) -> Self {
+        Self {
+            queue_url,
+            region: None,
+        }
+    }
+
+    pub fn with_region(mut self, region: Option<String>) -> Self {
+        self.region = region;
+        self
+    }
+
+    pub fn from_env() -> Option<Self> {
+        let queue_url = std::env::var("SQS_QUEUE_URL").ok()?;
+        Some(Self::new(queue_url).with_region(std::env::var("AWS_REGION").ok()))
+    }
+
+    /// FIFO queue URLs end in `.fifo`
+    pub fn is_fifo(&self) -> bool {
+        self.queue_url.ends_with(".fifo")
+    }
+}
+
+/// Sends events to an SQS queue in batches of up to [`SQS_MAX_BATCH`]
+///
+/// Events are buffered until a full batch is ready; `flush` sends the rest. On FIFO
+/// queues the program ID is the message group, so each program's events stay in order.
+pub struct SqsProducer {
+    client: Client,
+    config: SqsConfig,
+    buffer: Mutex<Vec<QueueEvent>>,
+}
+
+impl SqsProducer {
+    pub async fn new(config: SqsConfig) -> anyhow::Result<Self> {
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+        if let Some(region) = &config.region {
+            loader = loader.region(aws_config::Region::new(region.clone()));
+        }
+        let client = Client::new(&loader.load().await);
+
+        info!(
+            "SQS producer sending to: {} ({})",
+            config.queue_url,
+            if config.is_fifo() { "FIFO" } else { "standard" }
+        );
+
+        Ok(Self {
+            client,
+            config,
+            buffer: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Send one batch, retrying only the messages SQS rejected
+    async fn send_batch(&self, events: &[QueueEvent]) -> anyhow::Result<()> {
+        let entries = events
+            .iter()
+            .enumerate()
+            .map(|(i, event)| batch_entry(i, event, self.config.is_fifo()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let pending = std::sync::Mutex::new(entries);
+
+        retry_with_backoff(
+            || async {
+                let batch = pending.lock().unwrap().clone();
+                let output = self
+                    .client
+                    .send_message_batch()
+                    .queue_url(&self.config.queue_url)
+                    .set_entries(Some(batch))
+                    .send()
+                    .await
+                    .map_err(|e| anyhow::anyhow!("SQS send failed: {}", DisplayErrorContext(e)))?;
+
+                let failed = output.failed();
+                let Some(first) = failed.first() else {
+                    return Ok(());
+                };
+                let failed_ids: HashSet<&str> = failed.iter().map(|f| f.id()).collect();
+                pending
+                    .lock()
+                    .unwrap()
+                    .retain(|entry| failed_ids.contains(entry.id()));
+                Err(anyhow::anyhow!(
+                    "{} SQS message(s) rejected: {} {}",
+                    failed.len(),
+                    first.code(),
+                    first.message().unwrap_or_default()
+                ))
+            },
+            SQS_SEND_RETRIES,
+            Duration::from_millis(200),
+            Duration::from_secs(5),
+        )
+        .await?;
+
+        debug!("Sent {} event(s) to SQS", events.len());
+        Ok(())
+    }
+}
+
+/// Build a batch entry; FIFO queues need a group and a deduplication ID
+fn batch_entry(
+    index: usize,
+    event: &QueueEvent,
+    fifo: bool,
+) -> anyhow::Result<SendMessageBatchRequestEntry> {
+    let body = serde_json::to_string(event)?;
+    let dedup_id = fifo.then(|| hex::encode(Sha256::digest(body.as_bytes())));
+
+    SendMessageBatchRequestEntry::builder()
+        .id(index.to_string())
+        .message_body(body)
+        .set_message_group_id(fifo.then(|| event.program_id.clone()))
+        .set_message_deduplication_id(dedup_id)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Invalid SQS message: {}", e))
+}
+
+#[async_trait]
+impl EventQueue for SqsProducer {
+    async fn send(&self, event: &QueueEvent) -> anyhow::Result<()> {
+        let batch = {
+            let mut buffer = self.buffer.lock().await;
+            buffer.push(event.clone());
+            if buffer.len() < SQS_MAX_BATCH {
+                return Ok(());
+            }
+            std::mem::take(&mut *buffer)
+        };
+
+        self.send_batch(&batch).await
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        let buffered = std::mem::take(&mut *self.buffer.lock().await);
+        for batch in buffered.chunks(SQS_MAX_BATCH) {
+            if let Err(e) = self.send_batch(batch).await {
+                warn!("SQS flush failed: {}", e);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_entry_fifo_fields() {
+        let event = QueueEvent {
+            event_name: "Transfer".to_string(),
+            signature: "sig".to_string(),
+            program_id: "Program111".to_string(),
+            slot: 7,
+            discriminator: "0000000000000000".to_string(),
+            data: serde_json::json!({ "amount": "5" }),
+            timestamp: "1970-01-01T00:00:00+00:00".to_string(),
+            tx_failed: false,
+        };
+
+        let fifo = batch_entry(3, &event, true).unwrap();
+        assert_eq!(fifo.id(), "3");
+        assert_eq!(fifo.message_group_id(), Some("Program111"));
+        assert_eq!(fifo.message_deduplication_id().map(str::len), Some(64));
+        let body: QueueEvent = serde_json::from_str(fifo.message_body()).unwrap();
+        assert_eq!(body, event);
+
+        let standard = batch_entry(0, &event, false).unwrap();
+        assert_eq!(standard.message_group_id(), None);
+        assert_eq!(standard.message_deduplication_id(), None);
+
+        assert!(
+            SqsConfig::new("https://sqs.us-east-1.amazonaws.com/1/events.fifo".into()).is_fifo()
+        );
+    }
+}
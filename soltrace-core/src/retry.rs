@@ -1,4 +1,5 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
 use tokio::time::sleep;
 use tracing::{debug, warn};
 
@@ -111,6 +112,131 @@ where
     Err(last_error.unwrap())
 }
 
+/// Token bucket rate limiter for proactively capping request rates
+///
+/// Tokens refill continuously at `requests_per_second` up to `burst`. Share a
+/// single instance across concurrent tasks via `Arc` so the cap is global.
+pub struct RateLimiter {
+    state: Mutex<TokenBucket>,
+    rate: f64,
+    capacity: f64,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter allowing `requests_per_second` with a burst of the same size
+    pub fn new(requests_per_second: u32) -> Self {
+        let rate = requests_per_second.max(1) as f64;
+        Self {
+            state: Mutex::new(TokenBucket {
+                tokens: rate,
+                last_refill: Instant::now(),
+            }),
+            rate,
+            capacity: rate,
+        }
+    }
+
+    /// Configure the maximum number of requests allowed in a single burst
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.capacity = burst.max(1) as f64;
+        self.state.get_mut().tokens = self.capacity;
+        self
+    }
+
+    /// Wait until a token is available and consume it
+    pub async fn acquire(&self) {
+        let mut bucket = self.state.lock().await;
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens < 1.0 {
+            let wait = Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate);
+            debug!("Rate limiter exhausted, waiting {:?}", wait);
+            // Holding the lock while sleeping keeps waiters in FIFO order
+            sleep(wait).await;
+            bucket.tokens = 1.0;
+            bucket.last_refill = Instant::now();
+        }
+
+        bucket.tokens -= 1.0;
+    }
+}
+
+/// Circuit breaker that pauses all callers after a run of consecutive failures
+///
+/// Once `failure_threshold` failures happen in a row the breaker opens for
+/// `cooldown`; callers of [`CircuitBreaker::wait`] sleep until it closes again,
+/// so a burst of 429s backs off every task at once instead of each retrying
+/// on its own schedule.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: std::sync::Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            state: std::sync::Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Whether the breaker is currently open (callers should back off)
+    pub fn is_open(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.open_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Wait until the breaker is closed
+    pub async fn wait(&self) {
+        let open_until = self.state.lock().unwrap().open_until;
+        if let Some(until) = open_until {
+            let now = Instant::now();
+            if now < until {
+                debug!("Circuit breaker open, waiting {:?}", until - now);
+                sleep(until - now).await;
+            }
+        }
+    }
+
+    /// Record a successful call, closing the breaker
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.open_until = None;
+    }
+
+    /// Record a failed call, opening the breaker once the threshold is reached
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failure_threshold {
+            warn!(
+                "Circuit breaker opened after {} consecutive failures, pausing for {:?}",
+                state.consecutive_failures, self.cooldown
+            );
+            state.consecutive_failures = 0;
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
 /// Process items concurrently with a limit on the number of concurrent operations
 ///
 /// # Arguments
@@ -141,6 +267,91 @@ where
         .await
 }
 
+/// Like [`concurrent_process`], but retries each item with [`retry_with_rate_limit`]
+///
+/// Every attempt first waits on the shared `rate_limiter` and `circuit_breaker`
+/// (if given) and reports its outcome to the breaker, so concurrent tasks back
+/// off together.
+pub async fn concurrent_process_with_retry<T, R, E, F, Fut>(
+    items: Vec<T>,
+    concurrency: usize,
+    max_retries: u32,
+    rate_limiter: Option<&RateLimiter>,
+    circuit_breaker: Option<&CircuitBreaker>,
+    processor: F,
+) -> Vec<Result<R, E>>
+where
+    T: Clone,
+    E: std::fmt::Display,
+    F: Fn(T) -> Fut,
+    Fut: std::future::Future<Output = Result<R, E>>,
+{
+    use futures::stream::StreamExt;
+
+    concurrent_process_with_retry_stream(
+        items,
+        concurrency,
+        max_retries,
+        rate_limiter,
+        circuit_breaker,
+        &processor,
+    )
+    .collect()
+    .await
+}
+
+/// Like [`concurrent_process_with_retry`], but yields each result as soon as its item finishes
+///
+/// Up to `concurrency` items run at once and the next one starts whenever any of
+/// them finishes, so a slow item never holds up the others. Items only make
+/// progress while the stream is polled, and are only taken from `items` when a
+/// slot frees up.
+pub fn concurrent_process_with_retry_stream<'a, I, T, R, E, F, Fut>(
+    items: I,
+    concurrency: usize,
+    max_retries: u32,
+    rate_limiter: Option<&'a RateLimiter>,
+    circuit_breaker: Option<&'a CircuitBreaker>,
+    processor: &'a F,
+) -> impl futures::Stream<Item = Result<R, E>> + 'a
+where
+    I: IntoIterator<Item = T>,
+    I::IntoIter: 'a,
+    T: Clone + 'a,
+    R: 'a,
+    E: std::fmt::Display + 'a,
+    F: Fn(T) -> Fut,
+    Fut: std::future::Future<Output = Result<R, E>> + 'a,
+{
+    use futures::stream::{self, StreamExt};
+
+    stream::iter(items)
+        .map(move |item| async move {
+            retry_with_rate_limit(
+                || async {
+                    if let Some(limiter) = rate_limiter {
+                        limiter.acquire().await;
+                    }
+                    if let Some(breaker) = circuit_breaker {
+                        breaker.wait().await;
+                    }
+
+                    let result = processor(item.clone()).await;
+                    if let Some(breaker) = circuit_breaker {
+                        match &result {
+                            Ok(_) => breaker.record_success(),
+                            Err(_) => breaker.record_failure(),
+                        }
+                    }
+                    result
+                },
+                max_retries,
+            )
+            .await
+        })
+        .buffer_unordered(concurrency.max(1))
+}
+
 /// Process items in batches with progress reporting
 pub async fn process_batches<T, R, E, F, Fut>(
     items: Vec<T>,
@@ -156,7 +367,7 @@ where
     Fut: std::future::Future<Output = Vec<Result<R, E>>> + Send,
 {
     let total_items = items.len();
-    let total_batches = (total_items + batch_size - 1) / batch_size;
+    let total_batches = total_items.div_ceil(batch_size);
     let mut results = Vec::with_capacity(total_items);
 
     for (batch_num, batch) in items.chunks(batch_size).enumerate() {
@@ -211,6 +422,36 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_rate_limiter_caps_request_rate() {
+        let limiter = std::sync::Arc::new(RateLimiter::new(20).with_burst(1));
+        let start = Instant::now();
+
+        let mut handles = Vec::new();
+        for _ in 0..11 {
+            let limiter = limiter.clone();
+            handles.push(tokio::spawn(async move { limiter.acquire().await }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        // First token is immediate, the remaining 10 are spaced 50ms apart
+        assert!(start.elapsed() >= Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_allows_burst() {
+        let limiter = RateLimiter::new(5);
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
     #[tokio::test]
     async fn test_concurrent_process() {
         let items: Vec<i32> = (0..10).collect();
@@ -222,4 +463,72 @@ mod tests {
         let sum: i32 = results.into_iter().map(|r| r.unwrap()).sum();
         assert_eq!(sum, 90); // 0+2+4+6+8+10+12+14+16+18 = 90
     }
+
+    #[tokio::test]
+    async fn test_concurrent_process_with_retry() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let attempts = AtomicUsize::new(0);
+        let failed_once = std::sync::Mutex::new(std::collections::HashSet::new());
+
+        // Odd items fail on their first attempt only
+        let mut results =
+            concurrent_process_with_retry((0..6).collect::<Vec<i32>>(), 3, 2, None, None, |item| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                let first_attempt = failed_once.lock().unwrap().insert(item);
+                async move {
+                    if item % 2 == 1 && first_attempt {
+                        Err(format!("transient failure for {}", item))
+                    } else {
+                        Ok(item * 2)
+                    }
+                }
+            })
+            .await
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+
+        results.sort();
+        assert_eq!(results, vec![0, 2, 4, 6, 8, 10]);
+        assert_eq!(attempts.load(Ordering::SeqCst), 9);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_stream_refills_around_slow_items() {
+        use futures::StreamExt;
+
+        // Item 0 is slow; with two slots the other slot keeps working through the rest
+        let processor = |item: u64| async move {
+            let delay = if item == 0 { 200 } else { 5 };
+            sleep(Duration::from_millis(delay)).await;
+            Ok::<u64, String>(item)
+        };
+        let order: Vec<u64> =
+            concurrent_process_with_retry_stream(0..6, 2, 0, None, None, &processor)
+                .map(|r| r.unwrap())
+                .collect()
+                .await;
+
+        assert_eq!(order, vec![1, 2, 3, 4, 5, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_opens_and_recovers() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+        assert!(!breaker.is_open());
+
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+        breaker.record_failure();
+        assert!(breaker.is_open());
+
+        let start = Instant::now();
+        breaker.wait().await;
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert!(!breaker.is_open());
+
+        breaker.record_success();
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+    }
 }
@@ -0,0 +1,666 @@
+use crate::{
+    db::Database,
+    error::{Result, SoltraceError},
+    metrics::Metrics,
+    queue::QueueEvent,
+    types::{DecodedEvent, ProgramId, RawEvent, Signature, Slot},
+    utils::{insert_event_with_retry, is_duplicate_error},
+};
+use async_trait::async_trait;
+use hashlink::LruCache;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Where decoded events are written
+///
+/// Implemented for [`Database`], [`StdoutJsonSink`] and
+/// [`FileSink`](crate::archive::FileSink); [`FanoutSink`] writes to several.
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// Write one event
+    ///
+    /// Fails with [`SoltraceError::Duplicate`] (or the backend's equivalent) when
+    /// the event was already written.
+    async fn write(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<()>;
+
+    /// Write many events, returning how many were new
+    async fn write_batch(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        let mut written = 0;
+        for (event, raw) in events {
+            match self.write(event, raw).await {
+                Ok(()) => written += 1,
+                Err(e) if is_duplicate_error(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(written)
+    }
+
+    /// Write out anything buffered or still open, e.g. on shutdown
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for Database {
+    async fn write(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<()> {
+        insert_event_with_retry(self, event, raw).await?;
+        Ok(())
+    }
+
+    async fn write_batch(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        self.bulk_insert(events).await
+    }
+}
+
+/// Prints each event as one JSON object per line on stdout, for piping into other tools
+///
+/// Lines use the [`QueueEvent`] shape, the same one sent to queues.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutJsonSink;
+
+impl StdoutJsonSink {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn line(event: &DecodedEvent, raw: &RawEvent) -> Result<String> {
+        let line = QueueEvent::from_decoded(event, raw);
+        Ok(serde_json::to_string(&line)?)
+    }
+}
+
+#[async_trait]
+impl EventSink for StdoutJsonSink {
+    async fn write(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<()> {
+        let line = Self::line(event, raw)?;
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}", line)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes every event to each of its sinks in turn
+pub struct FanoutSink {
+    sinks: Vec<Arc<dyn EventSink>>,
+}
+
+impl FanoutSink {
+    pub fn new(sinks: Vec<Arc<dyn EventSink>>) -> Self {
+        Self { sinks }
+    }
+}
+
+#[async_trait]
+impl EventSink for FanoutSink {
+    /// Every sink is written even if an earlier one fails; the first error is returned
+    async fn write(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<()> {
+        let mut first_error = None;
+        for sink in &self.sinks {
+            if let Err(e) = sink.write(event, raw).await {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Each sink gets the whole batch; the count comes from the first sink that succeeds
+    async fn write_batch(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        let mut written = None;
+        let mut first_error = None;
+        for sink in &self.sinks {
+            match sink.write_batch(events).await {
+                Ok(count) => {
+                    written.get_or_insert(count);
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(written.unwrap_or(0)), Err)
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let mut first_error = None;
+        for sink in &self.sinks {
+            if let Err(e) = sink.flush().await {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+/// Callback run on each decoded event once it has been written
+pub type EventHook = Box<dyn Fn(&DecodedEvent, &RawEvent) + Send + Sync>;
+
+/// Runs a hook on every event its inner sink accepts, e.g. to enrich, alert or call a webhook
+///
+/// The hook runs after the write succeeds, so failed or duplicate events are skipped.
+/// A batch only reports how many events were new, so after a successful
+/// `write_batch` the hook sees every event in the batch. Keep hooks quick, or hand
+/// the work off to a task, since the write path waits for them.
+pub struct HookSink {
+    inner: Arc<dyn EventSink>,
+    hook: EventHook,
+}
+
+impl HookSink {
+    pub fn new(inner: Arc<dyn EventSink>, hook: EventHook) -> Self {
+        Self { inner, hook }
+    }
+}
+
+#[async_trait]
+impl EventSink for HookSink {
+    async fn write(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<()> {
+        self.inner.write(event, raw).await?;
+        (self.hook)(event, raw);
+        Ok(())
+    }
+
+    async fn write_batch(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        let written = self.inner.write_batch(events).await?;
+        for (event, raw) in events {
+            (self.hook)(event, raw);
+        }
+        Ok(written)
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.inner.flush().await
+    }
+}
+
+/// Rejects events a catch-up backfill already stored, so a live subscription
+/// started after it doesn't write the overlapping transactions twice
+///
+/// Events up to `until_slot` whose transaction [`Database::event_exists`] fail with
+/// [`SoltraceError::Duplicate`]; later events go straight to the inner sink.
+pub struct OverlapSink {
+    inner: Arc<dyn EventSink>,
+    db: Arc<Database>,
+    until_slot: Slot,
+}
+
+impl OverlapSink {
+    pub fn new(inner: Arc<dyn EventSink>, db: Arc<Database>, until_slot: Slot) -> Self {
+        Self {
+            inner,
+            db,
+            until_slot,
+        }
+    }
+
+    async fn already_stored(&self, raw: &RawEvent) -> Result<bool> {
+        if raw.slot > self.until_slot {
+            return Ok(false);
+        }
+        self.db.event_exists(raw.signature.as_str()).await
+    }
+}
+
+#[async_trait]
+impl EventSink for OverlapSink {
+    async fn write(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<()> {
+        if self.already_stored(raw).await? {
+            return Err(SoltraceError::Duplicate(format!(
+                "Transaction {} was already stored by the catch-up backfill",
+                raw.signature
+            )));
+        }
+        self.inner.write(event, raw).await
+    }
+
+    async fn write_batch(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        let mut fresh = Vec::with_capacity(events.len());
+        for (event, raw) in events {
+            if !self.already_stored(raw).await? {
+                fresh.push((event.clone(), raw.clone()));
+            }
+        }
+        if fresh.is_empty() {
+            return Ok(0);
+        }
+        self.inner.write_batch(&fresh).await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.inner.flush().await
+    }
+}
+
+/// Remembers the most recently written events and rejects repeats without
+/// touching the inner sink, saving a database round-trip per duplicate
+///
+/// Events are keyed by transaction signature, program and log position, like
+/// the unique key they are stored under. A repeat of one of the last `capacity` events fails with
+/// [`SoltraceError::Duplicate`]; older ones fall through to the inner sink,
+/// which still catches them.
+pub struct DedupSink {
+    inner: Arc<dyn EventSink>,
+    seen: Mutex<LruCache<(Signature, ProgramId, u32), ()>>,
+}
+
+impl DedupSink {
+    pub fn new(inner: Arc<dyn EventSink>, capacity: usize) -> Self {
+        Self {
+            inner,
+            seen: Mutex::new(LruCache::new(capacity.max(1))),
+        }
+    }
+
+    /// Whether the event was written recently, refreshing its place in the cache
+    fn seen(&self, raw: &RawEvent) -> bool {
+        let key = (raw.signature.clone(), raw.program_id, raw.log_index);
+        self.seen.lock().unwrap().get(&key).is_some()
+    }
+
+    fn remember(&self, raw: &RawEvent) {
+        let key = (raw.signature.clone(), raw.program_id, raw.log_index);
+        self.seen.lock().unwrap().insert(key, ());
+    }
+}
+
+#[async_trait]
+impl EventSink for DedupSink {
+    async fn write(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<()> {
+        if self.seen(raw) {
+            return Err(SoltraceError::Duplicate(format!(
+                "Event at log {} of transaction {} was written recently",
+                raw.log_index, raw.signature
+            )));
+        }
+        match self.inner.write(event, raw).await {
+            Err(e) if !is_duplicate_error(&e) => Err(e),
+            result => {
+                self.remember(raw);
+                result
+            }
+        }
+    }
+
+    async fn write_batch(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        let fresh: Vec<_> = events
+            .iter()
+            .filter(|(_, raw)| !self.seen(raw))
+            .cloned()
+            .collect();
+        if fresh.is_empty() {
+            return Ok(0);
+        }
+        let written = self.inner.write_batch(&fresh).await?;
+        for (_, raw) in &fresh {
+            self.remember(raw);
+        }
+        Ok(written)
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.inner.flush().await
+    }
+}
+
+/// Writes only a deterministic sample of transactions, for programs too chatty to store in full
+///
+/// Whether a transaction is kept depends only on a hash of its signature, so it is
+/// kept or dropped the same way across restarts and re-backfills. Dropped events
+/// are reported as written, so they still count toward event rates, and are
+/// counted in `events_sampled_out`. Sampling gives up completeness: the stored
+/// events are no longer every event the program emitted.
+pub struct SampledSink {
+    inner: Arc<dyn EventSink>,
+    rate: f64,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl SampledSink {
+    /// Keep about `rate` (0.0 to 1.0) of all transactions
+    pub fn new(inner: Arc<dyn EventSink>, rate: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(SoltraceError::InvalidIdl(format!(
+                "Sample rate must be between 0.0 and 1.0, got {}",
+                rate
+            )));
+        }
+        Ok(Self {
+            inner,
+            rate,
+            metrics: None,
+        })
+    }
+
+    /// Count sampled-out events in `events_sampled_out`
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Whether the sample includes this transaction
+    pub fn keeps(&self, signature: &Signature) -> bool {
+        let hash = Sha256::digest(signature.as_str().as_bytes());
+        let value = u64::from_be_bytes(hash[..8].try_into().expect("8-byte prefix"));
+        self.rate >= 1.0 || (value as f64) < self.rate * u64::MAX as f64
+    }
+
+    fn sample_out(&self) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_sampled_out();
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for SampledSink {
+    async fn write(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<()> {
+        if !self.keeps(&raw.signature) {
+            self.sample_out();
+            return Ok(());
+        }
+        self.inner.write(event, raw).await
+    }
+
+    async fn write_batch(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        let (kept, dropped): (Vec<_>, Vec<_>) = events
+            .iter()
+            .cloned()
+            .partition(|(_, raw)| self.keeps(&raw.signature));
+        for _ in &dropped {
+            self.sample_out();
+        }
+        let written = if kept.is_empty() {
+            0
+        } else {
+            self.inner.write_batch(&kept).await?
+        };
+        Ok(written + dropped.len() as u64)
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.inner.flush().await
+    }
+}
+
+/// Which sinks the binaries write events to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SinkKind {
+    /// The configured database
+    #[default]
+    Db,
+    /// JSON lines on stdout, no database needed
+    Stdout,
+    /// The database and stdout
+    Both,
+    /// No built-in sink, for writing only to an archive directory
+    None,
+}
+
+impl SinkKind {
+    /// Whether a database connection is needed
+    pub fn uses_database(&self) -> bool {
+        matches!(self, SinkKind::Db | SinkKind::Both)
+    }
+
+    /// Whether events are printed to stdout (so logs must go elsewhere)
+    pub fn uses_stdout(&self) -> bool {
+        matches!(self, SinkKind::Stdout | SinkKind::Both)
+    }
+
+    /// Build the sink, connecting to the database only when it is used
+    ///
+    /// `mongo_db_name` is passed to [`Database::with_options`].
+    pub async fn open(
+        &self,
+        database_url: &str,
+        table: &str,
+        mongo_db_name: Option<&str>,
+    ) -> Result<Arc<dyn EventSink>> {
+        let db = if self.uses_database() {
+            Some(Arc::new(
+                Database::with_options(database_url, table, mongo_db_name).await?,
+            ))
+        } else {
+            None
+        };
+
+        Ok(self.build(db))
+    }
+
+    /// Build the sink around an already connected database
+    ///
+    /// `db` is only used when [`uses_database`](Self::uses_database) is true.
+    pub fn build(&self, db: Option<Arc<Database>>) -> Arc<dyn EventSink> {
+        if *self == SinkKind::None {
+            return Arc::new(FanoutSink::new(Vec::new()));
+        }
+        match (db.filter(|_| self.uses_database()), self.uses_stdout()) {
+            (Some(db), false) => db,
+            (Some(db), true) => Arc::new(FanoutSink::new(vec![db, Arc::new(StdoutJsonSink)])),
+            (None, _) => Arc::new(StdoutJsonSink),
+        }
+    }
+}
+
+impl std::fmt::Display for SinkKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SinkKind::Db => write!(f, "db"),
+            SinkKind::Stdout => write!(f, "stdout"),
+            SinkKind::Both => write!(f, "both"),
+            SinkKind::None => write!(f, "none"),
+        }
+    }
+}
+
+impl std::str::FromStr for SinkKind {
+    type Err = SoltraceError;
+
+    /// Parse `db`, `stdout`, `both` or `none`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "db" | "database" => Ok(SinkKind::Db),
+            "stdout" => Ok(SinkKind::Stdout),
+            "both" => Ok(SinkKind::Both),
+            "none" => Ok(SinkKind::None),
+            _ => Err(SoltraceError::InvalidIdl(format!(
+                "Invalid sink '{}': expected db, stdout, both or none",
+                s
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::event;
+    use crate::types::Signature;
+
+    #[test]
+    fn test_stdout_line_and_sink_kind() {
+        let (event, raw) = event(77, 0);
+        let line: serde_json::Value =
+            serde_json::from_str(&StdoutJsonSink::line(&event, &raw).unwrap()).unwrap();
+        assert_eq!(line["event_name"], "tributary_Transfer");
+        assert_eq!(line["slot"], 77);
+        assert_eq!(line["discriminator"], "ABABABABABABABAB");
+        assert_eq!(line["data"]["amount"], "77");
+
+        assert_eq!("stdout".parse::<SinkKind>().unwrap(), SinkKind::Stdout);
+        assert!("both".parse::<SinkKind>().unwrap().uses_database());
+        assert!(!SinkKind::Stdout.uses_database());
+        assert!("file".parse::<SinkKind>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hook_sink_runs_after_writes() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_seen = seen.clone();
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let sink = HookSink::new(
+            db.clone(),
+            Box::new(move |event, raw| {
+                hook_seen
+                    .lock()
+                    .unwrap()
+                    .push((event.event_name.clone(), raw.slot));
+            }),
+        );
+
+        let (first, raw) = event(77, 0);
+        sink.write(&first, &raw).await.unwrap();
+        assert_eq!(
+            sink.write_batch(&[event(77, 1), event(77, 2)])
+                .await
+                .unwrap(),
+            2
+        );
+
+        let seen = std::mem::take(&mut *seen.lock().unwrap());
+        assert_eq!(seen.len(), 3);
+        assert!(seen
+            .iter()
+            .all(|(name, slot)| name == "tributary_Transfer" && *slot == 77));
+
+        let stored = db.get_events_by_name("tributary_Transfer").await.unwrap();
+        assert_eq!(stored.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fanout_writes_every_sink() {
+        let first = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let second = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let fanout = FanoutSink::new(vec![first.clone(), second.clone()]);
+
+        let events = vec![event(77, 0), event(77, 1)];
+        assert_eq!(fanout.write_batch(&events).await.unwrap(), 2);
+
+        for db in [first, second] {
+            let stored = db.get_events_by_name("tributary_Transfer").await.unwrap();
+            assert_eq!(stored.len(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sampled_sink_is_deterministic() {
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let metrics = Arc::new(Metrics::new());
+        let sink = SampledSink::new(db.clone(), 0.5)
+            .unwrap()
+            .with_metrics(metrics.clone());
+        assert!(SampledSink::new(db.clone(), 1.5).is_err());
+
+        let signatures: Vec<Signature> = (0..200u8)
+            .map(|byte| {
+                let signature = solana_sdk::signature::Signature::from([byte; 64]);
+                Signature::parse(&signature.to_string()).unwrap()
+            })
+            .collect();
+        let kept = signatures.iter().filter(|s| sink.keeps(s)).count();
+        assert!((60..140).contains(&kept), "kept {} of 200", kept);
+        let again = signatures.iter().filter(|s| sink.keeps(s)).count();
+        assert_eq!(kept, again);
+
+        let events: Vec<_> = signatures
+            .iter()
+            .map(|signature| {
+                let (event, mut raw) = event(77, 0);
+                raw.signature = signature.clone();
+                (event, raw)
+            })
+            .collect();
+        // Sampled-out events count as written but aren't stored
+        assert_eq!(sink.write_batch(&events).await.unwrap(), 200);
+        let stored = db.get_events_by_name("tributary_Transfer").await.unwrap();
+        assert_eq!(stored.len(), kept);
+        assert_eq!(
+            metrics
+                .events_sampled_out
+                .load(std::sync::atomic::Ordering::Relaxed),
+            200 - kept as u64
+        );
+
+        let none = SampledSink::new(db, 0.0).unwrap();
+        assert!(!signatures.iter().any(|s| none.keeps(s)));
+    }
+
+    #[tokio::test]
+    async fn test_overlap_sink_skips_stored_transactions() {
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let (first, raw) = event(77, 0);
+        db.write(&first, &raw).await.unwrap();
+
+        // The transaction at slot 77 was stored by the catch-up
+        let inner = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let sink = OverlapSink::new(inner.clone(), db.clone(), 77);
+        assert!(matches!(
+            sink.write(&first, &raw).await,
+            Err(SoltraceError::Duplicate(_))
+        ));
+        assert_eq!(sink.write_batch(&[event(77, 1)]).await.unwrap(), 0);
+
+        // Past the catch-up, events are written without checking
+        let sink = OverlapSink::new(inner.clone(), db, 76);
+        assert_eq!(
+            sink.write_batch(&[event(77, 0), event(77, 1)])
+                .await
+                .unwrap(),
+            2
+        );
+        let stored = inner
+            .get_events_by_name("tributary_Transfer")
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 2);
+    }
+
+    /// Counts the writes that reach it
+    #[derive(Default)]
+    struct CountingSink {
+        writes: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EventSink for CountingSink {
+        async fn write(&self, _event: &DecodedEvent, _raw: &RawEvent) -> Result<()> {
+            self.writes
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedup_sink_skips_recent_events() {
+        let inner = Arc::new(CountingSink::default());
+        let writes = || inner.writes.load(std::sync::atomic::Ordering::Relaxed);
+        let sink = DedupSink::new(inner.clone(), 2);
+
+        let (first, raw) = event(77, 0);
+        sink.write(&first, &raw).await.unwrap();
+        assert!(matches!(
+            sink.write(&first, &raw).await,
+            Err(SoltraceError::Duplicate(_))
+        ));
+        assert_eq!(writes(), 1);
+
+        // Only the unseen event of a batch reaches the inner sink
+        assert_eq!(
+            sink.write_batch(&[event(77, 0), event(77, 1)])
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(writes(), 2);
+
+        // Once pushed out of the cache, a repeat falls through again
+        sink.write_batch(&[event(77, 2), event(77, 3)])
+            .await
+            .unwrap();
+        sink.write(&first, &raw).await.unwrap();
+        assert_eq!(writes(), 5);
+    }
+}
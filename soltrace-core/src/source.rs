@@ -0,0 +1,366 @@
+use crate::{
+    error::{Result, SoltraceError},
+    types::Signature,
+};
+use async_trait::async_trait;
+use solana_account::Account;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction};
+use std::collections::HashMap;
+
+/// Most signatures `getSignaturesForAddress` returns in one call
+pub const MAX_SIGNATURES_PER_PAGE: usize = 1000;
+
+/// A signature from `getSignaturesForAddress` with the block time it was listed with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureInfo {
+    pub signature: Signature,
+    /// Unix timestamp of the block, when the node still knows it
+    pub block_time: Option<i64>,
+}
+
+/// Where historical transactions come from
+///
+/// Implemented for the (nonblocking) `RpcClient` and for [`MockSource`], so the whole
+/// fetch+decode+store path can be exercised without a validator. The `RpcClient`
+/// implementation queries at the client's configured commitment.
+#[async_trait]
+pub trait TransactionSource: Send + Sync {
+    /// Fetch an account by address
+    async fn get_account(&self, pubkey: &Pubkey) -> Result<Account>;
+
+    /// Fetch up to `limit` signatures involving `address`, newest first
+    ///
+    /// Sources return at most [`MAX_SIGNATURES_PER_PAGE`] per call; page with `before`.
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<&Signature>,
+        until: Option<&Signature>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>>;
+
+    /// Fetch a confirmed transaction with its status metadata
+    async fn get_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta>;
+}
+
+#[async_trait]
+impl TransactionSource for RpcClient {
+    async fn get_account(&self, pubkey: &Pubkey) -> Result<Account> {
+        RpcClient::get_account(self, pubkey)
+            .await
+            .map_err(|e| SoltraceError::SolanaClient(e.to_string()))
+    }
+
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<&Signature>,
+        until: Option<&Signature>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            before: before.map(Signature::to_solana),
+            until: until.map(Signature::to_solana),
+            limit: Some(limit),
+            commitment: Some(self.commitment()),
+        };
+
+        self.get_signatures_for_address_with_config(address, config)
+            .await
+            .map_err(|e| SoltraceError::SolanaClient(e.to_string()))?
+            .iter()
+            .map(|status| {
+                Ok(SignatureInfo {
+                    signature: Signature::parse(&status.signature)?,
+                    block_time: status.block_time,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+        self.get_transaction_with_config(
+            &signature.to_solana(),
+            RpcTransactionConfig {
+                encoding: Some(solana_transaction_status::UiTransactionEncoding::Json),
+                commitment: Some(self.commitment()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await
+        .map_err(|e| SoltraceError::SolanaClient(e.to_string()))
+    }
+}
+
+/// In-memory `TransactionSource` backed by fixture accounts and transactions
+#[derive(Debug, Clone, Default)]
+pub struct MockSource {
+    accounts: HashMap<Pubkey, Account>,
+    /// Signatures per address, newest first
+    signatures: HashMap<Pubkey, Vec<Signature>>,
+    /// Transactions stored as their RPC JSON form (the RPC types are not `Clone`)
+    transactions: HashMap<Signature, serde_json::Value>,
+}
+
+impl MockSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an account fixture
+    pub fn with_account(mut self, pubkey: Pubkey, account: Account) -> Self {
+        self.accounts.insert(pubkey, account);
+        self
+    }
+
+    /// Register a deployed program (an executable account owned by the BPF loader)
+    pub fn with_program(self, program_id: Pubkey) -> Self {
+        let account = Account {
+            owner: solana_sdk_ids::bpf_loader_upgradeable::ID,
+            executable: true,
+            ..Account::default()
+        };
+        self.with_account(program_id, account)
+    }
+
+    /// Register a transaction fixture for `address`
+    ///
+    /// Transactions must be added oldest first; the signature is taken from
+    /// the JSON-encoded transaction.
+    pub fn with_transaction(
+        mut self,
+        address: Pubkey,
+        transaction: EncodedConfirmedTransactionWithStatusMeta,
+    ) -> Self {
+        let signature = match &transaction.transaction.transaction {
+            EncodedTransaction::Json(ui_tx) => ui_tx
+                .signatures
+                .first()
+                .and_then(|s| Signature::parse(s).ok())
+                .expect("fixture transaction must carry a valid signature"),
+            _ => panic!("fixture transactions must be JSON-encoded"),
+        };
+
+        self.signatures
+            .entry(address)
+            .or_default()
+            .insert(0, signature.clone());
+        let fixture =
+            serde_json::to_value(&transaction).expect("fixture transaction serializes to JSON");
+        self.transactions.insert(signature, fixture);
+        self
+    }
+
+    /// Set the block time of an already registered transaction fixture
+    pub fn with_block_time(mut self, signature: &Signature, block_time: i64) -> Self {
+        let fixture = self
+            .transactions
+            .get_mut(signature)
+            .expect("block time set on a registered transaction");
+        fixture["blockTime"] = block_time.into();
+        self
+    }
+
+    /// Mark an already registered transaction fixture as failed (a custom program error)
+    pub fn with_failure(mut self, signature: &Signature) -> Self {
+        let fixture = self
+            .transactions
+            .get_mut(signature)
+            .expect("failure set on a registered transaction");
+        let err = serde_json::json!({ "InstructionError": [0, { "Custom": 6001 }] });
+        fixture["meta"]["status"] = serde_json::json!({ "Err": err });
+        fixture["meta"]["err"] = err;
+        self
+    }
+
+    /// Add a top-level instruction to an already registered transaction fixture
+    ///
+    /// The instruction invokes the fixture's first account key (the address it
+    /// was registered for) and passes that key as its only account.
+    pub fn with_instruction(mut self, signature: &Signature, data: &[u8]) -> Self {
+        let fixture = self
+            .transactions
+            .get_mut(signature)
+            .expect("instruction added to a registered transaction");
+        fixture["transaction"]["message"]["instructions"]
+            .as_array_mut()
+            .expect("fixture message has an instructions array")
+            .push(serde_json::json!({
+                "programIdIndex": 0,
+                "accounts": [0],
+                "data": bs58::encode(data).into_string(),
+            }));
+        self
+    }
+
+    /// Register a signature for `address` whose transaction can't be fetched (e.g. pruned)
+    pub fn with_missing_transaction(mut self, address: Pubkey, signature: &Signature) -> Self {
+        self.signatures
+            .entry(address)
+            .or_default()
+            .insert(0, signature.clone());
+        self
+    }
+
+    /// Register a successful transaction for `address` that emitted these log lines
+    pub fn with_logs(
+        self,
+        address: Pubkey,
+        signature: &Signature,
+        slot: u64,
+        logs: Vec<String>,
+    ) -> Self {
+        let transaction = serde_json::from_value(serde_json::json!({
+            "slot": slot,
+            "blockTime": null,
+            "transaction": {
+                "signatures": [signature.as_str()],
+                "message": {
+                    "header": {
+                        "numRequiredSignatures": 1,
+                        "numReadonlySignedAccounts": 0,
+                        "numReadonlyUnsignedAccounts": 1
+                    },
+                    "accountKeys": [address.to_string()],
+                    "recentBlockhash": "11111111111111111111111111111111",
+                    "instructions": []
+                }
+            },
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5000,
+                "preBalances": [],
+                "postBalances": [],
+                "logMessages": logs
+            }
+        }))
+        .expect("fixture transaction JSON is valid");
+
+        self.with_transaction(address, transaction)
+    }
+}
+
+#[async_trait]
+impl TransactionSource for MockSource {
+    async fn get_account(&self, pubkey: &Pubkey) -> Result<Account> {
+        self.accounts
+            .get(pubkey)
+            .cloned()
+            .ok_or_else(|| SoltraceError::SolanaClient(format!("AccountNotFound: {}", pubkey)))
+    }
+
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<&Signature>,
+        until: Option<&Signature>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let Some(signatures) = self.signatures.get(address) else {
+            return Ok(Vec::new());
+        };
+
+        let start = match before {
+            Some(before) => match signatures.iter().position(|s| s == before) {
+                Some(index) => index + 1,
+                None => return Ok(Vec::new()),
+            },
+            None => 0,
+        };
+
+        Ok(signatures[start..]
+            .iter()
+            .take_while(|s| Some(*s) != until)
+            .take(limit.min(MAX_SIGNATURES_PER_PAGE))
+            .map(|signature| SignatureInfo {
+                signature: signature.clone(),
+                block_time: self
+                    .transactions
+                    .get(signature)
+                    .and_then(|fixture| fixture["blockTime"].as_i64()),
+            })
+            .collect())
+    }
+
+    async fn get_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+        let fixture = self.transactions.get(signature).ok_or_else(|| {
+            SoltraceError::SolanaClient(format!("Transaction not found: {}", signature))
+        })?;
+
+        Ok(serde_json::from_value(fixture.clone())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sig(byte: u8) -> Signature {
+        Signature::parse(&solana_sdk::signature::Signature::from([byte; 64]).to_string()).unwrap()
+    }
+
+    fn signatures(infos: Vec<SignatureInfo>) -> Vec<Signature> {
+        infos.into_iter().map(|info| info.signature).collect()
+    }
+
+    #[tokio::test]
+    async fn test_mock_source_signature_cursors() {
+        let program = Pubkey::new_unique();
+        let source = (1..=5).fold(MockSource::new(), |source, i| {
+            source.with_logs(program, &sig(i), i as u64, vec![])
+        });
+
+        let all = source
+            .get_signatures_for_address(&program, None, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            signatures(all),
+            vec![sig(5), sig(4), sig(3), sig(2), sig(1)]
+        );
+
+        let page = source
+            .get_signatures_for_address(&program, Some(&sig(4)), Some(&sig(1)), 10)
+            .await
+            .unwrap();
+        assert_eq!(signatures(page), vec![sig(3), sig(2)]);
+
+        let limited = source
+            .get_signatures_for_address(&program, None, None, 2)
+            .await
+            .unwrap();
+        assert_eq!(signatures(limited), vec![sig(5), sig(4)]);
+    }
+
+    #[tokio::test]
+    async fn test_mock_source_transaction_fixture() {
+        let program = Pubkey::new_unique();
+        let source = MockSource::new().with_program(program).with_logs(
+            program,
+            &sig(7),
+            42,
+            vec!["Program log: hi".to_string()],
+        );
+
+        assert!(source.get_account(&program).await.is_ok());
+        assert!(source.get_account(&Pubkey::new_unique()).await.is_err());
+
+        let tx = source.get_transaction(&sig(7)).await.unwrap();
+        assert_eq!(tx.slot, 42);
+        assert!(source.get_transaction(&sig(8)).await.is_err());
+    }
+}
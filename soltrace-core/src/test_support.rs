@@ -0,0 +1,26 @@
+use crate::types::{DecodedEvent, RawEvent, Signature, Slot};
+
+/// A `tributary_Transfer` event at `slot` and `log_index`
+///
+/// The signature repeats the slot's low byte and `amount` is the slot as a string,
+/// so events from different slots are told apart by both.
+pub(crate) fn event(slot: Slot, log_index: u32) -> (DecodedEvent, RawEvent) {
+    let signature = solana_sdk::signature::Signature::from([slot as u8; 64]).to_string();
+    let event = DecodedEvent {
+        event_name: "tributary_Transfer".to_string(),
+        data: serde_json::json!({ "amount": slot.to_string() }),
+        discriminator: [0xab; 8],
+    };
+    let raw = RawEvent {
+        slot,
+        signature: Signature::parse(&signature).unwrap(),
+        program_id: solana_sdk::pubkey::Pubkey::default(),
+        log: String::new(),
+        log_index,
+        inner_instruction_index: None,
+        timestamp: chrono::Utc::now(),
+        raw_data: None,
+        tx_failed: false,
+    };
+    (event, raw)
+}
@@ -1,3 +1,4 @@
+use crate::error::{Result, SoltraceError};
 use serde::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
@@ -6,12 +7,77 @@ pub type Slot = u64;
 pub type ProgramId = Pubkey;
 pub type EventDiscriminator = [u8; 8];
 
+/// Base58-encoded transaction signature, validated on construction
+///
+/// Displays and serializes as the plain base58 string so storage formats are
+/// unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Signature(String);
+
+impl Signature {
+    /// Parse and validate a base58 transaction signature
+    pub fn parse(signature: &str) -> Result<Self> {
+        signature
+            .parse::<solana_sdk::signature::Signature>()
+            .map_err(|e| SoltraceError::InvalidSignature(format!("'{}': {}", signature, e)))?;
+        Ok(Self(signature.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Convert to the Solana SDK signature type for RPC calls
+    pub fn to_solana(&self) -> solana_sdk::signature::Signature {
+        self.0
+            .parse()
+            .expect("signature was validated on construction")
+    }
+}
+
+impl std::fmt::Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::str::FromStr for Signature {
+    type Err = SoltraceError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for Signature {
+    type Error = SoltraceError;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<Signature> for String {
+    fn from(signature: Signature) -> Self {
+        signature.0
+    }
+}
+
+impl AsRef<str> for Signature {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedIdl {
     #[serde(default)]
     pub version: Option<String>,
     pub name: Option<String>,
     pub events: Vec<IdlEventDefinition>,
+    /// Program ID; Anchor < 0.30 IDLs carry it under `metadata.address` instead
+    #[serde(default)]
     pub address: String,
 
     #[serde(default)]
@@ -43,15 +109,226 @@ pub struct IdlMetadata {
 
     #[serde(default)]
     pub description: Option<String>,
+
+    #[serde(default)]
+    pub address: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IdlEventDefinition {
     pub name: String,
+    /// Discriminator bytes declared by the IDL (Anchor >= 0.30), used by
+    /// [`DiscriminatorScheme::Declared`] and [`DiscriminatorScheme::Custom`]
+    #[serde(default)]
+    pub discriminator: Option<Vec<u8>>,
     #[serde(default)]
     pub fields: Option<Vec<IdlField>>,
     #[serde(default)]
     pub r#type: Option<serde_json::Value>,
+    /// Doc comment lines from the program source
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub docs: Vec<String>,
+}
+
+/// An instruction from the IDL `instructions` array
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    /// Discriminator bytes declared by the IDL (Anchor >= 0.30); older IDLs use
+    /// `sha256("global:<snake_case_name>")[..8]`
+    #[serde(default)]
+    pub discriminator: Option<Vec<u8>>,
+    #[serde(default)]
+    pub accounts: Vec<IdlInstructionAccount>,
+    #[serde(default)]
+    pub args: Vec<IdlField>,
+}
+
+/// An account an instruction takes, or a named group of them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlInstructionAccount {
+    pub name: String,
+    /// Nested accounts of a composite group (empty for a single account)
+    #[serde(default)]
+    pub accounts: Vec<IdlInstructionAccount>,
+}
+
+impl IdlInstructionAccount {
+    /// Account names in instruction order, nested ones as `group.name`
+    pub fn flatten(accounts: &[IdlInstructionAccount]) -> Vec<String> {
+        accounts
+            .iter()
+            .flat_map(|account| {
+                if account.accounts.is_empty() {
+                    vec![account.name.clone()]
+                } else {
+                    Self::flatten(&account.accounts)
+                        .into_iter()
+                        .map(|name| format!("{}.{}", account.name, name))
+                        .collect()
+                }
+            })
+            .collect()
+    }
+}
+
+/// How a program tags its event data with a discriminator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiscriminatorScheme {
+    /// `sha256("event:<name>")[..8]`, Anchor's event convention
+    #[default]
+    AnchorEvent,
+    /// `sha256("account:<name>")[..8]`, for programs that emit the raw struct
+    AnchorAccount,
+    /// The `discriminator` bytes declared on each IDL event, up to 8 bytes long
+    Declared,
+    /// The first N (1-8) bytes of each event's declared discriminator
+    Custom(usize),
+    /// No discriminator: the first N (0-8) bytes are skipped and the rest is decoded
+    /// as the program's first IDL event, for native programs with positional logs
+    Untagged(usize),
+}
+
+impl std::fmt::Display for DiscriminatorScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiscriminatorScheme::AnchorEvent => write!(f, "anchor-event"),
+            DiscriminatorScheme::AnchorAccount => write!(f, "anchor-account"),
+            DiscriminatorScheme::Declared => write!(f, "declared"),
+            DiscriminatorScheme::Custom(len) => write!(f, "custom:{}", len),
+            DiscriminatorScheme::Untagged(skip) => write!(f, "untagged:{}", skip),
+        }
+    }
+}
+
+impl std::str::FromStr for DiscriminatorScheme {
+    type Err = SoltraceError;
+
+    /// Parse `anchor-event`, `anchor-account`, `declared`, `custom:<0-8>` or
+    /// `untagged[:<0-8>]`; `custom:0` and `none` mean `untagged:0`
+    fn from_str(s: &str) -> Result<Self> {
+        let len = |prefix: &str, other: &str| {
+            other
+                .strip_prefix(prefix)
+                .and_then(|len| len.parse::<usize>().ok())
+                .filter(|len| *len <= 8)
+        };
+        match s.trim().to_lowercase().as_str() {
+            "anchor-event" | "anchor" => Ok(DiscriminatorScheme::AnchorEvent),
+            "anchor-account" => Ok(DiscriminatorScheme::AnchorAccount),
+            "declared" => Ok(DiscriminatorScheme::Declared),
+            "untagged" | "none" => Ok(DiscriminatorScheme::Untagged(0)),
+            other => match (len("custom:", other), len("untagged:", other)) {
+                (Some(0), _) => Ok(DiscriminatorScheme::Untagged(0)),
+                (Some(len), _) => Ok(DiscriminatorScheme::Custom(len)),
+                (_, Some(skip)) => Ok(DiscriminatorScheme::Untagged(skip)),
+                _ => Err(SoltraceError::InvalidIdl(format!(
+                    "Invalid discriminator scheme '{}': expected anchor-event, anchor-account, declared, custom:<0-8> or untagged:<0-8>",
+                    s
+                ))),
+            },
+        }
+    }
+}
+
+/// Casing applied to the full (prefixed) event name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventNameCase {
+    /// Keep the `<prefix>_<EventName>` form, e.g. `tributary_PaymentRecord`
+    #[default]
+    AsIs,
+    /// `tributary_payment_record`
+    Snake,
+    /// `tributaryPaymentRecord`
+    Camel,
+    /// `tributary-payment-record`
+    Kebab,
+}
+
+impl EventNameCase {
+    /// Convert `name` to this casing
+    pub fn apply(&self, name: &str) -> String {
+        match self {
+            EventNameCase::AsIs => name.to_string(),
+            EventNameCase::Snake => Self::words(name).join("_"),
+            EventNameCase::Kebab => Self::words(name).join("-"),
+            EventNameCase::Camel => Self::words(name)
+                .iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) if i > 0 => first.to_uppercase().chain(chars).collect(),
+                        _ => word.clone(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Split on `_`, `-`, spaces and case boundaries into lowercase words
+    ///
+    /// Acronyms stay together: `HTTPRequest` splits into `http` and `request`.
+    fn words(name: &str) -> Vec<String> {
+        let chars: Vec<char> = name.chars().collect();
+        let mut words = Vec::new();
+        let mut current = String::new();
+
+        for (i, &c) in chars.iter().enumerate() {
+            if c == '_' || c == '-' || c.is_whitespace() {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+
+            if c.is_uppercase() && !current.is_empty() {
+                let prev = chars[i - 1];
+                let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+                if prev.is_lowercase()
+                    || prev.is_ascii_digit()
+                    || (prev.is_uppercase() && next_is_lower)
+                {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+            current.extend(c.to_lowercase());
+        }
+        if !current.is_empty() {
+            words.push(current);
+        }
+
+        words
+    }
+}
+
+impl std::fmt::Display for EventNameCase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventNameCase::AsIs => write!(f, "as-is"),
+            EventNameCase::Snake => write!(f, "snake"),
+            EventNameCase::Camel => write!(f, "camel"),
+            EventNameCase::Kebab => write!(f, "kebab"),
+        }
+    }
+}
+
+impl std::str::FromStr for EventNameCase {
+    type Err = SoltraceError;
+
+    /// Parse `as-is`, `snake`, `camel` or `kebab`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "as-is" | "asis" | "none" => Ok(EventNameCase::AsIs),
+            "snake" | "snake_case" => Ok(EventNameCase::Snake),
+            "camel" | "camelcase" => Ok(EventNameCase::Camel),
+            "kebab" | "kebab-case" => Ok(EventNameCase::Kebab),
+            _ => Err(SoltraceError::InvalidIdl(format!(
+                "Invalid event name case '{}': expected as-is, snake, camel or kebab",
+                s
+            ))),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,24 +336,82 @@ pub struct IdlField {
     pub name: String,
     #[serde(rename = "type")]
     pub field_type: serde_json::Value,
+    /// Doc comment lines from the program source
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub docs: Vec<String>,
+}
+
+impl IdlField {
+    /// Rust-like rendering of the field type, e.g. `Option<Vec<u8>>` or `[u8; 32]`
+    pub fn type_name(&self) -> String {
+        Self::render_type(&self.field_type)
+    }
+
+    fn render_type(ty: &serde_json::Value) -> String {
+        if let Some(name) = ty.as_str() {
+            return name.to_string();
+        }
+
+        if let Some(array) = ty.get("array").and_then(|a| a.as_array()) {
+            if let [inner, len] = array.as_slice() {
+                return format!("[{}; {}]", Self::render_type(inner), len);
+            }
+        }
+        if let Some(inner) = ty.get("option") {
+            return format!("Option<{}>", Self::render_type(inner));
+        }
+        if let Some(inner) = ty.get("vec") {
+            return format!("Vec<{}>", Self::render_type(inner));
+        }
+        if let Some(defined) = ty.get("defined") {
+            let name = defined.get("name").unwrap_or(defined);
+            if let Some(name) = name.as_str() {
+                return name.to_string();
+            }
+        }
+
+        ty.to_string()
+    }
 }
 
 /// Represents a decoded Anchor event
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DecodedEvent {
     pub event_name: String,
     pub data: serde_json::Value,
+    /// Matched discriminator, zero-padded when the scheme uses fewer than 8 bytes
     pub discriminator: EventDiscriminator,
 }
 
+/// A program instruction decoded from transaction data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedInstruction {
+    /// Instruction name as written in the IDL
+    pub name: String,
+    /// Decoded instruction arguments
+    pub data: serde_json::Value,
+    /// IDL account names mapped to the pubkeys passed; extra accounts are listed
+    /// under `remaining_accounts`
+    pub accounts: serde_json::Value,
+}
+
 /// Raw event data from Solana logs
 #[derive(Debug, Clone)]
 pub struct RawEvent {
     pub slot: Slot,
-    pub signature: String,
+    pub signature: Signature,
     pub program_id: ProgramId,
     pub log: String,
+    /// Position of the event's (first) `Program data:` line among the transaction's logs
+    pub log_index: u32,
+    /// Inner instruction the event was logged from, counted within its top-level
+    /// instruction as in the transaction's `innerInstructions`; `None` at top level
+    pub inner_instruction_index: Option<u32>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Pre-decode event bytes, kept only when raw storage is enabled
+    pub raw_data: Option<Vec<u8>>,
+    /// The transaction failed but its events were kept (`--include-failed`)
+    pub tx_failed: bool,
 }
 
 /// Configuration for program-to-prefix mapping
@@ -120,7 +455,7 @@ impl ProgramPrefixConfig {
         &mut self,
         idls: &std::collections::HashMap<String, crate::types::ParsedIdl>,
     ) {
-        for (program_id, _) in idls {
+        for program_id in idls.keys() {
             if !self.program_mappings.contains_key(program_id) {
                 self.program_mappings
                     .entry(program_id.clone())
@@ -147,3 +482,245 @@ impl Default for ProgramPrefixConfig {
         Self::new()
     }
 }
+
+/// Human-friendly program names for logs and metric labels
+///
+/// Unlike the [`ProgramPrefixConfig`] prefix, an alias never reaches stored
+/// data, so it can be changed freely.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramAliases {
+    aliases: HashMap<String, String>,
+}
+
+impl ProgramAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse `program_id=name` pairs separated by commas
+    pub fn from_string(aliases: &str) -> Result<Self> {
+        let mut parsed = Self::new();
+        for pair in aliases.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (program_id, name) = pair
+                .split_once('=')
+                .map(|(id, name)| (id.trim(), name.trim()))
+                .filter(|(id, name)| !id.is_empty() && !name.is_empty())
+                .ok_or_else(|| {
+                    SoltraceError::InvalidIdl(format!(
+                        "Invalid program alias '{}': expected program_id=name",
+                        pair
+                    ))
+                })?;
+            parsed.add(program_id, name);
+        }
+        Ok(parsed)
+    }
+
+    pub fn add(&mut self, program_id: &str, name: &str) {
+        self.aliases
+            .insert(program_id.to_string(), name.to_string());
+    }
+
+    /// The program's alias, or its ID when it has none (for metric labels)
+    pub fn label<'a>(&'a self, program_id: &'a str) -> &'a str {
+        self.aliases
+            .get(program_id)
+            .map_or(program_id, String::as_str)
+    }
+
+    /// `alias (program_id)`, or just the ID when it has no alias (for logs)
+    pub fn display(&self, program_id: &str) -> String {
+        match self.aliases.get(program_id) {
+            Some(name) => format!("{} ({})", name, program_id),
+            None => program_id.to_string(),
+        }
+    }
+}
+
+/// A public Solana cluster, for `--cluster` instead of typing its endpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Cluster {
+    #[default]
+    Mainnet,
+    Devnet,
+    Testnet,
+    /// A local `solana-test-validator`
+    Localnet,
+}
+
+impl Cluster {
+    /// The cluster's public HTTP RPC endpoint
+    pub fn rpc_url(&self) -> &'static str {
+        match self {
+            Cluster::Mainnet => "https://api.mainnet-beta.solana.com",
+            Cluster::Devnet => "https://api.devnet.solana.com",
+            Cluster::Testnet => "https://api.testnet.solana.com",
+            Cluster::Localnet => "http://127.0.0.1:8899",
+        }
+    }
+
+    /// The cluster's public WebSocket endpoint
+    pub fn ws_url(&self) -> &'static str {
+        match self {
+            Cluster::Mainnet => "wss://api.mainnet-beta.solana.com",
+            Cluster::Devnet => "wss://api.devnet.solana.com",
+            Cluster::Testnet => "wss://api.testnet.solana.com",
+            Cluster::Localnet => "ws://127.0.0.1:8900",
+        }
+    }
+}
+
+impl std::fmt::Display for Cluster {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cluster::Mainnet => write!(f, "mainnet"),
+            Cluster::Devnet => write!(f, "devnet"),
+            Cluster::Testnet => write!(f, "testnet"),
+            Cluster::Localnet => write!(f, "localnet"),
+        }
+    }
+}
+
+impl std::str::FromStr for Cluster {
+    type Err = SoltraceError;
+
+    /// Parse `mainnet`, `devnet`, `testnet` or `localnet`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "mainnet" | "mainnet-beta" => Ok(Cluster::Mainnet),
+            "devnet" => Ok(Cluster::Devnet),
+            "testnet" => Ok(Cluster::Testnet),
+            "localnet" | "localhost" => Ok(Cluster::Localnet),
+            _ => Err(SoltraceError::InvalidIdl(format!(
+                "Invalid cluster '{}': expected mainnet, devnet, testnet or localnet",
+                s
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIG: &str =
+        "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW";
+
+    #[test]
+    fn test_signature_parse() {
+        let sig = Signature::parse(SIG).unwrap();
+        assert_eq!(sig.as_str(), SIG);
+        assert_eq!(sig.to_solana().to_string(), SIG);
+
+        assert!(Signature::parse("not-a-signature").is_err());
+        assert!(Signature::parse("").is_err());
+    }
+
+    #[test]
+    fn test_discriminator_scheme_from_str() {
+        assert_eq!(
+            "anchor-event".parse::<DiscriminatorScheme>().unwrap(),
+            DiscriminatorScheme::AnchorEvent
+        );
+        assert_eq!(
+            "Anchor-Account".parse::<DiscriminatorScheme>().unwrap(),
+            DiscriminatorScheme::AnchorAccount
+        );
+        assert_eq!(
+            "declared".parse::<DiscriminatorScheme>().unwrap(),
+            DiscriminatorScheme::Declared
+        );
+        assert_eq!(
+            "custom:1".parse::<DiscriminatorScheme>().unwrap(),
+            DiscriminatorScheme::Custom(1)
+        );
+        // A zero-length discriminator means the data isn't tagged at all
+        assert_eq!(
+            "custom:0".parse::<DiscriminatorScheme>().unwrap(),
+            DiscriminatorScheme::Untagged(0)
+        );
+        assert!("custom:9".parse::<DiscriminatorScheme>().is_err());
+        assert!("untagged:9".parse::<DiscriminatorScheme>().is_err());
+        assert!("sha512".parse::<DiscriminatorScheme>().is_err());
+
+        for scheme in [
+            DiscriminatorScheme::Declared,
+            DiscriminatorScheme::Custom(4),
+            DiscriminatorScheme::Untagged(2),
+        ] {
+            assert_eq!(
+                scheme.to_string().parse::<DiscriminatorScheme>().unwrap(),
+                scheme
+            );
+        }
+    }
+
+    #[test]
+    fn test_event_name_case() {
+        let name = "tributary_PaymentRecord";
+        assert_eq!(EventNameCase::AsIs.apply(name), "tributary_PaymentRecord");
+        assert_eq!(EventNameCase::Snake.apply(name), "tributary_payment_record");
+        assert_eq!(EventNameCase::Camel.apply(name), "tributaryPaymentRecord");
+        assert_eq!(EventNameCase::Kebab.apply(name), "tributary-payment-record");
+
+        // Acronyms and digits stay with their word
+        assert_eq!(
+            EventNameCase::Snake.apply("my-dex_NFTSale2Done"),
+            "my_dex_nft_sale2_done"
+        );
+        assert_eq!(EventNameCase::Camel.apply("my-dex_NFTSale"), "myDexNftSale");
+
+        for case in [EventNameCase::AsIs, EventNameCase::Kebab] {
+            assert_eq!(case.to_string().parse::<EventNameCase>().unwrap(), case);
+        }
+        assert!("shouty".parse::<EventNameCase>().is_err());
+    }
+
+    #[test]
+    fn test_cluster_urls() {
+        let localnet: Cluster = "localnet".parse().unwrap();
+        assert_eq!(localnet.rpc_url(), "http://127.0.0.1:8899");
+        assert_eq!(localnet.ws_url(), "ws://127.0.0.1:8900");
+
+        let devnet: Cluster = "Devnet".parse().unwrap();
+        assert_eq!(devnet.rpc_url(), "https://api.devnet.solana.com");
+        assert_eq!(devnet.ws_url(), "wss://api.devnet.solana.com");
+        assert_eq!(devnet.to_string(), "devnet");
+
+        assert_eq!("mainnet-beta".parse::<Cluster>().unwrap(), Cluster::Mainnet);
+        assert_eq!(
+            Cluster::default().rpc_url(),
+            "https://api.mainnet-beta.solana.com"
+        );
+        assert!("mainnett".parse::<Cluster>().is_err());
+    }
+
+    #[test]
+    fn test_idl_field_docs_and_type_name() {
+        let field: IdlField = serde_json::from_value(serde_json::json!({
+            "name": "payees",
+            "docs": ["Who gets paid"],
+            "type": {"option": {"vec": {"defined": {"name": "Payee"}}}}
+        }))
+        .unwrap();
+        assert_eq!(field.docs, vec!["Who gets paid"]);
+        assert_eq!(field.type_name(), "Option<Vec<Payee>>");
+
+        let field: IdlField = serde_json::from_value(
+            serde_json::json!({"name": "seed", "type": {"array": ["u8", 32]}}),
+        )
+        .unwrap();
+        assert!(field.docs.is_empty());
+        assert_eq!(field.type_name(), "[u8; 32]");
+    }
+
+    #[test]
+    fn test_signature_serde_validates() {
+        let json = serde_json::to_string(&Signature::parse(SIG).unwrap()).unwrap();
+        assert_eq!(json, format!("\"{}\"", SIG));
+
+        let sig: Signature = serde_json::from_str(&json).unwrap();
+        assert_eq!(sig.as_str(), SIG);
+        assert!(serde_json::from_str::<Signature>("\"bogus\"").is_err());
+    }
+}
@@ -1,7 +1,18 @@
-use crate::{db::Database, event::EventDecoder, idl::IdlParser, types::RawEvent};
+use crate::{
+    db::Database,
+    error::{SoltraceError, TransactionErrorKind},
+    event::EventDecoder,
+    filter::EventNameFilter,
+    idl::IdlParser,
+    metrics::Metrics,
+    retry::retry_with_backoff,
+    sink::EventSink,
+    types::{DecodedEvent, RawEvent, Signature},
+};
 use anyhow::Result;
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
 /// Load all IDL files from a directory
@@ -19,8 +30,11 @@ pub async fn load_idls(idl_parser: &mut IdlParser, idl_dir: &str) -> Result<()>
 
     while let Some(entry) = entries.next_entry().await? {
         let path = entry.path();
-        if path.extension().map_or(false, |ext| ext == "json") {
-            match idl_parser.load_from_file(path.to_str().unwrap()) {
+        let Some(path_str) = path.to_str() else {
+            continue;
+        };
+        if IdlParser::is_idl_file(path_str) {
+            match idl_parser.load_from_file(path_str) {
                 Ok(_) => {
                     loaded_count += 1;
                     info!("Loaded IDL: {}", path.display());
@@ -40,45 +54,125 @@ pub async fn load_idls(idl_parser: &mut IdlParser, idl_dir: &str) -> Result<()>
 }
 
 /// Process a single transaction and extract events
+///
+/// A transaction whose events can't be read fails with [`SoltraceError::Transaction`],
+/// carrying its signature and slot.
+#[allow(clippy::too_many_arguments)]
 pub async fn process_transaction(
     transaction: EncodedConfirmedTransactionWithStatusMeta,
     program_id_str: &str,
     event_decoder: &EventDecoder,
-    db: &Database,
-) -> Result<Vec<String>> {
+    sink: &dyn EventSink,
+    event_filter: &EventNameFilter,
+    metrics: &Metrics,
+    store_raw: bool,
+    include_failed: bool,
+) -> crate::error::Result<Vec<String>> {
     let mut processed_signatures = Vec::new();
 
+    let events = decode_transaction_events(
+        &transaction,
+        program_id_str,
+        event_decoder,
+        event_filter,
+        metrics,
+        store_raw,
+        include_failed,
+    )?;
+
+    let mut events_count = 0;
+    for (decoded_event, raw_event) in &events {
+        // Store event
+        let insert_started = Instant::now();
+        let inserted = sink.write(decoded_event, raw_event).await;
+        metrics.record_insert_latency(insert_started.elapsed());
+
+        match inserted {
+            Ok(_) => {
+                metrics.record_db_insert(false, false);
+                metrics.record_event(program_id_str, &decoded_event.event_name);
+                events_count += 1;
+                debug!(
+                    "Stored event: {} from {}",
+                    decoded_event.event_name, raw_event.signature
+                );
+            }
+            Err(e) => {
+                let duplicate = is_duplicate_error(&e);
+                metrics.record_db_insert(true, duplicate);
+                if duplicate {
+                    debug!("Event {} already exists, skipping", raw_event.signature);
+                } else {
+                    error!("Failed to store event: {}", e);
+                    metrics.record_error(format!("Failed to store event: {}", e));
+                }
+            }
+        }
+    }
+
+    // Every event carries the transaction's signature
+    if events_count > 0 {
+        processed_signatures.push(events[0].1.signature.to_string());
+    }
+
+    Ok(processed_signatures)
+}
+
+/// Decode the events a transaction emitted without storing them
+///
+/// Events come back in log order after the event name filter, each carrying its
+/// position in the logs (`RawEvent::log_index`). Failed transactions yield no events
+/// unless `include_failed` is set, in which case their events are marked `tx_failed`.
+/// Errors are [`SoltraceError::Transaction`], as for [`process_transaction`].
+pub fn decode_transaction_events(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    program_id_str: &str,
+    event_decoder: &EventDecoder,
+    event_filter: &EventNameFilter,
+    metrics: &Metrics,
+    store_raw: bool,
+    include_failed: bool,
+) -> crate::error::Result<Vec<(DecodedEvent, RawEvent)>> {
+    let mut events = Vec::new();
+
     let slot = transaction.slot;
+    let error = |signature: Option<&Signature>, kind| SoltraceError::Transaction {
+        signature: signature.map(|s| s.to_string()),
+        slot,
+        kind,
+    };
+
+    // Get transaction signature from the encoded transaction
+    let signature = match &transaction.transaction.transaction {
+        solana_transaction_status::EncodedTransaction::Json(ui_tx) => Signature::parse(
+            ui_tx
+                .signatures
+                .first()
+                .ok_or_else(|| error(None, TransactionErrorKind::NoSignature))?,
+        )?,
+        _ => return Err(error(None, TransactionErrorKind::UnsupportedEncoding)),
+    };
 
     let meta = transaction
         .transaction
         .meta
         .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("Transaction has no metadata"))?;
+        .ok_or_else(|| error(Some(&signature), TransactionErrorKind::NoMetadata))?;
 
-    // Skip failed transactions
+    metrics.record_transaction(meta.err.is_some());
+
+    // Skip failed transactions unless their events are wanted
     if let Some(err) = &meta.err {
-        debug!("Skipping failed transaction: {:?}", err);
-        return Ok(processed_signatures);
+        if !include_failed {
+            debug!("Skipping failed transaction: {:?}", err);
+            return Ok(events);
+        }
+        debug!("Keeping events from failed transaction: {:?}", err);
     }
 
     // Check if we have logs
     let logs: Option<Vec<String>> = meta.log_messages.clone().into();
-    let logs = logs.ok_or_else(|| anyhow::anyhow!("Transaction has no logs"))?;
-
-    // Get transaction signature from the encoded transaction
-    let signature = match &transaction.transaction.transaction {
-        solana_transaction_status::EncodedTransaction::Json(ui_tx) => ui_tx
-            .signatures
-            .first()
-            .ok_or_else(|| anyhow::anyhow!("Transaction has no signature"))?
-            .to_string(),
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Only JSON-encoded transactions are supported"
-            ));
-        }
-    };
+    let logs = logs.ok_or_else(|| error(Some(&signature), TransactionErrorKind::NoLogs))?;
 
     // Get block time from transaction if available
     let block_time = transaction.block_time;
@@ -87,53 +181,141 @@ pub async fn process_transaction(
         .unwrap_or_else(chrono::Utc::now);
 
     // Process logs for events
-    let mut events_count = 0;
-    for log in logs {
-        if let Some(event_data) = extract_event_from_log(&log) {
-            // Decode event
-            match event_decoder.decode_event(program_id_str, &signature, &event_data) {
-                Ok(decoded_event) => {
-                    // Create raw event record
-                    let raw_event = RawEvent {
-                        slot,
-                        signature: signature.clone(),
-                        program_id: program_id_str
-                            .parse()
-                            .unwrap_or_else(|_| solana_sdk::pubkey::Pubkey::default()),
-                        log: log.to_string(),
-                        timestamp,
-                    };
-
-                    // Store event
-                    match db.insert_event(&decoded_event, &raw_event, events_count).await {
-                        Ok(_) => {
-                            events_count += 1;
-                            debug!(
-                                "Stored event: {} from {}",
-                                decoded_event.event_name, signature
-                            );
-                        }
-                        Err(e) => {
-                            if e.to_string().contains("UNIQUE constraint") {
-                                debug!("Event {} already exists, skipping", signature);
-                            } else {
-                                error!("Failed to store event: {}", e);
-                            }
-                        }
-                    }
+    for log_event in extract_events_from_logs(&logs) {
+        let Some(event_data) = log_event.data else {
+            warn!(
+                "Event data in transaction {} looks truncated, skipping it",
+                signature
+            );
+            metrics.record_truncated_event();
+            continue;
+        };
+
+        // Decode event
+        let decode_started = Instant::now();
+        let decoded = event_decoder.decode_event(program_id_str, signature.as_str(), &event_data);
+        metrics.record_decode_latency(decode_started.elapsed());
+
+        match decoded {
+            Ok(decoded_event) => {
+                if decoded_event.data.get("decode_error").is_some() {
+                    metrics.record_decode_failure(program_id_str);
                 }
-                Err(e) => {
-                    debug!("Failed to decode event: {}", e);
+                if !event_filter.is_allowed(&decoded_event.event_name) {
+                    debug!("Filtered event: {}", decoded_event.event_name);
+                    metrics.record_filtered_event();
+                    continue;
                 }
+
+                // Create raw event record
+                let raw_event = RawEvent {
+                    slot,
+                    signature: signature.clone(),
+                    program_id: program_id_str
+                        .parse()
+                        .unwrap_or_else(|_| solana_sdk::pubkey::Pubkey::default()),
+                    log: log_event.log,
+                    log_index: log_event.log_index,
+                    inner_instruction_index: log_event.inner_instruction_index,
+                    timestamp,
+                    raw_data: store_raw.then_some(event_data),
+                    tx_failed: meta.err.is_some(),
+                };
+                events.push((decoded_event, raw_event));
+            }
+            Err(e) if event_decoder.is_ignored(&e) => {}
+            Err(e) => {
+                metrics.record_decode_failure(program_id_str);
+                metrics.record_error(format!("Failed to decode event: {}", e));
+                debug!("Failed to decode event: {}", e);
             }
         }
     }
 
-    if events_count > 0 {
-        processed_signatures.push(signature);
+    Ok(events)
+}
+
+/// Retries for an event insert that failed with a transient database error
+pub const DB_INSERT_RETRIES: u32 = 3;
+
+/// Store an event, retrying transient database errors with backoff
+///
+/// Duplicates and other permanent errors are returned on the first attempt.
+pub async fn insert_event_with_retry(
+    db: &Database,
+    event: &DecodedEvent,
+    raw: &RawEvent,
+) -> crate::error::Result<String> {
+    retry_transient_db_error(|| db.insert_event(event, raw)).await
+}
+
+/// Run a database operation, retrying only errors [`is_transient_db_error`] accepts
+async fn retry_transient_db_error<T, F, Fut>(operation: F) -> crate::error::Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = crate::error::Result<T>>,
+{
+    retry_with_backoff(
+        || {
+            let attempt = operation();
+            async move {
+                match attempt.await {
+                    Err(e) if is_transient_db_error(&e) => Err(e),
+                    result => Ok(result),
+                }
+            }
+        },
+        DB_INSERT_RETRIES,
+        Duration::from_millis(50),
+        Duration::from_secs(2),
+    )
+    .await?
+}
+
+/// Whether a database error is a unique-constraint violation on an already stored event
+///
+/// Backends report these as [`SoltraceError::Duplicate`]; raw sqlx errors are
+/// checked by error code in case one slips through unmapped.
+pub fn is_duplicate_error(error: &SoltraceError) -> bool {
+    match error {
+        SoltraceError::Duplicate(_) => true,
+        SoltraceError::Sqlx(sqlx::Error::Database(e)) => e.is_unique_violation(),
+        _ => false,
     }
+}
 
-    Ok(processed_signatures)
+/// Whether a database error is worth retrying (lost connection, deadlock, lock contention)
+pub fn is_transient_db_error(error: &SoltraceError) -> bool {
+    match error {
+        SoltraceError::Io(_) => true,
+        SoltraceError::Sqlx(e) => match e {
+            sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed => true,
+            // Postgres deadlock / serialization failure, SQLite busy / locked
+            sqlx::Error::Database(e) => {
+                matches!(e.code().as_deref(), Some("40P01" | "40001" | "5" | "6"))
+            }
+            _ => false,
+        },
+        SoltraceError::Database(message) => {
+            let message = message.to_lowercase();
+            message.contains("connection") || message.contains("timed out")
+        }
+        _ => false,
+    }
+}
+
+/// Whether a transaction fetch failed because the transaction is pruned or not yet available
+///
+/// The RPC reports these as errors rather than `null`; retrying them only wastes requests.
+pub fn is_missing_transaction_error(error: &crate::error::SoltraceError) -> bool {
+    let message = error.to_string().to_lowercase();
+    // `getTransaction` returns `null` for unknown signatures, which fails to deserialize
+    message.contains("invalid type: null")
+        || message.contains("transaction not found")
+        // -32011: history not available, -32009/-32007: slot pruned or skipped
+        || message.contains("transaction history is not available")
+        || message.contains("long-term storage")
+        || message.contains("was skipped, or missing")
 }
 
 /// Extract event data from a log line
@@ -153,10 +335,173 @@ pub fn extract_event_from_log(log: &str) -> Option<Vec<u8>> {
     None
 }
 
+/// A `Program data:` payload found in a transaction's logs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEvent {
+    /// Position of its (first) `Program data:` line among the logs
+    pub log_index: u32,
+    /// Inner instruction it was logged from, counted within its top-level instruction;
+    /// `None` when logged by a top-level instruction
+    pub inner_instruction_index: Option<u32>,
+    /// The log line, or the lines a split payload was joined from
+    pub log: String,
+    /// The decoded payload, `None` if it was cut off
+    pub data: Option<Vec<u8>>,
+}
+
+/// Follows `Program <id> invoke [n]` / `success` / `failed` lines to tell which
+/// inner instruction the logs are in
+#[derive(Default)]
+struct InvokeTracker {
+    /// Inner instruction index of each CPI currently executing, outermost first
+    frames: Vec<u32>,
+    /// Inner instructions invoked so far within the current top-level instruction
+    invoked: u32,
+}
+
+impl InvokeTracker {
+    fn observe(&mut self, log: &str) {
+        let Some((program, status)) = log
+            .strip_prefix("Program ")
+            .and_then(|rest| rest.split_once(' '))
+        else {
+            return;
+        };
+        // `Program log:`, `Program data:`, `Program return:` and the like
+        if program.ends_with(':') {
+            return;
+        }
+
+        if let Some(depth) = status
+            .strip_prefix("invoke [")
+            .and_then(|depth| depth.strip_suffix(']'))
+            .and_then(|depth| depth.parse::<usize>().ok())
+        {
+            if depth <= 1 {
+                self.frames.clear();
+                self.invoked = 0;
+            } else {
+                self.frames.truncate(depth - 2);
+                self.frames.push(self.invoked);
+                self.invoked += 1;
+            }
+        } else if status == "success" || status.starts_with("failed") {
+            self.frames.pop();
+        }
+    }
+
+    fn inner_instruction_index(&self) -> Option<u32> {
+        self.frames.last().copied()
+    }
+}
+
+/// Extract the event data of every `Program data:` entry in a transaction's logs
+///
+/// Returns each payload with the log it came from and its position, in log order. Some RPC
+/// responses split very large payloads across consecutive `Program data:` lines;
+/// a line whose base64 is cut short (length not a multiple of 4) is joined with
+/// the following ones until the whole decodes, and the log becomes those lines
+/// joined by newlines. A cut-off payload that never completes comes back as
+/// `None` so it can be reported rather than silently dropped. The inner
+/// instruction index is only as good as the logs: truncated logs can lose track of it.
+pub fn extract_events_from_logs(logs: &[String]) -> Vec<LogEvent> {
+    fn program_data(log: &str) -> Option<&str> {
+        log.strip_prefix("Program data: ").map(str::trim)
+    }
+    let mut events = Vec::new();
+    let mut invokes = InvokeTracker::default();
+    let mut i = 0;
+
+    while i < logs.len() {
+        let Some(chunk) = program_data(&logs[i]) else {
+            invokes.observe(&logs[i]);
+            i += 1;
+            continue;
+        };
+        let event = |log: String, data: Option<Vec<u8>>| LogEvent {
+            log_index: i as u32,
+            inner_instruction_index: invokes.inner_instruction_index(),
+            log,
+            data,
+        };
+        if let Ok(data) = STANDARD.decode(chunk) {
+            events.push(event(logs[i].clone(), Some(data)));
+            i += 1;
+            continue;
+        }
+        if chunk.len() % 4 == 0 {
+            // Complete but malformed, as `extract_event_from_log` would skip it
+            i += 1;
+            continue;
+        }
+
+        let mut joined = chunk.to_string();
+        let mut end = i + 1;
+        let mut reassembled = None;
+        while let Some(next) = logs.get(end).and_then(|log| program_data(log)) {
+            joined.push_str(next);
+            end += 1;
+            if let Ok(data) = STANDARD.decode(&joined) {
+                reassembled = Some(data);
+                break;
+            }
+        }
+
+        match reassembled {
+            Some(data) => {
+                events.push(event(logs[i..end].join("\n"), Some(data)));
+                i = end;
+            }
+            None => {
+                events.push(event(logs[i].clone(), None));
+                i += 1;
+            }
+        }
+    }
+
+    events
+}
+
+/// The `--env-file` path among command line arguments
+///
+/// Read before clap parses the arguments, since the file has to be loaded first
+/// for its variables to fill in settings that fall back to the environment.
+pub fn env_file_from_args(args: impl IntoIterator<Item = String>) -> Option<std::path::PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--env-file" {
+            return args.next().map(Into::into);
+        }
+        if let Some(path) = arg.strip_prefix("--env-file=") {
+            return Some(path.into());
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_env_file_from_args() {
+        let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            env_file_from_args(args("soltrace-live run --env-file prod.env -m x")),
+            Some("prod.env".into())
+        );
+        assert_eq!(
+            env_file_from_args(args("soltrace-backfill --env-file=staging.env")),
+            Some("staging.env".into())
+        );
+        assert!(env_file_from_args(args("soltrace-live run --db-url x")).is_none());
+        assert!(env_file_from_args(args("soltrace-live -- --env-file x")).is_none());
+    }
+
     #[test]
     fn test_extract_event_from_log() {
         // Base64 "eyJldmVudCI6IlRyYW5zZmVyIn0=" decodes to '{"event":"Transfer"}'
@@ -168,6 +513,83 @@ mod tests {
         assert_eq!(result.unwrap(), br#"{"event":"Transfer"}"#);
     }
 
+    #[test]
+    fn test_extract_events_reassembles_split_payloads() {
+        let payload = STANDARD.encode([7u8; 100]);
+        let event = STANDARD.encode(b"event");
+        let logs: Vec<String> = [
+            "Program log: Instruction: Pay".to_string(),
+            format!("Program data: {}", &payload[..50]),
+            format!("Program data: {}", &payload[50..101]),
+            format!("Program data: {}", &payload[101..]),
+            format!("Program data: {}", event),
+            format!("Program data: {}", &payload[..30]),
+        ]
+        .to_vec();
+
+        let events = extract_events_from_logs(&logs);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].log, logs[1..4].join("\n"));
+        assert_eq!(events[0].data.as_deref(), Some(&[7u8; 100][..]));
+        assert_eq!(events[1].data.as_deref(), Some(&b"event"[..]));
+        let log_indexes: Vec<_> = events.iter().map(|e| e.log_index).collect();
+        assert_eq!(log_indexes, [1, 4, 5]);
+        // A cut-off payload with nothing left to join is reported, not dropped
+        assert_eq!(
+            events[2],
+            LogEvent {
+                log_index: 5,
+                inner_instruction_index: None,
+                log: logs[5].clone(),
+                data: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_extract_events_track_inner_instructions() {
+        let data = format!("Program data: {}", STANDARD.encode(b"event"));
+        let logs: Vec<String> = [
+            "Program Outer invoke [1]",
+            &data,
+            "Program Cpi invoke [2]",
+            "Program log: Instruction: success",
+            &data,
+            "Program Nested invoke [3]",
+            &data,
+            "Program Nested success",
+            &data,
+            "Program Cpi success",
+            "Program Other invoke [2]",
+            &data,
+            "Program Other failed: custom program error: 0x1",
+            &data,
+            "Program Outer success",
+            "Program Next invoke [1]",
+            "Program Cpi invoke [2]",
+            &data,
+        ]
+        .map(String::from)
+        .to_vec();
+
+        let positions: Vec<_> = extract_events_from_logs(&logs)
+            .into_iter()
+            .map(|e| (e.log_index, e.inner_instruction_index))
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                (1, None),
+                (4, Some(0)),
+                (6, Some(1)),
+                (8, Some(0)),
+                (11, Some(2)),
+                (13, None),
+                (17, Some(0)),
+            ]
+        );
+    }
+
     #[test]
     fn test_extract_event_no_match() {
         let log = "Program log: Some other log";
@@ -175,4 +597,130 @@ mod tests {
 
         assert!(result.is_none());
     }
+
+    #[test]
+    fn test_transaction_errors_carry_context() {
+        let signature = solana_sdk::signature::Signature::from([4u8; 64]).to_string();
+        let json_tx = serde_json::json!({
+            "signatures": [signature],
+            "message": {
+                "header": {
+                    "numRequiredSignatures": 1,
+                    "numReadonlySignedAccounts": 0,
+                    "numReadonlyUnsignedAccounts": 0
+                },
+                "accountKeys": [],
+                "recentBlockhash": "11111111111111111111111111111111",
+                "instructions": []
+            }
+        });
+        let meta = serde_json::json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [],
+            "postBalances": []
+        });
+        let decode = |transaction: serde_json::Value, meta: serde_json::Value| {
+            let transaction: EncodedConfirmedTransactionWithStatusMeta =
+                serde_json::from_value(serde_json::json!({
+                    "slot": 42,
+                    "transaction": transaction,
+                    "meta": meta
+                }))
+                .unwrap();
+            decode_transaction_events(
+                &transaction,
+                "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ",
+                &EventDecoder::new(IdlParser::new(), crate::types::ProgramPrefixConfig::new()),
+                &EventNameFilter::new(),
+                &Metrics::new(),
+                false,
+                false,
+            )
+            .unwrap_err()
+        };
+
+        let cases = [
+            (
+                json_tx.clone(),
+                serde_json::Value::Null,
+                TransactionErrorKind::NoMetadata,
+            ),
+            (json_tx, meta.clone(), TransactionErrorKind::NoLogs),
+            (
+                serde_json::json!(["AQ==", "base64"]),
+                meta,
+                TransactionErrorKind::UnsupportedEncoding,
+            ),
+        ];
+        for (transaction, meta, expected) in cases {
+            let SoltraceError::Transaction {
+                signature: error_signature,
+                slot,
+                kind,
+            } = decode(transaction, meta)
+            else {
+                panic!("expected a transaction error");
+            };
+            assert_eq!(kind, expected);
+            assert_eq!(slot, 42);
+            // The signature can't be read from a binary-encoded transaction
+            let readable = expected != TransactionErrorKind::UnsupportedEncoding;
+            assert_eq!(error_signature.is_some(), readable);
+            assert_eq!(kind.is_benign(), expected == TransactionErrorKind::NoLogs);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_event_with_retry() {
+        use std::sync::atomic::Ordering;
+
+        let event = DecodedEvent {
+            event_name: "tributary_PaymentRecord".to_string(),
+            data: serde_json::json!({}),
+            discriminator: [0; 8],
+        };
+        let raw = RawEvent {
+            slot: 1,
+            signature: Signature::parse(
+                "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW",
+            )
+            .unwrap(),
+            program_id: solana_sdk::pubkey::Pubkey::default(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+
+        // Connection resets are retried until the operation goes through
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let result = retry_transient_db_error(|| async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(std::io::Error::from(std::io::ErrorKind::ConnectionReset).into()),
+                _ => Ok("stored"),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "stored");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // Duplicates stop retrying immediately
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let result: crate::error::Result<()> = retry_transient_db_error(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(SoltraceError::Duplicate("event".to_string()))
+        })
+        .await;
+        assert!(is_duplicate_error(&result.unwrap_err()));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        assert!(insert_event_with_retry(&db, &event, &raw).await.is_ok());
+        let result = insert_event_with_retry(&db, &event, &raw).await;
+        assert!(is_duplicate_error(&result.unwrap_err()));
+    }
 }
@@ -1,5 +1,7 @@
 use crate::error::{Result, SoltraceError};
+use solana_commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
 use std::path::Path;
 
 /// Validate a Solana program ID string
@@ -70,6 +72,55 @@ pub fn validate_db_url(url: &str) -> Result<()> {
     Ok(())
 }
 
+/// Longest table name accepted, leaving room for derived index names within
+/// Postgres' 63-byte identifier limit
+pub const MAX_TABLE_NAME_LEN: usize = 48;
+
+/// Validate an events table name
+///
+/// Table names are interpolated into SQL, so only plain identifiers are
+/// accepted: an ASCII letter or underscore followed by letters, digits or
+/// underscores.
+pub fn validate_table_name(table: &str) -> Result<()> {
+    let mut chars = table.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    let valid_rest = chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if !valid_start || !valid_rest || table.len() > MAX_TABLE_NAME_LEN {
+        return Err(SoltraceError::InvalidIdl(format!(
+            "Invalid table name '{}': expected a letter or underscore followed by up to {} letters, digits or underscores",
+            table,
+            MAX_TABLE_NAME_LEN - 1
+        )));
+    }
+
+    Ok(())
+}
+
+/// Longest MongoDB database name, in bytes
+pub const MAX_MONGO_DB_NAME_LEN: usize = 63;
+
+/// Validate a MongoDB database name
+///
+/// MongoDB rejects empty names, names of 64 bytes or more, and names
+/// containing any of `/\. "$*<>:|?` or NUL.
+pub fn validate_mongo_db_name(name: &str) -> Result<()> {
+    const FORBIDDEN: &[char] = &[
+        '/', '\\', '.', ' ', '"', '$', '*', '<', '>', ':', '|', '?', '\0',
+    ];
+
+    if name.is_empty() || name.len() > MAX_MONGO_DB_NAME_LEN || name.contains(FORBIDDEN) {
+        return Err(SoltraceError::Database(format!(
+            "Invalid MongoDB database name '{}': expected up to {} bytes without spaces or any of /\\.\"$*<>:|?",
+            name, MAX_MONGO_DB_NAME_LEN
+        )));
+    }
+
+    Ok(())
+}
+
 /// Validate an RPC URL
 pub fn validate_rpc_url(url: &str) -> Result<()> {
     if url.is_empty() {
@@ -108,18 +159,115 @@ pub fn validate_ws_url(url: &str) -> Result<()> {
     Ok(())
 }
 
+/// Derive the WebSocket URL of an RPC endpoint: `https` becomes `wss` and `http`
+/// becomes `ws`, keeping the host, path and query (where providers put API keys)
+///
+/// As with the Solana CLI, an explicit port is incremented by one, so a local
+/// validator's `http://localhost:8899` maps to `ws://localhost:8900`.
+pub fn derive_ws_url(rpc_url: &str) -> Result<String> {
+    validate_rpc_url(rpc_url)?;
+    let mut url = url::Url::parse(rpc_url)
+        .map_err(|e| SoltraceError::InvalidIdl(format!("Invalid RPC URL '{}': {}", rpc_url, e)))?;
+
+    let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+    let port = url.port().map(|port| port.saturating_add(1));
+    url.set_scheme(scheme)
+        .and_then(|_| url.set_port(port))
+        .map_err(|_| {
+            SoltraceError::InvalidIdl(format!("Cannot derive a WebSocket URL from '{}'", rpc_url))
+        })?;
+
+    Ok(url.to_string())
+}
+
 /// Validate commitment level string
 pub fn validate_commitment(commitment: &str) -> Result<()> {
-    let valid = ["processed", "confirmed", "finalized"];
+    parse_commitment(commitment).map(|_| ())
+}
 
-    if !valid.contains(&commitment.to_lowercase().as_str()) {
-        return Err(SoltraceError::InvalidIdl(format!(
-            "Invalid commitment level '{}': must be one of {:?}",
-            commitment, valid
-        )));
+/// Parse a commitment level string (case-insensitive)
+///
+/// The deprecated names used by older Solana tooling map to their closest
+/// equivalent: `recent` to processed, `single`/`singlegossip` to confirmed and
+/// `root`/`max` to finalized.
+pub fn parse_commitment(commitment: &str) -> Result<CommitmentConfig> {
+    match commitment.to_lowercase().as_str() {
+        "processed" | "recent" => Ok(CommitmentConfig::processed()),
+        "confirmed" | "single" | "singlegossip" => Ok(CommitmentConfig::confirmed()),
+        "finalized" | "root" | "max" => Ok(CommitmentConfig::finalized()),
+        _ => Err(SoltraceError::InvalidIdl(format!(
+            "Invalid commitment level '{}': must be one of [\"processed\", \"confirmed\", \"finalized\"]",
+            commitment
+        ))),
     }
+}
 
-    Ok(())
+/// Commitment level per program, falling back to a default for unlisted programs
+#[derive(Debug, Clone)]
+pub struct CommitmentMap {
+    default: CommitmentConfig,
+    programs: HashMap<String, CommitmentConfig>,
+}
+
+impl CommitmentMap {
+    /// Use `default` for every program
+    pub fn new(default: &str) -> Result<Self> {
+        Ok(Self {
+            default: parse_commitment(default)?,
+            programs: HashMap::new(),
+        })
+    }
+
+    /// Override programs from `program_id=level,...` mappings
+    pub fn with_mappings_from_string(mut self, mappings_str: &str) -> Result<Self> {
+        for mapping in mappings_str.split(',') {
+            let mapping = mapping.trim();
+            if mapping.is_empty() {
+                continue;
+            }
+            let (program_id, level) = mapping.split_once('=').ok_or_else(|| {
+                SoltraceError::InvalidIdl(format!(
+                    "Invalid commitment mapping '{}': expected program_id=level",
+                    mapping
+                ))
+            })?;
+            let program_id = validate_program_id(program_id.trim())?;
+            let commitment = parse_commitment(level.trim())?;
+            self.programs.insert(program_id.to_string(), commitment);
+        }
+        Ok(self)
+    }
+
+    /// Commitment for programs without an override
+    pub fn default_commitment(&self) -> CommitmentConfig {
+        self.default
+    }
+
+    /// Whether any program has its own commitment
+    pub fn has_overrides(&self) -> bool {
+        !self.programs.is_empty()
+    }
+
+    /// Commitment for one program
+    pub fn get(&self, program_id: &str) -> CommitmentConfig {
+        self.programs
+            .get(program_id)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Group programs that share a commitment, in order of first appearance
+    pub fn group(&self, program_ids: &[String]) -> Vec<(CommitmentConfig, Vec<String>)> {
+        let mut groups: Vec<(CommitmentConfig, Vec<String>)> = Vec::new();
+        for program_id in program_ids {
+            let commitment = self.get(program_id);
+            match groups.iter_mut().find(|(c, _)| *c == commitment) {
+                Some((_, ids)) => ids.push(program_id.clone()),
+                None => groups.push((commitment, vec![program_id.clone()])),
+            }
+        }
+        groups
+    }
 }
 
 /// Configuration validator for backfill
@@ -133,6 +281,7 @@ pub struct BackfillConfig {
     pub batch_delay: u64,
     pub concurrency: usize,
     pub max_retries: u32,
+    pub commitment: String,
 }
 
 impl BackfillConfig {
@@ -177,6 +326,9 @@ impl BackfillConfig {
             ));
         }
 
+        // Validate commitment
+        validate_commitment(&self.commitment)?;
+
         Ok(())
     }
 }
@@ -242,6 +394,33 @@ mod tests {
         assert!(validate_program_id(invalid_id).is_err());
     }
 
+    #[test]
+    fn test_validate_table_name() {
+        assert!(validate_table_name("events").is_ok());
+        assert!(validate_table_name("_tenant_a_events2").is_ok());
+        assert!(validate_table_name(&"a".repeat(MAX_TABLE_NAME_LEN)).is_ok());
+
+        assert!(validate_table_name("").is_err());
+        assert!(validate_table_name("2events").is_err());
+        assert!(validate_table_name("public.events").is_err());
+        assert!(validate_table_name("events; DROP TABLE events").is_err());
+        assert!(validate_table_name("\"events\"").is_err());
+        assert!(validate_table_name(&"a".repeat(MAX_TABLE_NAME_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_validate_mongo_db_name() {
+        assert!(validate_mongo_db_name("soltrace").is_ok());
+        assert!(validate_mongo_db_name("tenant-a_events").is_ok());
+        assert!(validate_mongo_db_name(&"a".repeat(MAX_MONGO_DB_NAME_LEN)).is_ok());
+
+        assert!(validate_mongo_db_name("").is_err());
+        assert!(validate_mongo_db_name(&"a".repeat(MAX_MONGO_DB_NAME_LEN + 1)).is_err());
+        assert!(validate_mongo_db_name("my.db").is_err());
+        assert!(validate_mongo_db_name("a/b").is_err());
+        assert!(validate_mongo_db_name("$cmd").is_err());
+    }
+
     #[test]
     fn test_validate_rpc_url_valid() {
         assert!(validate_rpc_url("https://api.mainnet-beta.solana.com").is_ok());
@@ -266,6 +445,28 @@ mod tests {
         assert!(validate_ws_url("http://example.com").is_err());
     }
 
+    #[test]
+    fn test_derive_ws_url() {
+        for (rpc_url, ws_url) in [
+            (
+                "https://api.mainnet-beta.solana.com",
+                "wss://api.mainnet-beta.solana.com/",
+            ),
+            (
+                "https://mainnet.helius-rpc.com/?api-key=abc",
+                "wss://mainnet.helius-rpc.com/?api-key=abc",
+            ),
+            (
+                "https://example.solana-mainnet.quiknode.pro/token/",
+                "wss://example.solana-mainnet.quiknode.pro/token/",
+            ),
+            ("http://localhost:8899", "ws://localhost:8900/"),
+        ] {
+            assert_eq!(derive_ws_url(rpc_url).unwrap(), ws_url);
+        }
+        assert!(derive_ws_url("ftp://example.com").is_err());
+    }
+
     #[test]
     fn test_validate_commitment() {
         assert!(validate_commitment("confirmed").is_ok());
@@ -273,4 +474,80 @@ mod tests {
         assert!(validate_commitment("finalized").is_ok());
         assert!(validate_commitment("invalid").is_err());
     }
+
+    #[test]
+    fn test_parse_commitment() {
+        assert_eq!(
+            parse_commitment("confirmed").unwrap(),
+            CommitmentConfig::confirmed()
+        );
+        assert_eq!(
+            parse_commitment("processed").unwrap(),
+            CommitmentConfig::processed()
+        );
+        assert_eq!(
+            parse_commitment("Finalized").unwrap(),
+            CommitmentConfig::finalized()
+        );
+        assert!(parse_commitment("invalid").is_err());
+    }
+
+    #[test]
+    fn test_commitment_map() {
+        let program = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ";
+        let other = "11111111111111111111111111111111";
+        let commitments = CommitmentMap::new("confirmed")
+            .unwrap()
+            .with_mappings_from_string(&format!("{}=finalized, ", program))
+            .unwrap();
+
+        assert!(commitments.has_overrides());
+        assert_eq!(commitments.get(program), CommitmentConfig::finalized());
+        assert_eq!(commitments.get(other), CommitmentConfig::confirmed());
+        assert_eq!(
+            commitments.group(&[other.to_string(), program.to_string()]),
+            vec![
+                (CommitmentConfig::confirmed(), vec![other.to_string()]),
+                (CommitmentConfig::finalized(), vec![program.to_string()]),
+            ]
+        );
+
+        let base = || CommitmentMap::new("confirmed").unwrap();
+        assert!(base()
+            .with_mappings_from_string(&format!("{}=eventually", program))
+            .is_err());
+        assert!(base()
+            .with_mappings_from_string(&format!("{}:finalized", program))
+            .is_err());
+        assert!(base()
+            .with_mappings_from_string("not-a-program=finalized")
+            .is_err());
+        assert!(CommitmentMap::new("soon").is_err());
+    }
+
+    #[test]
+    fn test_parse_commitment_legacy_aliases() {
+        assert_eq!(
+            parse_commitment("recent").unwrap(),
+            CommitmentConfig::processed()
+        );
+        assert_eq!(
+            parse_commitment("single").unwrap(),
+            CommitmentConfig::confirmed()
+        );
+        assert_eq!(
+            parse_commitment("singleGossip").unwrap(),
+            CommitmentConfig::confirmed()
+        );
+        assert_eq!(
+            parse_commitment("root").unwrap(),
+            CommitmentConfig::finalized()
+        );
+        assert_eq!(
+            parse_commitment("MAX").unwrap(),
+            CommitmentConfig::finalized()
+        );
+        assert!(validate_commitment("max").is_ok());
+        assert!(parse_commitment("").is_err());
+    }
 }
@@ -0,0 +1,246 @@
+use crate::{
+    error::{Result, SoltraceError},
+    metrics::Metrics,
+    queue::QueueEvent,
+    retry::retry_with_backoff,
+    sink::EventSink,
+    types::{DecodedEvent, RawEvent},
+};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::{debug, error};
+
+/// POSTs in flight at once unless [`WebhookSink::with_concurrency`] says otherwise
+pub const DEFAULT_WEBHOOK_CONCURRENCY: usize = 4;
+
+/// Retries for a failed POST before the event is dropped
+pub const DEFAULT_WEBHOOK_RETRIES: u32 = 3;
+
+/// POSTs each event as [`QueueEvent`] JSON to an HTTP endpoint (e.g. a serverless function)
+///
+/// Failed POSTs (connection errors and non-2xx responses) are retried with backoff;
+/// an event that still fails is dropped, counted in `webhook_dropped` and reported
+/// as an error.
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    headers: HeaderMap,
+    permits: Arc<Semaphore>,
+    max_retries: u32,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl WebhookSink {
+    /// POST to `url`, which must be `http` or `https`
+    pub fn new(url: &str) -> Result<Self> {
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| SoltraceError::Webhook(format!("Invalid webhook URL '{}': {}", url, e)))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(SoltraceError::Webhook(format!(
+                "Webhook URL must be http or https: {}",
+                url
+            )));
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| SoltraceError::Webhook(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            url: parsed,
+            headers: HeaderMap::new(),
+            permits: Arc::new(Semaphore::new(DEFAULT_WEBHOOK_CONCURRENCY)),
+            max_retries: DEFAULT_WEBHOOK_RETRIES,
+            metrics: None,
+        })
+    }
+
+    /// Send a `Name: value` header with every request (e.g. `Authorization: Bearer ...`)
+    pub fn with_header(mut self, header: &str) -> Result<Self> {
+        let (name, value) = header.split_once(':').ok_or_else(|| {
+            SoltraceError::Webhook(format!("Expected 'Name: value', got '{}'", header))
+        })?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|e| SoltraceError::Webhook(format!("Invalid header name: {}", e)))?;
+        let value = HeaderValue::from_str(value.trim())
+            .map_err(|e| SoltraceError::Webhook(format!("Invalid value for {}: {}", name, e)))?;
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Apply several `Name: value` headers, as given on the command line
+    pub fn with_headers(self, headers: &[String]) -> Result<Self> {
+        headers
+            .iter()
+            .try_fold(self, |sink, header| sink.with_header(header))
+    }
+
+    /// Keep at most `concurrency` POSTs in flight (at least one)
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.permits = Arc::new(Semaphore::new(concurrency.max(1)));
+        self
+    }
+
+    /// Retry a failed POST this many times before dropping the event
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Count dropped events in a shared metrics instance
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    async fn post(&self, body: &[u8]) -> std::result::Result<(), String> {
+        let response = self
+            .client
+            .post(self.url.clone())
+            .headers(self.headers.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(format!("webhook answered {}", status))
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    async fn write(&self, event: &DecodedEvent, raw: &RawEvent) -> Result<()> {
+        let body = serde_json::to_vec(&QueueEvent::from_decoded(event, raw))?;
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| SoltraceError::Webhook(e.to_string()))?;
+
+        match retry_with_backoff(
+            || self.post(&body),
+            self.max_retries,
+            Duration::from_millis(200),
+            Duration::from_secs(5),
+        )
+        .await
+        {
+            Ok(()) => {
+                debug!(
+                    "Posted {} from {} to webhook",
+                    event.event_name, raw.signature
+                );
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    "Dropping {} from {} after {} webhook attempt(s): {}",
+                    event.event_name,
+                    raw.signature,
+                    self.max_retries + 1,
+                    e
+                );
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_webhook_dropped();
+                }
+                Err(SoltraceError::Webhook(e))
+            }
+        }
+    }
+
+    /// POSTs run concurrently, up to the configured limit
+    async fn write_batch(&self, events: &[(DecodedEvent, RawEvent)]) -> Result<u64> {
+        let results =
+            futures::future::join_all(events.iter().map(|(event, raw)| self.write(event, raw)))
+                .await;
+
+        let mut written = 0;
+        for result in results {
+            result?;
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::event;
+    use std::sync::atomic::Ordering;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answer every request with `status`, passing each request's text to `requests`
+    async fn serve(status: &'static str) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 16 * 1024];
+                let mut len = 0;
+                // Read until the JSON body's closing brace
+                while !buf[..len].ends_with(b"}") {
+                    len += stream.read(&mut buf[len..]).await.unwrap();
+                }
+                tx.send(String::from_utf8_lossy(&buf[..len]).into_owned())
+                    .unwrap();
+                let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\n\r\n", status);
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_and_drops() {
+        let (event, raw) = event(9, 0);
+
+        let (url, mut requests) = serve("200 OK").await;
+        let sink = WebhookSink::new(&url)
+            .unwrap()
+            .with_header("Authorization: Bearer secret")
+            .unwrap();
+        sink.write(&event, &raw).await.unwrap();
+        let request = requests.recv().await.unwrap();
+        assert!(request.starts_with("POST /hook "));
+        assert!(request
+            .to_lowercase()
+            .contains("authorization: bearer secret"));
+        assert!(request.contains("\"event_name\":\"tributary_Transfer\""));
+
+        let (url, mut requests) = serve("500 Internal Server Error").await;
+        let metrics = Arc::new(Metrics::new());
+        let sink = WebhookSink::new(&url)
+            .unwrap()
+            .with_max_retries(1)
+            .with_metrics(metrics.clone());
+        assert!(matches!(
+            sink.write(&event, &raw).await,
+            Err(SoltraceError::Webhook(_))
+        ));
+        // The first attempt and one retry
+        requests.recv().await.unwrap();
+        requests.recv().await.unwrap();
+        assert_eq!(metrics.webhook_dropped.load(Ordering::Relaxed), 1);
+
+        assert!(WebhookSink::new("ftp://example.com").is_err());
+        assert!(WebhookSink::new(&url)
+            .unwrap()
+            .with_header("nocolon")
+            .is_err());
+    }
+}
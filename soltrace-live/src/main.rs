@@ -1,21 +1,34 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use futures::StreamExt;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_client::rpc_response::RpcLogsResponse;
 use solana_commitment_config::CommitmentConfig;
 use solana_pubsub_client::nonblocking::pubsub_client::PubsubClient;
 use solana_sdk::pubkey::Pubkey;
 use soltrace_core::{
-    load_idls, types::RawEvent, utils::extract_event_from_log, Database, EventDecoder, EventQueue,
-    IdlParser, ProgramPrefixConfig, QueueEvent,
+    derive_ws_url, env_file_from_args, load_idls,
+    types::{DecodedEvent, RawEvent, Signature, Slot},
+    utils::extract_events_from_logs,
+    Backfiller, Cluster, CommitmentMap, Database, DedupSink, EventDecoder, EventNameCase,
+    EventNameFilter, EventQueue, EventSink, FanoutSink, FileSink, HealthCheck, HealthServer,
+    IdlParser, Metrics, OverlapSink, ProgramAliases, ProgramPrefixConfig, QueueEvent, SampledSink,
+    SinkKind, WebhookSink, DEFAULT_WEBHOOK_CONCURRENCY,
 };
 #[cfg(feature = "kafka")]
 use soltrace_core::{KafkaConfig, KafkaProducer};
+#[cfg(feature = "sqs")]
+use soltrace_core::{SqsConfig, SqsProducer};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::mpsc;
-use tokio::time::{sleep, timeout};
-use tracing::{debug, error, info};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+use tokio::time::{sleep, timeout, MissedTickBehavior};
+use tracing::{debug, error, info, warn};
 
 /// Soltrace Live - Real-time Solana event indexer via WebSocket
 #[derive(Parser)]
@@ -24,52 +37,93 @@ use tracing::{debug, error, info};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Load settings from this dotenv file instead of ./.env
+    #[arg(long, global = true)]
+    env_file: Option<PathBuf>,
+
+    /// MongoDB database name, overriding the DB_URL path (default: soltrace)
+    #[arg(long, global = true, env("MONGO_DB_NAME"))]
+    mongo_db_name: Option<String>,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Initialize database
     Init {
         /// Database URL
         #[arg(short, long, default_value = "sqlite:./soltrace.db", env("DB_URL"))]
         db_url: String,
+
+        /// Table (or MongoDB collection) to store events in
+        #[arg(long, default_value = "events", env("TABLE_NAME"))]
+        table_name: String,
     },
     /// Start real-time event indexing
     Run {
-        /// Solana RPC WebSocket URL
-        #[arg(
-            short,
-            long,
-            default_value = "wss://api.mainnet-beta.solana.com",
-            env("SOLANA_WS_URL")
-        )]
-        ws_url: String,
+        /// Solana cluster whose public endpoints to use (mainnet, devnet, testnet, localnet)
+        #[arg(long, env("SOLANA_CLUSTER"))]
+        cluster: Option<Cluster>,
 
-        /// Solana RPC HTTP URL (for initial validation)
-        #[arg(
-            short,
-            long,
-            default_value = "https://api.mainnet-beta.solana.com",
-            env("SOLANA_RPC_URL")
-        )]
-        rpc_url: String,
+        /// Solana RPC WebSocket URL (from --cluster, or derived from --rpc-url, when unset)
+        #[arg(short, long, env("SOLANA_WS_URL"))]
+        ws_url: Option<String>,
+
+        /// Solana RPC HTTP URL for initial validation (overrides --cluster; defaults to mainnet)
+        #[arg(short, long, env("SOLANA_RPC_URL"))]
+        rpc_url: Option<String>,
 
         /// Program prefix mappings (format: program_id:prefix, e.g., "TRibg8...:tributary")
         #[arg(short = 'm', long, env("PROGRAM_PREFIXES"))]
         program_prefixes: String,
 
+        /// Display names for programs in logs and metrics, leaving stored event names as they are
+        /// (format: program_id=name, comma-separated)
+        #[arg(long, env("PROGRAM_ALIASES"))]
+        program_alias: Option<String>,
+
+        /// Non-Anchor discriminator schemes (format: program_id:scheme, e.g. "TRibg8...:custom:1")
+        #[arg(long, env("DISCRIMINATOR_SCHEMES"))]
+        discriminator_schemes: Option<String>,
+
+        /// Casing for stored event names, prefix included (as-is, snake, camel, kebab)
+        #[arg(long, default_value = "as-is", env("EVENT_NAME_CASE"))]
+        event_name_case: EventNameCase,
+
         /// Database URL
         #[arg(short, long, default_value = "sqlite:./soltrace.db", env("DB_URL"))]
         db_url: String,
 
+        /// Table (or MongoDB collection) to store events in
+        #[arg(long, default_value = "events", env("TABLE_NAME"))]
+        table_name: String,
+
+        /// Don't create or upgrade the schema on connect (it must already exist, e.g. via `init`)
+        #[arg(long, env("SKIP_MIGRATIONS"))]
+        skip_migrations: bool,
+
+        /// Where decoded events go: db, stdout (JSON lines, no database), both, or none
+        /// (only --output-dir)
+        #[arg(long, default_value = "db", env("SINK"))]
+        sink: SinkKind,
+
         /// IDL directory path
         #[arg(short, long, default_value = "./idls", env("IDL_DIR"))]
         idl_dir: String,
 
+        /// Single JSON file mapping program ID to IDL, loaded in addition to --idl-dir
+        #[arg(long, env("IDL_BUNDLE"))]
+        idl_bundle: Option<String>,
+
         /// Log commitment level (processed, confirmed, finalized)
         #[arg(short, long, default_value = "confirmed", env("COMMITMENT"))]
         commitment: String,
 
+        /// Per-program commitment levels (program_id=level, comma-separated); others use --commitment
+        #[arg(long, env("COMMITMENT_MAP"))]
+        commitment_map: Option<String>,
+
         /// Reconnect delay in seconds
         #[arg(long, default_value = "5", env("RECONNECT_DELAY"))]
         reconnect_delay: u64,
@@ -78,55 +132,406 @@ enum Commands {
         #[arg(long, default_value = "0", env("MAX_RECONNECT_ATTEMPTS"))]
         max_reconnects: u32,
 
-        /// WebSocket ping interval in seconds (0 = disable)
+        /// Probe the WebSocket after this many seconds without notifications (0 = disable)
         #[arg(long, default_value = "30", env("WS_PING_INTERVAL"))]
         ping_interval: u64,
 
+        /// Reconnect if a keepalive probe gets no answer within this many seconds
+        #[arg(long, default_value = "10", env("WS_PING_TIMEOUT"))]
+        ping_timeout: u64,
+
+        /// How to subscribe to program logs: combined, per-program, or all
+        #[arg(
+            long,
+            value_enum,
+            default_value = "combined",
+            env("SUBSCRIPTION_STRATEGY")
+        )]
+        subscription_strategy: SubscriptionStrategy,
+
+        /// Write decoded events in batches of this many (1 = write each event immediately)
+        #[arg(long, default_value = "1", env("LIVE_BATCH_SIZE"))]
+        batch_size: usize,
+
+        /// Flush a partial batch after this many milliseconds
+        #[arg(long, default_value = "1000", env("BATCH_FLUSH_INTERVAL_MS"))]
+        batch_flush_interval_ms: u64,
+
         /// Kafka broker URLs (comma-separated, enables Kafka if set)
         #[arg(long, env("KAFKA_BROKERS"))]
         kafka_brokers: Option<String>,
+
+        /// Amazon SQS queue URL (enables SQS if set; `.fifo` queues group by program)
+        #[arg(long, env("SQS_QUEUE_URL"), conflicts_with = "kafka_brokers")]
+        sqs_queue_url: Option<String>,
+
+        /// AWS region for SQS (default: the AWS SDK's region chain)
+        #[arg(long, env("AWS_REGION"))]
+        sqs_region: Option<String>,
+
+        /// POST every event as JSON to this URL, in addition to the sink
+        #[arg(long, env("WEBHOOK_URL"))]
+        webhook_url: Option<String>,
+
+        /// Header sent with each webhook POST, as "Name: value" (repeatable)
+        #[arg(long, env("WEBHOOK_HEADERS"), value_delimiter = ',')]
+        webhook_header: Vec<String>,
+
+        /// Maximum webhook POSTs in flight at once
+        #[arg(long, default_value_t = DEFAULT_WEBHOOK_CONCURRENCY, env("WEBHOOK_CONCURRENCY"))]
+        webhook_concurrency: usize,
+
+        /// Also archive every event as JSON lines to rotating files in this directory
+        #[arg(long, env("OUTPUT_DIR"))]
+        output_dir: Option<PathBuf>,
+
+        /// Rotate archive files once they hold this many MiB of JSON
+        #[arg(long, default_value_t = 100, env("ROTATE_SIZE_MB"))]
+        rotate_size_mb: u64,
+
+        /// Also rotate archive files before they span this many slots
+        #[arg(long, env("ROTATE_SLOTS"))]
+        rotate_slots: Option<u64>,
+
+        /// Gzip archive files (needs the `gzip` feature)
+        #[arg(long, env("OUTPUT_GZIP"))]
+        output_gzip: bool,
+
+        /// Only store events matching these names (comma-separated, trailing * wildcard)
+        #[arg(long, env("INCLUDE_EVENTS"))]
+        include_events: Option<String>,
+
+        /// Skip events matching these names (comma-separated, trailing * wildcard, wins over include)
+        #[arg(long, env("EXCLUDE_EVENTS"))]
+        exclude_events: Option<String>,
+
+        /// Store the pre-decode event bytes alongside decoded data (enables re-decoding)
+        #[arg(long, env("STORE_RAW"))]
+        store_raw: bool,
+
+        /// Keep events from failed transactions, marked `tx_failed`, instead of skipping them
+        #[arg(long, env("INCLUDE_FAILED"))]
+        include_failed: bool,
+
+        /// Quietly skip log data whose discriminator matches no loaded IDL event
+        #[arg(long, env("DECODE_ONLY_KNOWN"))]
+        decode_only_known: bool,
+
+        /// Also decode log data prefixed with an IDL account discriminator (account snapshots)
+        #[arg(long, env("DECODE_ACCOUNTS"))]
+        decode_accounts: bool,
+
+        /// Shut down cleanly after running this many seconds
+        #[arg(long, env("MAX_RUNTIME"))]
+        max_runtime: Option<u64>,
+
+        /// Shut down cleanly if no log notifications arrive for this many seconds
+        #[arg(long, env("IDLE_TIMEOUT"))]
+        idle_timeout: Option<u64>,
+
+        /// Warn at startup when stored events are more than this many slots behind the chain
+        #[arg(long, default_value = "150", env("MAX_SLOT_GAP"))]
+        max_slot_gap: u64,
+
+        /// Backfill the gap since the newest stored event before subscribing
+        #[arg(long, env("AUTO_GAP_FILL"))]
+        auto_gap_fill: bool,
+
+        /// Catch up from the newest stored event to the chain tip, however small the gap,
+        /// then subscribe, skipping live events the catch-up already stored
+        #[arg(long, env("CATCHUP"))]
+        catchup: bool,
+
+        /// Store only this fraction (0.0-1.0) of transactions, chosen by signature hash.
+        /// Sampled-out events still count in metrics; stored events are no longer complete
+        #[arg(long, default_value = "1.0", env("SAMPLE_RATE"))]
+        sample_rate: f64,
+
+        /// Remember this many recently written events and drop repeats (e.g. after a
+        /// reconnect) without asking the database (0 = disabled)
+        #[arg(long, default_value = "0", env("DEDUP_CACHE_SIZE"))]
+        dedup_cache_size: usize,
+
+        /// Append every received log notification as a JSON line to this file (for `replay`)
+        #[arg(long, env("RECORD_FILE"))]
+        record: Option<PathBuf>,
+
+        /// Serve `GET /health` and `GET /metrics` on this port (disabled by default)
+        #[arg(long, env("HEALTH_PORT"))]
+        health_port: Option<u16>,
+
+        /// Report degraded health when no event has been stored for this many seconds
+        #[arg(long, env("MAX_EVENT_AGE"))]
+        max_event_age: Option<u64>,
+
+        /// Log a metrics summary every N seconds (0 = only on shutdown)
+        #[arg(long, default_value = "60", env("METRICS_INTERVAL"))]
+        metrics_interval: u64,
+    },
+    /// Replay recorded log notifications through the indexing pipeline (no network)
+    Replay {
+        /// Newline-delimited JSON file of recorded `RpcLogsResponse` notifications
+        file: PathBuf,
+
+        /// Program prefix mappings (format: program_id:prefix, e.g., "TRibg8...:tributary")
+        #[arg(short = 'm', long, default_value = "", env("PROGRAM_PREFIXES"))]
+        program_prefixes: String,
+
+        /// Non-Anchor discriminator schemes (format: program_id:scheme, e.g. "TRibg8...:custom:1")
+        #[arg(long, env("DISCRIMINATOR_SCHEMES"))]
+        discriminator_schemes: Option<String>,
+
+        /// Casing for stored event names, prefix included (as-is, snake, camel, kebab)
+        #[arg(long, default_value = "as-is", env("EVENT_NAME_CASE"))]
+        event_name_case: EventNameCase,
+
+        /// Database URL
+        #[arg(short, long, default_value = "sqlite:./soltrace.db", env("DB_URL"))]
+        db_url: String,
+
+        /// Table (or MongoDB collection) to store events in
+        #[arg(long, default_value = "events", env("TABLE_NAME"))]
+        table_name: String,
+
+        /// Where decoded events go: db, stdout (JSON lines, no database), or both
+        #[arg(long, default_value = "db", env("SINK"))]
+        sink: SinkKind,
+
+        /// IDL directory path
+        #[arg(short, long, default_value = "./idls", env("IDL_DIR"))]
+        idl_dir: String,
+
+        /// Single JSON file mapping program ID to IDL, loaded in addition to --idl-dir
+        #[arg(long, env("IDL_BUNDLE"))]
+        idl_bundle: Option<String>,
+
+        /// Only store events matching these names (comma-separated, trailing * wildcard)
+        #[arg(long, env("INCLUDE_EVENTS"))]
+        include_events: Option<String>,
+
+        /// Skip events matching these names (comma-separated, trailing * wildcard, wins over include)
+        #[arg(long, env("EXCLUDE_EVENTS"))]
+        exclude_events: Option<String>,
+
+        /// Store the pre-decode event bytes alongside decoded data (enables re-decoding)
+        #[arg(long, env("STORE_RAW"))]
+        store_raw: bool,
+
+        /// Keep events from failed transactions, marked `tx_failed`, instead of skipping them
+        #[arg(long, env("INCLUDE_FAILED"))]
+        include_failed: bool,
+
+        /// Quietly skip log data whose discriminator matches no loaded IDL event
+        #[arg(long, env("DECODE_ONLY_KNOWN"))]
+        decode_only_known: bool,
+
+        /// Also decode log data prefixed with an IDL account discriminator (account snapshots)
+        #[arg(long, env("DECODE_ACCOUNTS"))]
+        decode_accounts: bool,
     },
 }
 
+impl Commands {
+    /// Where the command writes decoded events (`Init` writes none)
+    fn sink(&self) -> SinkKind {
+        match self {
+            Commands::Init { .. } => SinkKind::Db,
+            Commands::Run { sink, .. } | Commands::Replay { sink, .. } => *sink,
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Load .env file if present
-    dotenv::dotenv().ok();
+    // Precedence: command line > process environment > env file > default
+    let env_file = env_file_from_args(std::env::args());
+    let env_file_keys = load_env_file(env_file.as_deref())?;
 
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
+    let command = Cli::command();
+    let matches = command.clone().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    let cli = Cli::parse();
+    // Keep stdout clean for piped events
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    if cli.command.sink().uses_stdout() {
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
+            .init();
+    } else {
+        tracing_subscriber::fmt().with_env_filter(filter).init();
+    }
+    if let Some(path) = &cli.env_file {
+        info!("Loaded settings from {}", path.display());
+    }
+    log_config_sources(&command, &matches, &env_file_keys);
 
+    let mongo_db_name = cli.mongo_db_name.as_deref();
     match cli.command {
-        Commands::Init { db_url } => init_db(&db_url).await?,
+        Commands::Init { db_url, table_name } => {
+            init_db(&db_url, &table_name, mongo_db_name).await?
+        }
         Commands::Run {
+            cluster,
             ws_url,
             rpc_url,
             program_prefixes,
+            program_alias,
+            discriminator_schemes,
+            event_name_case,
             db_url,
+            table_name,
+            skip_migrations,
+            sink,
             idl_dir,
+            idl_bundle,
             commitment,
+            commitment_map,
             reconnect_delay,
             max_reconnects,
             ping_interval,
+            ping_timeout,
+            subscription_strategy,
+            batch_size,
+            batch_flush_interval_ms,
             kafka_brokers,
+            sqs_queue_url,
+            sqs_region,
+            webhook_url,
+            webhook_header,
+            webhook_concurrency,
+            output_dir,
+            rotate_size_mb,
+            rotate_slots,
+            output_gzip,
+            include_events,
+            exclude_events,
+            store_raw,
+            include_failed,
+            decode_only_known,
+            decode_accounts,
+            max_runtime,
+            idle_timeout,
+            max_slot_gap,
+            auto_gap_fill,
+            catchup,
+            sample_rate,
+            dedup_cache_size,
+            record,
+            health_port,
+            max_event_age,
+            metrics_interval,
         } => {
+            let recorder = record
+                .as_deref()
+                .map(NotificationRecorder::open)
+                .transpose()?;
+            let rpc_url_given = rpc_url.is_some();
+            let rpc_url =
+                rpc_url.unwrap_or_else(|| cluster.unwrap_or_default().rpc_url().to_string());
+            // An explicit RPC URL points at a provider the cluster's WS URL may not match
+            let ws_url = match (ws_url, cluster) {
+                (Some(ws_url), _) => ws_url,
+                (None, Some(cluster)) if !rpc_url_given => cluster.ws_url().to_string(),
+                (None, _) => derive_ws_url(&rpc_url)?,
+            };
+            let commitments = CommitmentMap::new(&commitment)?
+                .with_mappings_from_string(commitment_map.as_deref().unwrap_or_default())?;
             run_indexer(
                 ws_url,
                 rpc_url,
                 program_prefixes,
+                ProgramAliases::from_string(program_alias.as_deref().unwrap_or_default())?,
+                discriminator_schemes,
+                event_name_case,
+                decode_only_known,
+                decode_accounts,
                 db_url,
+                table_name,
+                mongo_db_name.map(str::to_string),
+                skip_migrations,
+                sink,
                 idl_dir,
-                commitment,
+                idl_bundle,
+                commitments,
                 reconnect_delay,
                 max_reconnects,
-                ping_interval,
-                kafka_brokers,
+                Keepalive::new(ping_interval, ping_timeout),
+                subscription_strategy,
+                batch_size,
+                Duration::from_millis(batch_flush_interval_ms.max(1)),
+                QueueConfig {
+                    kafka_brokers,
+                    sqs_queue_url,
+                    sqs_region,
+                },
+                WebhookConfig {
+                    url: webhook_url,
+                    headers: webhook_header,
+                    concurrency: webhook_concurrency,
+                },
+                ArchiveConfig {
+                    dir: output_dir,
+                    rotate_size: rotate_size_mb * 1024 * 1024,
+                    rotate_slots,
+                    gzip: output_gzip,
+                },
+                EventNameFilter::from_patterns(
+                    include_events.as_deref(),
+                    exclude_events.as_deref(),
+                ),
+                store_raw,
+                include_failed,
+                StopConditions::new(max_runtime, idle_timeout),
+                max_slot_gap,
+                auto_gap_fill,
+                catchup,
+                sample_rate,
+                dedup_cache_size,
+                recorder,
+                health_port,
+                max_event_age.map(Duration::from_secs),
+                metrics_interval,
+            )
+            .await?;
+        }
+        Commands::Replay {
+            file,
+            program_prefixes,
+            discriminator_schemes,
+            event_name_case,
+            db_url,
+            table_name,
+            sink,
+            idl_dir,
+            idl_bundle,
+            include_events,
+            exclude_events,
+            store_raw,
+            include_failed,
+            decode_only_known,
+            decode_accounts,
+        } => {
+            run_replay(
+                &file,
+                &program_prefixes,
+                discriminator_schemes.as_deref(),
+                event_name_case,
+                decode_only_known,
+                decode_accounts,
+                &db_url,
+                &table_name,
+                mongo_db_name,
+                sink,
+                &idl_dir,
+                idl_bundle.as_deref(),
+                EventNameFilter::from_patterns(
+                    include_events.as_deref(),
+                    exclude_events.as_deref(),
+                ),
+                store_raw,
+                include_failed,
             )
             .await?;
         }
@@ -135,52 +540,462 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn init_db(db_url: &str) -> Result<()> {
+/// Load a dotenv file: `--env-file` if given, otherwise `.env` in the working directory if present
+///
+/// Variables already set in the process environment win over the file. Returns the
+/// names the file set, so their source can be reported.
+fn load_env_file(path: Option<&Path>) -> Result<HashSet<String>> {
+    let before: HashSet<_> = std::env::vars_os().map(|(key, _)| key).collect();
+
+    match path {
+        Some(path) => {
+            dotenv::from_path(path).map_err(|e| {
+                anyhow::anyhow!("Failed to read env file {}: {}", path.display(), e)
+            })?;
+        }
+        None => {
+            dotenv::dotenv().ok();
+        }
+    }
+
+    Ok(std::env::vars_os()
+        .filter_map(|(key, _)| (!before.contains(&key)).then(|| key.into_string().ok())?)
+        .collect())
+}
+
+/// Log (at debug) where each setting came from: command line, environment, env file or default
+fn log_config_sources(
+    command: &clap::Command,
+    matches: &ArgMatches,
+    env_file_keys: &HashSet<String>,
+) {
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        let source = match matches.value_source(id) {
+            Some(ValueSource::CommandLine) => "command line",
+            Some(ValueSource::EnvVariable) => match arg.get_env().and_then(|name| name.to_str()) {
+                Some(name) if env_file_keys.contains(name) => "env file",
+                _ => "environment",
+            },
+            Some(ValueSource::DefaultValue) => "default",
+            _ => continue,
+        };
+        debug!("Setting {} from {}", id, source);
+    }
+
+    if let Some((name, sub_matches)) = matches.subcommand() {
+        if let Some(subcommand) = command.find_subcommand(name) {
+            log_config_sources(subcommand, sub_matches, env_file_keys);
+        }
+    }
+}
+
+async fn init_db(db_url: &str, table_name: &str, mongo_db_name: Option<&str>) -> Result<()> {
     info!("Initializing database...");
 
-    let _db = Database::new(db_url).await?;
+    let db = Database::with_options(db_url, table_name, mongo_db_name).await?;
+    let applied = db.applied_migrations();
+    if applied.is_empty() {
+        info!("Schema is up to date");
+    }
+    for migration in &applied {
+        info!(
+            "Applied migration {}: {}",
+            migration.version, migration.description
+        );
+    }
     info!("Database initialized successfully at: {}", db_url);
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_indexer(
     ws_url: String,
     rpc_url: String,
     program_prefixes: String,
+    program_aliases: ProgramAliases,
+    discriminator_schemes: Option<String>,
+    event_name_case: EventNameCase,
+    decode_only_known: bool,
+    decode_accounts: bool,
     db_url: String,
+    table_name: String,
+    mongo_db_name: Option<String>,
+    skip_migrations: bool,
+    sink_kind: SinkKind,
     idl_dir: String,
-    commitment: String,
+    idl_bundle: Option<String>,
+    commitments: CommitmentMap,
     reconnect_delay: u64,
     max_reconnects: u32,
-    ping_interval: u64,
-    kafka_brokers: Option<String>,
+    keepalive: Keepalive,
+    subscription_strategy: SubscriptionStrategy,
+    batch_size: usize,
+    batch_flush_interval: Duration,
+    queue_config: QueueConfig,
+    webhook_config: WebhookConfig,
+    archive_config: ArchiveConfig,
+    event_filter: EventNameFilter,
+    store_raw: bool,
+    include_failed: bool,
+    stop_conditions: StopConditions,
+    max_slot_gap: u64,
+    auto_gap_fill: bool,
+    catchup: bool,
+    sample_rate: f64,
+    dedup_cache_size: usize,
+    recorder: Option<NotificationRecorder>,
+    health_port: Option<u16>,
+    max_event_age: Option<Duration>,
+    metrics_interval: u64,
 ) -> Result<()> {
     info!("Starting Soltrace Live indexer");
     info!("RPC URL: {}", rpc_url);
     info!("WebSocket URL: {}", ws_url);
-    info!("Commitment: {}", commitment);
+    info!(
+        "Commitment: {:?}",
+        commitments.default_commitment().commitment
+    );
     info!("Reconnect delay: {}s", reconnect_delay);
+    match keepalive.interval {
+        Some(interval) => info!(
+            "WebSocket keepalive: probe after {:?} without notifications, {:?} timeout",
+            interval, keepalive.probe_timeout
+        ),
+        None => info!("WebSocket keepalive: disabled"),
+    }
+    info!("Subscription strategy: {:?}", subscription_strategy);
+    if commitments.has_overrides() && subscription_strategy == SubscriptionStrategy::All {
+        warn!("--commitment-map is ignored with --subscription-strategy all; every program uses --commitment");
+    }
+    if batch_size > 1 {
+        info!(
+            "Batching inserts: {} event(s) or every {:?}",
+            batch_size, batch_flush_interval
+        );
+    }
+    if store_raw {
+        info!("Storing raw event data");
+    }
+    if include_failed {
+        info!("Keeping events from failed transactions");
+    }
+    if let Some(max_runtime) = stop_conditions.max_runtime {
+        info!("Max runtime: {:?}", max_runtime);
+    }
+    if let Some(idle_timeout) = stop_conditions.idle_timeout {
+        info!("Idle timeout: {:?}", idle_timeout);
+    }
+
+    let event_queue = queue_config.open().await?;
+
+    // Initialize the event sink (and database, unless events only go to stdout)
+    let db = if sink_kind.uses_database() {
+        let db = Database::open(
+            &db_url,
+            &table_name,
+            mongo_db_name.as_deref(),
+            !skip_migrations,
+        )
+        .await?;
+        let db = Arc::new(db);
+        info!("Database connected: {}", db_url);
+        Some(db)
+    } else {
+        None
+    };
+    if catchup && db.is_none() {
+        anyhow::bail!("--catchup needs a database sink to find where to catch up from");
+    }
+    let metrics = Arc::new(Metrics::new().with_program_aliases(program_aliases));
+    let sink = archive_config.wrap(sink_kind.build(db.clone()), sink_kind)?;
+    let sink = webhook_config.wrap(sink, &metrics)?;
+    let sink: Arc<dyn EventSink> = if sample_rate == 1.0 {
+        sink
+    } else {
+        warn!(
+            "Storing a {}% sample of transactions; stored events are incomplete",
+            sample_rate * 100.0
+        );
+        Arc::new(SampledSink::new(sink, sample_rate)?.with_metrics(metrics.clone()))
+    };
+    info!("Event sink: {}", sink_kind);
+
+    let (event_decoder, pubkeys) = load_event_decoder(
+        &idl_dir,
+        idl_bundle.as_deref(),
+        &program_prefixes,
+        discriminator_schemes.as_deref(),
+        event_name_case,
+        decode_only_known,
+        decode_accounts,
+    )
+    .await?;
+    if pubkeys.is_empty() {
+        error!("No IDLs found. Use --idl-dir <path> or --idl-bundle <file>");
+        return Ok(());
+    }
+    let event_decoder = Arc::new(event_decoder);
+    if commitments.has_overrides() {
+        info!("Commitment per program:");
+        for program_id in &pubkeys {
+            let commitment = commitments.get(&program_id.to_string());
+            info!(
+                "  - {}: {:?}",
+                metrics.program_aliases().display(&program_id.to_string()),
+                commitment.commitment
+            );
+        }
+    }
+
+    if !event_filter.is_empty() {
+        info!("Event filters: {:?}", event_filter);
+    }
+    let event_filter = Arc::new(event_filter);
+    for program_id in &pubkeys {
+        metrics.register_program(&program_id.to_string()).await;
+    }
+
+    if let Some(port) = health_port {
+        let addr = format!("0.0.0.0:{}", port);
+        let mut health = HealthCheck::new(metrics.clone());
+        if let Some(db) = &db {
+            health = health.with_database(db.clone());
+        }
+        if let Some(max_age) = max_event_age {
+            health = health.with_max_event_age(max_age);
+        }
+        let server = HealthServer::bind(&addr, health)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to start health server on {}: {}", addr, e))?;
+        tokio::spawn(async move {
+            if let Err(e) = server.serve().await {
+                error!("Health server stopped: {}", e);
+            }
+        });
+    }
+
+    // Report (or catch up on) events missed while the indexer was down
+    let mut catchup_slot = None;
+    if let Some(db) = &db {
+        let rpc_client =
+            RpcClient::new_with_commitment(rpc_url.clone(), commitments.default_commitment());
+        match rpc_client.get_slot().await {
+            Ok(current_slot) => {
+                let max_slot_gap = if catchup { 0 } else { max_slot_gap };
+                let gaps =
+                    find_slot_gaps(db, &pubkeys, current_slot, max_slot_gap, &metrics).await?;
+                if !gaps.is_empty() && (auto_gap_fill || catchup) {
+                    // Fetch each program's history at its own commitment
+                    let backfiller_for = |program_id: &Pubkey| {
+                        let rpc_client = RpcClient::new_with_commitment(
+                            rpc_url.clone(),
+                            commitments.get(&program_id.to_string()),
+                        );
+                        Backfiller::new(Arc::new(rpc_client), event_decoder.clone(), sink.clone())
+                            .with_metrics(metrics.clone())
+                            .with_event_filter(event_filter.as_ref().clone())
+                            .with_store_raw(store_raw)
+                            .with_include_failed(include_failed)
+                    };
+                    let events = fill_slot_gaps(backfiller_for, db, &gaps).await?;
+                    info!("Gap fill stored {} event(s)", events);
+                } else if !gaps.is_empty() {
+                    warn!("Run soltrace-backfill or restart with --auto-gap-fill to fill the gap");
+                }
+                if catchup {
+                    // The catch-up may have run past the slot it started at
+                    catchup_slot = Some(rpc_client.get_slot().await.unwrap_or(current_slot));
+                }
+            }
+            Err(e) => error!(
+                "Failed to fetch the current slot, skipping gap check: {}",
+                e
+            ),
+        }
+    }
+
+    let sink: Arc<dyn EventSink> = match (catchup_slot, &db) {
+        (Some(slot), Some(db)) => {
+            info!("Caught up to slot {}, switching to live indexing", slot);
+            Arc::new(OverlapSink::new(sink, db.clone(), slot))
+        }
+        _ => sink,
+    };
+    let sink: Arc<dyn EventSink> = if dedup_cache_size > 0 {
+        info!(
+            "Dropping repeats of the last {} written events in memory",
+            dedup_cache_size
+        );
+        Arc::new(DedupSink::new(sink, dedup_cache_size))
+    } else {
+        sink
+    };
+
+    let summary_logger = (metrics_interval > 0)
+        .then(|| metrics.spawn_summary_logger(Duration::from_secs(metrics_interval)));
+
+    let (shutdown_tx, mut shutdown) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutdown signal received, flushing buffered events");
+        let _ = shutdown_tx.send(true);
+    });
+
+    // Start WebSocket subscription with auto-reconnect
+    let result = run_websocket_loop(
+        &ws_url,
+        &pubkeys,
+        event_decoder,
+        sink.clone(),
+        event_queue,
+        event_filter,
+        metrics.clone(),
+        store_raw,
+        include_failed,
+        &commitments,
+        reconnect_delay,
+        max_reconnects,
+        keepalive,
+        subscription_strategy,
+        batch_size,
+        batch_flush_interval,
+        stop_conditions,
+        recorder,
+        &mut shutdown,
+    )
+    .await;
+
+    // Close any archive file still open so it is named after its slot range
+    if let Err(e) = sink.flush().await {
+        error!("Failed to flush event sink: {}", e);
+    }
+    if let Some(summary_logger) = summary_logger {
+        summary_logger.abort();
+    }
+
+    match &result {
+        Ok(reason) => info!("Indexer stopped: {}", reason),
+        Err(e) => error!("Indexer stopped: {}", e),
+    }
+    metrics.log_summary().await;
+
+    result.map(|_| ())
+}
+
+/// Resolve on Ctrl-C or, on Unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-    let kafka_producer: Option<Arc<dyn EventQueue>> = match &kafka_brokers {
-        #[allow(unused_variables)]
-        Some(brokers) => {
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// How program logs are subscribed to over the WebSocket
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum SubscriptionStrategy {
+    /// One subscription mentioning every program
+    Combined,
+    /// One subscription per program, so a noisy program can't starve the others
+    PerProgram,
+    /// Every non-vote transaction, filtered down to the programs locally
+    All,
+}
+
+impl SubscriptionStrategy {
+    /// `logsSubscribe` filters to open, one per subscription, with their commitment
+    ///
+    /// `Combined` opens one subscription per distinct commitment; `All` can only
+    /// use the default.
+    fn filters(
+        self,
+        program_ids: &[String],
+        commitments: &CommitmentMap,
+    ) -> Vec<(RpcTransactionLogsFilter, CommitmentConfig)> {
+        match self {
+            SubscriptionStrategy::Combined => commitments
+                .group(program_ids)
+                .into_iter()
+                .map(|(commitment, ids)| (RpcTransactionLogsFilter::Mentions(ids), commitment))
+                .collect(),
+            SubscriptionStrategy::PerProgram => program_ids
+                .iter()
+                .map(|id| {
+                    (
+                        RpcTransactionLogsFilter::Mentions(vec![id.clone()]),
+                        commitments.get(id),
+                    )
+                })
+                .collect(),
+            SubscriptionStrategy::All => vec![(
+                RpcTransactionLogsFilter::All,
+                commitments.default_commitment(),
+            )],
+        }
+    }
+
+    /// Whether a notification involves one of the programs (only `All` receives others)
+    fn is_relevant(self, message: &RpcLogsResponse, program_ids: &[String]) -> bool {
+        self != SubscriptionStrategy::All
+            || message
+                .logs
+                .iter()
+                .any(|log| program_ids.iter().any(|id| log.contains(id.as_str())))
+    }
+}
+
+/// Queue settings; at most one of Kafka and SQS is configured
+struct QueueConfig {
+    kafka_brokers: Option<String>,
+    sqs_queue_url: Option<String>,
+    #[cfg_attr(not(feature = "sqs"), allow(dead_code))]
+    sqs_region: Option<String>,
+}
+
+impl QueueConfig {
+    /// Connect the configured queue, if any
+    #[allow(unused_variables)]
+    async fn open(&self) -> Result<Option<Arc<dyn EventQueue>>> {
+        if let Some(brokers) = &self.kafka_brokers {
             #[cfg(feature = "kafka")]
             {
                 let config = KafkaConfig::new(brokers.clone());
-                match KafkaProducer::new(config) {
+                return match KafkaProducer::new(config) {
                     Ok(producer) => {
                         info!(
                             "Kafka enabled: {} (dynamic topics from event names)",
                             brokers
                         );
-                        Some(Arc::new(producer))
+                        Ok(Some(Arc::new(producer)))
                     }
                     Err(e) => {
                         error!("Failed to initialize Kafka producer: {}", e);
-                        return Err(e);
+                        Err(e)
                     }
-                }
+                };
             }
             #[cfg(not(feature = "kafka"))]
             {
@@ -188,87 +1003,233 @@ async fn run_indexer(
                 return Err(anyhow::anyhow!("Kafka feature not enabled"));
             }
         }
-        None => {
-            info!("Kafka not configured (set KAFKA_BROKERS to enable)");
-            None
+
+        if let Some(queue_url) = &self.sqs_queue_url {
+            #[cfg(feature = "sqs")]
+            {
+                let config = SqsConfig::new(queue_url.clone()).with_region(self.sqs_region.clone());
+                let producer = SqsProducer::new(config).await?;
+                info!("SQS enabled: {}", queue_url);
+                return Ok(Some(Arc::new(producer)));
+            }
+            #[cfg(not(feature = "sqs"))]
+            {
+                error!("SQS queue configured but 'sqs' feature not enabled. Recompile with --features sqs");
+                return Err(anyhow::anyhow!("SQS feature not enabled"));
+            }
         }
-    };
 
-    // Initialize database
-    let db = Arc::new(Database::new(&db_url).await?);
-    info!("Database connected: {}", db_url);
+        info!("No queue configured (set KAFKA_BROKERS or SQS_QUEUE_URL to enable)");
+        Ok(None)
+    }
+}
 
-    // Load IDLs first to extract program IDs
-    let mut idl_parser = IdlParser::new();
-    load_idls(&mut idl_parser, &idl_dir).await?;
+/// Webhook settings; events are only POSTed when a URL is set
+struct WebhookConfig {
+    url: Option<String>,
+    headers: Vec<String>,
+    concurrency: usize,
+}
 
-    let loaded_idls = idl_parser.get_idls();
-    info!("Loaded {} IDL(s) from {}", loaded_idls.len(), idl_dir);
-    for (addr, idl) in loaded_idls {
-        info!("  - {}: {} events", addr, idl.events.len());
+impl WebhookConfig {
+    /// Write to the webhook alongside `sink`, if one is configured
+    fn wrap(&self, sink: Arc<dyn EventSink>, metrics: &Arc<Metrics>) -> Result<Arc<dyn EventSink>> {
+        let Some(url) = &self.url else {
+            return Ok(sink);
+        };
+        let webhook = WebhookSink::new(url)?
+            .with_headers(&self.headers)?
+            .with_concurrency(self.concurrency)
+            .with_metrics(metrics.clone());
+        info!(
+            "Webhook enabled: {} (up to {} concurrent POSTs)",
+            url, self.concurrency
+        );
+        Ok(Arc::new(FanoutSink::new(vec![sink, Arc::new(webhook)])))
     }
+}
 
-    // Create program prefix configuration from CLI/env
-    let mut prefix_config = ProgramPrefixConfig::new();
-    // Load programs from IDLs with default prefix
-    prefix_config.load_from_idls(loaded_idls);
-    // Apply custom prefix mappings from CLI/env
-    if !program_prefixes.is_empty() {
-        prefix_config.add_mappings_from_string(&program_prefixes);
-        info!(
-            "Applied {} custom program prefix mapping(s)",
-            program_prefixes
+/// Archive settings; events are only written to files when a directory is set
+struct ArchiveConfig {
+    dir: Option<PathBuf>,
+    rotate_size: u64,
+    rotate_slots: Option<u64>,
+    gzip: bool,
+}
+
+impl ArchiveConfig {
+    /// Write to rotating files alongside `sink`, or instead of it for `--sink none`
+    fn wrap(&self, sink: Arc<dyn EventSink>, sink_kind: SinkKind) -> Result<Arc<dyn EventSink>> {
+        let Some(dir) = &self.dir else {
+            if sink_kind == SinkKind::None {
+                anyhow::bail!("--sink none needs --output-dir");
+            }
+            return Ok(sink);
+        };
+        let archive = Arc::new(
+            FileSink::new(dir)?
+                .with_rotate_size(self.rotate_size)
+                .with_rotate_slots(self.rotate_slots)
+                .with_gzip(self.gzip),
         );
+        info!("Archiving events to {}", dir.display());
+        if sink_kind == SinkKind::None {
+            return Ok(archive);
+        }
+        Ok(Arc::new(FanoutSink::new(vec![sink, archive])))
     }
+}
 
-    let program_ids = prefix_config.get_program_ids();
-    if program_ids.is_empty() {
-        error!("No IDLs found in directory. Use --idl-dir <path>");
-        return Ok(());
+/// Send anything the queue is still buffering
+async fn flush_queue(queue: Option<&Arc<dyn EventQueue>>) {
+    if let Some(queue) = queue {
+        if let Err(e) = queue.flush().await {
+            error!("Failed to flush queue: {}", e);
+        }
     }
+}
 
-    // Convert program IDs to Pubkeys for WebSocket subscription
-    let pubkeys: Vec<Pubkey> = program_ids
-        .iter()
-        .map(|s| s.parse::<Pubkey>())
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| anyhow::anyhow!("Failed to parse program IDs: {}", e))?;
+/// Why the indexer shut itself down
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StopReason {
+    MaxRuntime,
+    IdleTimeout,
+    Shutdown,
+}
 
-    // Create event decoder
-    let event_decoder = Arc::new(EventDecoder::new(idl_parser, prefix_config));
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::MaxRuntime => write!(f, "max runtime reached"),
+            StopReason::IdleTimeout => write!(f, "idle timeout reached"),
+            StopReason::Shutdown => write!(f, "shutdown signal received"),
+        }
+    }
+}
 
-    // Start WebSocket subscription with auto-reconnect
-    run_websocket_loop(
-        &ws_url,
-        &pubkeys,
-        event_decoder,
-        db,
-        kafka_producer,
-        &commitment,
-        reconnect_delay,
-        max_reconnects,
-        ping_interval,
-    )
-    .await?;
+/// When to check a quiet WebSocket connection is still alive
+#[derive(Debug, Clone, Copy)]
+struct Keepalive {
+    /// Probe after this long without notifications; `None` disables probing
+    interval: Option<Duration>,
+    /// How long a probe may take before the connection counts as stale
+    probe_timeout: Duration,
+}
 
-    Ok(())
+impl Keepalive {
+    fn new(interval_secs: u64, probe_timeout_secs: u64) -> Self {
+        Self {
+            interval: (interval_secs > 0).then(|| Duration::from_secs(interval_secs)),
+            probe_timeout: Duration::from_secs(probe_timeout_secs.max(1)),
+        }
+    }
+}
+
+/// Round-trip a slot subscription over the connection to prove it still answers
+///
+/// A half-open connection (no data, no FIN) still accepts writes, so only a
+/// request that needs a reply can tell it apart from a quiet one.
+async fn probe_connection(pubsub_client: &PubsubClient, probe_timeout: Duration) -> Result<()> {
+    let probe = async {
+        let (_slots, unsubscribe) = pubsub_client
+            .slot_subscribe()
+            .await
+            .map_err(|e| anyhow::anyhow!("probe subscription failed: {}", e))?;
+        unsubscribe().await;
+        Ok(())
+    };
+
+    timeout(probe_timeout, probe)
+        .await
+        .map_err(|_| anyhow::anyhow!("no answer to probe within {:?}", probe_timeout))?
+}
+
+/// Optional deadlines after which the indexer shuts down cleanly
+#[derive(Debug, Clone, Copy)]
+struct StopConditions {
+    started: Instant,
+    max_runtime: Option<Duration>,
+    idle_timeout: Option<Duration>,
+}
+
+impl StopConditions {
+    fn new(max_runtime_secs: Option<u64>, idle_timeout_secs: Option<u64>) -> Self {
+        Self {
+            started: Instant::now(),
+            max_runtime: max_runtime_secs.map(Duration::from_secs),
+            idle_timeout: idle_timeout_secs.map(Duration::from_secs),
+        }
+    }
+
+    /// Check whether a stop condition has been met
+    fn check(&self, last_activity: Instant) -> Option<StopReason> {
+        let now = Instant::now();
+        if self
+            .max_runtime
+            .is_some_and(|max| now.duration_since(self.started) >= max)
+        {
+            return Some(StopReason::MaxRuntime);
+        }
+        if self
+            .idle_timeout
+            .is_some_and(|idle| now.duration_since(last_activity) >= idle)
+        {
+            return Some(StopReason::IdleTimeout);
+        }
+        None
+    }
+
+    /// Time remaining until the earliest stop condition could fire
+    fn remaining(&self, last_activity: Instant) -> Option<Duration> {
+        let now = Instant::now();
+        let runtime_left = self
+            .max_runtime
+            .map(|max| (self.started + max).saturating_duration_since(now));
+        let idle_left = self
+            .idle_timeout
+            .map(|idle| (last_activity + idle).saturating_duration_since(now));
+
+        match (runtime_left, idle_left) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_websocket_loop(
     ws_url: &str,
     program_ids: &[Pubkey],
     event_decoder: Arc<EventDecoder>,
-    db: Arc<Database>,
-    kafka_producer: Option<Arc<dyn EventQueue>>,
-    commitment: &str,
+    sink: Arc<dyn EventSink>,
+    event_queue: Option<Arc<dyn EventQueue>>,
+    event_filter: Arc<EventNameFilter>,
+    metrics: Arc<Metrics>,
+    store_raw: bool,
+    include_failed: bool,
+    commitments: &CommitmentMap,
     reconnect_delay: u64,
     max_reconnects: u32,
-    ping_interval: u64,
-) -> Result<()> {
+    keepalive: Keepalive,
+    subscription_strategy: SubscriptionStrategy,
+    batch_size: usize,
+    batch_flush_interval: Duration,
+    stop_conditions: StopConditions,
+    mut recorder: Option<NotificationRecorder>,
+    shutdown: &mut watch::Receiver<bool>,
+) -> Result<StopReason> {
     let mut reconnect_count: u32 = 0;
     let program_ids_vec: Vec<_> = program_ids.iter().map(|p| p.to_string()).collect();
+    let mut last_activity = Instant::now();
 
     loop {
+        if *shutdown.borrow() {
+            return Ok(StopReason::Shutdown);
+        }
+        if let Some(reason) = stop_conditions.check(last_activity) {
+            return Ok(reason);
+        }
+
         if max_reconnects > 0 && reconnect_count >= max_reconnects {
             error!(
                 "Maximum reconnection attempts ({}) reached. Exiting.",
@@ -287,27 +1248,46 @@ async fn run_websocket_loop(
             program_ids,
             &program_ids_vec,
             event_decoder.clone(),
-            db.clone(),
-            kafka_producer.clone(),
-            commitment,
-            ping_interval,
+            sink.clone(),
+            event_queue.clone(),
+            event_filter.clone(),
+            metrics.clone(),
+            store_raw,
+            include_failed,
+            commitments,
+            keepalive,
+            subscription_strategy,
+            batch_size,
+            batch_flush_interval,
+            &stop_conditions,
+            &mut last_activity,
+            recorder.as_mut(),
+            shutdown,
         )
         .await
         {
-            Ok(_) => {
+            Ok(Some(reason)) => return Ok(reason),
+            Ok(None) => {
                 info!("WebSocket connection closed normally, reconnecting...");
                 reconnect_count += 1;
+                metrics.record_ws_reconnection();
                 let delay = if reconnect_count > 10 {
                     Duration::from_secs(60)
                 } else {
                     Duration::from_secs(reconnect_delay * reconnect_count as u64)
                 };
                 info!("Reconnecting in {:?}...", delay);
-                sleep(delay).await;
+                let delay = cap_delay(delay, &stop_conditions, last_activity);
+                tokio::select! {
+                    _ = sleep(delay) => {}
+                    _ = shutdown.changed() => {}
+                }
             }
             Err(e) => {
                 error!("WebSocket error: {}", e);
+                metrics.record_error(format!("WebSocket error: {}", e));
                 reconnect_count += 1;
+                metrics.record_ws_reconnection();
 
                 let delay = if reconnect_count > 10 {
                     Duration::from_secs(60)
@@ -316,31 +1296,55 @@ async fn run_websocket_loop(
                 };
 
                 info!("Reconnecting in {:?}...", delay);
-                sleep(delay).await;
+                let delay = cap_delay(delay, &stop_conditions, last_activity);
+                tokio::select! {
+                    _ = sleep(delay) => {}
+                    _ = shutdown.changed() => {}
+                }
             }
         }
     }
 }
 
+/// Don't sleep past the point where a stop condition fires
+fn cap_delay(
+    delay: Duration,
+    stop_conditions: &StopConditions,
+    last_activity: Instant,
+) -> Duration {
+    stop_conditions
+        .remaining(last_activity)
+        .map_or(delay, |remaining| delay.min(remaining))
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn websocket_handler(
     ws_url: &str,
     program_ids: &[Pubkey],
     program_ids_str: &[String],
     event_decoder: Arc<EventDecoder>,
-    db: Arc<Database>,
-    kafka_producer: Option<Arc<dyn EventQueue>>,
-    commitment: &str,
-    ping_interval: u64,
-) -> Result<()> {
+    sink: Arc<dyn EventSink>,
+    event_queue: Option<Arc<dyn EventQueue>>,
+    event_filter: Arc<EventNameFilter>,
+    metrics: Arc<Metrics>,
+    store_raw: bool,
+    include_failed: bool,
+    commitments: &CommitmentMap,
+    keepalive: Keepalive,
+    subscription_strategy: SubscriptionStrategy,
+    batch_size: usize,
+    batch_flush_interval: Duration,
+    stop_conditions: &StopConditions,
+    last_activity: &mut Instant,
+    mut recorder: Option<&mut NotificationRecorder>,
+    shutdown: &mut watch::Receiver<bool>,
+) -> Result<Option<StopReason>> {
     info!("Connecting to WebSocket at: {}", ws_url);
     info!("Monitoring {} program(s):", program_ids.len());
-    for pid in program_ids {
-        info!("  - {}", pid);
+    for pid in program_ids_str {
+        info!("  - {}", metrics.program_aliases().display(pid));
     }
 
-    // Parse commitment config
-    let commitment_config = parse_commitment(commitment)?;
-
     // Create PubsubClient
     let pubsub_client = PubsubClient::new(ws_url)
         .await
@@ -348,64 +1352,117 @@ async fn websocket_handler(
 
     info!("WebSocket connected successfully");
 
-    // Subscribe to logs for the specified programs
-    let filter = RpcTransactionLogsFilter::Mentions(program_ids_str.to_vec());
-    let logs_config = RpcTransactionLogsConfig {
-        commitment: Some(commitment_config),
-    };
+    // Subscribe to logs for the specified programs, merging the streams into one
+    let filters = subscription_strategy.filters(program_ids_str, commitments);
+    let mut streams = Vec::with_capacity(filters.len());
+    let mut unsubscribes = Vec::with_capacity(filters.len());
+    for (filter, commitment) in filters {
+        let logs_config = RpcTransactionLogsConfig {
+            commitment: Some(commitment),
+        };
+        let (stream, unsubscribe) = pubsub_client
+            .logs_subscribe(filter, logs_config)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to subscribe to logs: {}", e))?;
+        streams.push(stream);
+        unsubscribes.push(unsubscribe);
+    }
+    let mut notifications = futures::stream::select_all(streams);
 
-    let (mut notifications, unsubscribe) = pubsub_client
-        .logs_subscribe(filter, logs_config)
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to subscribe to logs: {}", e))?;
+    info!(
+        "Successfully subscribed to program logs ({} subscription(s))",
+        unsubscribes.len()
+    );
 
-    info!("Successfully subscribed to program logs");
-    info!("WebSocket keep-alive: read timeout = {}s", ping_interval);
+    let connection_metrics = metrics.clone();
 
     // Create channel for processing logs asynchronously
-    let (tx, mut rx) = mpsc::channel::<solana_client::rpc_response::RpcLogsResponse>(100);
-    let db_clone = db.clone();
+    let (tx, mut rx) = mpsc::channel::<(Slot, RpcLogsResponse)>(100);
+    let sink_clone = sink.clone();
     let event_decoder_clone = event_decoder.clone();
-    let kafka_producer_clone = kafka_producer.clone();
+    let event_queue_clone = event_queue.clone();
     let program_ids_clone: Vec<_> = program_ids.to_vec();
 
-    // Spawn processing task
+    // Spawn processing task, flushing partial batches on a timer
     let processor_handle = tokio::spawn(async move {
-        while let Some(message) = rx.recv().await {
-            match process_logs_message(
-                message,
-                &program_ids_clone,
-                &event_decoder_clone,
-                &db_clone,
-                kafka_producer_clone.as_ref(),
-            )
-            .await
-            {
-                Ok(count) => {
-                    if count > 0 {
-                        debug!("Processed {} events", count);
+        let mut batcher = EventBatcher::new(sink_clone, batch_size);
+        let mut flush_timer = tokio::time::interval(batch_flush_interval);
+        flush_timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                message = rx.recv() => {
+                    let Some((slot, message)) = message else { break };
+                    match process_logs_message(
+                        slot,
+                        message,
+                        &program_ids_clone,
+                        &event_decoder_clone,
+                        &mut batcher,
+                        event_queue_clone.as_ref(),
+                        &event_filter,
+                        &metrics,
+                        store_raw,
+                        include_failed,
+                    )
+                    .await
+                    {
+                        Ok(count) => {
+                            if count > 0 {
+                                debug!("Processed {} events", count);
+                            }
+                        }
+                        Err(e) => {
+                            error!("Error processing logs message: {}", e);
+                        }
                     }
                 }
-                Err(e) => {
-                    error!("Error processing logs message: {}", e);
+                _ = flush_timer.tick() => {
+                    batcher.flush(&metrics).await;
+                    flush_queue(event_queue_clone.as_ref()).await;
                 }
             }
         }
+
+        // The channel closed: store and send whatever is still buffered
+        batcher.flush(&metrics).await;
+        flush_queue(event_queue_clone.as_ref()).await;
     });
 
     // Main loop: receive notifications and send to processor
-    let read_timeout = if ping_interval > 0 {
-        Duration::from_secs(ping_interval)
-    } else {
-        Duration::from_secs(60) // default if disabled
-    };
+    let read_timeout = keepalive.interval.unwrap_or(Duration::from_secs(60));
 
-    let result: Result<()> = async {
+    let result: Result<Option<StopReason>> = async {
         loop {
-            match timeout(read_timeout, notifications.next()).await {
+            if let Some(reason) = stop_conditions.check(*last_activity) {
+                info!("Stopping: {}", reason);
+                return Ok(Some(reason));
+            }
+
+            // Wake up in time to honour any stop condition
+            let wait = cap_delay(read_timeout, stop_conditions, *last_activity);
+
+            let next = tokio::select! {
+                next = timeout(wait, notifications.next()) => next,
+                _ = shutdown.changed() => {
+                    info!("Stopping: {}", StopReason::Shutdown);
+                    return Ok(Some(StopReason::Shutdown));
+                }
+            };
+
+            match next {
                 Ok(Some(response)) => {
-                    // Response is Response<RpcLogsResponse>, extract the value
-                    if let Err(e) = tx.send(response.value).await {
+                    *last_activity = Instant::now();
+                    if !subscription_strategy.is_relevant(&response.value, program_ids_str) {
+                        continue;
+                    }
+                    if let Some(recorder) = recorder.as_mut() {
+                        if let Err(e) = recorder.record(&response.value) {
+                            error!("Failed to record notification: {}", e);
+                        }
+                    }
+                    // Response is Response<RpcLogsResponse>; the slot comes from its context
+                    if let Err(e) = tx.send((response.context.slot, response.value)).await {
                         error!("Failed to send log to processor: {}", e);
                         break;
                     }
@@ -414,118 +1471,462 @@ async fn websocket_handler(
                     info!("WebSocket stream ended");
                     break;
                 }
+                Err(_) if keepalive.interval.is_some() && wait == read_timeout => {
+                    debug!("No messages received in {:?}, probing connection", wait);
+                    if let Err(e) = probe_connection(&pubsub_client, keepalive.probe_timeout).await
+                    {
+                        warn!("WebSocket connection is stale ({}), reconnecting", e);
+                        connection_metrics.record_ws_stale_connection();
+                        break;
+                    }
+                }
                 Err(_) => {
-                    // Timeout - connection is still alive but no messages
-                    debug!(
-                        "No messages received in {:?}, connection still alive",
-                        read_timeout
-                    );
+                    debug!("No messages received in {:?}", wait);
                 }
             }
         }
-        Ok(())
+        Ok(None)
     }
     .await;
 
-    // Cleanup
+    // Cleanup: drain queued messages before unsubscribing so nothing is lost
     drop(tx);
     let _ = processor_handle.await;
 
     // Unsubscribe
-    unsubscribe().await;
+    for unsubscribe in unsubscribes {
+        unsubscribe().await;
+    }
 
     result
 }
 
-fn parse_commitment(commitment: &str) -> Result<CommitmentConfig> {
-    match commitment.to_lowercase().as_str() {
-        "processed" => Ok(CommitmentConfig::processed()),
-        "confirmed" => Ok(CommitmentConfig::confirmed()),
-        "finalized" => Ok(CommitmentConfig::finalized()),
-        _ => Err(anyhow::anyhow!(
-            "Invalid commitment level: {}. Use 'processed', 'confirmed', or 'finalized'",
-            commitment
-        )),
+/// Most signatures `--auto-gap-fill` fetches per program
+const GAP_FILL_MAX_SIGNATURES: usize = 10_000;
+
+/// Programs whose newest stored event is more than `max_slot_gap` slots behind `current_slot`
+///
+/// Every gap is logged and the largest is recorded in the metrics. Programs without
+/// stored events are skipped since there is nothing to catch up from.
+async fn find_slot_gaps(
+    db: &Database,
+    program_ids: &[Pubkey],
+    current_slot: Slot,
+    max_slot_gap: u64,
+    metrics: &Metrics,
+) -> Result<Vec<(Pubkey, Slot)>> {
+    let mut gaps = Vec::new();
+    let mut largest_gap = 0;
+
+    for program_id in program_ids {
+        let program_name = metrics.program_aliases().display(&program_id.to_string());
+        let Some(latest_slot) = db.get_latest_slot(&program_id.to_string()).await? else {
+            info!("No stored events for {}, skipping gap check", program_name);
+            continue;
+        };
+
+        let gap = current_slot.saturating_sub(latest_slot);
+        largest_gap = largest_gap.max(gap);
+        if gap > max_slot_gap {
+            warn!(
+                "{} is {} slot(s) behind: newest stored event at slot {}, chain at slot {}",
+                program_name, gap, latest_slot, current_slot
+            );
+            gaps.push((*program_id, latest_slot));
+        } else {
+            info!("{} is {} slot(s) behind the chain", program_name, gap);
+        }
+    }
+
+    metrics.record_slot_gap(largest_gap);
+    Ok(gaps)
+}
+
+/// Backfill each program from its newest stored event up to the chain tip
+async fn fill_slot_gaps(
+    backfiller_for: impl Fn(&Pubkey) -> Backfiller,
+    db: &Database,
+    gaps: &[(Pubkey, Slot)],
+) -> Result<usize> {
+    let mut events = 0;
+
+    for (program_id, latest_slot) in gaps {
+        let program_id_str = program_id.to_string();
+        let backfiller = backfiller_for(program_id);
+
+        // Stop paging once we reach a transaction that is already stored
+        let stored = db
+            .get_events_by_program_and_slot_range(&program_id_str, *latest_slot, *latest_slot)
+            .await?;
+        let until = stored.first().map(|event| event.signature.clone());
+
+        let signatures = backfiller
+            .fetch_signatures(program_id, None, until.as_ref(), GAP_FILL_MAX_SIGNATURES)
+            .await?;
+        if signatures.len() >= GAP_FILL_MAX_SIGNATURES {
+            warn!(
+                "Gap for {} exceeds {} signatures; only the newest are filled",
+                program_id, GAP_FILL_MAX_SIGNATURES
+            );
+        }
+
+        info!(
+            "Filling gap for {}: {} signature(s) since slot {}",
+            program_id,
+            signatures.len(),
+            latest_slot
+        );
+        let report = backfiller
+            .process_signatures(&program_id_str, signatures)
+            .await?;
+        events += report.events;
+    }
+
+    Ok(events)
+}
+
+/// Load IDLs and build the event decoder, returning it with the configured program IDs
+async fn load_event_decoder(
+    idl_dir: &str,
+    idl_bundle: Option<&str>,
+    program_prefixes: &str,
+    discriminator_schemes: Option<&str>,
+    event_name_case: EventNameCase,
+    decode_only_known: bool,
+    decode_accounts: bool,
+) -> Result<(EventDecoder, Vec<Pubkey>)> {
+    // Load IDLs first to extract program IDs
+    let mut idl_parser = IdlParser::new();
+    load_idls(&mut idl_parser, idl_dir).await?;
+    if let Some(bundle) = idl_bundle {
+        let count = idl_parser
+            .load_from_bundle(bundle)
+            .map_err(|e| anyhow::anyhow!("Failed to load IDL bundle {}: {}", bundle, e))?;
+        info!("Loaded {} IDL(s) from bundle {}", count, bundle);
+    }
+    idl_parser.validate_field_types()?;
+
+    let loaded_idls = idl_parser.get_idls();
+    info!("Loaded {} IDL(s) in total", loaded_idls.len());
+    for (addr, idl) in loaded_idls {
+        info!("  - {}: {} events", addr, idl.events.len());
+    }
+
+    // Create program prefix configuration from CLI/env
+    let mut prefix_config = ProgramPrefixConfig::new();
+    // Load programs from IDLs with default prefix
+    prefix_config.load_from_idls(loaded_idls);
+    // Apply custom prefix mappings from CLI/env
+    if !program_prefixes.is_empty() {
+        prefix_config.add_mappings_from_string(program_prefixes);
+        info!(
+            "Applied {} custom program prefix mapping(s)",
+            program_prefixes
+        );
+    }
+
+    if let Some(schemes) = discriminator_schemes {
+        idl_parser.add_discriminator_schemes_from_string(schemes)?;
+        info!("Applied discriminator scheme(s): {}", schemes);
+    }
+
+    // Convert program IDs to Pubkeys for WebSocket subscription
+    let pubkeys: Vec<Pubkey> = prefix_config
+        .get_program_ids()
+        .iter()
+        .map(|s| s.parse::<Pubkey>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("Failed to parse program IDs: {}", e))?;
+
+    Ok((
+        EventDecoder::new(idl_parser, prefix_config)
+            .with_name_case(event_name_case)
+            .with_decode_only_known(decode_only_known)
+            .with_decode_accounts(decode_accounts),
+        pubkeys,
+    ))
+}
+
+/// Appends received log notifications to a newline-delimited JSON file
+struct NotificationRecorder {
+    writer: std::io::LineWriter<std::fs::File>,
+}
+
+impl NotificationRecorder {
+    fn open(path: &Path) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open record file {}: {}", path.display(), e))?;
+        info!("Recording notifications to {}", path.display());
+
+        Ok(Self {
+            writer: std::io::LineWriter::new(file),
+        })
+    }
+
+    fn record(&mut self, message: &RpcLogsResponse) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, message)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// Run recorded notifications through the same pipeline as live mode
+#[allow(clippy::too_many_arguments)]
+async fn run_replay(
+    file: &Path,
+    program_prefixes: &str,
+    discriminator_schemes: Option<&str>,
+    event_name_case: EventNameCase,
+    decode_only_known: bool,
+    decode_accounts: bool,
+    db_url: &str,
+    table_name: &str,
+    mongo_db_name: Option<&str>,
+    sink_kind: SinkKind,
+    idl_dir: &str,
+    idl_bundle: Option<&str>,
+    event_filter: EventNameFilter,
+    store_raw: bool,
+    include_failed: bool,
+) -> Result<()> {
+    info!("Replaying notifications from {}", file.display());
+
+    let (event_decoder, pubkeys) = load_event_decoder(
+        idl_dir,
+        idl_bundle,
+        program_prefixes,
+        discriminator_schemes,
+        event_name_case,
+        decode_only_known,
+        decode_accounts,
+    )
+    .await?;
+    if pubkeys.is_empty() {
+        error!("No IDLs found. Use --idl-dir <path> or --idl-bundle <file>");
+        return Ok(());
+    }
+
+    if sink_kind == SinkKind::None {
+        anyhow::bail!("replay writes events to db, stdout or both; --sink none would drop them");
+    }
+    let sink = sink_kind.open(db_url, table_name, mongo_db_name).await?;
+    if sink_kind.uses_database() {
+        info!("Database connected: {}", db_url);
+    }
+
+    let metrics = Metrics::new();
+    let mut batcher = EventBatcher::new(sink, 1);
+    let contents = tokio::fs::read_to_string(file)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file.display(), e))?;
+
+    let mut notifications = 0;
+    let mut events = 0;
+    for (line_no, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let message: RpcLogsResponse = serde_json::from_str(line)
+            .map_err(|e| anyhow::anyhow!("Invalid notification on line {}: {}", line_no + 1, e))?;
+        notifications += 1;
+
+        // Recordings don't keep the notification slot
+        match process_logs_message(
+            0,
+            message,
+            &pubkeys,
+            &event_decoder,
+            &mut batcher,
+            None,
+            &event_filter,
+            &metrics,
+            store_raw,
+            include_failed,
+        )
+        .await
+        {
+            Ok(count) => events += count,
+            Err(e) => error!("Error processing line {}: {}", line_no + 1, e),
+        }
+    }
+    events += batcher.flush(&metrics).await;
+
+    info!("\nReplay complete!");
+    info!("Notifications replayed: {}", notifications);
+    info!("Events stored: {}", events);
+    metrics.log_summary().await;
+
+    Ok(())
+}
+
+/// Buffers decoded events and writes them to the sink in batches
+struct EventBatcher {
+    sink: Arc<dyn EventSink>,
+    batch_size: usize,
+    pending: Vec<(DecodedEvent, RawEvent)>,
+}
+
+impl EventBatcher {
+    fn new(sink: Arc<dyn EventSink>, batch_size: usize) -> Self {
+        let batch_size = batch_size.max(1);
+        Self {
+            sink,
+            batch_size,
+            pending: Vec::with_capacity(batch_size),
+        }
+    }
+
+    /// Queue an event, flushing once the batch is full; returns how many events were stored
+    async fn push(&mut self, event: DecodedEvent, raw: RawEvent, metrics: &Metrics) -> usize {
+        self.pending.push((event, raw));
+        if self.pending.len() >= self.batch_size {
+            self.flush(metrics).await
+        } else {
+            0
+        }
+    }
+
+    /// Write the buffered events, returning how many were new
+    ///
+    /// Duplicates are skipped here rather than when the event is queued.
+    async fn flush(&mut self, metrics: &Metrics) -> usize {
+        if self.pending.is_empty() {
+            return 0;
+        }
+        let events = std::mem::take(&mut self.pending);
+
+        let insert_started = Instant::now();
+        let written = self.sink.write_batch(&events).await;
+        metrics.record_insert_latency(insert_started.elapsed());
+
+        let inserted = match written {
+            Ok(inserted) => inserted as usize,
+            Err(e) => {
+                error!("Failed to store {} event(s): {}", events.len(), e);
+                metrics.record_error(format!("Failed to store {} event(s): {}", events.len(), e));
+                for _ in &events {
+                    metrics.record_db_insert(true, false);
+                }
+                return 0;
+            }
+        };
+
+        metrics.record_batch_insert(&events, inserted);
+        if inserted == events.len() {
+            for (event, raw) in &events {
+                info!("Stored event: {} from {}", event.event_name, raw.signature);
+            }
+        } else {
+            info!(
+                "Stored {} of {} event(s), skipping {} already stored",
+                inserted,
+                events.len(),
+                events.len() - inserted
+            );
+        }
+
+        inserted
     }
 }
 
-/// Process a logs message from PubsubClient
+/// Process a logs message from PubsubClient, returning how many events were stored
+///
+/// Decoded events go through `batcher`, so they may be stored by a later flush.
+#[allow(clippy::too_many_arguments)]
 async fn process_logs_message(
-    message: solana_client::rpc_response::RpcLogsResponse,
+    slot: Slot,
+    message: RpcLogsResponse,
     program_ids: &[Pubkey],
     event_decoder: &EventDecoder,
-    db: &Database,
-    kafka_producer: Option<&Arc<dyn EventQueue>>,
+    batcher: &mut EventBatcher,
+    event_queue: Option<&Arc<dyn EventQueue>>,
+    event_filter: &EventNameFilter,
+    metrics: &Metrics,
+    store_raw: bool,
+    include_failed: bool,
 ) -> Result<usize> {
     use chrono::Utc;
 
-    // Skip failed transactions
+    metrics.record_transaction(message.err.is_some());
+
+    // Skip failed transactions unless their events are wanted
     if let Some(err) = &message.err {
-        debug!("Skipping failed transaction: {:?}", err);
-        return Ok(0);
+        if !include_failed {
+            debug!("Skipping failed transaction: {:?}", err);
+            return Ok(0);
+        }
+        debug!("Keeping events from failed transaction: {:?}", err);
     }
 
-    let signature = &message.signature;
+    let signature = Signature::parse(&message.signature)?;
     let logs = &message.logs;
 
     // Process logs for events
     let mut events_found = 0;
 
-    for log in logs {
+    for log_event in extract_events_from_logs(logs) {
+        let Some(event_data) = log_event.data else {
+            warn!(
+                "Event data in transaction {} looks truncated, skipping it",
+                signature
+            );
+            metrics.record_truncated_event();
+            continue;
+        };
         for program_id in program_ids {
-            if let Some(event_data) = extract_event_from_log(log) {
-                // Decode event
-                match event_decoder.decode_event(&program_id.to_string(), &signature, &event_data) {
-                    Ok(decoded_event) => {
-                        // Create raw event record
-                        let raw_event = RawEvent {
-                            slot: 0, // Not provided in RpcLogsResponse
-                            signature: signature.clone(),
-                            program_id: *program_id,
-                            log: log.clone(),
-                            timestamp: Utc::now(),
-                        };
-
-                        // Store event in database
-                        match db
-                            .insert_event(&decoded_event, &raw_event, events_found)
-                            .await
-                        {
-                            Ok(_) => {
-                                info!(
-                                    "Stored event: {} from {}",
-                                    decoded_event.event_name, signature
-                                );
-                                events_found += 1;
-                            }
-                            Err(e) => {
-                                let err_str = e.to_string();
-                                if err_str.contains("UNIQUE constraint")
-                                    || err_str.contains("duplicate")
-                                {
-                                    debug!("Event {} already exists, skipping", signature);
-                                } else {
-                                    error!("Failed to store event: {}", e);
-                                }
-                            }
-                        }
+            // Decode event
+            let decode_started = Instant::now();
+            let decoded = event_decoder.decode_event(
+                &program_id.to_string(),
+                signature.as_str(),
+                &event_data,
+            );
+            metrics.record_decode_latency(decode_started.elapsed());
 
-                        // Send to Kafka if configured
-                        if let Some(producer) = kafka_producer {
-                            let queue_event = QueueEvent::new(
-                                decoded_event.event_name.clone(),
-                                signature.clone(),
-                                program_id.to_string(),
-                                decoded_event.data.clone(),
-                            );
-                            if let Err(e) = producer.send(&queue_event).await {
-                                error!("Failed to send event to Kafka: {}", e);
-                            }
-                        }
+            match decoded {
+                Ok(decoded_event) => {
+                    if decoded_event.data.get("decode_error").is_some() {
+                        metrics.record_decode_failure(&program_id.to_string());
                     }
-                    Err(e) => {
-                        debug!("Failed to decode event: {}", e);
+                    if !event_filter.is_allowed(&decoded_event.event_name) {
+                        debug!("Filtered event: {}", decoded_event.event_name);
+                        metrics.record_filtered_event();
+                        continue;
+                    }
+
+                    // Create raw event record
+                    let raw_event = RawEvent {
+                        slot,
+                        signature: signature.clone(),
+                        program_id: *program_id,
+                        log: log_event.log.clone(),
+                        log_index: log_event.log_index,
+                        inner_instruction_index: log_event.inner_instruction_index,
+                        timestamp: Utc::now(),
+                        raw_data: store_raw.then(|| event_data.clone()),
+                        tx_failed: message.err.is_some(),
+                    };
+
+                    // Send to the queue if configured
+                    if let Some(queue) = event_queue {
+                        let queue_event = QueueEvent::from_decoded(&decoded_event, &raw_event);
+                        if let Err(e) = queue.send(&queue_event).await {
+                            error!("Failed to send event to queue: {}", e);
+                        }
                     }
+
+                    // Queue the event for storage
+                    events_found += batcher.push(decoded_event, raw_event, metrics).await;
+                }
+                Err(e) if event_decoder.is_ignored(&e) => {}
+                Err(e) => {
+                    metrics.record_decode_failure(&program_id.to_string());
+                    metrics.record_error(format!("Failed to decode event: {}", e));
+                    debug!("Failed to decode event: {}", e);
                 }
             }
         }
@@ -538,6 +1939,208 @@ async fn process_logs_message(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_recorded_notifications_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("soltrace-record-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let message = RpcLogsResponse {
+            signature: "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW".to_string(),
+            err: None,
+            logs: vec!["Program data: AQID".to_string()],
+        };
+
+        let mut recorder = NotificationRecorder::open(&path).unwrap();
+        recorder.record(&message).unwrap();
+        recorder.record(&message).unwrap();
+        drop(recorder);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let replayed: Vec<RpcLogsResponse> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(replayed, vec![message.clone(), message]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_stop_conditions() {
+        let none = StopConditions::new(None, None);
+        assert_eq!(none.check(Instant::now()), None);
+        assert_eq!(none.remaining(Instant::now()), None);
+
+        let expired = StopConditions::new(Some(0), None);
+        assert_eq!(expired.check(Instant::now()), Some(StopReason::MaxRuntime));
+
+        let idle = StopConditions::new(Some(3600), Some(60));
+        assert_eq!(idle.check(Instant::now()), None);
+        assert_eq!(
+            idle.check(Instant::now() - Duration::from_secs(61)),
+            Some(StopReason::IdleTimeout)
+        );
+        assert!(idle.remaining(Instant::now()).unwrap() <= Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_subscription_strategy_filters() {
+        let ids = vec!["Prog1".to_string(), "Prog2".to_string()];
+        let confirmed = CommitmentConfig::confirmed();
+        let commitments = CommitmentMap::new("confirmed").unwrap();
+
+        assert_eq!(
+            SubscriptionStrategy::Combined.filters(&ids, &commitments),
+            vec![(RpcTransactionLogsFilter::Mentions(ids.clone()), confirmed)]
+        );
+        assert_eq!(
+            SubscriptionStrategy::PerProgram.filters(&ids, &commitments),
+            vec![
+                (
+                    RpcTransactionLogsFilter::Mentions(vec!["Prog1".to_string()]),
+                    confirmed
+                ),
+                (
+                    RpcTransactionLogsFilter::Mentions(vec!["Prog2".to_string()]),
+                    confirmed
+                ),
+            ]
+        );
+        assert_eq!(
+            SubscriptionStrategy::All.filters(&ids, &commitments),
+            vec![(RpcTransactionLogsFilter::All, confirmed)]
+        );
+
+        // Programs with their own commitment get their own combined subscription
+        let program = "TRibg8W8zmPHQqWtyAD1rEBRXEdyU13Mu6qX1Sg42tJ".to_string();
+        let commitments = commitments
+            .with_mappings_from_string(&format!("{}=finalized", program))
+            .unwrap();
+        let ids = vec!["Prog1".to_string(), program.clone(), "Prog2".to_string()];
+        assert_eq!(
+            SubscriptionStrategy::Combined.filters(&ids, &commitments),
+            vec![
+                (
+                    RpcTransactionLogsFilter::Mentions(vec![
+                        "Prog1".to_string(),
+                        "Prog2".to_string()
+                    ]),
+                    confirmed
+                ),
+                (
+                    RpcTransactionLogsFilter::Mentions(vec![program]),
+                    CommitmentConfig::finalized()
+                ),
+            ]
+        );
+
+        let message = |log: &str| RpcLogsResponse {
+            signature: String::new(),
+            err: None,
+            logs: vec![log.to_string()],
+        };
+        let other = message("Program Other invoke [1]");
+        assert!(SubscriptionStrategy::Combined.is_relevant(&other, &ids));
+        assert!(!SubscriptionStrategy::All.is_relevant(&other, &ids));
+        assert!(SubscriptionStrategy::All.is_relevant(&message("Program Prog2 invoke [1]"), &ids));
+    }
+
+    #[tokio::test]
+    async fn test_event_batcher_flushes_full_batches() {
+        use std::sync::atomic::Ordering;
+
+        let db = Arc::new(Database::new("sqlite::memory:").await.unwrap());
+        let mut batcher = EventBatcher::new(db.clone(), 2);
+        let metrics = Metrics::new();
+
+        let signature = solana_sdk::signature::Signature::from([3u8; 64]).to_string();
+        let event = |amount: u64| DecodedEvent {
+            event_name: "tributary_Transfer".to_string(),
+            data: serde_json::json!({ "amount": amount }),
+            discriminator: [1; 8],
+        };
+        let raw = RawEvent {
+            slot: 1,
+            signature: Signature::parse(&signature).unwrap(),
+            program_id: Pubkey::default(),
+            log: String::new(),
+            log_index: 0,
+            inner_instruction_index: None,
+            timestamp: chrono::Utc::now(),
+            raw_data: None,
+            tx_failed: false,
+        };
+
+        let second = RawEvent {
+            log_index: 1,
+            ..raw.clone()
+        };
+
+        assert_eq!(batcher.push(event(1), raw.clone(), &metrics).await, 0);
+        assert!(db
+            .get_events_by_name("tributary_Transfer")
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(batcher.push(event(2), second, &metrics).await, 2);
+
+        // A partial batch waits for the flush; the re-sent event is a duplicate
+        assert_eq!(batcher.push(event(1), raw, &metrics).await, 0);
+        assert_eq!(batcher.flush(&metrics).await, 0);
+        assert_eq!(batcher.flush(&metrics).await, 0);
+
+        assert_eq!(
+            db.get_events_by_name("tributary_Transfer")
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(metrics.db_inserts.load(Ordering::Relaxed), 2);
+        assert_eq!(metrics.duplicate_events.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.db_insert_failures.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn test_find_slot_gaps() {
+        use std::sync::atomic::Ordering;
+
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let (behind, current, empty) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let signature = solana_sdk::signature::Signature::from([4u8; 64]).to_string();
+        for (index, (program_id, slot)) in [(behind, 100), (current, 950)].into_iter().enumerate() {
+            let event = DecodedEvent {
+                event_name: "tributary_Transfer".to_string(),
+                data: serde_json::json!({}),
+                discriminator: [1; 8],
+            };
+            let raw = RawEvent {
+                slot,
+                signature: Signature::parse(&signature).unwrap(),
+                program_id,
+                log: String::new(),
+                log_index: index as u32,
+                inner_instruction_index: None,
+                timestamp: chrono::Utc::now(),
+                raw_data: None,
+                tx_failed: false,
+            };
+            db.insert_event(&event, &raw).await.unwrap();
+        }
+
+        let metrics = Metrics::new();
+        let gaps = find_slot_gaps(&db, &[behind, current, empty], 1000, 150, &metrics)
+            .await
+            .unwrap();
+        assert_eq!(gaps, vec![(behind, 100)]);
+        assert_eq!(metrics.slot_gap.load(Ordering::Relaxed), 900);
+    }
+
     #[test]
     fn test_program_id_parsing() {
         let programs =
@@ -551,12 +2154,4 @@ mod tests {
         assert_eq!(parsed.len(), 2);
         assert_eq!(parsed[0], "11111111111111111111111111111111");
     }
-
-    #[test]
-    fn test_parse_commitment() {
-        assert!(parse_commitment("confirmed").is_ok());
-        assert!(parse_commitment("processed").is_ok());
-        assert!(parse_commitment("finalized").is_ok());
-        assert!(parse_commitment("invalid").is_err());
-    }
 }